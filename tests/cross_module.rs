@@ -14,10 +14,9 @@ use zkir_spec::{Instruction, Program, Register, Opcode, Config};
 #[test]
 fn test_assembled_program_runs() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
-        addi t2, zero, 0    # syscall: exit (R10)
-        addi a0, zero, 42   # exit code (R11)
+        addi a0, zero, 0    # syscall: exit (R10)
+        addi a1, zero, 42   # exit code (R11)
         ecall
     "#;
 
@@ -31,20 +30,19 @@ fn test_assembled_program_runs() {
 #[test]
 fn test_assembled_io_program() {
     // Syscall convention: R10 = syscall number, R11 = first arg, return in R10
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         # Read input
-        addi t2, zero, 1    # syscall: read (R10)
-        ecall               # result in t2 (R10)
+        addi a0, zero, 1    # syscall: read (R10)
+        ecall               # result in a0 (R10)
 
-        # Write output (copy t2 to a0)
-        addi a0, t2, 0      # a0 = t2 (input value, R11)
-        addi t2, zero, 2    # syscall: write (R10)
+        # Write output (copy a0 to a1)
+        addi a1, a0, 0      # a1 = a0 (input value, R11)
+        addi a0, zero, 2    # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0    # syscall: exit (R10)
-        addi a0, zero, 0    # exit code 0 (R11)
+        addi a0, zero, 0    # syscall: exit (R10)
+        addi a1, zero, 0    # exit code 0 (R11)
         ecall
     "#;
 
@@ -59,7 +57,6 @@ fn test_assembled_io_program() {
 #[test]
 fn test_assembled_arithmetic() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         # Compute 10 + 20 + 30
         addi r1, zero, 10
@@ -69,13 +66,13 @@ fn test_assembled_arithmetic() {
         add r4, r4, r3      # r4 = 60
 
         # Write result
-        addi a0, r4, 0      # a0 = result (R11)
-        addi t2, zero, 2    # syscall: write (R10)
+        addi a1, r4, 0      # a1 = result (R11)
+        addi a0, zero, 2    # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0    # syscall: exit (R10)
-        addi a0, zero, 0    # exit code (R11)
+        addi a0, zero, 0    # syscall: exit (R10)
+        addi a1, zero, 0    # exit code (R11)
         ecall
     "#;
 
@@ -136,7 +133,6 @@ fn test_full_roundtrip_simple() {
 #[test]
 fn test_full_roundtrip_complex() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         .config limb_bits 20
         .config data_limbs 2
@@ -154,13 +150,13 @@ fn test_full_roundtrip_complex() {
         bne r3, zero, -16     # loop if counter != 0
 
         # Write result
-        addi a0, r2, 0        # a0 = result (R11)
-        addi t2, zero, 2      # syscall: write (R10)
+        addi a1, r2, 0        # a1 = result (R11)
+        addi a0, zero, 2      # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0      # syscall: exit (R10)
-        addi a0, zero, 0      # exit code (R11)
+        addi a0, zero, 0      # syscall: exit (R10)
+        addi a1, zero, 0      # exit code (R11)
         ecall
     "#;
 
@@ -219,7 +215,7 @@ fn test_encode_decode_all_instructions() {
     ];
 
     for original in instructions {
-        let encoded = zkir_assembler::encode(&original);
+        let encoded = zkir_assembler::try_encode(&original, &Config::DEFAULT).unwrap();
         let decoded = decode(encoded).unwrap();
         assert_eq!(decoded, original, "Roundtrip failed for {:?}", original);
     }
@@ -249,7 +245,7 @@ fn test_encode_decode_edge_immediates() {
             imm,
         };
 
-        let encoded = zkir_assembler::encode(&original);
+        let encoded = zkir_assembler::try_encode(&original, &Config::DEFAULT).unwrap();
         let decoded = decode(encoded).unwrap();
         assert_eq!(decoded, original, "Failed for immediate {}", imm);
     }
@@ -334,7 +330,6 @@ fn test_config_preserved_through_pipeline() {
 #[test]
 fn test_memory_roundtrip() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         # Store a value to memory
         addi r1, zero, 42       # value
@@ -345,13 +340,13 @@ fn test_memory_roundtrip() {
         lw r3, 0(r2)            # load
 
         # Write result
-        addi a0, r3, 0          # a0 = result (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        addi a1, r3, 0          # a1 = result (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -369,7 +364,6 @@ fn test_memory_roundtrip() {
 #[test]
 fn test_conditional_branch() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 10
         addi r2, zero, 10
@@ -384,13 +378,13 @@ fn test_conditional_branch() {
         addi r4, zero, 2
 
         # Write r3 (should be 0)
-        addi a0, r3, 0          # a0 = r3 (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        addi a1, r3, 0          # a1 = r3 (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -405,7 +399,6 @@ fn test_conditional_branch() {
 #[test]
 fn test_loop_with_branch() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         # Sum 1 to 5
         addi r1, zero, 0    # sum = 0
@@ -418,13 +411,13 @@ fn test_loop_with_branch() {
         bne r2, r3, -8      # loop if i != limit
 
         # Write sum
-        addi a0, r1, 0      # a0 = sum (R11)
-        addi t2, zero, 2    # syscall: write (R10)
+        addi a1, r1, 0      # a1 = sum (R11)
+        addi a0, zero, 2    # syscall: write (R10)
         ecall
 
         # Exit
-        addi t2, zero, 0    # syscall: exit (R10)
-        addi a0, zero, 0    # exit code (R11)
+        addi a0, zero, 0    # syscall: exit (R10)
+        addi a1, zero, 0    # exit code (R11)
         ecall
     "#;
 
@@ -6,13 +6,16 @@
 //! 3. Verify outputs and execution traces
 //! 4. Disassemble the program back to source
 //!
-//! Syscall conventions:
+//! Syscall conventions (`zkir_spec::abi`):
 //! - R10 (a0): syscall number (0=exit, 1=read, 2=write)
 //! - R11 (a1): syscall argument (exit code for exit, value for write)
 
-use zkir_assembler::assemble;
-use zkir_disassembler::disassemble;
-use zkir_runtime::{VM, VMConfig};
+use zkir_assembler::ast::{Statement, StatementKind};
+use zkir_assembler::{assemble, assemble_object, format_source, parse, FormatOptions, RegisterNames};
+use zkir_disassembler::{control_flow_graph, decode, disassemble, disassemble_reassemblable, format};
+use zkir_linker::link;
+use zkir_runtime::{HaltReason, VM, VMConfig};
+use zkir_spec::memory::CODE_BASE;
 
 // ============================================================================
 // Assemble -> Execute Tests
@@ -253,6 +256,44 @@ fn test_roundtrip_shifts() {
     assert!(disasm.contains("srai"));
 }
 
+#[test]
+fn test_roundtrip_register_names() {
+    // Every register, formatted by its ABI name, reassembles to itself
+    let source: String = (0..16).map(|i| format!("add r{}, r{}, r{}\n", i, (i + 1) % 16, (i + 2) % 16)).collect();
+    let program = assemble(&source).expect("Assembly failed");
+
+    let formatted: String = program
+        .code
+        .iter()
+        .map(|&word| format!("{}\n", format(&decode(word).expect("Decode failed"))))
+        .collect();
+    assert!(formatted.starts_with("add zero, ra, sp\n"), "{}", formatted);
+
+    let reassembled = assemble(&format!(".abi 2\n{}", formatted)).expect("Reassembly failed");
+    assert_eq!(reassembled.code, program.code);
+}
+
+#[test]
+fn test_syscalls_with_abi_names() {
+    let source = r#"
+        .abi 2
+        li a0, 1        # read
+        ecall
+        mv a1, a0
+        li a0, 2        # write
+        ecall
+        li a0, 0        # exit
+        li a1, 7
+        ecall
+    "#;
+
+    let program = assemble(source).expect("Assembly failed");
+    let vm = VM::new(program, vec![42], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![42]);
+    assert_eq!(result.halt_reason, HaltReason::Exit(7));
+}
+
 // ============================================================================
 // VM Configuration Tests
 // ============================================================================
@@ -352,6 +393,283 @@ fn test_sum_values() {
     assert!(result.cycles > 0);
 }
 
+#[test]
+fn test_load_from_data_section() {
+    // Five instructions of code place .data at CODE_BASE + 20 = 4116
+    let source = r#"
+        addi r1, r0, 4116
+        lw r11, 4(r1)
+        addi r10, r0, 2
+        ecall
+        ebreak
+
+        .data
+    table:
+        .word table, 1234
+    "#;
+
+    let program = assemble(source).expect("Assembly failed");
+    assert_eq!(&program.data[..4], &4116u32.to_le_bytes());
+
+    let vm = VM::new(program, vec![], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![1234]);
+}
+
+#[test]
+fn test_pseudo_instructions_execute() {
+    // 40-bit constant via multi-instruction li, la into the data section,
+    // call/ret and bgt
+    let source = r#"
+        li r11, 0xFFFFFFFFFE
+        call emit
+        la r1, value
+        lw r11, 0(r1)
+        call emit
+        li r2, 5
+        li r3, 3
+        bgt r2, r3, done
+        li r11, 99
+        call emit
+    done:
+        li r10, 0
+        ecall
+    emit:
+        li r10, 2
+        ecall
+        ret
+
+        .data
+    value:
+        .word 777
+    "#;
+
+    let program = assemble(source).expect("Assembly failed");
+    let vm = VM::new(program, vec![], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![0xFF_FFFF_FFFE, 777]);
+}
+
+#[test]
+fn test_entry_point_after_helpers() {
+    // Helpers come first; execution starts at the global `_start`
+    let source = r#"
+        .global _start
+    emit:
+        li r10, 2
+        ecall
+        ret
+    _start:
+        li r11, 5
+        call emit
+        li r10, 0
+        ecall
+    "#;
+
+    let program = assemble(source).expect("Assembly failed");
+    assert_eq!(program.header.entry_point, 0x100C);
+    let vm = VM::new(program, vec![], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![5]);
+}
+
+#[test]
+fn test_relaxed_branches_execute() {
+    // The padding puts `emit` and `skipped` past the reach of a `jal`, so
+    // the branches and the call are rewritten into far jumps
+    let source = r#"
+        .abi 2
+        li a1, 1
+        beq a1, zero, skipped
+        call emit
+        bne a1, zero, done
+        li a0, 0
+        li a1, 13
+        ecall
+        .rept 257
+        nop
+        .align 12
+        .endr
+    emit:
+        li a0, 2
+        li a1, 5
+        ecall
+        li a1, 1
+        ret
+    skipped:
+        li a0, 0
+        li a1, 14
+        ecall
+    done:
+        li a0, 0
+        li a1, 0
+        ecall
+    "#;
+
+    let program = assemble(source).expect("Assembly failed");
+    assert!(program.code.len() > 1 << 18);
+    let vm = VM::new(program, vec![], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![5]);
+    assert_eq!(result.halt_reason, HaltReason::Exit(0));
+}
+
+#[test]
+fn test_linked_modules_execute() {
+    // The application calls into a separately assembled I/O module and reads
+    // a table through a pointer defined in a third, data-only module
+    let app = assemble_object(r#"
+        .global _start
+        .extern emit, table_ptr
+    _start:
+        la r1, table_ptr
+        lw r1, 0(r1)
+        lw r11, 4(r1)
+        call emit
+        li r10, 0
+        ecall
+    "#)
+    .expect("Assembly failed");
+
+    let io = assemble_object(r#"
+        .global emit
+    emit:
+        li r10, 2
+        ecall
+        ret
+    "#)
+    .expect("Assembly failed");
+
+    let data = assemble_object(r#"
+        .global table_ptr
+        .data
+    table:
+        .word 11, 22
+    table_ptr:
+        .word table
+    "#)
+    .expect("Assembly failed");
+
+    let program = link([("app", app), ("io", io), ("data", data)]).expect("Link failed");
+    let vm = VM::new(program, vec![], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![22]);
+}
+
+// ============================================================================
+// Example Programs
+// ============================================================================
+
+#[test]
+fn test_example_add() {
+    let program = assemble(include_str!("../examples/add.zkasm")).expect("Assembly failed");
+    let vm = VM::new(program, vec![3, 4], VMConfig::default());
+    let result = vm.run().expect("Execution failed");
+    assert_eq!(result.outputs, vec![7]);
+}
+
+#[test]
+fn test_example_fibonacci() {
+    for (n, expected) in [(0, 0), (1, 1), (2, 1), (10, 55), (20, 6765)] {
+        let program = assemble(include_str!("../examples/fibonacci.zkasm")).expect("Assembly failed");
+        let vm = VM::new(program, vec![n], VMConfig::default());
+        let result = vm.run().expect("Execution failed");
+        assert_eq!(result.outputs, vec![expected], "fib({})", n);
+    }
+}
+
+#[test]
+fn test_parse_examples() {
+    for source in [include_str!("../examples/add.zkasm"), include_str!("../examples/fibonacci.zkasm")] {
+        let module = parse(source).expect("Parse failed");
+        assert!(module.statements.contains(&Statement {
+            kind: StatementKind::Label("_start".to_string()),
+            line: source.lines().position(|line| line.starts_with("_start:")).unwrap() + 1,
+            span: source.find("_start:").unwrap()..source.find("_start:").unwrap() + 7,
+        }));
+        // Each statement spans text on its own line
+        for statement in &module.statements {
+            let text = &source[statement.span.clone()];
+            assert!(source.lines().nth(statement.line - 1).unwrap().contains(text), "{:?}", statement);
+        }
+    }
+}
+
+/// Sources embedded as raw strings in the assembler's test files
+fn test_corpus() -> Vec<&'static str> {
+    let files = [
+        include_str!("../zkir-assembler/tests/integration_tests.rs"),
+        include_str!("end_to_end.rs"),
+        include_str!("cross_module.rs"),
+    ];
+    let mut sources = Vec::new();
+    for file in files {
+        let mut rest = file;
+        while let Some(start) = rest.find("r#\"") {
+            let body = &rest[start + 3..];
+            let Some(end) = body.find("\"#") else { break };
+            sources.push(&body[..end]);
+            rest = &body[end + 2..];
+        }
+    }
+    sources
+}
+
+#[test]
+fn test_format_preserves_programs() {
+    let examples = [include_str!("../examples/add.zkasm"), include_str!("../examples/fibonacci.zkasm")];
+    let mut formatted_count = 0;
+    for source in examples.into_iter().chain(test_corpus()) {
+        // Sources that are meant to fail (or need include paths) are skipped
+        let Ok(program) = assemble(source) else { continue };
+        for registers in [RegisterNames::Abi, RegisterNames::Numeric] {
+            let options = FormatOptions { registers };
+            let formatted = format_source(source, &options).unwrap_or_else(|e| panic!("{}\n{}", e, source));
+            let reformatted = assemble(&formatted).unwrap_or_else(|e| panic!("{}\n{}", e, formatted));
+            assert_eq!(reformatted.to_bytes(), program.to_bytes(), "{}\n{}", source, formatted);
+            assert_eq!(format_source(&formatted, &options).unwrap(), formatted, "not idempotent:\n{}", source);
+        }
+        formatted_count += 1;
+    }
+    assert!(formatted_count > 50, "only {} sources formatted", formatted_count);
+}
+
+#[test]
+fn test_disassembly_reassembles() {
+    let examples = [include_str!("../examples/add.zkasm"), include_str!("../examples/fibonacci.zkasm")];
+    let mut reassembled_count = 0;
+    for source in examples.into_iter().chain(test_corpus()) {
+        // Sources that are meant to fail (or need include paths) are skipped
+        let Ok(program) = assemble(source) else { continue };
+        let disassembly = disassemble_reassemblable(&program).unwrap_or_else(|e| panic!("{}\n{}", e, source));
+        let reassembled = assemble(&disassembly).unwrap_or_else(|e| panic!("{}\n{}", e, disassembly));
+        assert_eq!(reassembled.to_bytes(), program.to_bytes(), "{}\n{}", source, disassembly);
+        reassembled_count += 1;
+    }
+    assert!(reassembled_count > 50, "only {} sources reassembled", reassembled_count);
+}
+
+#[test]
+fn test_control_flow_graph_covers_code() {
+    let examples = [include_str!("../examples/add.zkasm"), include_str!("../examples/fibonacci.zkasm")];
+    for source in examples.into_iter().chain(test_corpus()) {
+        let Ok(program) = assemble(source) else { continue };
+        let graph = control_flow_graph(&program).unwrap_or_else(|e| panic!("{}\n{}", e, source));
+
+        // Blocks partition the code, and every function starts a block
+        let mut next = CODE_BASE;
+        for block in &graph.blocks {
+            assert_eq!(block.start, next, "{}", source);
+            assert!(block.end > block.start, "{}", source);
+            next = block.end;
+        }
+        assert_eq!(next, CODE_BASE + program.header.code_size as u64, "{}", source);
+        for function in &graph.functions {
+            assert!(graph.block(function.entry).is_some(), "{}", source);
+        }
+    }
+}
+
 // ============================================================================
 // Error Handling Tests
 // ============================================================================
@@ -10,7 +10,7 @@ fn create_program_from_instructions(instructions: Vec<Instruction>) -> Program {
     let mut program = Program::new();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
@@ -78,7 +78,6 @@ fn test_many_labels_program() {
 fn test_tight_loop_many_iterations() {
     // Loop 10000 times
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 0        # counter
         addi r2, zero, 10000    # limit
@@ -88,8 +87,8 @@ fn test_tight_loop_many_iterations() {
         bne r1, r2, -4          # loop if counter != limit
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -104,7 +103,6 @@ fn test_tight_loop_many_iterations() {
 fn test_nested_loops() {
     // Double nested loop: 100 * 100 = 10000 iterations
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 0        # outer counter
         addi r3, zero, 100      # limit
@@ -120,8 +118,8 @@ fn test_nested_loops() {
         bne r1, r3, -16         # outer loop
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -204,8 +202,8 @@ fn test_many_memory_operations() {
         source.push_str("    addi r2, r2, 1\n");
     }
 
-    source.push_str("    addi t2, zero, 0\n");  // syscall: exit (R10)
-    source.push_str("    addi a0, zero, 0\n");  // exit code (R11)
+    source.push_str("    addi a0, zero, 0\n");  // syscall: exit (R10)
+    source.push_str("    addi a1, zero, 0\n");  // exit code (R11)
     source.push_str("    ecall\n");
 
     let program = assemble(&source).unwrap();
@@ -219,7 +217,6 @@ fn test_many_memory_operations() {
 fn test_sparse_memory_access() {
     // Access memory at widely separated addresses
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 42
 
@@ -234,8 +231,8 @@ fn test_sparse_memory_access() {
         sw r1, 0(r2)
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -301,7 +298,6 @@ fn test_repeated_multiplication() {
 #[test]
 fn test_all_arithmetic_ops() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 100
         addi r2, zero, 7
@@ -314,8 +310,8 @@ fn test_all_arithmetic_ops() {
         remu r7, r1, r2     # 2
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -334,7 +330,6 @@ fn test_all_arithmetic_ops() {
 fn test_many_branches() {
     // Program with many branch instructions
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let mut source = String::new();
 
     source.push_str("    addi r1, zero, 0\n");
@@ -346,8 +341,8 @@ fn test_many_branches() {
         source.push_str("    add r1, r1, r1\n"); // Should be skipped
     }
 
-    source.push_str("    addi t2, zero, 0\n");  // syscall: exit (R10)
-    source.push_str("    addi a0, zero, 0\n");  // exit code (R11)
+    source.push_str("    addi a0, zero, 0\n");  // syscall: exit (R10)
+    source.push_str("    addi a1, zero, 0\n");  // exit code (R11)
     source.push_str("    ecall\n");
 
     let program = assemble(&source).unwrap();
@@ -361,7 +356,6 @@ fn test_many_branches() {
 fn test_alternating_branches() {
     // Branches that alternate between taken and not taken
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 1
         addi r2, zero, 0
@@ -377,8 +371,8 @@ fn test_alternating_branches() {
         addi r3, r3, -1
         bne r3, zero, -16       # branch back to loop: (4 instructions * 4 bytes)
 
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -396,19 +390,18 @@ fn test_alternating_branches() {
 #[test]
 fn test_many_io_operations() {
     // Syscall convention: R10 = syscall number, R11 = first arg, return in R10
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         # Read 5 inputs and write them back
         addi r3, zero, 5    # count
 
     loop:
         # Read
-        addi t2, zero, 1        # syscall: read (R10)
+        addi a0, zero, 1        # syscall: read (R10)
         ecall
 
-        # Write (t2 now has the value from read)
-        addi a0, t2, 0          # a0 = value (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        # Write (a0 now has the value from read)
+        addi a1, a0, 0          # a1 = value (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
         # Decrement
@@ -416,8 +409,8 @@ fn test_many_io_operations() {
         bne r3, zero, -24       # branch back to loop: (6 instructions * 4 bytes)
 
         # Exit
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -436,19 +429,18 @@ fn test_many_io_operations() {
 #[test]
 fn test_division_by_one() {
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 12345
         addi r2, zero, 1
         divu r3, r1, r2
 
         # Write result
-        addi a0, r3, 0          # a0 = result (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        addi a1, r3, 0          # a1 = result (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -463,7 +455,6 @@ fn test_division_by_one() {
 fn test_self_modifying_registers() {
     // Operations where rd == rs1 or rd == rs2
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi r1, zero, 10
 
@@ -477,12 +468,12 @@ fn test_self_modifying_registers() {
         add r1, r1, r1      # 80
 
         # Write result
-        addi a0, r1, 0          # a0 = result (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        addi a1, r1, 0          # a1 = result (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -497,17 +488,16 @@ fn test_self_modifying_registers() {
 fn test_zero_register_destination() {
     // Writing to zero register should have no effect
     // Syscall convention: R10 = syscall number, R11 = first arg
-    // t2 maps to R10, a0 maps to R11 in the assembler ABI
     let source = r#"
         addi zero, zero, 100    # Should be ignored
 
         # Write zero (should still be 0)
-        addi a0, zero, 0        # a0 = 0 (R11)
-        addi t2, zero, 2        # syscall: write (R10)
+        addi a1, zero, 0        # a1 = 0 (R11)
+        addi a0, zero, 2        # syscall: write (R10)
         ecall
 
-        addi t2, zero, 0        # syscall: exit (R10)
-        addi a0, zero, 0        # exit code (R11)
+        addi a0, zero, 0        # syscall: exit (R10)
+        addi a1, zero, 0        # exit code (R11)
         ecall
     "#;
 
@@ -1,30 +1,957 @@
 //! Main assembler logic with label resolution and config directive support
 
-use zkir_spec::{Program, Instruction, Config, memory::CODE_BASE};
+use zkir_spec::{Program, Instruction, Config, Register, AbiVersion, Binding, ObjectFile, SectionKind, memory::CODE_BASE};
+use zkir_spec::encoding::{IMM_BITS, OFFSET_BITS};
+use zkir_spec::validation::MAX_SHIFT_AMOUNT;
 use crate::error::{Result, AssemblerError};
-use crate::parser::{parse_register, tokenize, extract_number};
-use crate::encoder::encode;
+use crate::diagnostic::Diagnostic;
+use crate::listing::{Listing, ListingLine, ListingSection, ListingSymbol};
+use crate::parser::{self, parse_register_with_abi, tokenize, tokenize_spanned, extract_number, Body};
+use crate::encoder::try_encode;
+use crate::expr::Expr;
+use crate::macros::{self, Macros};
+use crate::source::{self, LineSource, Location, SourceFile, SourceLine};
+use crate::pseudo;
 use crate::lexer::Token;
-use std::collections::HashMap;
+use crate::object;
+use crate::relax;
+use crate::lint;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Maximum nesting depth when constants refer to other constants
+pub(crate) const MAX_CONSTANT_DEPTH: usize = 64;
 
 /// Assembly item (parsed line)
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
-enum Item {
+pub(crate) enum Item {
     Label(String),
-    Instruction(Instruction),
+    Instruction {
+        line: usize,
+        instr: Instruction,
+        /// Symbolic operand patched in during the second pass
+        fixup: Option<Fixup>,
+        /// File and macro expansion the instruction came from
+        location: Location,
+        /// Part of a branch or jump rewritten to reach its target (see [`crate::relax`])
+        relaxed: bool,
+        /// Expanded from a pseudo-instruction or `.align` padding rather than written
+        pseudo: bool,
+    },
+    /// `.align` in `.text`: padded with `nop`s once branches are relaxed
+    Align { align: u64, line: usize, location: Location },
     ConfigDirective { key: String, value: u64 },
     Empty,
 }
 
+/// Operand expression that depends on label addresses, resolved in the second pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Fixup {
+    /// PC-relative offset to the address the expression evaluates to
+    PcRel(Expr),
+    /// Value of the expression, patched into the immediate field
+    Imm(Expr),
+}
+
+/// Instruction parsed from a single line, before label resolution
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Parsed {
+    pub(crate) instr: Instruction,
+    pub(crate) fixup: Option<Fixup>,
+}
+
+impl From<Instruction> for Parsed {
+    fn from(instr: Instruction) -> Self {
+        Self { instr, fixup: None }
+    }
+}
+
+/// Output section selected by `.text`, `.data`, `.rodata`, `.bss` or `.section`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Section {
+    Text,
+    Data,
+    Rodata,
+    Bss,
+}
+
+impl Section {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Section::Text),
+            "data" => Some(Section::Data),
+            "rodata" => Some(Section::Rodata),
+            "bss" => Some(Section::Bss),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn kind(self) -> SectionKind {
+        match self {
+            Section::Text => SectionKind::Text,
+            Section::Data => SectionKind::Data,
+            Section::Rodata => SectionKind::Rodata,
+            Section::Bss => SectionKind::Bss,
+        }
+    }
+}
+
+/// Label definition: owning section and byte offset within it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Symbol {
+    pub(crate) section: Section,
+    pub(crate) offset: u64,
+}
+
+/// Symbolic constant defined by `.equ` (fixed) or `.set` (redefinable)
+#[derive(Debug, Clone)]
+pub(crate) struct Constant {
+    pub(crate) expr: Expr,
+    redefinable: bool,
+}
+
+/// Open conditional block
+#[derive(Debug, Clone)]
+struct Conditional {
+    /// Directive that opened the block (`if`, `ifdef` or `ifndef`)
+    directive: String,
+    /// Whether the enclosing lines are assembled
+    enclosing: bool,
+    /// Whether the condition held
+    condition: bool,
+    /// Whether `.else` has been seen
+    in_else: bool,
+    line: usize,
+    location: Location,
+}
+
+impl Conditional {
+    /// Whether lines in the current branch are assembled
+    fn active(&self) -> bool {
+        self.enclosing && self.condition != self.in_else
+    }
+}
+
+/// Data value that depends on label addresses, patched once the layout is known
+#[derive(Debug, Clone)]
+pub(crate) struct DataFixup {
+    pub(crate) line: usize,
+    pub(crate) section: Section,
+    pub(crate) offset: usize,
+    pub(crate) width: usize,
+    pub(crate) expr: Expr,
+    pub(crate) location: Location,
+}
+
+impl DataFixup {
+    /// Range-check a resolved value against the directive width
+    pub(crate) fn check(&self, value: i64) -> Result<()> {
+        let directive = match self.width {
+            1 => "byte",
+            2 => "half",
+            4 => "word",
+            _ => "dword",
+        };
+        check_width(value, self.width, directive, &self.expr, self.line)
+    }
+}
+
+/// State accumulated by the first pass
+pub(crate) struct Unit {
+    pub(crate) items: Vec<Item>,
+    pub(crate) labels: HashMap<String, Symbol>,
+    pub(crate) constants: HashMap<String, Constant>,
+    /// Bindings set by `.global`/`.globl` and `.local`
+    pub(crate) bindings: HashMap<String, Binding>,
+    /// Names declared by `.global` or `.extern`, in order; those never
+    /// defined are imported from other objects
+    pub(crate) imports: Vec<String>,
+    /// Symbol named by `.entry`, with the directive's line and location
+    pub(crate) entry: Option<(String, usize, Location)>,
+    macros: Macros,
+    pub(crate) config: Config,
+    section: Section,
+    /// Byte offset of the next instruction in `.text`
+    pub(crate) pc: u64,
+    pub(crate) data: Vec<u8>,
+    pub(crate) rodata: Vec<u8>,
+    pub(crate) bss_size: u64,
+    /// Largest alignment requested in `.data`, `.rodata` and `.bss`
+    pub(crate) data_align: u64,
+    pub(crate) rodata_align: u64,
+    pub(crate) bss_align: u64,
+    pub(crate) fixups: Vec<DataFixup>,
+    /// Register naming selected by `.abi` (`None` until a source picks one)
+    abi: Option<AbiVersion>,
+    /// Number of definitions so far of each numeric local label
+    local_labels: HashMap<u64, usize>,
+    /// Open `.if`/`.ifdef`/`.ifndef` blocks, innermost last
+    conditionals: Vec<Conditional>,
+    /// Errors on lines skipped so far
+    pub(crate) errors: Vec<AssemblerError>,
+    /// Warnings on lines assembled so far
+    pub(crate) warnings: Vec<AssemblerError>,
+}
+
+impl Unit {
+    fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            bindings: HashMap::new(),
+            imports: Vec::new(),
+            entry: None,
+            macros: Macros::default(),
+            config: Config::DEFAULT,
+            section: Section::Text,
+            pc: 0,
+            data: Vec::new(),
+            rodata: Vec::new(),
+            bss_size: 0,
+            data_align: 1,
+            rodata_align: 1,
+            bss_align: 1,
+            fixups: Vec::new(),
+            abi: None,
+            local_labels: HashMap::new(),
+            conditionals: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Operand parsing context for a line
+    fn context(&self, line_num: usize) -> Context<'_> {
+        Context {
+            config: &self.config,
+            constants: &self.constants,
+            abi: self.abi.unwrap_or(AbiVersion::CURRENT),
+            line_num,
+        }
+    }
+
+    /// Warn about ABI register names that mean different registers in ABI v1 and v2
+    ///
+    /// Under `.abi 1` every such name is reported with its ABI v2 spelling.
+    /// Without an `.abi` directive the names follow ABI v2, and the first
+    /// one is reported so that sources written for ABI v1 are not silently
+    /// assembled to different registers.
+    fn check_register_names(&mut self, tokens: &[Token], line: usize, location: &Location) {
+        for token in tokens {
+            let Token::Register(name) = token else { continue };
+            let name = name.to_lowercase();
+            let (Some(v1), v2) = (AbiVersion::V1.register(&name), AbiVersion::V2.register(&name)) else {
+                continue;
+            };
+            if Some(v1) == v2 {
+                continue;
+            }
+            let message = match (self.abi, v2) {
+                (Some(AbiVersion::V1), _) => {
+                    format!("{} is {} under ABI v1, which is deprecated; write {} for ABI v2", name, v1.numeric_name(), v1.name())
+                }
+                (None, Some(v2)) if !self.warnings.iter().any(is_abi_warning) => format!(
+                    "{} is {} under ABI v2 ({} under ABI v1); add `.abi 2` to confirm or `.abi 1` to keep the old names",
+                    name,
+                    v2.numeric_name(),
+                    v1.numeric_name()
+                ),
+                _ => continue,
+            };
+            let warning = AssemblerError::RegisterName { line, register: name, message };
+            self.warnings.push(location.wrap(warning));
+        }
+    }
+
+    /// Current location counter of the active section
+    fn offset(&self) -> u64 {
+        match self.section {
+            Section::Text => self.pc,
+            Section::Data => self.data.len() as u64,
+            Section::Rodata => self.rodata.len() as u64,
+            Section::Bss => self.bss_size,
+        }
+    }
+
+    /// Record that the active section needs at least `align` byte alignment
+    fn require_align(&mut self, align: u64) {
+        let slot = match self.section {
+            Section::Text => return,
+            Section::Data => &mut self.data_align,
+            Section::Rodata => &mut self.rodata_align,
+            Section::Bss => &mut self.bss_align,
+        };
+        *slot = (*slot).max(align);
+    }
+
+    /// Initialized bytes of the active section (`.data` or `.rodata`)
+    fn bytes_mut(&mut self, directive: &str, line: usize) -> Result<&mut Vec<u8>> {
+        match self.section {
+            Section::Data => Ok(&mut self.data),
+            Section::Rodata => Ok(&mut self.rodata),
+            Section::Text | Section::Bss => Err(AssemblerError::SyntaxError {
+                line,
+                message: format!(".{} is not allowed in the {} section", directive, self.section_name()),
+            }),
+        }
+    }
+
+    fn section_name(&self) -> &'static str {
+        match self.section {
+            Section::Text => ".text",
+            Section::Data => ".data",
+            Section::Rodata => ".rodata",
+            Section::Bss => ".bss",
+        }
+    }
+
+    /// Apply `.global`/`.globl`, `.local` or `.extern` to `name`
+    ///
+    /// `.global` exports a label, or imports the name if this unit never
+    /// defines it; `.extern` only allows it to be undefined. A name cannot
+    /// be both `.local` and `.global`/`.extern`, and constants have no
+    /// address to export.
+    fn declare(&mut self, name: &str, directive: &str, line: usize) -> Result<()> {
+        let error = |message: String| Err(AssemblerError::SyntaxError { line, message });
+        if self.constants.contains_key(name) {
+            return error(format!("Constant {} cannot be declared .{}", name, directive));
+        }
+
+        let local = directive == "local";
+        let imported = self.imports.iter().any(|n| n == name);
+        let conflict = if local { imported } else { self.bindings.get(name) == Some(&Binding::Local) };
+        if conflict {
+            return error(format!("Symbol {} cannot be both .local and .global/.extern", name));
+        }
+
+        match directive {
+            "local" => {
+                self.bindings.insert(name.to_string(), Binding::Local);
+            }
+            "extern" => {}
+            _ => {
+                self.bindings.insert(name.to_string(), Binding::Global);
+            }
+        }
+        if !local && !imported {
+            self.imports.push(name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Label named by `.entry`, which must be in `.text`
+    ///
+    /// `None` if there is no `.entry` or it names something other than a
+    /// label (objects may import it); errors are wrapped in its location.
+    pub(crate) fn entry_label(&self) -> Result<Option<&Symbol>> {
+        let Some((name, line, location)) = &self.entry else {
+            return Ok(None);
+        };
+        match self.labels.get(name) {
+            Some(symbol) if symbol.section != Section::Text => Err(location.wrap(AssemblerError::SyntaxError {
+                line: *line,
+                message: format!("Entry point {} is not in .text", name),
+            })),
+            symbol => Ok(symbol),
+        }
+    }
+
+    /// Address execution starts at
+    ///
+    /// The `.entry` label if there is one, otherwise `_start` if it is a
+    /// `.global` label in `.text`, otherwise the first instruction.
+    fn entry_point(&self, layout: &Layout) -> Result<u64> {
+        if let Some((name, line, location)) = &self.entry {
+            let symbol = self.entry_label()?.ok_or_else(|| {
+                location.wrap(AssemblerError::UndefinedLabel {
+                    line: *line,
+                    label: name.clone(),
+                })
+            })?;
+            return Ok(layout.address(symbol));
+        }
+
+        let start = self
+            .labels
+            .get("_start")
+            .filter(|symbol| symbol.section == Section::Text)
+            .filter(|_| self.bindings.get("_start") == Some(&Binding::Global));
+        Ok(start.map_or(layout.text, |symbol| layout.address(symbol)))
+    }
+
+    /// Define a label at the current location of the active section
+    fn define_label(&mut self, name: String) {
+        let symbol = Symbol {
+            section: self.section,
+            offset: self.offset(),
+        };
+        self.labels.insert(name.clone(), symbol);
+        self.items.push(Item::Label(name));
+    }
+
+    /// Define the next instance of numeric local label `number`
+    fn define_local_label(&mut self, number: u64) {
+        let count = self.local_labels.entry(number).or_insert(0);
+        *count += 1;
+        let name = local_label_name(number, *count);
+        self.define_label(name);
+    }
+
+    /// Replace numeric local label references (`1b`, `1f`) with the names
+    /// of the definitions they refer to
+    fn resolve_local_labels(&self, tokens: &[Token], line: usize) -> Result<Vec<Token>> {
+        tokens
+            .iter()
+            .map(|token| {
+                let Token::LocalLabel(reference) = token else {
+                    return Ok(token.clone());
+                };
+                let (digits, direction) = reference.split_at(reference.len() - 1);
+                let number = digits.parse::<u64>().map_err(|_| AssemblerError::SyntaxError {
+                    line,
+                    message: format!("Invalid local label `{}`", reference),
+                })?;
+                let defined = self.local_labels.get(&number).copied().unwrap_or(0);
+                match direction {
+                    "f" => Ok(Token::Identifier(local_label_name(number, defined + 1))),
+                    _ if defined == 0 => Err(AssemblerError::UndefinedLabel {
+                        line,
+                        label: reference.clone(),
+                    }),
+                    _ => Ok(Token::Identifier(local_label_name(number, defined))),
+                }
+            })
+            .collect()
+    }
+
+    /// Check that `name` is not already a label or constant
+    fn check_unique(&self, name: &str, line: usize) -> Result<()> {
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(AssemblerError::SyntaxError {
+                line,
+                message: format!("Duplicate label: {}", name),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether lines are assembled (not inside a false conditional branch)
+    fn is_assembling(&self) -> bool {
+        self.conditionals.iter().all(Conditional::active)
+    }
+
+    /// Handle `.if`, `.ifdef`, `.ifndef`, `.else` and `.endif`
+    ///
+    /// Conditions are only evaluated where the enclosing lines are assembled.
+    fn conditional(&mut self, directive: &str, args: &str, source_line: &SourceLine) -> Result<()> {
+        let line = source_line.line;
+        let unmatched = || AssemblerError::SyntaxError {
+            line,
+            message: format!(".{} without matching .if", directive),
+        };
+
+        match directive {
+            "else" => {
+                let open = self.conditionals.last_mut().ok_or_else(unmatched)?;
+                if open.in_else {
+                    return Err(AssemblerError::SyntaxError {
+                        line,
+                        message: format!("Second .else for the .{} at line {}", open.directive, open.line),
+                    });
+                }
+                open.in_else = true;
+            }
+            "endif" => {
+                self.conditionals.pop().ok_or_else(unmatched)?;
+            }
+            _ => {
+                let condition = if self.is_assembling() {
+                    self.condition(directive, args, line)
+                } else {
+                    Ok(false)
+                };
+                // A bad condition still opens the block, skipping both branches
+                self.conditionals.push(Conditional {
+                    directive: directive.to_string(),
+                    enclosing: self.is_assembling() && condition.is_ok(),
+                    condition: condition.as_ref().is_ok_and(|condition| *condition),
+                    in_else: false,
+                    line,
+                    location: source_line.location.clone(),
+                });
+                condition?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the condition of `.if expr`, `.ifdef name` or `.ifndef name`
+    fn condition(&self, directive: &str, args: &str, line: usize) -> Result<bool> {
+        let tokens = tokenize(args).map_err(|e| e.at_line(line))?;
+        if directive == "if" {
+            let ctx = self.context(line - 1);
+            let expr = ctx.expression(&tokens)?;
+            // Config fields are visible unless shadowed by a constant
+            let expr = expr.substitute(&|name| {
+                config_symbol(&self.config, name)
+                    .filter(|_| !self.constants.contains_key(name))
+                    .map(Expr::Number)
+            });
+            return Ok(ctx.constant(&expr)? != 0);
+        }
+
+        let [Token::Identifier(name)] = tokens.as_slice() else {
+            return Err(AssemblerError::SyntaxError {
+                line,
+                message: format!(".{} requires 1 argument: symbol", directive),
+            });
+        };
+        let defined = self.constants.contains_key(name) || self.labels.contains_key(name);
+        Ok(defined == (directive == "ifdef"))
+    }
+}
+
+/// Value of a `Config` field visible to `.if` (`DATA_BITS` and so on)
+fn config_symbol(config: &Config, name: &str) -> Option<i64> {
+    let value = match name {
+        "LIMB_BITS" => config.limb_bits.into(),
+        "DATA_LIMBS" => config.data_limbs.into(),
+        "ADDR_LIMBS" => config.addr_limbs.into(),
+        "DATA_BITS" => config.data_bits(),
+        "ADDR_BITS" => config.addr_bits(),
+        _ => return None,
+    };
+    Some(i64::from(value))
+}
+
+/// Runtime addresses of each section
+///
+/// The runtime loads `Program.data` directly after the code, so `.data` and
+/// `.rodata` follow `.text` (padded to their alignment) and `.bss` follows
+/// the initialized data.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    text: u64,
+    data: u64,
+    rodata: u64,
+    bss: u64,
+    /// Address where `Program.data` is loaded (end of code)
+    data_start: u64,
+    /// End of the initialized data image
+    data_end: u64,
+}
+
+impl Layout {
+    fn compute(unit: &Unit) -> Self {
+        let text = CODE_BASE;
+        let data_start = text + unit.pc;
+        let data = place(data_start, unit.data_align, unit.data.len() as u64);
+        let rodata = place(data + unit.data.len() as u64, unit.rodata_align, unit.rodata.len() as u64);
+        let data_end = rodata + unit.rodata.len() as u64;
+        let bss = place(data_end, unit.bss_align, unit.bss_size);
+
+        Self { text, data, rodata, bss, data_start, data_end }
+    }
+
+    /// Absolute address of a label
+    fn address(&self, symbol: &Symbol) -> u64 {
+        let base = match symbol.section {
+            Section::Text => self.text,
+            Section::Data => self.data,
+            Section::Rodata => self.rodata,
+            Section::Bss => self.bss,
+        };
+        base + symbol.offset
+    }
+}
+
+/// Round `value` up to a multiple of `align`
+pub(crate) fn align_up(value: u64, align: u64) -> u64 {
+    value.div_ceil(align) * align
+}
+
+/// Start address of a section placed at `addr`; empty sections take no padding
+fn place(addr: u64, align: u64, size: u64) -> u64 {
+    if size == 0 {
+        addr
+    } else {
+        align_up(addr, align)
+    }
+}
+
+/// Symbol values visible to expression evaluation
+///
+/// Constants are always visible; label addresses only once the layout is
+/// known (second pass).
+struct Scope<'a> {
+    constants: &'a HashMap<String, Constant>,
+    labels: Option<(&'a HashMap<String, Symbol>, &'a Layout)>,
+}
+
+impl Scope<'_> {
+    fn eval(&self, expr: &Expr, line: usize) -> Result<i64> {
+        self.eval_nested(expr, line, 0)
+    }
+
+    fn eval_nested(&self, expr: &Expr, line: usize, depth: usize) -> Result<i64> {
+        expr.eval(line, &|name| self.lookup(name, line, depth))
+    }
+
+    fn lookup(&self, name: &str, line: usize, depth: usize) -> Result<i64> {
+        if let Some(constant) = self.constants.get(name) {
+            if depth >= MAX_CONSTANT_DEPTH {
+                return Err(AssemblerError::InvalidExpression {
+                    line,
+                    expr: name.to_string(),
+                    message: "recursive constant definition".to_string(),
+                });
+            }
+            return self.eval_nested(&constant.expr, line, depth + 1);
+        }
+
+        self.labels
+            .and_then(|(labels, layout)| labels.get(name).map(|symbol| layout.address(symbol) as i64))
+            .ok_or_else(|| AssemblerError::UndefinedLabel {
+                line,
+                label: written_label(name),
+            })
+    }
+
+    /// Check whether `expr` only refers to constants (no label addresses)
+    fn is_absolute(&self, expr: &Expr, depth: usize) -> bool {
+        depth < MAX_CONSTANT_DEPTH
+            && expr.symbols().iter().all(|name| {
+                self.constants
+                    .get(*name)
+                    .is_some_and(|constant| self.is_absolute(&constant.expr, depth + 1))
+            })
+    }
+}
+
+/// Assembler options
+///
+/// `.include` and `.incbin` look for files relative to the including file
+/// first, then in each include path in order. Each define is a fixed
+/// constant, as if set by `.equ` before the first line.
+#[derive(Debug, Clone, Default)]
+pub struct AssemblerOptions {
+    /// Directories searched for included files
+    pub include_paths: Vec<PathBuf>,
+    /// Constants predefined for the source, for example to select `.if` branches
+    pub defines: BTreeMap<String, i64>,
+    /// Report warnings, including the static checks run over assembled code, as errors
+    pub deny_warnings: bool,
+}
+
+/// Assembler with include search paths and predefined constants
+///
+/// # Example
+/// ```no_run
+/// use zkir_assembler::{Assembler, AssemblerOptions};
+///
+/// let program = Assembler::with_include_paths(["lib", "vendor/zkir"])
+///     .assemble_file("src/main.zkasm")
+///     .unwrap();
+///
+/// let mut options = AssemblerOptions::default();
+/// options.defines.insert("DEBUG".to_string(), 1);
+/// let program = Assembler::with_options(options).assemble_file("src/main.zkasm").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    options: AssemblerOptions,
+}
+
+impl Assembler {
+    /// Create an assembler without include search paths or defines
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an assembler with `options`
+    pub fn with_options(options: AssemblerOptions) -> Self {
+        Self { options }
+    }
+
+    /// Create an assembler that searches `paths` for included files
+    pub fn with_include_paths<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self::with_options(AssemblerOptions {
+            include_paths: paths.into_iter().map(Into::into).collect(),
+            ..AssemblerOptions::default()
+        })
+    }
+
+    /// Options used for every source
+    pub fn options(&self) -> &AssemblerOptions {
+        &self.options
+    }
+
+    /// Directories searched for included files
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.options.include_paths
+    }
+
+    /// Assemble in-memory source; see [`assemble`]
+    ///
+    /// If several lines are bad, the error is [`AssemblerError::Multiple`].
+    pub fn assemble(&self, source: &str) -> Result<Program> {
+        self.assemble_lines(&mut LineSource::new(source, None), &mut Vec::new())
+            .map(|assembled| assembled.program)
+            .map_err(AssemblerError::from_errors)
+    }
+
+    /// Assemble a source file
+    ///
+    /// Errors are wrapped in [`AssemblerError::InFile`] naming the file
+    /// (and, for included files, the include site).
+    pub fn assemble_file(&self, path: impl AsRef<Path>) -> Result<Program> {
+        let (file, source) = SourceFile::read(path.as_ref())?;
+        self.assemble_lines(&mut LineSource::new(&source, Some(file)), &mut Vec::new())
+            .map(|assembled| assembled.program)
+            .map_err(AssemblerError::from_errors)
+    }
+
+    /// Assemble in-memory source, also returning a [`Listing`]
+    ///
+    /// # Example
+    /// ```
+    /// use zkir_assembler::Assembler;
+    ///
+    /// let (program, listing) = Assembler::new().assemble_with_listing("main:\n    li a1, 10\n    ecall").unwrap();
+    /// assert_eq!(listing.lines.len(), program.code.len());
+    /// assert_eq!(listing.lines[0].source, "    li a1, 10");
+    /// println!("{}", listing);
+    /// ```
+    pub fn assemble_with_listing(&self, source: &str) -> Result<(Program, Listing)> {
+        let mut lines = LineSource::new(source, None);
+        let assembled = self.assemble_lines(&mut lines, &mut Vec::new()).map_err(AssemblerError::from_errors)?;
+        Ok(assembled.with_listing(&lines))
+    }
+
+    /// Assemble a source file, also returning a [`Listing`]
+    pub fn assemble_file_with_listing(&self, path: impl AsRef<Path>) -> Result<(Program, Listing)> {
+        let (file, source) = SourceFile::read(path.as_ref())?;
+        let mut lines = LineSource::new(&source, Some(file));
+        let assembled = self.assemble_lines(&mut lines, &mut Vec::new()).map_err(AssemblerError::from_errors)?;
+        Ok(assembled.with_listing(&lines))
+    }
+
+    /// Assemble in-memory source, reporting every error as a [`Diagnostic`]
+    ///
+    /// The program is only returned if there are no errors.
+    pub fn assemble_with_diagnostics(&self, source: &str) -> (Option<Program>, Vec<Diagnostic>) {
+        let mut lines = LineSource::new(source, None);
+        let mut warnings = Vec::new();
+        let result = self.assemble_lines(&mut lines, &mut warnings).map(|assembled| assembled.program);
+        report(result, &warnings, &lines)
+    }
+
+    /// Assemble a source file, reporting every error as a [`Diagnostic`]
+    pub fn assemble_file_with_diagnostics(&self, path: impl AsRef<Path>) -> (Option<Program>, Vec<Diagnostic>) {
+        match SourceFile::read(path.as_ref()) {
+            Ok((file, source)) => {
+                let mut lines = LineSource::new(&source, Some(file));
+                let mut warnings = Vec::new();
+                let result = self.assemble_lines(&mut lines, &mut warnings).map(|assembled| assembled.program);
+                report(result, &warnings, &lines)
+            }
+            Err(err) => (None, vec![Diagnostic::from_error(&err, |_, _| None)]),
+        }
+    }
+
+    /// Assemble in-memory source into a relocatable object; see [`assemble_object`]
+    pub fn assemble_object(&self, source: &str) -> Result<ObjectFile> {
+        let unit = first_pass(self, &mut LineSource::new(source, None));
+        object::build(unit).map_err(AssemblerError::from_errors)
+    }
+
+    /// Assemble a source file into a relocatable object
+    pub fn assemble_object_file(&self, path: impl AsRef<Path>) -> Result<ObjectFile> {
+        let (file, source) = SourceFile::read(path.as_ref())?;
+        let unit = first_pass(self, &mut LineSource::new(&source, Some(file)));
+        object::build(unit).map_err(AssemblerError::from_errors)
+    }
+
+    /// Run both passes, collecting errors line by line (and warnings into `warnings`)
+    fn assemble_lines(
+        &self,
+        lines: &mut LineSource,
+        warnings: &mut Vec<AssemblerError>,
+    ) -> std::result::Result<Assembled, Vec<AssemblerError>> {
+        // First pass: parse all lines and collect config/labels/data
+        let mut unit = first_pass(self, lines);
+        relax::relax(&mut unit, &|unit, target, pc| {
+            let layout = Layout::compute(unit);
+            let scope = Scope {
+                constants: &unit.constants,
+                labels: Some((&unit.labels, &layout)),
+            };
+            scope.eval(target, 0).ok().map(|address| address - (layout.text + pc) as i64)
+        });
+        warnings.append(&mut unit.warnings);
+        let layout = Layout::compute(&unit);
+        let scope = Scope {
+            constants: &unit.constants,
+            labels: Some((&unit.labels, &layout)),
+        };
+
+        // Second pass: encode instructions with resolved labels
+        let mut errors = std::mem::take(&mut unit.errors);
+        let (instructions, code) = second_pass(&unit.items, &scope, &layout, &unit.config, &mut errors);
+        let data = emit_data(&unit, &scope, &layout, &mut errors);
+        let entry_point = unit.entry_point(&layout).unwrap_or_else(|err| {
+            errors.push(err);
+            layout.text
+        });
+        if errors.is_empty() {
+            let (mut lint_errors, mut lint_warnings) = lint::check(&unit.items, &instructions, &unit.config);
+            errors.append(&mut lint_errors);
+            warnings.append(&mut lint_warnings);
+        }
+        if self.options.deny_warnings {
+            errors.append(warnings);
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Create program with configuration
+        let mut program = Program::with_config(unit.config)
+            .map_err(|e| vec![AssemblerError::SpecError(e.into())])?;
+        program.code = code;
+        program.data = data;
+        program.header.code_size = (program.code.len() * 4) as u32;
+        program.header.data_size = program.data.len() as u32;
+        program.header.bss_size = (layout.bss + unit.bss_size - layout.data_end) as u32;
+        program.header.entry_point = entry_point as u32;
+
+        Ok(Assembled { program, instructions, unit, layout })
+    }
+}
+
+/// Program with the unit and layout it was built from
+struct Assembled {
+    program: Program,
+    /// Resolved instructions, one per code word
+    instructions: Vec<Instruction>,
+    unit: Unit,
+    layout: Layout,
+}
+
+impl Assembled {
+    /// Map every code word back to its source line
+    fn with_listing(self, lines: &LineSource) -> (Program, Listing) {
+        let Assembled { program, instructions, unit, layout } = self;
+
+        let sources = unit.items.iter().filter_map(|item| match item {
+            Item::Instruction { line, location, relaxed, .. } => Some((*line, location, *relaxed)),
+            _ => None,
+        });
+        let listing_lines = sources
+            .zip(instructions.into_iter().zip(&program.code))
+            .enumerate()
+            .map(|(i, ((line, location, relaxed), (instruction, &word)))| {
+                let file = location.file_name();
+                ListingLine {
+                    address: layout.text + i as u64 * 4,
+                    word,
+                    instruction,
+                    source: lines.source_line(file.as_deref(), line).unwrap_or_default().to_string(),
+                    file,
+                    line,
+                    relaxed,
+                }
+            })
+            .collect();
+
+        let sections = [
+            (Section::Text, layout.text, unit.pc),
+            (Section::Data, layout.data, unit.data.len() as u64),
+            (Section::Rodata, layout.rodata, unit.rodata.len() as u64),
+            (Section::Bss, layout.bss, unit.bss_size),
+        ]
+        .into_iter()
+        .map(|(section, address, size)| ListingSection { section: section.kind(), address, size })
+        .collect();
+
+        let mut symbols: Vec<_> = unit
+            .labels
+            .iter()
+            .map(|(name, symbol)| ListingSymbol {
+                name: name.clone(),
+                section: symbol.section.kind(),
+                address: layout.address(symbol),
+            })
+            .collect();
+        symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+
+        let listing = Listing { lines: listing_lines, sections, symbols };
+        (program, listing)
+    }
+}
+
+/// Resolve the errors of an assembly run against its source
+fn report(
+    result: std::result::Result<Program, Vec<AssemblerError>>,
+    warnings: &[AssemblerError],
+    lines: &LineSource,
+) -> (Option<Program>, Vec<Diagnostic>) {
+    let source_line = |file: Option<&str>, line| lines.source_line(file, line);
+    let mut diagnostics: Vec<_> = warnings.iter().map(|warning| Diagnostic::from_warning(warning, source_line)).collect();
+    match result {
+        Ok(program) => (Some(program), diagnostics),
+        Err(errors) => {
+            diagnostics.extend(errors.iter().map(|err| Diagnostic::from_error(err, source_line)));
+            (None, diagnostics)
+        }
+    }
+}
+
 /// Assemble source code into a program
 ///
 /// Supports:
 /// - All v3.4 instructions
 /// - `.config` directives for limb configuration
-/// - Labels for branch/jump targets
+/// - Labels for branch/jump targets (resolved to PC-relative offsets)
+/// - Numeric local labels (`1:`) that may be redefined, referenced as `1b`
+///   (nearest definition backward) or `1f` (nearest forward)
+/// - Pseudo-instructions (`li`, `la`, `mv`, `j`, `call`, `ret`, ...; see [`crate::pseudo`])
+/// - Sections (`.text`, `.data`, `.rodata`, `.bss`, `.section <name>`)
+/// - Data directives (`.byte`, `.half`, `.word`, `.dword`, `.ascii`, `.asciz`,
+///   `.space`, `.align`)
+/// - Constants (`.equ NAME, expr` and redefinable `.set NAME, expr`) and
+///   expressions in immediates, offsets and data (see [`crate::expr`])
+/// - Macros (`.macro`/`.endm`, `.rept`/`.irp`/`.endr`; see [`crate::macros`])
+/// - Conditional assembly (`.if expr`, `.ifdef name`, `.ifndef name`,
+///   `.else`, `.endif`), which may nest; `.if` also sees the active config
+///   as `LIMB_BITS`, `DATA_LIMBS`, `ADDR_LIMBS`, `DATA_BITS` and `ADDR_BITS`
+///   (see [`AssemblerOptions`] for defines)
+/// - `.include "file"` and `.incbin "file"[, skip[, count]]`, resolved
+///   relative to the working directory (see [`Assembler`] for search paths)
+/// - Symbol bindings (`.global`/`.globl`, `.local`, `.extern`), used when
+///   assembling objects (see [`assemble_object`])
+/// - Entry point selection: `.entry symbol`, or a `.global _start` label;
+///   without either, execution starts at the first instruction
+/// - Register names `r0`-`r15` and the ABI names of [`zkir_spec::ABI_NAMES`];
+///   `.abi 1` selects the deprecated pre-v2 names (see [`zkir_spec::AbiVersion`])
 /// - Comments (# style)
 ///
+/// Data labels resolve to the addresses the runtime loads them at: `.data`
+/// and `.rodata` directly after the code, `.bss` after the initialized data.
+/// Branches and jumps to labels out of their range are rewritten into
+/// longer sequences, marked `[relaxed]` in listings; far jumps that clobber
+/// `t1` are warned about.
+/// The assembled code is then checked with
+/// [`zkir_spec::validation::validate_program`] and config-aware lints;
+/// warnings are reported by [`Assembler::assemble_with_diagnostics`], or
+/// fail assembly with [`AssemblerOptions::deny_warnings`].
+///
 /// # Example
 /// ```
 /// use zkir_assembler::assemble;
@@ -32,291 +959,1006 @@ enum Item {
 /// let source = r#"
 ///     .config limb_bits 20
 ///     .config data_limbs 2
+///     .equ COUNT, 4
 ///
 /// main:
 ///     add a0, zero, zero
 ///     ecall
+///
+///     .data
+/// table:
+///     .word 1, 2, 3, COUNT
 /// "#;
 ///
 /// let program = assemble(source).unwrap();
+/// assert_eq!(program.data.len(), 16);
 /// ```
 pub fn assemble(source: &str) -> Result<Program> {
-    // First pass: parse all lines and collect config/labels
-    let (items, labels, config) = first_pass(source)?;
+    Assembler::new().assemble(source)
+}
+
+/// Assemble a source file, resolving includes relative to it
+pub fn assemble_file(path: impl AsRef<Path>) -> Result<Program> {
+    Assembler::new().assemble_file(path)
+}
+
+/// Assemble source code into a relocatable object file
+///
+/// Sections are assembled as if they started at address 0. References to
+/// labels and to symbols declared `.extern` (or `.global` but not defined)
+/// are left for the linker as relocation records; they must have the form
+/// `symbol + constant`, or `%hi`/`%lo` of that in immediates. Labels are
+/// local to the object unless declared `.global`.
+///
+/// # Example
+/// ```
+/// use zkir_assembler::assemble_object;
+///
+/// let object = assemble_object(r#"
+///     .global main
+///     .extern helper
+/// main:
+///     call helper
+///     ecall
+/// "#).unwrap();
+/// assert!(object.symbol("helper").unwrap().is_undefined());
+/// ```
+pub fn assemble_object(source: &str) -> Result<ObjectFile> {
+    Assembler::new().assemble_object(source)
+}
+
+/// First pass: expand macros, parse lines and collect config/labels/data
+///
+/// A bad line is recorded in `Unit::errors` and skipped, so one run reports
+/// every bad line.
+fn first_pass(assembler: &Assembler, lines: &mut LineSource) -> Unit {
+    let mut unit = Unit::new();
+    for (name, &value) in &assembler.options.defines {
+        if is_valid_label(name) {
+            unit.constants.insert(name.clone(), Constant { expr: Expr::Number(value), redefinable: false });
+        } else {
+            unit.errors.push(AssemblerError::Other(format!("Invalid define name: {}", name)));
+        }
+    }
+
+    while let Some(line) = lines.next_line() {
+        let location = line.location.clone();
+        if let Err(err) = parse_line(assembler, &mut unit, lines, line) {
+            unit.errors.push(location.wrap(err));
+        }
+    }
+
+    for open in std::mem::take(&mut unit.conditionals) {
+        unit.errors.push(open.location.wrap(AssemblerError::SyntaxError {
+            line: open.line,
+            message: format!(".{} without matching .endif", open.directive),
+        }));
+    }
+
+    unit
+}
+
+/// Parse one line, expanding includes, macro blocks and invocations into `lines`
+fn parse_line(assembler: &Assembler, unit: &mut Unit, lines: &mut LineSource, source_line: SourceLine) -> Result<()> {
+    let line_num = source_line.line - 1;
+    let line_text = source_line.text.as_str();
+
+    // Conditionals are tracked even inside branches that are skipped
+    let directive = source::directive_name(line_text);
+    if let Some(directive @ ("if" | "ifdef" | "ifndef" | "else" | "endif")) = directive {
+        return unit.conditional(directive, &line_text[directive.len() + 1..], &source_line);
+    }
+    if !unit.is_assembling() {
+        return Ok(());
+    }
+
+    if line_text.is_empty() {
+        unit.items.push(Item::Empty);
+        return Ok(());
+    }
+
+    // Block directives work on raw text: bodies are tokenized once expanded
+    if let Some(directive) = directive {
+        let args = &line_text[directive.len() + 1..];
+        match directive {
+            "macro" => {
+                let body = lines.collect_block(&source_line, &["macro"], "endm")?;
+                return unit.macros.define(args, body, source_line.line);
+            }
+            "rept" => {
+                let body = lines.collect_block(&source_line, &["rept", "irp"], "endr")?;
+                let count = extract_count(unit, &tokenize(args).map_err(|e| e.at_line(line_num + 1))?, directive, line_num)?;
+                let repeated = (0..count).flat_map(|_| body.iter().cloned()).collect();
+                lines.push_front(repeated);
+                return Ok(());
+            }
+            "irp" => {
+                let body = lines.collect_block(&source_line, &["rept", "irp"], "endr")?;
+                lines.push_front(macros::expand_irp(args, &body, source_line.line)?);
+                return Ok(());
+            }
+            "endm" | "endr" => {
+                return Err(AssemblerError::SyntaxError {
+                    line: source_line.line,
+                    message: format!(".{} without matching block start", directive),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // Tokenize the line and split it the way `parser::parse` does
+    let spanned = tokenize_spanned(line_text).map_err(|e| e.at_line(line_num + 1))?;
+    if spanned.is_empty() {
+        unit.items.push(Item::Empty);
+        return Ok(());
+    }
+    let split = parser::split(&spanned);
+
+    if let Some(label) = split.label {
+        match &label[0].0 {
+            Token::Identifier(name) => {
+                // Validate label name
+                if !is_valid_label(name) {
+                    return Err(AssemblerError::SyntaxError {
+                        line: line_num + 1,
+                        message: format!("Invalid label name: {}", name),
+                    });
+                }
 
-    // Second pass: encode instructions with resolved labels
-    let code = second_pass(&items, &labels)?;
+                // Check for duplicate
+                unit.check_unique(name, line_num + 1)?;
+                unit.define_label(name.clone());
+            }
+            // Numeric local labels may be redefined
+            Token::Number(number) => match u64::try_from(*number) {
+                Ok(number) => unit.define_local_label(number),
+                Err(_) => {
+                    return Err(AssemblerError::SyntaxError {
+                        line: line_num + 1,
+                        message: format!("Invalid label name: {}", number),
+                    })
+                }
+            },
+            _ => unreachable!("split only accepts identifier and number labels"),
+        }
+    }
 
-    // Create program with configuration
-    let mut program = Program::with_config(config)
-        .map_err(|e| AssemblerError::SpecError(e.into()))?;
-    program.code = code;
-    program.header.code_size = (program.code.len() * 4) as u32;
+    // Anything after the label is handled like a line of its own
+    let (name, args) = match split.body {
+        Body::Empty => return Ok(()),
+        Body::Invalid(token) => return Err(Body::invalid(token, line_num + 1)),
+        Body::Directive(name, args) | Body::Instruction(name, args) => (name, args),
+    };
+    let mut tokens: Vec<Token> = split.rest.iter().map(|(token, _)| token.clone()).collect();
+    if args.iter().any(|(token, _)| matches!(token, Token::LocalLabel(_))) {
+        tokens = unit.resolve_local_labels(&tokens, line_num + 1)?;
+    }
 
-    Ok(program)
+    match split.body {
+        // Macro invocation
+        Body::Instruction(_, _) if unit.macros.contains(name) => {
+            let args = &line_text[split.rest[0].1.end..];
+            let expansion = unit.macros.invoke(name, args, &source_line)?;
+            lines.push_front(expansion);
+            return Ok(());
+        }
+        Body::Directive(directive, _) => {
+            let args = &tokens[1..];
+            return match directive {
+                "include" => {
+                    let name = include_name(args, directive, line_num)?;
+                    lines.include(&name, &source_line, &assembler.options.include_paths)
+                }
+                "incbin" => include_binary(assembler, unit, args, &source_line),
+                _ => parse_directive(unit, directive, args, line_num, &source_line.location),
+            };
+        }
+        _ => {}
+    }
+
+    if unit.section != Section::Text {
+        return Err(AssemblerError::SyntaxError {
+            line: line_num + 1,
+            message: format!("Instruction outside of .text section ({})", unit.section_name()),
+        });
+    }
+
+    // Parse as instruction; pseudo-instructions may expand to several
+    unit.check_register_names(&tokens, line_num + 1, &source_line.location);
+    let pseudo = pseudo::is_pseudo(&name.to_lowercase());
+    for parsed in parse_instruction_tokens(&unit.context(line_num), &tokens)? {
+        unit.items.push(Item::Instruction {
+            line: line_num + 1,
+            instr: parsed.instr,
+            fixup: parsed.fixup,
+            location: source_line.location.clone(),
+            relaxed: false,
+            pseudo,
+        });
+        unit.pc += 4;
+    }
+
+    Ok(())
 }
 
-/// First pass: parse lines and collect config/labels
-fn first_pass(source: &str) -> Result<(Vec<Item>, HashMap<String, u64>, Config)> {
-    let mut items = Vec::new();
-    let mut labels = HashMap::new();
-    let mut config = Config::DEFAULT;
-    let mut pc = CODE_BASE;
+/// Handle a directive line (tokens after the directive name)
+fn parse_directive(unit: &mut Unit, directive: &str, args: &[Token], line_num: usize, location: &Location) -> Result<()> {
+    let line = line_num + 1;
+
+    match directive {
+        "config" => {
+            let (key, value) = parse_config(args, &mut unit.config, line_num)?;
+            unit.items.push(Item::ConfigDirective { key, value });
+        }
 
-    for (line_num, line_text) in source.lines().enumerate() {
-        let line_text = line_text.trim();
+        "abi" => {
+            let version = match args {
+                [token] => extract_number(token).map_err(|e| e.at_line(line))?,
+                _ => {
+                    return Err(AssemblerError::SyntaxError {
+                        line,
+                        message: ".abi requires 1 argument: version".to_string(),
+                    });
+                }
+            };
+            let abi = u64::try_from(version).ok().and_then(AbiVersion::from_number).ok_or_else(|| AssemblerError::SyntaxError {
+                line,
+                message: format!("Unsupported ABI version {} (expected 1 or 2)", version),
+            })?;
+            unit.abi = Some(abi);
+        }
 
-        // Skip empty lines and comments
-        if line_text.is_empty() || line_text.starts_with('#') {
-            items.push(Item::Empty);
-            continue;
+        // ========== Constants ==========
+        "equ" | "set" => {
+            let redefinable = directive == "set";
+            let (name, expr) = match args {
+                [Token::Identifier(name), Token::Comma, rest @ ..] if !rest.is_empty() => {
+                    (name.clone(), unit.context(line_num).expression(rest)?)
+                }
+                _ => {
+                    return Err(AssemblerError::SyntaxError {
+                        line,
+                        message: format!(".{} requires 2 arguments: name, value", directive),
+                    });
+                }
+            };
+            match unit.constants.get(&name) {
+                Some(existing) if redefinable && existing.redefinable => {}
+                _ => unit.check_unique(&name, line)?,
+            }
+            if unit.imports.contains(&name) {
+                return Err(AssemblerError::SyntaxError {
+                    line,
+                    message: format!("Constant {} cannot be declared .global or .extern", name),
+                });
+            }
+            unit.constants.insert(name, Constant { expr, redefinable });
         }
 
-        // Remove inline comments
-        let line_text = if let Some(pos) = line_text.find('#') {
-            line_text[..pos].trim()
-        } else {
-            line_text
-        };
-
-        if line_text.is_empty() {
-            items.push(Item::Empty);
-            continue;
+        // ========== Section Switching ==========
+        "text" | "data" | "rodata" | "bss" => {
+            expect_no_operands(args, line_num)?;
+            unit.section = Section::from_name(directive).expect("section directive");
         }
-
-        // Tokenize the line
-        let tokens = tokenize(line_text)?;
-        if tokens.is_empty() {
-            items.push(Item::Empty);
-            continue;
+        "section" => {
+            unit.section = match args {
+                [Token::Directive(name)] | [Token::Identifier(name)] => Section::from_name(name),
+                _ => None,
+            }
+            .ok_or_else(|| AssemblerError::InvalidDirective {
+                line,
+                directive: format!(".section {}", describe_tokens(args)),
+            })?;
         }
 
-        // Check for label (identifier followed by colon)
-        if tokens.len() >= 2 {
-            if let (Token::Identifier(name), Token::Colon) = (&tokens[0], &tokens[1]) {
-                // Validate label name
-                if !is_valid_label(name) {
+        // ========== Data Emission ==========
+        "byte" => emit_values(unit, directive, args, 1, line_num, location)?,
+        "half" => emit_values(unit, directive, args, 2, line_num, location)?,
+        "word" => emit_values(unit, directive, args, 4, line_num, location)?,
+        "dword" => emit_values(unit, directive, args, 8, line_num, location)?,
+        "ascii" | "asciz" => {
+            let nul = directive == "asciz";
+            let mut bytes = Vec::new();
+            for arg in split_operands(args, line_num)? {
+                match arg {
+                    [Token::Str(s)] => {
+                        bytes.extend_from_slice(s);
+                        if nul {
+                            bytes.push(0);
+                        }
+                    }
+                    _ => {
+                        return Err(AssemblerError::SyntaxError {
+                            line,
+                            message: format!(".{} expects string literals, got {}", directive, describe_tokens(arg)),
+                        });
+                    }
+                }
+            }
+            unit.bytes_mut(directive, line)?.extend_from_slice(&bytes);
+        }
+        "space" => {
+            let size = match split_operands(args, line_num)?.as_slice() {
+                [size] => extract_count(unit, size, directive, line_num)?,
+                _ => {
                     return Err(AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: format!("Invalid label name: {}", name),
+                        line,
+                        message: ".space requires 1 argument: size".to_string(),
                     });
                 }
-
-                // Check for duplicate
-                if labels.contains_key(name) {
+            };
+            if unit.section == Section::Bss {
+                unit.bss_size += size;
+            } else {
+                let bytes = unit.bytes_mut(directive, line)?;
+                bytes.resize(bytes.len() + size as usize, 0);
+            }
+        }
+        "align" => {
+            let power = match split_operands(args, line_num)?.as_slice() {
+                [power] => extract_count(unit, power, directive, line_num)?,
+                _ => {
                     return Err(AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: format!("Duplicate label: {}", name),
+                        line,
+                        message: ".align requires 1 argument: power of two".to_string(),
                     });
                 }
-
-                labels.insert(name.clone(), pc);
-                items.push(Item::Label(name.clone()));
-
-                // Check if there's an instruction after the label
-                if tokens.len() > 2 {
-                    let instr = parse_instruction_tokens(&tokens[2..], line_num)?;
-                    items.push(Item::Instruction(instr));
-                    pc += 4;
-                }
-                continue;
+            };
+            if power > 12 {
+                return Err(AssemblerError::InvalidImmediate {
+                    line,
+                    value: format!(".align {} (maximum is 12)", power),
+                });
             }
+            align_section(unit, 1 << power, line, location);
         }
 
-        // Check for directive
-        if let Token::Directive(directive) = &tokens[0] {
-            if directive == "config" {
-                if tokens.len() != 3 {
+        // ========== Entry Point ==========
+        "entry" => {
+            let name = match args {
+                [Token::Identifier(name)] => name.clone(),
+                _ => {
                     return Err(AssemblerError::SyntaxError {
-                        line: line_num + 1,
-                        message: ".config requires 2 arguments: key value".to_string(),
+                        line,
+                        message: ".entry requires 1 argument: symbol".to_string(),
                     });
                 }
+            };
+            if let Some((_, first, _)) = &unit.entry {
+                return Err(AssemblerError::SyntaxError {
+                    line,
+                    message: format!("Duplicate .entry (already set at line {})", first),
+                });
+            }
+            unit.entry = Some((name, line, location.clone()));
+        }
 
-                let key = match &tokens[1] {
-                    Token::Identifier(k) => k.clone(),
+        // ========== Symbol Binding ==========
+        "global" | "globl" | "local" | "extern" => {
+            let names = split_operands(args, line_num)?;
+            if names.is_empty() {
+                return Err(AssemblerError::SyntaxError {
+                    line,
+                    message: format!(".{} requires at least one symbol name", directive),
+                });
+            }
+            for name in names {
+                match name {
+                    [Token::Identifier(name)] => unit.declare(name, directive, line)?,
                     _ => {
                         return Err(AssemblerError::SyntaxError {
-                            line: line_num + 1,
-                            message: "Config key must be an identifier".to_string(),
+                            line,
+                            message: format!(".{} expects symbol names, got {}", directive, describe_tokens(name)),
                         });
                     }
-                };
+                }
+            }
+        }
 
-                let value = extract_number(&tokens[2])? as u64;
+        // Ignore other directives (e.g., .type)
+        _ => unit.items.push(Item::Empty),
+    }
 
-                // Apply config
-                match key.as_str() {
-                    "limb_bits" => {
-                        config.limb_bits = value as u8;
-                        config.validate().map_err(|e| AssemblerError::ConfigError {
-                            line: line_num + 1,
-                            source: e,
-                        })?;
-                    }
-                    "data_limbs" => {
-                        config.data_limbs = value as u8;
-                        config.validate().map_err(|e| AssemblerError::ConfigError {
-                            line: line_num + 1,
-                            source: e,
-                        })?;
-                    }
-                    "addr_limbs" => {
-                        config.addr_limbs = value as u8;
-                        config.validate().map_err(|e| AssemblerError::ConfigError {
-                            line: line_num + 1,
-                            source: e,
-                        })?;
-                    }
-                    _ => {
-                        return Err(AssemblerError::InvalidConfigValue {
-                            line: line_num + 1,
-                            key: key.clone(),
-                            value: value.to_string(),
-                        });
-                    }
-                }
+    Ok(())
+}
 
-                items.push(Item::ConfigDirective { key, value });
-            } else {
-                // Ignore other directives (e.g., .text, .data)
-                items.push(Item::Empty);
+/// File name argument of `.include`/`.incbin`
+fn include_name(args: &[Token], directive: &str, line_num: usize) -> Result<String> {
+    let name = match args {
+        [Token::Str(name)] => String::from_utf8(name.clone()).ok(),
+        _ => None,
+    };
+    name.ok_or_else(|| AssemblerError::SyntaxError {
+        line: line_num + 1,
+        message: format!(".{} requires a file name string", directive),
+    })
+}
+
+/// Handle `.incbin "file"[, skip[, count]]`: copy the file's bytes into the section
+fn include_binary(assembler: &Assembler, unit: &mut Unit, args: &[Token], source_line: &SourceLine) -> Result<()> {
+    let line_num = source_line.line - 1;
+    let line = source_line.line;
+    let operands = split_operands(args, line_num)?;
+    if operands.len() > 3 {
+        return Err(AssemblerError::SyntaxError {
+            line,
+            message: ".incbin takes at most 3 arguments: file, skip, count".to_string(),
+        });
+    }
+    let name = include_name(operands.first().copied().unwrap_or_default(), "incbin", line_num)?;
+    let skip = match operands.get(1) {
+        Some(skip) => extract_count(unit, skip, "incbin", line_num)? as usize,
+        None => 0,
+    };
+    let count = match operands.get(2) {
+        Some(count) => Some(extract_count(unit, count, "incbin", line_num)? as usize),
+        None => None,
+    };
+
+    let contents = source::read_binary(&name, source_line, &assembler.options.include_paths)?;
+    let end = count.map_or(contents.len(), |count| skip.saturating_add(count));
+    let bytes = contents.get(skip..end).ok_or_else(|| AssemblerError::InvalidImmediate {
+        line,
+        value: format!(".incbin range {}..{} exceeds the {}-byte file", skip, end, contents.len()),
+    })?;
+    unit.bytes_mut("incbin", line)?.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Parse `.config key value` and apply it to `config`
+fn parse_config(args: &[Token], config: &mut Config, line_num: usize) -> Result<(String, u64)> {
+    if args.len() != 2 {
+        return Err(AssemblerError::SyntaxError {
+            line: line_num + 1,
+            message: ".config requires 2 arguments: key value".to_string(),
+        });
+    }
+
+    let key = match &args[0] {
+        Token::Identifier(k) => k.clone(),
+        _ => {
+            return Err(AssemblerError::SyntaxError {
+                line: line_num + 1,
+                message: "Config key must be an identifier".to_string(),
+            });
+        }
+    };
+
+    let value = extract_number(&args[1]).map_err(|e| e.at_line(line_num + 1))? as u64;
+
+    // Apply config
+    match key.as_str() {
+        "limb_bits" => config.limb_bits = value as u8,
+        "data_limbs" => config.data_limbs = value as u8,
+        "addr_limbs" => config.addr_limbs = value as u8,
+        _ => {
+            return Err(AssemblerError::InvalidConfigValue {
+                line: line_num + 1,
+                key: key.clone(),
+                value: value.to_string(),
+            });
+        }
+    }
+    config.validate().map_err(|e| AssemblerError::ConfigError {
+        line: line_num + 1,
+        source: e,
+    })?;
+
+    Ok((key, value))
+}
+
+/// Emit `.byte`/`.half`/`.word`/`.dword` values into the active section
+fn emit_values(
+    unit: &mut Unit,
+    directive: &str,
+    args: &[Token],
+    width: usize,
+    line_num: usize,
+    location: &Location,
+) -> Result<()> {
+    let line = line_num + 1;
+    let values = split_operands(args, line_num)?;
+    if values.is_empty() {
+        return Err(AssemblerError::SyntaxError {
+            line,
+            message: format!(".{} requires at least one value", directive),
+        });
+    }
+
+    // Evaluate everything known now; the rest is patched after layout
+    let ctx = unit.context(line_num);
+    let mut values_out = Vec::with_capacity(values.len());
+    for value in values {
+        let expr = ctx.expression(value)?;
+        let known = ctx.constant_value(&expr)?;
+        if let Some(value) = known {
+            check_width(value, width, directive, &expr, line)?;
+        }
+        values_out.push((expr, known));
+    }
+
+    let section = unit.section;
+    unit.require_align(width as u64);
+    let bytes = unit.bytes_mut(directive, line)?;
+    let mut fixups = Vec::new();
+
+    for (expr, known) in values_out {
+        let value = match known {
+            Some(value) => value,
+            None => {
+                fixups.push(DataFixup {
+                    line,
+                    section,
+                    offset: bytes.len(),
+                    width,
+                    expr,
+                    location: location.clone(),
+                });
+                0
             }
-            continue;
+        };
+        bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+    }
+
+    unit.fixups.extend(fixups);
+    Ok(())
+}
+
+/// Pad the active section to a multiple of `align` bytes
+///
+/// `.text` is padded with `nop` (`addi zero, zero, 0`) instructions.
+fn align_section(unit: &mut Unit, align: u64, line: usize, location: &Location) {
+    unit.require_align(align);
+    let padding = align_up(unit.offset(), align) - unit.offset();
+
+    match unit.section {
+        Section::Text => {
+            unit.items.push(Item::Align { align, line, location: location.clone() });
+            unit.pc += padding.div_ceil(4) * 4;
         }
+        Section::Data => unit.data.resize(unit.data.len() + padding as usize, 0),
+        Section::Rodata => unit.rodata.resize(unit.rodata.len() + padding as usize, 0),
+        Section::Bss => unit.bss_size += padding,
+    }
+}
+
+/// Check that `value` is representable in `width` bytes (signed or unsigned)
+fn fits_width(value: i64, width: usize) -> bool {
+    if width >= 8 {
+        return true;
+    }
+    let bits = width as u32 * 8;
+    value >= -(1i64 << (bits - 1)) && value < (1i64 << bits)
+}
+
+/// Range-check a data value against its directive width
+fn check_width(value: i64, width: usize, directive: &str, expr: &Expr, line: usize) -> Result<()> {
+    if fits_width(value, width) {
+        return Ok(());
+    }
+    Err(AssemblerError::ValueOutOfRange {
+        line,
+        expr: expr.to_string(),
+        value,
+        field: format!(".{} ({} bytes)", directive, width),
+    })
+}
+
+/// Evaluate a non-negative constant count (size or alignment power)
+fn extract_count(unit: &Unit, tokens: &[Token], directive: &str, line_num: usize) -> Result<u64> {
+    let ctx = unit.context(line_num);
+    let expr = ctx.expression(tokens)?;
+    let value = ctx.constant(&expr)?;
+    u64::try_from(value).map_err(|_| AssemblerError::InvalidImmediate {
+        line: line_num + 1,
+        value: format!(".{} {}", directive, expr),
+    })
+}
+
+/// Split comma-separated operands into token groups
+///
+/// Every group must be non-empty, so doubled and trailing commas are errors.
+pub(crate) fn split_operands(tokens: &[Token], line_num: usize) -> Result<Vec<&[Token]>> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
 
-        // Parse as instruction
-        let instr = parse_instruction_tokens(&tokens, line_num)?;
-        items.push(Item::Instruction(instr));
-        pc += 4;
+    let groups: Vec<&[Token]> = tokens.split(|t| matches!(t, Token::Comma)).collect();
+    if groups.iter().any(|group| group.is_empty()) {
+        let message = if groups.last().is_some_and(|group| group.is_empty()) {
+            "Trailing comma in operands".to_string()
+        } else {
+            "Expected operand between commas".to_string()
+        };
+        return Err(AssemblerError::SyntaxError { line: line_num + 1, message });
     }
+    Ok(groups)
+}
 
-    Ok((items, labels, config))
+/// Render tokens for error messages
+fn describe_tokens(tokens: &[Token]) -> String {
+    tokens.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>().join(" ")
 }
 
-/// Second pass: encode instructions
-fn second_pass(items: &[Item], _labels: &HashMap<String, u64>) -> Result<Vec<u32>> {
+/// Second pass: resolve symbolic operands
+///
+/// Instructions that fail to resolve are kept unpatched; their errors are
+/// pushed to `errors`.
+fn second_pass(
+    items: &[Item],
+    scope: &Scope,
+    layout: &Layout,
+    config: &Config,
+    errors: &mut Vec<AssemblerError>,
+) -> (Vec<Instruction>, Vec<u32>) {
+    let mut instructions = Vec::new();
     let mut code = Vec::new();
+    let mut pc = layout.text;
 
     for item in items.iter() {
-        if let Item::Instruction(instr) = item {
-            let encoded = encode(instr);
-            code.push(encoded);
+        if let Item::Instruction { line, instr, fixup, location, .. } = item {
+            let patched = match fixup {
+                Some(Fixup::PcRel(expr)) => scope
+                    .eval(expr, *line)
+                    .and_then(|target| patch_offset(instr, target - pc as i64, expr, *line)),
+                Some(Fixup::Imm(expr)) => scope
+                    .eval(expr, *line)
+                    .and_then(|value| patch_imm(instr, value, expr, *line)),
+                None => Ok(*instr),
+            };
+            let encoded = patched.and_then(|patched| {
+                try_encode(&patched, config)
+                    .map(|word| (patched, word))
+                    .map_err(|source| AssemblerError::Encode { line: *line, source })
+            });
+            match encoded {
+                Ok((patched, word)) => {
+                    instructions.push(patched);
+                    code.push(word);
+                }
+                Err(err) => {
+                    errors.push(location.wrap(err));
+                    instructions.push(*instr);
+                    code.push(0);
+                }
+            }
+            pc += 4;
         }
     }
 
-    Ok(code)
+    (instructions, code)
 }
 
-/// Parse tokens into an instruction
-fn parse_instruction_tokens(tokens: &[Token], line_num: usize) -> Result<Instruction> {
-    if tokens.is_empty() {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "Empty instruction".to_string(),
+/// Build the `Program.data` image, patching label-dependent values into data
+fn emit_data(unit: &Unit, scope: &Scope, layout: &Layout, errors: &mut Vec<AssemblerError>) -> Vec<u8> {
+    let mut data = unit.data.clone();
+    let mut rodata = unit.rodata.clone();
+
+    for fixup in &unit.fixups {
+        let value = match scope
+            .eval(&fixup.expr, fixup.line)
+            .and_then(|value| fixup.check(value).map(|_| value))
+        {
+            Ok(value) => value,
+            Err(err) => {
+                errors.push(fixup.location.wrap(err));
+                continue;
+            }
+        };
+        let bytes = match fixup.section {
+            Section::Data => &mut data,
+            Section::Rodata => &mut rodata,
+            Section::Text | Section::Bss => unreachable!("data fixups only live in initialized sections"),
+        };
+        bytes[fixup.offset..fixup.offset + fixup.width]
+            .copy_from_slice(&value.to_le_bytes()[..fixup.width]);
+    }
+
+    if data.is_empty() && rodata.is_empty() {
+        return Vec::new();
+    }
+
+    let mut image = vec![0u8; (layout.data - layout.data_start) as usize];
+    image.extend_from_slice(&data);
+    image.resize((layout.rodata - layout.data_start) as usize, 0);
+    image.extend_from_slice(&rodata);
+    image
+}
+
+/// Patch a PC-relative offset into a branch or jump, checking it fits the field
+pub(crate) fn patch_offset(instr: &Instruction, offset: i64, target: &Expr, line: usize) -> Result<Instruction> {
+    let bits = if matches!(instr, Instruction::Jal { .. }) { OFFSET_BITS } else { IMM_BITS };
+    let limit = 1i64 << (bits - 1);
+    if offset < -limit || offset >= limit {
+        return Err(AssemblerError::OffsetOutOfRange {
+            line,
+            label: target.to_string(),
+            offset,
+            bits,
         });
     }
 
+    let offset = offset as i32;
+    let mut instr = *instr;
+    match &mut instr {
+        Instruction::Beq { offset: o, .. }
+        | Instruction::Bne { offset: o, .. }
+        | Instruction::Blt { offset: o, .. }
+        | Instruction::Bge { offset: o, .. }
+        | Instruction::Bltu { offset: o, .. }
+        | Instruction::Bgeu { offset: o, .. }
+        | Instruction::Jal { offset: o, .. } => *o = offset,
+        _ => unreachable!("target operands are only parsed for branches and jumps"),
+    }
+    Ok(instr)
+}
+
+/// Patch an immediate operand into its field, checking it fits
+pub(crate) fn patch_imm(instr: &Instruction, value: i64, expr: &Expr, line: usize) -> Result<Instruction> {
+    let mut instr = *instr;
+
+    if let Instruction::Slli { shamt, .. } | Instruction::Srli { shamt, .. } | Instruction::Srai { shamt, .. } =
+        &mut instr
+    {
+        if !(0..=MAX_SHIFT_AMOUNT as i64).contains(&value) {
+            return Err(AssemblerError::ValueOutOfRange {
+                line,
+                expr: expr.to_string(),
+                value,
+                field: format!("shift amount (0-{})", MAX_SHIFT_AMOUNT),
+            });
+        }
+        *shamt = value as u8;
+        return Ok(instr);
+    }
+
+    if !fits_imm(value) {
+        return Err(AssemblerError::ValueOutOfRange {
+            line,
+            expr: expr.to_string(),
+            value,
+            field: format!("{}-bit signed immediate", IMM_BITS),
+        });
+    }
+
+    let value = value as i32;
+    match &mut instr {
+        Instruction::Addi { imm, .. }
+        | Instruction::Xori { imm, .. }
+        | Instruction::Ori { imm, .. }
+        | Instruction::Andi { imm, .. }
+        | Instruction::Lw { imm, .. }
+        | Instruction::Lh { imm, .. }
+        | Instruction::Lhu { imm, .. }
+        | Instruction::Lb { imm, .. }
+        | Instruction::Lbu { imm, .. }
+        | Instruction::Ld { imm, .. }
+        | Instruction::Sw { imm, .. }
+        | Instruction::Sh { imm, .. }
+        | Instruction::Sb { imm, .. }
+        | Instruction::Sd { imm, .. }
+        | Instruction::Jalr { imm, .. } => *imm = value,
+        _ => unreachable!("immediate operands are only parsed for I-type and S-type instructions"),
+    }
+    Ok(instr)
+}
+
+/// Whether a (possibly wrapped) warning is about an ABI register name
+fn is_abi_warning(warning: &AssemblerError) -> bool {
+    match warning {
+        AssemblerError::RegisterName { .. } => true,
+        AssemblerError::InFile { source, .. } | AssemblerError::MacroExpansion { source, .. } => is_abi_warning(source),
+        _ => false,
+    }
+}
+
+/// Check whether a value fits the signed I-type immediate field
+pub(crate) fn fits_imm(value: i64) -> bool {
+    let limit = 1i64 << (IMM_BITS - 1);
+    (-limit..limit).contains(&value)
+}
+
+/// Operand parsing context: the active config and constants defined so far
+pub(crate) struct Context<'a> {
+    pub(crate) config: &'a Config,
+    constants: &'a HashMap<String, Constant>,
+    abi: AbiVersion,
+    pub(crate) line_num: usize,
+}
+
+impl Context<'_> {
+    fn scope(&self) -> Scope<'_> {
+        Scope {
+            constants: self.constants,
+            labels: None,
+        }
+    }
+
+    pub(crate) fn syntax_error(&self, message: impl Into<String>) -> AssemblerError {
+        AssemblerError::SyntaxError {
+            line: self.line_num + 1,
+            message: message.into(),
+        }
+    }
+
+    /// Split operands on commas, requiring exactly `count` groups
+    pub(crate) fn operands<'t>(&self, tokens: &'t [Token], count: usize, usage: &str) -> Result<Vec<&'t [Token]>> {
+        let groups = split_operands(tokens, self.line_num)?;
+        if groups.len() != count {
+            return Err(self.syntax_error(usage));
+        }
+        Ok(groups)
+    }
+
+    /// Parse a single-register operand
+    pub(crate) fn register(&self, group: &[Token]) -> Result<Register> {
+        match group {
+            [Token::Register(name)] => parse_register_with_abi(name, self.abi).map_err(|e| e.at_line(self.line_num + 1)),
+            _ => Err(self.syntax_error(format!("Expected register, got {}", describe_tokens(group)))),
+        }
+    }
+
+    /// Parse an operand expression, inlining the current value of `.set` symbols
+    pub(crate) fn expression(&self, group: &[Token]) -> Result<Expr> {
+        let expr = Expr::parse(group, self.line_num)?;
+        Ok(expr.substitute(&|name| match self.constants.get(name) {
+            Some(constant) if constant.redefinable => Some(constant.expr.clone()),
+            _ => None,
+        }))
+    }
+
+    /// Value of `expr` if it only involves constants defined so far
+    pub(crate) fn constant_value(&self, expr: &Expr) -> Result<Option<i64>> {
+        let scope = self.scope();
+        if !scope.is_absolute(expr, 0) {
+            return Ok(None);
+        }
+        scope.eval(expr, self.line_num + 1).map(Some)
+    }
+
+    /// Value of an expression that must be constant at this point
+    pub(crate) fn constant(&self, expr: &Expr) -> Result<i64> {
+        self.constant_value(expr)?.ok_or_else(|| AssemblerError::InvalidExpression {
+            line: self.line_num + 1,
+            expr: expr.to_string(),
+            message: "must be a constant defined before this line".to_string(),
+        })
+    }
+
+    /// Attach an immediate operand: patched now if constant, else after layout
+    pub(crate) fn with_imm(&self, instr: Instruction, expr: Expr) -> Result<Parsed> {
+        match self.constant_value(&expr)? {
+            Some(value) => Ok(patch_imm(&instr, value, &expr, self.line_num + 1)?.into()),
+            None => Ok(Parsed {
+                instr,
+                fixup: Some(Fixup::Imm(expr)),
+            }),
+        }
+    }
+
+    /// Attach a branch/jump target
+    ///
+    /// Constant targets are raw byte offsets; targets involving labels are
+    /// addresses converted to PC-relative offsets after layout.
+    pub(crate) fn with_target(&self, instr: Instruction, group: &[Token]) -> Result<Parsed> {
+        let expr = self.expression(group)?;
+        match self.constant_value(&expr)? {
+            Some(offset) => Ok(patch_offset(&instr, offset, &expr, self.line_num + 1)?.into()),
+            None => Ok(Parsed {
+                instr,
+                fixup: Some(Fixup::PcRel(expr)),
+            }),
+        }
+    }
+}
+
+/// Parse tokens into one or more instructions (pseudo-instructions expand)
+fn parse_instruction_tokens(ctx: &Context, tokens: &[Token]) -> Result<Vec<Parsed>> {
+    if tokens.is_empty() {
+        return Err(ctx.syntax_error("Empty instruction"));
+    }
+
     let mnemonic = match &tokens[0] {
         Token::Identifier(s) => s.to_lowercase(),
         _ => {
-            return Err(AssemblerError::SyntaxError {
-                line: line_num + 1,
-                message: format!("Expected instruction mnemonic, got {:?}", tokens[0]),
-            });
+            return Err(ctx.syntax_error(format!("Expected instruction mnemonic, got {:?}", tokens[0])));
         }
     };
 
     let operands = &tokens[1..];
 
-    parse_mnemonic(&mnemonic, operands, line_num)
+    if let Some(expansion) = pseudo::expand(ctx, &mnemonic, operands)? {
+        return Ok(expansion);
+    }
+    Ok(vec![parse_mnemonic(ctx, &mnemonic, operands)?])
 }
 
 /// Parse instruction mnemonic and operands
-fn parse_mnemonic(mnemonic: &str, operands: &[Token], line_num: usize) -> Result<Instruction> {
+fn parse_mnemonic(ctx: &Context, mnemonic: &str, operands: &[Token]) -> Result<Parsed> {
     match mnemonic {
         // ========== System Instructions ==========
         "ecall" => {
-            expect_no_operands(operands, line_num)?;
-            Ok(Instruction::Ecall)
+            expect_no_operands(operands, ctx.line_num)?;
+            Ok(Instruction::Ecall.into())
         }
         "ebreak" => {
-            expect_no_operands(operands, line_num)?;
-            Ok(Instruction::Ebreak)
+            expect_no_operands(operands, ctx.line_num)?;
+            Ok(Instruction::Ebreak.into())
         }
 
         // ========== R-type Arithmetic ==========
-        "add" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Add { rd, rs1, rs2 }),
-        "sub" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sub { rd, rs1, rs2 }),
-        "mul" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 }),
-        "mulh" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 }),
-        "div" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 }),
-        "divu" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 }),
-        "rem" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 }),
-        "remu" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 }),
+        "add" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Add { rd, rs1, rs2 }),
+        "sub" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sub { rd, rs1, rs2 }),
+        "mul" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 }),
+        "mulh" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 }),
+        "div" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 }),
+        "divu" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 }),
+        "rem" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 }),
+        "remu" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 }),
 
         // ========== R-type Logical ==========
-        "and" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::And { rd, rs1, rs2 }),
-        "or" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Or { rd, rs1, rs2 }),
-        "xor" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Xor { rd, rs1, rs2 }),
+        "and" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::And { rd, rs1, rs2 }),
+        "or" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Or { rd, rs1, rs2 }),
+        "xor" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Xor { rd, rs1, rs2 }),
 
         // ========== R-type Shift ==========
-        "sll" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sll { rd, rs1, rs2 }),
-        "srl" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Srl { rd, rs1, rs2 }),
-        "sra" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sra { rd, rs1, rs2 }),
+        "sll" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sll { rd, rs1, rs2 }),
+        "srl" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Srl { rd, rs1, rs2 }),
+        "sra" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sra { rd, rs1, rs2 }),
 
         // ========== R-type Compare ==========
-        "slt" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Slt { rd, rs1, rs2 }),
-        "sltu" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sltu { rd, rs1, rs2 }),
-        "sge" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sge { rd, rs1, rs2 }),
-        "sgeu" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sgeu { rd, rs1, rs2 }),
-        "seq" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Seq { rd, rs1, rs2 }),
-        "sne" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Sne { rd, rs1, rs2 }),
+        "slt" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Slt { rd, rs1, rs2 }),
+        "sltu" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sltu { rd, rs1, rs2 }),
+        "sge" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sge { rd, rs1, rs2 }),
+        "sgeu" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sgeu { rd, rs1, rs2 }),
+        "seq" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Seq { rd, rs1, rs2 }),
+        "sne" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Sne { rd, rs1, rs2 }),
 
         // ========== R-type Conditional Move ==========
-        "cmov" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Cmov { rd, rs1, rs2 }),
-        "cmovz" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Cmovz { rd, rs1, rs2 }),
-        "cmovnz" => parse_r_type(operands, line_num, |rd, rs1, rs2| Instruction::Cmovnz { rd, rs1, rs2 }),
+        "cmov" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Cmov { rd, rs1, rs2 }),
+        "cmovz" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Cmovz { rd, rs1, rs2 }),
+        "cmovnz" => parse_r_type(ctx, operands, |rd, rs1, rs2| Instruction::Cmovnz { rd, rs1, rs2 }),
 
         // ========== I-type Arithmetic ==========
-        "addi" => parse_i_type(operands, line_num, |rd, rs1, imm| Instruction::Addi { rd, rs1, imm }),
-        "xori" => parse_i_type(operands, line_num, |rd, rs1, imm| Instruction::Xori { rd, rs1, imm }),
-        "ori" => parse_i_type(operands, line_num, |rd, rs1, imm| Instruction::Ori { rd, rs1, imm }),
-        "andi" => parse_i_type(operands, line_num, |rd, rs1, imm| Instruction::Andi { rd, rs1, imm }),
+        "addi" => parse_i_type(ctx, operands, |rd, rs1, imm| Instruction::Addi { rd, rs1, imm }),
+        "xori" => parse_i_type(ctx, operands, |rd, rs1, imm| Instruction::Xori { rd, rs1, imm }),
+        "ori" => parse_i_type(ctx, operands, |rd, rs1, imm| Instruction::Ori { rd, rs1, imm }),
+        "andi" => parse_i_type(ctx, operands, |rd, rs1, imm| Instruction::Andi { rd, rs1, imm }),
 
         // ========== I-type Shift ==========
-        "slli" => parse_shift_imm(operands, line_num, |rd, rs1, shamt| Instruction::Slli { rd, rs1, shamt }),
-        "srli" => parse_shift_imm(operands, line_num, |rd, rs1, shamt| Instruction::Srli { rd, rs1, shamt }),
-        "srai" => parse_shift_imm(operands, line_num, |rd, rs1, shamt| Instruction::Srai { rd, rs1, shamt }),
+        "slli" => parse_shift_imm(ctx, operands, |rd, rs1, shamt| Instruction::Slli { rd, rs1, shamt }),
+        "srli" => parse_shift_imm(ctx, operands, |rd, rs1, shamt| Instruction::Srli { rd, rs1, shamt }),
+        "srai" => parse_shift_imm(ctx, operands, |rd, rs1, shamt| Instruction::Srai { rd, rs1, shamt }),
 
         // ========== Load ==========
-        "lw" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Lw { rd, rs1, imm }),
-        "lh" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Lh { rd, rs1, imm }),
-        "lhu" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Lhu { rd, rs1, imm }),
-        "lb" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Lb { rd, rs1, imm }),
-        "lbu" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Lbu { rd, rs1, imm }),
-        "ld" => parse_load(operands, line_num, |rd, rs1, imm| Instruction::Ld { rd, rs1, imm }),
+        "lw" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Lw { rd, rs1, imm }),
+        "lh" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Lh { rd, rs1, imm }),
+        "lhu" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Lhu { rd, rs1, imm }),
+        "lb" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Lb { rd, rs1, imm }),
+        "lbu" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Lbu { rd, rs1, imm }),
+        "ld" => parse_load(ctx, operands, |rd, rs1, imm| Instruction::Ld { rd, rs1, imm }),
 
         // ========== Store ==========
-        "sw" => parse_store(operands, line_num, |rs1, rs2, imm| Instruction::Sw { rs1, rs2, imm }),
-        "sh" => parse_store(operands, line_num, |rs1, rs2, imm| Instruction::Sh { rs1, rs2, imm }),
-        "sb" => parse_store(operands, line_num, |rs1, rs2, imm| Instruction::Sb { rs1, rs2, imm }),
-        "sd" => parse_store(operands, line_num, |rs1, rs2, imm| Instruction::Sd { rs1, rs2, imm }),
+        "sw" => parse_store(ctx, operands, |rs1, rs2, imm| Instruction::Sw { rs1, rs2, imm }),
+        "sh" => parse_store(ctx, operands, |rs1, rs2, imm| Instruction::Sh { rs1, rs2, imm }),
+        "sb" => parse_store(ctx, operands, |rs1, rs2, imm| Instruction::Sb { rs1, rs2, imm }),
+        "sd" => parse_store(ctx, operands, |rs1, rs2, imm| Instruction::Sd { rs1, rs2, imm }),
 
         // ========== Branch ==========
-        "beq" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Beq { rs1, rs2, offset }),
-        "bne" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Bne { rs1, rs2, offset }),
-        "blt" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Blt { rs1, rs2, offset }),
-        "bge" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Bge { rs1, rs2, offset }),
-        "bltu" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Bltu { rs1, rs2, offset }),
-        "bgeu" => parse_branch(operands, line_num, |rs1, rs2, offset| Instruction::Bgeu { rs1, rs2, offset }),
+        "beq" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Beq { rs1, rs2, offset }),
+        "bne" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Bne { rs1, rs2, offset }),
+        "blt" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Blt { rs1, rs2, offset }),
+        "bge" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Bge { rs1, rs2, offset }),
+        "bltu" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Bltu { rs1, rs2, offset }),
+        "bgeu" => parse_branch(ctx, operands, |rs1, rs2, offset| Instruction::Bgeu { rs1, rs2, offset }),
 
         // ========== Jump ==========
-        "jal" => parse_jal(operands, line_num),
-        "jalr" => parse_jalr(operands, line_num),
+        "jal" => parse_jal(ctx, operands),
+        "jalr" => parse_jalr(ctx, operands),
 
         _ => Err(AssemblerError::InvalidInstruction {
-            line: line_num + 1,
+            line: ctx.line_num + 1,
             instruction: mnemonic.to_string(),
         }),
     }
@@ -324,7 +1966,7 @@ fn parse_mnemonic(mnemonic: &str, operands: &[Token], line_num: usize) -> Result
 
 // ========== Helper Functions ==========
 
-fn expect_no_operands(operands: &[Token], line_num: usize) -> Result<()> {
+pub(crate) fn expect_no_operands(operands: &[Token], line_num: usize) -> Result<()> {
     if !operands.is_empty() {
         return Err(AssemblerError::SyntaxError {
             line: line_num + 1,
@@ -335,207 +1977,129 @@ fn expect_no_operands(operands: &[Token], line_num: usize) -> Result<()> {
 }
 
 /// Parse R-type: rd, rs1, rs2
-fn parse_r_type<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+fn parse_r_type<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, zkir_spec::Register) -> Instruction,
 {
-    if operands.len() != 5 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "R-type requires 3 operands: rd, rs1, rs2".to_string(),
-        });
-    }
+    let ops = ctx.operands(operands, 3, "R-type requires 3 operands: rd, rs1, rs2")?;
+    let rd = ctx.register(ops[0])?;
+    let rs1 = ctx.register(ops[1])?;
+    let rs2 = ctx.register(ops[2])?;
 
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let rs1 = extract_register(&operands[2], line_num)?;
-    expect_comma(&operands[3], line_num)?;
-    let rs2 = extract_register(&operands[4], line_num)?;
-
-    Ok(constructor(rd, rs1, rs2))
+    Ok(constructor(rd, rs1, rs2).into())
 }
 
 /// Parse I-type: rd, rs1, imm
-fn parse_i_type<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+fn parse_i_type<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, i32) -> Instruction,
 {
-    if operands.len() != 5 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "I-type requires 3 operands: rd, rs1, imm".to_string(),
-        });
-    }
-
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let rs1 = extract_register(&operands[2], line_num)?;
-    expect_comma(&operands[3], line_num)?;
-    let imm = extract_number(&operands[4])? as i32;
+    let ops = ctx.operands(operands, 3, "I-type requires 3 operands: rd, rs1, imm")?;
+    let rd = ctx.register(ops[0])?;
+    let rs1 = ctx.register(ops[1])?;
+    let imm = ctx.expression(ops[2])?;
 
-    Ok(constructor(rd, rs1, imm))
+    ctx.with_imm(constructor(rd, rs1, 0), imm)
 }
 
 /// Parse shift immediate: rd, rs1, shamt
-fn parse_shift_imm<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+fn parse_shift_imm<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, u8) -> Instruction,
 {
-    if operands.len() != 5 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "Shift requires 3 operands: rd, rs1, shamt".to_string(),
-        });
-    }
+    let ops = ctx.operands(operands, 3, "Shift requires 3 operands: rd, rs1, shamt")?;
+    let rd = ctx.register(ops[0])?;
+    let rs1 = ctx.register(ops[1])?;
+    let shamt = ctx.expression(ops[2])?;
 
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let rs1 = extract_register(&operands[2], line_num)?;
-    expect_comma(&operands[3], line_num)?;
-    let shamt = extract_number(&operands[4])? as u8;
+    ctx.with_imm(constructor(rd, rs1, 0), shamt)
+}
 
-    Ok(constructor(rd, rs1, shamt))
+/// Split a memory operand `offset(rs1)` into the offset expression and base register
+///
+/// The offset may be omitted (`(rs1)` means `0(rs1)`).
+fn parse_memory_operand(ctx: &Context, group: &[Token], usage: &str) -> Result<(Expr, zkir_spec::Register)> {
+    match group {
+        [offset @ .., Token::LParen, base, Token::RParen] => {
+            let offset = if offset.is_empty() { Expr::Number(0) } else { ctx.expression(offset)? };
+            Ok((offset, ctx.register(std::slice::from_ref(base))?))
+        }
+        _ => Err(ctx.syntax_error(usage)),
+    }
 }
 
 /// Parse load: rd, offset(rs1)
-fn parse_load<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+fn parse_load<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, i32) -> Instruction,
 {
-    if operands.len() != 6 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "Load requires format: rd, offset(rs1)".to_string(),
-        });
-    }
+    const USAGE: &str = "Load requires format: rd, offset(rs1)";
+    let ops = ctx.operands(operands, 2, USAGE)?;
+    let rd = ctx.register(ops[0])?;
+    let (offset, rs1) = parse_memory_operand(ctx, ops[1], USAGE)?;
 
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let offset = extract_number(&operands[2])? as i32;
-    expect_lparen(&operands[3], line_num)?;
-    let rs1 = extract_register(&operands[4], line_num)?;
-    expect_rparen(&operands[5], line_num)?;
-
-    Ok(constructor(rd, rs1, offset))
+    ctx.with_imm(constructor(rd, rs1, 0), offset)
 }
 
 /// Parse store: rs2, offset(rs1)
-fn parse_store<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+fn parse_store<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, i32) -> Instruction,
 {
-    if operands.len() != 6 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "Store requires format: rs2, offset(rs1)".to_string(),
-        });
-    }
-
-    let rs2 = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let offset = extract_number(&operands[2])? as i32;
-    expect_lparen(&operands[3], line_num)?;
-    let rs1 = extract_register(&operands[4], line_num)?;
-    expect_rparen(&operands[5], line_num)?;
+    const USAGE: &str = "Store requires format: rs2, offset(rs1)";
+    let ops = ctx.operands(operands, 2, USAGE)?;
+    let rs2 = ctx.register(ops[0])?;
+    let (offset, rs1) = parse_memory_operand(ctx, ops[1], USAGE)?;
 
-    Ok(constructor(rs1, rs2, offset))
+    ctx.with_imm(constructor(rs1, rs2, 0), offset)
 }
 
-/// Parse branch: rs1, rs2, offset
-fn parse_branch<F>(operands: &[Token], line_num: usize, constructor: F) -> Result<Instruction>
+/// Parse branch: rs1, rs2, offset|label
+fn parse_branch<F>(ctx: &Context, operands: &[Token], constructor: F) -> Result<Parsed>
 where
     F: FnOnce(zkir_spec::Register, zkir_spec::Register, i32) -> Instruction,
 {
-    if operands.len() != 5 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "Branch requires 3 operands: rs1, rs2, offset".to_string(),
-        });
-    }
+    let ops = ctx.operands(operands, 3, "Branch requires 3 operands: rs1, rs2, offset")?;
+    let rs1 = ctx.register(ops[0])?;
+    let rs2 = ctx.register(ops[1])?;
 
-    let rs1 = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let rs2 = extract_register(&operands[2], line_num)?;
-    expect_comma(&operands[3], line_num)?;
-    let offset = extract_number(&operands[4])? as i32;
-
-    Ok(constructor(rs1, rs2, offset))
+    ctx.with_target(constructor(rs1, rs2, 0), ops[2])
 }
 
-/// Parse JAL: rd, offset
-fn parse_jal(operands: &[Token], line_num: usize) -> Result<Instruction> {
-    if operands.len() != 3 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "JAL requires 2 operands: rd, offset".to_string(),
-        });
-    }
-
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let offset = extract_number(&operands[2])? as i32;
+/// Parse JAL: rd, offset|label
+fn parse_jal(ctx: &Context, operands: &[Token]) -> Result<Parsed> {
+    let ops = ctx.operands(operands, 2, "JAL requires 2 operands: rd, offset")?;
+    let rd = ctx.register(ops[0])?;
 
-    Ok(Instruction::Jal { rd, offset })
+    ctx.with_target(Instruction::Jal { rd, offset: 0 }, ops[1])
 }
 
 /// Parse JALR: rd, rs1, offset
-fn parse_jalr(operands: &[Token], line_num: usize) -> Result<Instruction> {
-    if operands.len() != 5 {
-        return Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: "JALR requires 3 operands: rd, rs1, offset".to_string(),
-        });
-    }
+fn parse_jalr(ctx: &Context, operands: &[Token]) -> Result<Parsed> {
+    let ops = ctx.operands(operands, 3, "JALR requires 3 operands: rd, rs1, offset")?;
+    let rd = ctx.register(ops[0])?;
+    let rs1 = ctx.register(ops[1])?;
+    let imm = ctx.expression(ops[2])?;
 
-    let rd = extract_register(&operands[0], line_num)?;
-    expect_comma(&operands[1], line_num)?;
-    let rs1 = extract_register(&operands[2], line_num)?;
-    expect_comma(&operands[3], line_num)?;
-    let imm = extract_number(&operands[4])? as i32;
-
-    Ok(Instruction::Jalr { rd, rs1, imm })
-}
-
-// ========== Token Extraction ==========
-
-fn extract_register(token: &Token, line_num: usize) -> Result<zkir_spec::Register> {
-    match token {
-        Token::Register(name) => parse_register(name),
-        _ => Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: format!("Expected register, got {:?}", token),
-        }),
-    }
-}
-
-fn expect_comma(token: &Token, line_num: usize) -> Result<()> {
-    match token {
-        Token::Comma => Ok(()),
-        _ => Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: format!("Expected comma, got {:?}", token),
-        }),
-    }
+    ctx.with_imm(Instruction::Jalr { rd, rs1, imm: 0 }, imm)
 }
 
-fn expect_lparen(token: &Token, line_num: usize) -> Result<()> {
-    match token {
-        Token::LParen => Ok(()),
-        _ => Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: format!("Expected '(', got {:?}", token),
-        }),
-    }
+/// Symbol name of the `instance`-th definition of numeric local label `number`
+///
+/// Names starting with `.` cannot be written as labels, so these never
+/// clash with the source's own.
+fn local_label_name(number: u64, instance: usize) -> String {
+    format!(".L{}.{}", number, instance)
 }
 
-fn expect_rparen(token: &Token, line_num: usize) -> Result<()> {
-    match token {
-        Token::RParen => Ok(()),
-        _ => Err(AssemblerError::SyntaxError {
-            line: line_num + 1,
-            message: format!("Expected ')', got {:?}", token),
-        }),
-    }
+/// Label name as written in the source, for reporting undefined labels
+///
+/// Only forward references to numeric local labels can be left undefined,
+/// so those are reported as `1f`.
+pub(crate) fn written_label(name: &str) -> String {
+    name.strip_prefix(".L")
+        .and_then(|rest| rest.split_once('.'))
+        .map_or_else(|| name.to_string(), |(number, _)| format!("{}f", number))
 }
 
 /// Check if a label name is valid
@@ -597,6 +2161,50 @@ mod tests {
         assert_eq!(program.code.len(), 4);
     }
 
+    #[test]
+    fn test_patch_offset_checks_range() {
+        let l = Expr::Symbol("l".to_string());
+        let beq = Instruction::Beq { rs1: zkir_spec::Register::R1, rs2: zkir_spec::Register::R2, offset: 0 };
+        let resolved = patch_offset(&beq, -65536, &l, 1).unwrap();
+        assert!(matches!(resolved, Instruction::Beq { offset: -65536, .. }));
+        assert!(patch_offset(&beq, 65536, &l, 1).is_err());
+
+        let jal = Instruction::Jal { rd: zkir_spec::Register::R1, offset: 0 };
+        assert!(patch_offset(&jal, 65536, &l, 1).is_ok());
+        assert!(patch_offset(&jal, 1 << 20, &l, 1).is_err());
+    }
+
+    #[test]
+    fn test_patch_imm_checks_field() {
+        let n = Expr::Number(0);
+        let addi = Instruction::Addi { rd: zkir_spec::Register::R1, rs1: zkir_spec::Register::R0, imm: 0 };
+        assert!(matches!(patch_imm(&addi, -65536, &n, 1), Ok(Instruction::Addi { imm: -65536, .. })));
+        assert!(patch_imm(&addi, 65536, &n, 1).is_err());
+
+        let slli = Instruction::Slli { rd: zkir_spec::Register::R1, rs1: zkir_spec::Register::R1, shamt: 0 };
+        assert!(matches!(patch_imm(&slli, 63, &n, 1), Ok(Instruction::Slli { shamt: 63, .. })));
+        assert!(patch_imm(&slli, 64, &n, 1).is_err());
+        assert!(patch_imm(&slli, -1, &n, 1).is_err());
+    }
+
+    #[test]
+    fn test_fits_width() {
+        assert!(fits_width(255, 1));
+        assert!(fits_width(-128, 1));
+        assert!(!fits_width(256, 1));
+        assert!(!fits_width(-129, 1));
+        assert!(fits_width(0xFFFF_FFFF, 4));
+        assert!(!fits_width(0x1_0000_0000, 4));
+        assert!(fits_width(i64::MIN, 8));
+    }
+
+    #[test]
+    fn test_bss_only_program() {
+        let program = assemble(".bss\nbuf: .space 16\n.text\necall").unwrap();
+        assert!(program.data.is_empty());
+        assert_eq!(program.header.bss_size, 16);
+    }
+
     #[test]
     fn test_is_valid_label() {
         assert!(is_valid_label("main"));
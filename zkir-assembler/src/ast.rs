@@ -0,0 +1,96 @@
+//! Parsed zkasm source
+//!
+//! [`parse`](crate::parse) turns source text into a [`Module`] without
+//! assembling it: nothing is encoded, no symbol is resolved, and macros,
+//! includes and conditionals are left as the directives that were written.
+//! Formatters, linters and editor tooling can work on the same statements
+//! the assembler reads.
+//!
+//! Every statement and operand carries a byte span into the source, so
+//! `&source[statement.span.clone()]` is the text it was parsed from.
+//!
+//! ```text
+//! loop:   addi a0, a0, -1   # count down
+//! ^^^^^   ^^^^^^^^^^^^^^^   ^^^^^^^^^^^^
+//! Label   Instruction       Comment
+//! ```
+
+use crate::expr::Expr;
+use std::ops::Range;
+
+/// Parsed source: every statement in source order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Module {
+    pub statements: Vec<Statement>,
+}
+
+/// Label, directive, instruction or comment
+///
+/// A line holds at most one of each, in the order label, directive or
+/// instruction, comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// 1-based source line
+    pub line: usize,
+    /// Byte range in the source (trailing whitespace excluded)
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatementKind {
+    /// `name:`, or a numeric local label such as `1:`
+    Label(String),
+    Directive(Directive),
+    Instruction(Instruction),
+    /// Comment text after the `#`
+    Comment(String),
+    /// Line of a `.macro` or `.irp` body that refers to parameters (`\arg`)
+    /// and is only parsed once expanded
+    Raw(String),
+}
+
+/// Directive such as `.word 1, 2` (name without the dot)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    pub name: String,
+    /// Comma-separated arguments
+    pub args: Vec<Operand>,
+}
+
+/// Instruction, pseudo-instruction or macro invocation, as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub mnemonic: String,
+    pub operands: Vec<Operand>,
+}
+
+/// One comma-separated operand or directive argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    /// Byte range in the source
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OperandKind {
+    /// Register name as written (`a0`, `r3`)
+    Register(String),
+    /// Memory operand `offset(base)`; the offset may be omitted
+    Memory { offset: Option<Expr>, base: String },
+    /// Immediate, label or expression; local label references keep their
+    /// written name (`Expr::Symbol("1f")`)
+    Expr(Expr),
+    /// String literal, escapes processed
+    Str(Vec<u8>),
+    /// Anything else, such as `limb_bits 20` in `.config limb_bits 20`
+    Raw(String),
+}
+
+impl Module {
+    /// Statements on `line` (1-based)
+    pub fn line(&self, line: usize) -> impl Iterator<Item = &Statement> {
+        self.statements.iter().filter(move |statement| statement.line == line)
+    }
+}
@@ -0,0 +1,81 @@
+//! `zkasm` command-line tool
+//!
+//! ```text
+//! zkasm fmt [--check] [--registers abi|numeric] [FILE...]
+//! ```
+//!
+//! `fmt` rewrites each file in the canonical layout (see
+//! [`zkir_assembler::formatter`]), or formats standard input to standard
+//! output when no file is given. With `--check` nothing is written; the
+//! files that would change are listed and the exit status is 1.
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use zkir_assembler::{format_source, FormatOptions, RegisterNames};
+
+const USAGE: &str = "usage: zkasm fmt [--check] [--registers abi|numeric] [FILE...]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.split_first() {
+        Some((command, rest)) if command == "fmt" => match fmt(rest) {
+            Ok(code) => code,
+            Err(message) => {
+                eprintln!("zkasm fmt: {}", message);
+                ExitCode::FAILURE
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn fmt(args: &[String]) -> Result<ExitCode, String> {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut files = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--registers" => {
+                options.registers = match args.next().map(String::as_str) {
+                    Some("abi") => RegisterNames::Abi,
+                    Some("numeric") => RegisterNames::Numeric,
+                    _ => return Err(format!("--registers takes abi or numeric\n{}", USAGE)),
+                };
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}\n{}", flag, USAGE)),
+            file => files.push(file),
+        }
+    }
+
+    if files.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).map_err(|e| e.to_string())?;
+        let formatted = format_source(&source, &options).map_err(|e| e.to_string())?;
+        if check {
+            return Ok(if formatted == source { ExitCode::SUCCESS } else { ExitCode::FAILURE });
+        }
+        io::stdout().write_all(formatted.as_bytes()).map_err(|e| e.to_string())?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut unformatted = false;
+    for file in files {
+        let source = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+        let formatted = format_source(&source, &options).map_err(|e| format!("{}: {}", file, e))?;
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            unformatted = true;
+        } else {
+            std::fs::write(file, formatted).map_err(|e| format!("{}: {}", file, e))?;
+        }
+    }
+    Ok(if unformatted { ExitCode::FAILURE } else { ExitCode::SUCCESS })
+}
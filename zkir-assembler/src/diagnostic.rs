@@ -0,0 +1,278 @@
+//! Structured diagnostics for ZKIR v3.4 assembly
+//!
+//! A [`Diagnostic`] is an [`AssemblerError`] resolved against the source:
+//! file, line, column span and severity, plus notes for the macro
+//! expansions it occurred in. Its `Display` renders a source snippet with
+//! the span underlined:
+//!
+//! ```text
+//! error: Invalid register: x9
+//!  --> main.zkasm:3:13
+//!   |
+//! 3 |     add r1, x9, r3
+//!   |             ^^
+//! ```
+
+use crate::error::AssemblerError;
+use std::fmt;
+use std::ops::Range;
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Source position of a diagnostic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// File name (`None` for in-memory source)
+    pub file: Option<String>,
+    /// 1-based line number
+    pub line: usize,
+    /// Byte columns within the line (0-based, end exclusive)
+    pub columns: Range<usize>,
+}
+
+/// Assembler diagnostic with location and source snippet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Message without location (e.g. "Invalid register: x9")
+    pub message: String,
+    /// Where the problem is, if it is tied to a line
+    pub span: Option<Span>,
+    /// Text of the line `span` points into
+    pub source_line: Option<String>,
+    /// Additional context, innermost first (e.g. macro invocation sites)
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Resolve an error against the source
+    ///
+    /// `source_line(file, line)` returns the raw text of a line; it supplies
+    /// the snippet and is searched for the offending operand to narrow the
+    /// column span. Errors are reported with [`Severity::Error`].
+    pub fn from_error<'a>(
+        err: &AssemblerError,
+        source_line: impl Fn(Option<&str>, usize) -> Option<&'a str>,
+    ) -> Self {
+        let mut file: Option<String> = None;
+        let mut notes = Vec::new();
+        let mut err = err;
+        loop {
+            match err {
+                AssemblerError::InFile { file: name, source } => {
+                    file = Some(name.clone());
+                    err = source;
+                }
+                AssemblerError::MacroExpansion { line, name, source } => {
+                    notes.push(format!("in expansion of macro `{}` at {}", name, location(file.as_deref(), *line)));
+                    // Body lines carry their own file (none for in-memory source)
+                    file = None;
+                    err = source;
+                }
+                _ => break,
+            }
+        }
+        notes.reverse();
+
+        let span = err.line().filter(|&line| line > 0).map(|line| (file, line));
+        let text = span.as_ref().and_then(|(file, line)| source_line(file.as_deref(), *line));
+        Diagnostic {
+            severity: Severity::Error,
+            message: message(err),
+            span: span.map(|(file, line)| Span {
+                file,
+                line,
+                columns: text.map_or(0..0, |text| columns(err, text)),
+            }),
+            source_line: text.map(str::to_string),
+            notes,
+        }
+    }
+
+    /// Resolve a warning against the source, like [`Diagnostic::from_error`]
+    pub fn from_warning<'a>(
+        warning: &AssemblerError,
+        source_line: impl Fn(Option<&str>, usize) -> Option<&'a str>,
+    ) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Self::from_error(warning, source_line)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+
+        if let Some(span) = &self.span {
+            let line_label = span.line.to_string();
+            let gutter = " ".repeat(line_label.len());
+            write!(f, "\n{}--> {}:{}", gutter, location(span.file.as_deref(), span.line), span.columns.start + 1)?;
+
+            if let Some(text) = &self.source_line {
+                // Tabs become single spaces so the caret lines up
+                let text = text.replace('\t', " ");
+                let start = text.get(..span.columns.start).map_or(0, |s| s.chars().count());
+                let width = text.get(span.columns.clone()).map_or(1, |s| s.chars().count().max(1));
+                write!(f, "\n{} |", gutter)?;
+                write!(f, "\n{} | {}", line_label, text)?;
+                write!(f, "\n{} | {}{}", gutter, " ".repeat(start), "^".repeat(width))?;
+            }
+        }
+
+        for note in &self.notes {
+            write!(f, "\n  = note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+fn location(file: Option<&str>, line: usize) -> String {
+    format!("{}:{}", file.unwrap_or("<source>"), line)
+}
+
+/// Error message without its "at line N"
+fn message(err: &AssemblerError) -> String {
+    let text = err.to_string();
+    match err.line() {
+        // Every located variant formats its line as " at line {line}"
+        Some(line) => text.replacen(&format!(" at line {}", line), "", 1),
+        None => text,
+    }
+}
+
+/// Columns of the offending operand in `text`, or of the whole statement
+fn columns(err: &AssemblerError, text: &str) -> Range<usize> {
+    let fragment = match err {
+        AssemblerError::InvalidInstruction { instruction: s, .. }
+        | AssemblerError::InvalidRegister { register: s, .. }
+        | AssemblerError::RegisterName { register: s, .. }
+        | AssemblerError::UndefinedLabel { label: s, .. }
+        | AssemblerError::DuplicateLabel { label: s, .. }
+        | AssemblerError::OffsetOutOfRange { label: s, .. }
+        | AssemblerError::InvalidExpression { expr: s, .. }
+        | AssemblerError::ValueOutOfRange { expr: s, .. }
+        | AssemblerError::Include { path: s, .. } => Some(s.as_str()),
+        AssemblerError::SyntaxError { message, .. } => quoted(message),
+        _ => None,
+    };
+
+    let code = crate::parser::strip_comment(text).trim_end();
+    let statement = code.len() - code.trim_start().len()..code.len();
+    fragment
+        .and_then(|fragment| find_ignoring_whitespace(code, fragment))
+        .unwrap_or(statement)
+}
+
+/// First backquoted part of a message (e.g. the token in "Invalid token `@`")
+fn quoted(message: &str) -> Option<&str> {
+    let start = message.find('`')? + 1;
+    let len = message[start..].find('`')?;
+    Some(&message[start..start + len])
+}
+
+/// Find `needle` in `haystack` as a whole word, ignoring whitespace differences
+///
+/// Expressions are reported in their normalized form (`a * 4` for `a*4`),
+/// so whitespace is skipped on both sides while matching. Case is ignored
+/// because register names are reported lowercased.
+fn find_ignoring_whitespace(haystack: &str, needle: &str) -> Option<Range<usize>> {
+    let needle: Vec<char> = needle.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_ascii_lowercase()).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    for (start, first) in haystack.char_indices() {
+        if first.is_whitespace() || haystack[..start].chars().next_back().is_some_and(|c| is_word(c) && is_word(first)) {
+            continue;
+        }
+        let mut matched = 0;
+        let mut end = start;
+        for (i, c) in haystack[start..].char_indices() {
+            if matched == needle.len() {
+                break;
+            }
+            if c.is_whitespace() {
+                continue;
+            }
+            if c.to_ascii_lowercase() != needle[matched] {
+                break;
+            }
+            matched += 1;
+            end = start + i + c.len_utf8();
+        }
+        let at_boundary = !haystack[end..].chars().next().is_some_and(|c| is_word(c) && is_word(needle[needle.len() - 1]));
+        if matched == needle.len() && at_boundary {
+            return Some(start..end);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_ignoring_whitespace() {
+        assert_eq!(find_ignoring_whitespace("add r1, x9, r3", "x9"), Some(8..10));
+        assert_eq!(find_ignoring_whitespace("addi r1, r1, N*4 + 1", "N * 4 + 1"), Some(13..20));
+        // Whole words only: `r1` is not found inside `r11`
+        assert_eq!(find_ignoring_whitespace("mv r11, r1", "r1"), Some(8..10));
+        assert_eq!(find_ignoring_whitespace("nop", "x"), None);
+    }
+
+    #[test]
+    fn test_diagnostic_from_nested_error() {
+        let err = AssemblerError::InFile {
+            file: "main.zkasm".to_string(),
+            source: Box::new(AssemblerError::MacroExpansion {
+                line: 9,
+                name: "push".to_string(),
+                source: Box::new(AssemblerError::InFile {
+                    file: "macros.zkasm".to_string(),
+                    source: Box::new(AssemblerError::InvalidRegister {
+                        line: 2,
+                        register: "x9".to_string(),
+                    }),
+                }),
+            }),
+        };
+        let diagnostic = Diagnostic::from_error(&err, |file, line| match (file, line) {
+            (Some("macros.zkasm"), 2) => Some("    sd x9, 0(sp)"),
+            _ => None,
+        });
+
+        assert_eq!(diagnostic.message, "Invalid register: x9");
+        let span = diagnostic.span.clone().unwrap();
+        assert_eq!((span.file.as_deref(), span.line, span.columns), (Some("macros.zkasm"), 2, 7..9));
+        assert_eq!(diagnostic.notes, vec!["in expansion of macro `push` at main.zkasm:9"]);
+        assert_eq!(
+            diagnostic.to_string(),
+            "error: Invalid register: x9\n --> macros.zkasm:2:8\n  |\n2 |     sd x9, 0(sp)\n  |        ^^\n  = note: in expansion of macro `push` at main.zkasm:9"
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_without_line() {
+        let diagnostic = Diagnostic::from_error(&AssemblerError::Other("boom".to_string()), |_, _| None);
+        assert_eq!(diagnostic.span, None);
+        assert_eq!(diagnostic.to_string(), "error: boom");
+    }
+}
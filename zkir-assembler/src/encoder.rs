@@ -10,11 +10,113 @@
 //! Note: Despite documentation claiming "6-bit opcodes", the actual opcode values
 //! range from 0x00-0x51 which requires 7 bits. We use 7 bits for the opcode field.
 
-use zkir_spec::{Instruction, Opcode, Register};
+use thiserror::Error;
+use zkir_spec::validation::{
+    B_TYPE_OFFSET_MAX, B_TYPE_OFFSET_MIN, I_TYPE_IMM_MAX, I_TYPE_IMM_MIN, J_TYPE_OFFSET_MAX, J_TYPE_OFFSET_MIN,
+    MAX_SHIFT_AMOUNT, OFFSET_ALIGNMENT,
+};
+use zkir_spec::{Config, Instruction, Opcode, Register};
+
+/// Instruction field that cannot be encoded
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EncodeError {
+    /// Immediate or offset outside the range of its field
+    #[error("{instruction} {field} {value} out of range [{min}, {max}]")]
+    OutOfRange {
+        instruction: &'static str,
+        field: &'static str,
+        value: i32,
+        min: i32,
+        max: i32,
+    },
+
+    /// Shift amount above [`MAX_SHIFT_AMOUNT`]
+    #[error("{instruction} shift amount {shamt} exceeds maximum {max}")]
+    ShiftAmountOutOfRange { instruction: &'static str, shamt: u8, max: u8 },
+
+    /// Branch or jump offset that is not a whole number of instructions
+    #[error("{instruction} offset {offset} is not aligned to {alignment} bytes")]
+    MisalignedOffset {
+        instruction: &'static str,
+        offset: i32,
+        alignment: u32,
+    },
+}
+
+/// Encode instruction to 32-bit word, checking every field
+///
+/// Immediates and offsets must fit their fields (the limits of
+/// [`zkir_spec::validation`]), branch and jump offsets must be multiples of
+/// the instruction size, and shift amounts must be at most
+/// [`MAX_SHIFT_AMOUNT`].
+///
+/// No field width depends on the configuration in v3.4, so `config` is not
+/// consulted yet; callers pass it so that config-dependent limits can be
+/// added without changing them. Shift amounts at or above
+/// `config.data_bits()` are well-formed and encode.
+pub fn try_encode(instr: &Instruction, _config: &Config) -> Result<u32, EncodeError> {
+    let instruction = instr.mnemonic();
+    match *instr {
+        Instruction::Addi { imm, .. }
+        | Instruction::Andi { imm, .. }
+        | Instruction::Ori { imm, .. }
+        | Instruction::Xori { imm, .. }
+        | Instruction::Lb { imm, .. }
+        | Instruction::Lbu { imm, .. }
+        | Instruction::Lh { imm, .. }
+        | Instruction::Lhu { imm, .. }
+        | Instruction::Lw { imm, .. }
+        | Instruction::Ld { imm, .. }
+        | Instruction::Sb { imm, .. }
+        | Instruction::Sh { imm, .. }
+        | Instruction::Sw { imm, .. }
+        | Instruction::Sd { imm, .. }
+        | Instruction::Jalr { imm, .. } => check_range(instruction, "immediate", imm, I_TYPE_IMM_MIN, I_TYPE_IMM_MAX)?,
+
+        Instruction::Slli { shamt, .. } | Instruction::Srli { shamt, .. } | Instruction::Srai { shamt, .. }
+            if shamt > MAX_SHIFT_AMOUNT =>
+        {
+            return Err(EncodeError::ShiftAmountOutOfRange { instruction, shamt, max: MAX_SHIFT_AMOUNT });
+        }
+
+        Instruction::Beq { offset, .. }
+        | Instruction::Bne { offset, .. }
+        | Instruction::Blt { offset, .. }
+        | Instruction::Bge { offset, .. }
+        | Instruction::Bltu { offset, .. }
+        | Instruction::Bgeu { offset, .. } => check_offset(instruction, offset, B_TYPE_OFFSET_MIN, B_TYPE_OFFSET_MAX)?,
+        Instruction::Jal { offset, .. } => check_offset(instruction, offset, J_TYPE_OFFSET_MIN, J_TYPE_OFFSET_MAX)?,
+
+        _ => {}
+    }
+    Ok(encode(instr))
+}
+
+fn check_range(instruction: &'static str, field: &'static str, value: i32, min: i32, max: i32) -> Result<(), EncodeError> {
+    if (min..=max).contains(&value) {
+        Ok(())
+    } else {
+        Err(EncodeError::OutOfRange { instruction, field, value, min, max })
+    }
+}
+
+fn check_offset(instruction: &'static str, offset: i32, min: i32, max: i32) -> Result<(), EncodeError> {
+    check_range(instruction, "offset", offset, min, max)?;
+    if offset % OFFSET_ALIGNMENT as i32 != 0 {
+        return Err(EncodeError::MisalignedOffset {
+            instruction,
+            offset,
+            alignment: OFFSET_ALIGNMENT,
+        });
+    }
+    Ok(())
+}
 
 /// Encode instruction to 32-bit word
 ///
-/// Uses opcodes from the ZKIR v3.4 spec (values 0x00-0x51)
+/// Uses opcodes from the ZKIR v3.4 spec (values 0x00-0x51). Fields are
+/// truncated to their width without checking; use [`try_encode`] unless the
+/// instruction is known to be valid.
 pub fn encode(instr: &Instruction) -> u32 {
     match instr {
         // ========== Arithmetic (R-type: 0x00-0x07) ==========
@@ -327,6 +429,51 @@ mod tests {
         assert_eq!(imm_bits, 0x1FFFF);
     }
 
+    #[test]
+    fn test_try_encode_checks_ranges() {
+        let config = Config::DEFAULT;
+        let addi = Instruction::Addi { rd: Register::R10, rs1: Register::R10, imm: 200000 };
+        assert_eq!(
+            try_encode(&addi, &config),
+            Err(EncodeError::OutOfRange {
+                instruction: "addi",
+                field: "immediate",
+                value: 200000,
+                min: -65536,
+                max: 65535,
+            })
+        );
+
+        let sw = Instruction::Sw { rs1: Register::R2, rs2: Register::R1, imm: -65536 };
+        assert_eq!(try_encode(&sw, &config), Ok(encode(&sw)));
+
+        let jal = Instruction::Jal { rd: Register::R0, offset: 1 << 20 };
+        assert!(matches!(try_encode(&jal, &config), Err(EncodeError::OutOfRange { field: "offset", .. })));
+    }
+
+    #[test]
+    fn test_try_encode_checks_offset_alignment() {
+        let beq = Instruction::Beq { rs1: Register::R1, rs2: Register::R2, offset: 6 };
+        let err = try_encode(&beq, &Config::DEFAULT).unwrap_err();
+        assert_eq!(err.to_string(), "beq offset 6 is not aligned to 4 bytes");
+
+        let beq = Instruction::Beq { rs1: Register::R1, rs2: Register::R2, offset: -8 };
+        assert!(try_encode(&beq, &Config::DEFAULT).is_ok());
+    }
+
+    #[test]
+    fn test_try_encode_shift_amount_limit() {
+        // Past the 40-bit data width of the default config, but encodable
+        let slli = Instruction::Slli { rd: Register::R1, rs1: Register::R1, shamt: 45 };
+        assert_eq!(try_encode(&slli, &Config::DEFAULT), Ok(encode(&slli)));
+
+        let slli = Instruction::Slli { rd: Register::R1, rs1: Register::R1, shamt: 64 };
+        assert_eq!(
+            try_encode(&slli, &Config::DEFAULT),
+            Err(EncodeError::ShiftAmountOutOfRange { instruction: "slli", shamt: 64, max: 63 })
+        );
+    }
+
     #[test]
     fn test_opcode_values() {
         // Verify opcodes match ZKIR v3.4 spec (using 7-bit field)
@@ -1,5 +1,6 @@
 //! # Error Types for ZKIR Assembler v3.4
 
+use crate::encoder::EncodeError;
 use thiserror::Error;
 use zkir_spec::{ConfigError, ZkIrError};
 
@@ -18,6 +19,25 @@ pub enum AssemblerError {
     #[error("Invalid register at line {line}: {register}")]
     InvalidRegister { line: usize, register: String },
 
+    /// ABI register name that needs attention (reported as a warning)
+    #[error("Register name at line {line}: {message}")]
+    RegisterName {
+        line: usize,
+        register: String,
+        message: String,
+    },
+
+    /// Finding of the static checks run over assembled instructions
+    /// (a warning, or an error for invalid instructions)
+    #[error("Static check at line {line}: {message}")]
+    StaticCheck { line: usize, message: String },
+
+    /// Side effect of branch relaxation the source did not ask for (a
+    /// warning for a clobbered scratch register, an error for an explicit
+    /// offset that no longer reaches its instruction)
+    #[error("Branch relaxation at line {line}: {message}")]
+    Relaxation { line: usize, message: String },
+
     /// Invalid immediate value
     #[error("Invalid immediate value at line {line}: {value}")]
     InvalidImmediate { line: usize, value: String },
@@ -26,6 +46,62 @@ pub enum AssemblerError {
     #[error("Undefined label at line {line}: {label}")]
     UndefinedLabel { line: usize, label: String },
 
+    /// Branch or jump target does not fit the offset field
+    #[error("Offset out of range at line {line}: {label} is {offset} bytes away ({bits}-bit offset field)")]
+    OffsetOutOfRange {
+        line: usize,
+        label: String,
+        offset: i64,
+        bits: u32,
+    },
+
+    /// Expression cannot be evaluated (overflow, division by zero, ...)
+    #[error("Invalid expression at line {line}: `{expr}`: {message}")]
+    InvalidExpression {
+        line: usize,
+        expr: String,
+        message: String,
+    },
+
+    /// Expression value does not fit the field it is assembled into
+    #[error("Value out of range at line {line}: `{expr}` = {value} does not fit in {field}")]
+    ValueOutOfRange {
+        line: usize,
+        expr: String,
+        value: i64,
+        field: String,
+    },
+
+    /// Resolved instruction has a field its encoding cannot hold
+    #[error("Cannot encode instruction at line {line}: {source}")]
+    Encode { line: usize, source: EncodeError },
+
+    /// Error on a line produced by a macro expansion
+    ///
+    /// `line` is the invocation site; the line inside `source` is the
+    /// macro-body line.
+    #[error("In expansion of macro {name} at line {line}: {source}")]
+    MacroExpansion {
+        line: usize,
+        name: String,
+        source: Box<AssemblerError>,
+    },
+
+    /// `.include` or `.incbin` file could not be found, read, or would recurse
+    #[error("Cannot include \"{path}\" at line {line}: {message}")]
+    Include {
+        line: usize,
+        path: String,
+        message: String,
+    },
+
+    /// Error in a named source file (from `assemble_file` or `.include`)
+    #[error("{file}: {source}")]
+    InFile {
+        file: String,
+        source: Box<AssemblerError>,
+    },
+
     /// Duplicate label
     #[error("Duplicate label at line {line}: {label}")]
     DuplicateLabel { line: usize, label: String },
@@ -57,11 +133,86 @@ pub enum AssemblerError {
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 
+    /// Several errors from one assembly run, in the order they were found
+    #[error("{}", format_errors(.0))]
+    Multiple(Vec<AssemblerError>),
+
     /// General error
     #[error("{0}")]
     Other(String),
 }
 
+impl AssemblerError {
+    /// Combine the errors of an assembly run (a single error is returned as is)
+    pub(crate) fn from_errors(mut errors: Vec<AssemblerError>) -> Self {
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            AssemblerError::Multiple(errors)
+        }
+    }
+
+    /// Split [`AssemblerError::Multiple`] into its errors
+    pub fn into_errors(self) -> Vec<AssemblerError> {
+        match self {
+            AssemblerError::Multiple(errors) => errors,
+            err => vec![err],
+        }
+    }
+
+    /// Source line the error refers to, if any
+    ///
+    /// For [`AssemblerError::InFile`] this is the line within that file; for
+    /// [`AssemblerError::MacroExpansion`] it is the invocation line.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            AssemblerError::SyntaxError { line, .. }
+            | AssemblerError::InvalidInstruction { line, .. }
+            | AssemblerError::InvalidRegister { line, .. }
+            | AssemblerError::RegisterName { line, .. }
+            | AssemblerError::StaticCheck { line, .. }
+            | AssemblerError::Relaxation { line, .. }
+            | AssemblerError::InvalidImmediate { line, .. }
+            | AssemblerError::UndefinedLabel { line, .. }
+            | AssemblerError::OffsetOutOfRange { line, .. }
+            | AssemblerError::InvalidExpression { line, .. }
+            | AssemblerError::ValueOutOfRange { line, .. }
+            | AssemblerError::Encode { line, .. }
+            | AssemblerError::MacroExpansion { line, .. }
+            | AssemblerError::Include { line, .. }
+            | AssemblerError::DuplicateLabel { line, .. }
+            | AssemblerError::InvalidDirective { line, .. }
+            | AssemblerError::ConfigError { line, .. }
+            | AssemblerError::InvalidConfigValue { line, .. } => Some(*line),
+            AssemblerError::InFile { source, .. } => source.line(),
+            AssemblerError::SpecError(_)
+            | AssemblerError::IoError(_)
+            | AssemblerError::Multiple(_)
+            | AssemblerError::Other(_) => None,
+        }
+    }
+
+    /// Fill in the line of an error raised without one (line 0)
+    pub(crate) fn at_line(mut self, line: usize) -> Self {
+        match &mut self {
+            AssemblerError::SyntaxError { line: l, .. } | AssemblerError::InvalidRegister { line: l, .. } if *l == 0 => {
+                *l = line;
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+fn format_errors(errors: &[AssemblerError]) -> String {
+    let mut text = format!("{} errors:", errors.len());
+    for err in errors {
+        text.push_str("\n  ");
+        text.push_str(&err.to_string());
+    }
+    text
+}
+
 /// Result type for assembler operations
 pub type Result<T> = std::result::Result<T, AssemblerError>;
 
@@ -118,6 +269,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_encode_display() {
+        let err = AssemblerError::Encode {
+            line: 2,
+            source: EncodeError::MisalignedOffset {
+                instruction: "bne",
+                offset: 6,
+                alignment: 4,
+            },
+        };
+        assert_eq!(err.to_string(), "Cannot encode instruction at line 2: bne offset 6 is not aligned to 4 bytes");
+    }
+
+    #[test]
+    fn test_value_out_of_range_display() {
+        let err = AssemblerError::ValueOutOfRange {
+            line: 4,
+            expr: "BUF_SIZE * 4".to_string(),
+            value: 262144,
+            field: "17-bit signed immediate".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Value out of range at line 4: `BUF_SIZE * 4` = 262144 does not fit in 17-bit signed immediate"
+        );
+    }
+
+    #[test]
+    fn test_macro_expansion_display() {
+        let err = AssemblerError::MacroExpansion {
+            line: 20,
+            name: "save".to_string(),
+            source: Box::new(AssemblerError::SyntaxError {
+                line: 3,
+                message: "Expected register".to_string(),
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "In expansion of macro save at line 20: Syntax error at line 3: Expected register"
+        );
+    }
+
+    #[test]
+    fn test_in_file_display() {
+        let err = AssemblerError::InFile {
+            file: "lib/io.zkasm".to_string(),
+            source: Box::new(AssemblerError::Include {
+                line: 4,
+                path: "missing.zkasm".to_string(),
+                message: "file not found".to_string(),
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "lib/io.zkasm: Cannot include \"missing.zkasm\" at line 4: file not found"
+        );
+    }
+
+    #[test]
+    fn test_multiple_display_and_line() {
+        let err = AssemblerError::from_errors(vec![
+            AssemblerError::InvalidRegister {
+                line: 2,
+                register: "x9".to_string(),
+            },
+            AssemblerError::UndefinedLabel {
+                line: 7,
+                label: "done".to_string(),
+            },
+        ]);
+        assert_eq!(err.line(), None);
+        assert_eq!(
+            err.to_string(),
+            "2 errors:\n  Invalid register at line 2: x9\n  Undefined label at line 7: done"
+        );
+
+        let errors = err.into_errors();
+        assert_eq!(errors[1].line(), Some(7));
+        assert_eq!(AssemblerError::from_errors(errors.into_iter().take(1).collect()).line(), Some(2));
+    }
+
+    #[test]
+    fn test_offset_out_of_range_display() {
+        let err = AssemblerError::OffsetOutOfRange {
+            line: 12,
+            label: "far".to_string(),
+            offset: 70000,
+            bits: 17,
+        };
+        assert_eq!(
+            err.to_string(),
+            "Offset out of range at line 12: far is 70000 bytes away (17-bit offset field)"
+        );
+    }
+
     #[test]
     fn test_duplicate_label_display() {
         let err = AssemblerError::DuplicateLabel {
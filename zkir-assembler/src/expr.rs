@@ -0,0 +1,637 @@
+//! Assemble-time expressions for ZKIR v3.4
+//!
+//! Immediates, branch targets, data values and `.equ`/`.set` definitions
+//! accept integer expressions over numbers and symbols:
+//!
+//! - Binary operators, loosest to tightest: `||`, `&&`, `|`, `^`, `&`,
+//!   `==` `!=`, `<` `<=` `>` `>=`, `<<` `>>`, `+` `-`, `*` `/` `%`
+//! - Unary operators: `-`, `~`, `!`
+//!
+//! Comparisons and the logical operators `||`, `&&` and `!` yield 1 for
+//! true and 0 for false; any non-zero operand counts as true.
+//! - Parentheses for grouping
+//! - `%hi(expr)` = `expr >> 16` and `%lo(expr)` = `expr & 0xFFFF`, so that
+//!   `(%hi(x) << 16) | %lo(x) == x` and `%lo` always fits a positive 17-bit
+//!   immediate
+//!
+//! Arithmetic is 64-bit signed; overflow and division by zero are errors
+//! that name the offending sub-expression.
+
+use crate::error::{AssemblerError, Result};
+use crate::lexer::Token;
+use std::fmt;
+
+/// Unary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `~x`
+    Not,
+    /// `!x`
+    LogicalNot,
+}
+
+impl UnaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "~",
+            UnaryOp::LogicalNot => "!",
+        }
+    }
+}
+
+/// Binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    LogicalOr,
+    LogicalAnd,
+    Or,
+    Xor,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    /// Binding strength (higher binds tighter)
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::LogicalOr => 1,
+            BinaryOp::LogicalAnd => 2,
+            BinaryOp::Or => 3,
+            BinaryOp::Xor => 4,
+            BinaryOp::And => 5,
+            BinaryOp::Eq | BinaryOp::Ne => 6,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => 7,
+            BinaryOp::Shl | BinaryOp::Shr => 8,
+            BinaryOp::Add | BinaryOp::Sub => 9,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 10,
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::OrOr => Some(BinaryOp::LogicalOr),
+            Token::AndAnd => Some(BinaryOp::LogicalAnd),
+            Token::Pipe => Some(BinaryOp::Or),
+            Token::Caret => Some(BinaryOp::Xor),
+            Token::Amp => Some(BinaryOp::And),
+            Token::EqEq => Some(BinaryOp::Eq),
+            Token::NotEq => Some(BinaryOp::Ne),
+            Token::Lt => Some(BinaryOp::Lt),
+            Token::Le => Some(BinaryOp::Le),
+            Token::Gt => Some(BinaryOp::Gt),
+            Token::Ge => Some(BinaryOp::Ge),
+            Token::Shl => Some(BinaryOp::Shl),
+            Token::Shr => Some(BinaryOp::Shr),
+            Token::Plus => Some(BinaryOp::Add),
+            Token::Minus => Some(BinaryOp::Sub),
+            Token::Star => Some(BinaryOp::Mul),
+            Token::Slash => Some(BinaryOp::Div),
+            Token::Percent => Some(BinaryOp::Rem),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::LogicalOr => "||",
+            BinaryOp::LogicalAnd => "&&",
+            BinaryOp::Or => "|",
+            BinaryOp::Xor => "^",
+            BinaryOp::And => "&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+        }
+    }
+}
+
+/// Expression tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(i64),
+    Symbol(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `%hi(expr)`
+    Hi(Box<Expr>),
+    /// `%lo(expr)`
+    Lo(Box<Expr>),
+}
+
+/// Precedence of unary operators and atoms when printing
+const UNARY_PRECEDENCE: u8 = 11;
+
+impl Expr {
+    /// Parse a complete expression from `tokens`
+    pub fn parse(tokens: &[Token], line_num: usize) -> Result<Expr> {
+        let tokens = split_negative_literals(tokens);
+        let mut parser = Parser { tokens: &tokens, pos: 0, line: line_num + 1 };
+        let expr = parser.expression(1)?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(parser.error(format!("Unexpected {:?} in expression", token))),
+        }
+    }
+
+    /// Symbols referenced by the expression, in order of appearance
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut out = Vec::new();
+        self.collect_symbols(&mut out);
+        out
+    }
+
+    fn collect_symbols<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Symbol(name) => out.push(name),
+            Expr::Unary(_, e) | Expr::Hi(e) | Expr::Lo(e) => e.collect_symbols(out),
+            Expr::Binary(_, a, b) => {
+                a.collect_symbols(out);
+                b.collect_symbols(out);
+            }
+        }
+    }
+
+    /// Replace symbols for which `f` returns an expression
+    pub fn substitute(&self, f: &dyn Fn(&str) -> Option<Expr>) -> Expr {
+        match self {
+            Expr::Number(_) => self.clone(),
+            Expr::Symbol(name) => f(name).unwrap_or_else(|| self.clone()),
+            Expr::Unary(op, e) => Expr::Unary(*op, Box::new(e.substitute(f))),
+            Expr::Binary(op, a, b) => Expr::Binary(*op, Box::new(a.substitute(f)), Box::new(b.substitute(f))),
+            Expr::Hi(e) => Expr::Hi(Box::new(e.substitute(f))),
+            Expr::Lo(e) => Expr::Lo(Box::new(e.substitute(f))),
+        }
+    }
+
+    /// Evaluate the expression, looking up symbols with `resolve`
+    pub fn eval(&self, line: usize, resolve: &dyn Fn(&str) -> Result<i64>) -> Result<i64> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Symbol(name) => resolve(name),
+            Expr::Unary(op, e) => {
+                let value = e.eval(line, resolve)?;
+                match op {
+                    UnaryOp::Neg => value.checked_neg().ok_or_else(|| self.overflow(line)),
+                    UnaryOp::Not => Ok(!value),
+                    UnaryOp::LogicalNot => Ok((value == 0).into()),
+                }
+            }
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(line, resolve)?, b.eval(line, resolve)?);
+                let result = match op {
+                    BinaryOp::LogicalOr => Some((a != 0 || b != 0).into()),
+                    BinaryOp::LogicalAnd => Some((a != 0 && b != 0).into()),
+                    BinaryOp::Or => Some(a | b),
+                    BinaryOp::Xor => Some(a ^ b),
+                    BinaryOp::And => Some(a & b),
+                    BinaryOp::Eq => Some((a == b).into()),
+                    BinaryOp::Ne => Some((a != b).into()),
+                    BinaryOp::Lt => Some((a < b).into()),
+                    BinaryOp::Le => Some((a <= b).into()),
+                    BinaryOp::Gt => Some((a > b).into()),
+                    BinaryOp::Ge => Some((a >= b).into()),
+                    BinaryOp::Shl => shift_amount(b).and_then(|b| a.checked_mul(1i64.checked_shl(b)?)),
+                    BinaryOp::Shr => shift_amount(b).map(|b| a >> b),
+                    BinaryOp::Add => a.checked_add(b),
+                    BinaryOp::Sub => a.checked_sub(b),
+                    BinaryOp::Mul => a.checked_mul(b),
+                    BinaryOp::Div | BinaryOp::Rem if b == 0 => {
+                        return Err(self.error(line, "division by zero"));
+                    }
+                    BinaryOp::Div => a.checked_div(b),
+                    BinaryOp::Rem => a.checked_rem(b),
+                };
+                result.ok_or_else(|| self.overflow(line))
+            }
+            Expr::Hi(e) => Ok(e.eval(line, resolve)? >> 16),
+            Expr::Lo(e) => Ok(e.eval(line, resolve)? & 0xFFFF),
+        }
+    }
+
+    /// Evaluate the expression for a relocatable object
+    ///
+    /// `resolve` maps symbols to link-time values. Only `+`, `-` and
+    /// multiplication by a constant may involve link-time addresses; every
+    /// other operator needs operands that are absolute (for example label
+    /// differences within one section).
+    pub(crate) fn eval_relocatable<B: Clone + PartialEq>(
+        &self,
+        line: usize,
+        resolve: &dyn Fn(&str) -> Result<Relocatable<B>>,
+    ) -> Result<Relocatable<B>> {
+        let result = match self {
+            Expr::Number(n) => Some(Relocatable::absolute(*n)),
+            Expr::Symbol(name) => return resolve(name),
+            Expr::Unary(UnaryOp::Neg, e) => e.eval_relocatable(line, resolve)?.scale(-1),
+            Expr::Binary(op @ (BinaryOp::Add | BinaryOp::Sub), a, b) => {
+                let a = a.eval_relocatable(line, resolve)?;
+                let b = b.eval_relocatable(line, resolve)?;
+                a.combine(&b, if *op == BinaryOp::Add { 1 } else { -1 })
+            }
+            Expr::Binary(BinaryOp::Mul, a, b) => {
+                let a = a.eval_relocatable(line, resolve)?;
+                let b = b.eval_relocatable(line, resolve)?;
+                match (a.absolute_value(), b.absolute_value()) {
+                    (_, Some(k)) => a.scale(k),
+                    (Some(k), None) => b.scale(k),
+                    (None, None) => return Err(self.not_relocatable(line)),
+                }
+            }
+            _ => {
+                // Evaluate the operator on absolute operands
+                let operand = |e: &Expr| -> Result<Expr> {
+                    e.eval_relocatable(line, resolve)?
+                        .absolute_value()
+                        .map(Expr::Number)
+                        .ok_or_else(|| self.not_relocatable(line))
+                };
+                let folded = match self {
+                    Expr::Unary(op, e) => Expr::Unary(*op, Box::new(operand(e)?)),
+                    Expr::Binary(op, a, b) => Expr::Binary(*op, Box::new(operand(a)?), Box::new(operand(b)?)),
+                    Expr::Hi(e) => Expr::Hi(Box::new(operand(e)?)),
+                    Expr::Lo(e) => Expr::Lo(Box::new(operand(e)?)),
+                    Expr::Number(_) | Expr::Symbol(_) => unreachable!("handled above"),
+                };
+                // Report errors against the original expression
+                let value = folded
+                    .eval(line, &|_| unreachable!("folded expressions have no symbols"))
+                    .map_err(|err| match err {
+                        AssemblerError::InvalidExpression { message, .. } => self.error(line, &message),
+                        err => err,
+                    })?;
+                Some(Relocatable::absolute(value))
+            }
+        };
+        result.ok_or_else(|| self.overflow(line))
+    }
+
+    /// Error for an expression that cannot be expressed as a relocation
+    pub(crate) fn not_relocatable(&self, line: usize) -> AssemblerError {
+        self.error(line, "depends on a link-time address; only `symbol + constant` can be relocated")
+    }
+
+    fn overflow(&self, line: usize) -> AssemblerError {
+        self.error(line, "arithmetic overflow")
+    }
+
+    fn error(&self, line: usize, message: &str) -> AssemblerError {
+        AssemblerError::InvalidExpression {
+            line,
+            expr: self.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn fmt_prec(&self, f: &mut fmt::Formatter<'_>, min_prec: u8) -> fmt::Result {
+        match self {
+            Expr::Number(n) if *n < 0 && min_prec > 1 => write!(f, "({})", n),
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Unary(op, e) => {
+                write!(f, "{}", op.symbol())?;
+                e.fmt_prec(f, UNARY_PRECEDENCE)
+            }
+            Expr::Binary(op, a, b) => {
+                let prec = op.precedence();
+                if prec < min_prec {
+                    write!(f, "(")?;
+                }
+                a.fmt_prec(f, prec)?;
+                write!(f, " {} ", op.symbol())?;
+                b.fmt_prec(f, prec + 1)?;
+                if prec < min_prec {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Expr::Hi(e) => {
+                write!(f, "%hi(")?;
+                e.fmt_prec(f, 1)?;
+                write!(f, ")")
+            }
+            Expr::Lo(e) => {
+                write!(f, "%lo(")?;
+                e.fmt_prec(f, 1)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_prec(f, 1)
+    }
+}
+
+/// Value of an expression in a relocatable object: `addend + Σ coefficient × base`
+///
+/// Bases stand for addresses known only after linking (section starts and
+/// external symbols); label differences within a section cancel out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Relocatable<B> {
+    pub(crate) addend: i64,
+    pub(crate) terms: Vec<(B, i64)>,
+}
+
+impl<B: Clone + PartialEq> Relocatable<B> {
+    pub(crate) fn absolute(value: i64) -> Self {
+        Self { addend: value, terms: Vec::new() }
+    }
+
+    /// Link-time address of `base` plus `offset`
+    pub(crate) fn relative(base: B, offset: i64) -> Self {
+        Self { addend: offset, terms: vec![(base, 1)] }
+    }
+
+    /// Value if no link-time address is involved
+    pub(crate) fn absolute_value(&self) -> Option<i64> {
+        self.terms.is_empty().then_some(self.addend)
+    }
+
+    /// Split into `(base, addend)`: no base for absolute values, `None` if
+    /// the value is not a single base plus a constant
+    pub(crate) fn single(&self) -> Option<(Option<&B>, i64)> {
+        match self.terms.as_slice() {
+            [] => Some((None, self.addend)),
+            [(base, 1)] => Some((Some(base), self.addend)),
+            _ => None,
+        }
+    }
+
+    /// `self + sign × other`
+    fn combine(&self, other: &Self, sign: i64) -> Option<Self> {
+        let mut result = self.clone();
+        result.addend = result.addend.checked_add(other.addend.checked_mul(sign)?)?;
+        for (base, coefficient) in &other.terms {
+            let coefficient = coefficient.checked_mul(sign)?;
+            match result.terms.iter_mut().find(|(b, _)| b == base) {
+                Some((_, c)) => *c = c.checked_add(coefficient)?,
+                None => result.terms.push((base.clone(), coefficient)),
+            }
+        }
+        result.terms.retain(|(_, c)| *c != 0);
+        Some(result)
+    }
+
+    fn scale(&self, k: i64) -> Option<Self> {
+        let mut terms = Vec::with_capacity(self.terms.len());
+        for (base, c) in &self.terms {
+            terms.push((base.clone(), c.checked_mul(k)?));
+        }
+        terms.retain(|(_, c)| *c != 0);
+        Some(Self { addend: self.addend.checked_mul(k)?, terms })
+    }
+}
+
+/// Shift amounts must be in `0..64`
+fn shift_amount(b: i64) -> Option<u32> {
+    u32::try_from(b).ok().filter(|b| *b < 64)
+}
+
+/// Split negative literals that follow an operand into `-` and a literal
+///
+/// The lexer reads `a-1` as `a` followed by `Number(-1)`; in operator
+/// position that is a subtraction.
+fn split_negative_literals(tokens: &[Token]) -> Vec<Token> {
+    let mut out: Vec<Token> = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        let after_operand = matches!(
+            out.last(),
+            Some(Token::Number(_) | Token::Hex(_) | Token::Binary(_) | Token::Identifier(_) | Token::RParen)
+        );
+        match token {
+            Token::Number(n) if *n < 0 && after_operand => {
+                out.push(Token::Minus);
+                // -i64::MIN does not fit; keep the literal so parsing reports it
+                out.push(n.checked_neg().map_or(token.clone(), Token::Number));
+            }
+            _ => out.push(token.clone()),
+        }
+    }
+    out
+}
+
+/// Precedence-climbing expression parser
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn error(&self, message: String) -> AssemblerError {
+        AssemblerError::SyntaxError { line: self.line, message }
+    }
+
+    /// Parse a binary expression whose operators bind at least `min_prec`
+    fn expression(&mut self, min_prec: u8) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek().and_then(BinaryOp::from_token) {
+            if op.precedence() < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.expression(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.unary()?)))
+            }
+            Some(Token::Tilde) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)))
+            }
+            Some(Token::Bang) => {
+                self.pos += 1;
+                Ok(Expr::Unary(UnaryOp::LogicalNot, Box::new(self.unary()?)))
+            }
+            Some(Token::Plus) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.atom(),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(*n)),
+            Some(Token::Hex(n)) | Some(Token::Binary(n)) => Ok(Expr::Number(*n as i64)),
+            Some(Token::Identifier(name)) => Ok(Expr::Symbol(name.clone())),
+            Some(Token::LParen) => {
+                let expr = self.expression(1)?;
+                self.expect_rparen()?;
+                Ok(expr)
+            }
+            Some(Token::Percent) => {
+                let op = match self.next() {
+                    Some(Token::Identifier(op)) if op == "hi" || op == "lo" => op,
+                    other => return Err(self.error(format!("Expected %hi or %lo, got %{:?}", other))),
+                };
+                if !matches!(self.next(), Some(Token::LParen)) {
+                    return Err(self.error(format!("Expected '(' after %{}", op)));
+                }
+                let inner = Box::new(self.expression(1)?);
+                self.expect_rparen()?;
+                Ok(if op == "hi" { Expr::Hi(inner) } else { Expr::Lo(inner) })
+            }
+            Some(token) => Err(self.error(format!("Expected number or symbol, got {:?}", token))),
+            None => Err(self.error("Expected expression".to_string())),
+        }
+    }
+
+    fn expect_rparen(&mut self) -> Result<()> {
+        match self.next() {
+            Some(Token::RParen) => Ok(()),
+            other => Err(self.error(format!("Expected ')', got {:?}", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::tokenize;
+
+    fn parse(source: &str) -> Expr {
+        Expr::parse(&tokenize(source).unwrap(), 0).unwrap()
+    }
+
+    fn eval(source: &str) -> Result<i64> {
+        parse(source).eval(1, &|name| match name {
+            "BUF_SIZE" => Ok(64),
+            "big" => Ok(i64::MAX),
+            _ => Err(AssemblerError::UndefinedLabel { line: 1, label: name.to_string() }),
+        })
+    }
+
+    fn eval_relocatable(source: &str) -> Result<Relocatable<&'static str>> {
+        parse(source).eval_relocatable(1, &|name| match name {
+            "start" => Ok(Relocatable::relative(".text", 8)),
+            "end" => Ok(Relocatable::relative(".text", 24)),
+            "ext" => Ok(Relocatable::relative("ext", 0)),
+            _ => Ok(Relocatable::absolute(4)),
+        })
+    }
+
+    #[test]
+    fn test_eval_relocatable() {
+        assert_eq!(eval_relocatable("ext + 4*N").unwrap().single(), Some((Some(&"ext"), 16)));
+        assert_eq!(eval_relocatable("start - 4").unwrap().single(), Some((Some(&".text"), 4)));
+        // Differences within a section are absolute, so any operator applies
+        assert_eq!(eval_relocatable("(end - start) / 4").unwrap().single(), Some((None, 4)));
+        assert_eq!(eval_relocatable("ext + end - start").unwrap().single(), Some((Some(&"ext"), 16)));
+
+        assert!(eval_relocatable("ext + start").unwrap().single().is_none());
+        let err = eval_relocatable("%hi(ext)").unwrap_err();
+        assert!(err.to_string().contains("`%hi(ext)`"), "{}", err);
+        let err = eval_relocatable("(end - start) / (start - start)").unwrap_err();
+        assert!(err.to_string().contains("division by zero"), "{}", err);
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(eval("BUF_SIZE*4 + 8").unwrap(), 264);
+        assert_eq!(eval("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(eval("(1 + 2) * 3").unwrap(), 9);
+        assert_eq!(eval("1 << 4 | 1").unwrap(), 17);
+        assert_eq!(eval("0xFF & ~0xF").unwrap(), 0xF0);
+        assert_eq!(eval("10 - 2 - 3").unwrap(), 5);
+        assert_eq!(eval("-7 / 2").unwrap(), -3);
+        assert_eq!(eval("17 % 5").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_comparisons_and_logic() {
+        assert_eq!(eval("BUF_SIZE > 40").unwrap(), 1);
+        assert_eq!(eval("BUF_SIZE <= 40").unwrap(), 0);
+        assert_eq!(eval("1 + 1 == 2 && 3 != 3 || !0").unwrap(), 1);
+        assert_eq!(eval("!(BUF_SIZE >= 64) || 2 < 1").unwrap(), 0);
+        // Comparisons bind looser than shifts and tighter than bitwise operators
+        assert_eq!(eval("1 << 2 == 4 & 1").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_negative_literal_after_operand() {
+        // The lexer reads `-1` as a single negative number
+        assert_eq!(eval("BUF_SIZE-1").unwrap(), 63);
+        assert_eq!(eval("2*-1").unwrap(), -2);
+    }
+
+    #[test]
+    fn test_hi_lo_recombine() {
+        for value in [0x12345678i64, 0x1000, -5, 0xFFFF] {
+            let source = format!("(%hi({0}) << 16) | %lo({0})", value);
+            assert_eq!(eval(&source).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_errors_name_sub_expression() {
+        let err = eval("1 + big * 2").unwrap_err();
+        assert!(err.to_string().contains("`big * 2`"), "{}", err);
+
+        let err = eval("BUF_SIZE / (BUF_SIZE - 64)").unwrap_err();
+        assert!(err.to_string().contains("division by zero"), "{}", err);
+
+        assert!(matches!(eval("missing + 1"), Err(AssemblerError::UndefinedLabel { .. })));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for source in ["1 +", "(1", "1 2", "%foo(1)", "%hi 1", "*"] {
+            let tokens = tokenize(source).unwrap();
+            assert!(Expr::parse(&tokens, 0).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for source in ["a + b * 4", "(a + b) * 4", "a - (b - c)", "%hi(sym + 8)", "-x", "~(a | b)", "!(a < b) && c == 1"] {
+            assert_eq!(parse(source).to_string(), source);
+        }
+    }
+}
@@ -0,0 +1,440 @@
+//! Canonical formatting of zkasm source
+//!
+//! [`format_source`] rewrites source in one layout, so that diffs between
+//! versions of a file only show real changes:
+//!
+//! ```text
+//! .equ COUNT, 3
+//!
+//! main:
+//!     li      a0, COUNT * 4       # operands start in column 12
+//! 1:
+//!     addi    a0, a0, -1
+//!     bnez    a0, 1b
+//!     .word   0xFF, main
+//! ```
+//!
+//! - Labels stand alone in column 0; an instruction written after a label
+//!   moves to the next line
+//! - Instructions and the directives that emit data (`.word`, `.ascii`,
+//!   `.space`, `.align`, ...) are indented four spaces, with operands
+//!   aligned after the mnemonic; other directives start in column 0
+//! - Operands are separated by `, ` and binary operators by single spaces
+//! - Hexadecimal digits are uppercase (`0xFF`)
+//! - Registers all get the naming chosen by [`RegisterNames`], following
+//!   any `.abi` directive; known mnemonics and all directive names are
+//!   lowercase
+//! - Trailing comments line up within each paragraph; full-line comments
+//!   stay in column 0 or are indented with the code
+//! - Runs of blank lines collapse to one
+//!
+//! Formatting never changes the program a source assembles to, and
+//! formatted source is left unchanged by formatting it again. Lines of
+//! macro bodies that use parameters (`\arg`) are only re-indented.
+
+use crate::ast::{Directive, Instruction, Operand, OperandKind, Statement, StatementKind};
+use crate::error::Result;
+use crate::expr::Expr;
+use crate::lexer::Token;
+use crate::parser::{parse, tokenize_spanned};
+use crate::pseudo;
+use std::collections::HashSet;
+use zkir_spec::{AbiVersion, Opcode};
+
+/// Indentation of instructions and data directives
+const INDENT: &str = "    ";
+
+/// Width of the mnemonic column, including the space before the operands
+const MNEMONIC_WIDTH: usize = 8;
+
+/// Column trailing comments start in, unless code in the paragraph is wider
+const COMMENT_COLUMN: usize = 28;
+
+/// Directives laid out like instructions
+const DATA_DIRECTIVES: &[&str] = &["byte", "half", "word", "dword", "ascii", "asciz", "space", "align"];
+
+/// How registers are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RegisterNames {
+    /// ABI names (`a0`, `sp`) of the `.abi` version in effect
+    #[default]
+    Abi,
+    /// Numeric names (`r10`, `r2`)
+    Numeric,
+}
+
+/// Formatting options
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub registers: RegisterNames,
+}
+
+/// Format `source` in the canonical layout
+///
+/// Fails if the source does not parse (see [`parse`]); nothing is
+/// assembled, so undefined symbols and bad operands are kept as written.
+///
+/// # Example
+/// ```
+/// use zkir_assembler::formatter::{format_source, FormatOptions, RegisterNames};
+///
+/// let options = FormatOptions { registers: RegisterNames::Numeric };
+/// let formatted = format_source("loop: ADDI a0,a0,0x1f # step\n", &options).unwrap();
+/// assert_eq!(formatted, "loop:\n    addi    r10, r10, 0x1F  # step\n");
+/// ```
+pub fn format_source(source: &str, options: &FormatOptions) -> Result<String> {
+    let module = parse(source)?;
+    let mut formatter = Formatter {
+        source,
+        options,
+        abi: AbiVersion::CURRENT,
+        macros: HashSet::new(),
+        lines: Vec::new(),
+    };
+
+    let mut rest = &module.statements[..];
+    for line in 1..=source.lines().count() {
+        let count = rest.iter().take_while(|statement| statement.line == line).count();
+        let (statements, tail) = rest.split_at(count);
+        formatter.line(statements);
+        rest = tail;
+    }
+    Ok(formatter.finish())
+}
+
+/// Output line before comments are aligned
+#[derive(Debug, Default)]
+struct Line {
+    code: String,
+    /// Trailing comment, from the `#`
+    comment: Option<String>,
+}
+
+impl Line {
+    fn is_blank(&self) -> bool {
+        self.code.is_empty() && self.comment.is_none()
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    options: &'a FormatOptions,
+    /// Register naming selected by the last `.abi` directive
+    abi: AbiVersion,
+    /// Macros defined so far; their invocations keep the name as written
+    macros: HashSet<String>,
+    lines: Vec<Line>,
+}
+
+impl Formatter<'_> {
+    /// Lay out the statements of one source line
+    fn line(&mut self, statements: &[Statement]) {
+        let (mut label, mut code, mut comment) = (None, None, None);
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Label(name) => label = Some(format!("{}:", name)),
+                StatementKind::Directive(directive) => code = Some(self.directive(directive)),
+                StatementKind::Instruction(instruction) => code = Some(self.instruction(instruction)),
+                StatementKind::Raw(text) => code = Some(raw(text)),
+                StatementKind::Comment(text) => comment = Some((format!("#{}", text), statement.span.start)),
+            }
+        }
+
+        match (label, code) {
+            (None, None) => {
+                let code = match comment {
+                    Some((comment, start)) if self.source[..start].ends_with(['\n', '\r']) || start == 0 => comment,
+                    Some((comment, _)) => format!("{}{}", INDENT, comment),
+                    None => String::new(),
+                };
+                self.lines.push(Line { code, comment: None });
+            }
+            (Some(label), Some(code)) => {
+                self.lines.push(Line { code: label, comment: None });
+                self.lines.push(Line { code, comment: comment.map(|(comment, _)| comment) });
+            }
+            (Some(code), None) | (None, Some(code)) => {
+                self.lines.push(Line { code, comment: comment.map(|(comment, _)| comment) });
+            }
+        }
+    }
+
+    fn directive(&mut self, directive: &Directive) -> String {
+        let lowercase = directive.name.to_lowercase();
+        let name = format!(".{}", lowercase);
+        // Macro and `.irp` parameters are substituted by name, so they keep
+        // their spelling even when they look like registers
+        let args = match lowercase.as_str() {
+            "abi" => {
+                if let Some(Operand { kind: OperandKind::Expr(Expr::Number(number)), .. }) = directive.args.first() {
+                    self.abi = u64::try_from(*number).ok().and_then(AbiVersion::from_number).unwrap_or(self.abi);
+                }
+                self.operands(&directive.args, 0)
+            }
+            "macro" => {
+                if let Some(header) = directive.args.first() {
+                    let name = self.source[header.span.clone()].split_whitespace().next().unwrap_or_default();
+                    self.macros.insert(name.to_string());
+                }
+                self.operands(&directive.args, directive.args.len())
+            }
+            "irp" => self.operands(&directive.args, 1),
+            _ => self.operands(&directive.args, 0),
+        };
+
+        if DATA_DIRECTIVES.contains(&lowercase.as_str()) {
+            columns(&name, &args)
+        } else if args.is_empty() {
+            name
+        } else {
+            format!("{} {}", name, args)
+        }
+    }
+
+    fn instruction(&self, instruction: &Instruction) -> String {
+        let lowercase = instruction.mnemonic.to_lowercase();
+        let known = pseudo::is_pseudo(&lowercase)
+            || (0..=u8::MAX).filter_map(Opcode::from_u8).any(|opcode| opcode.to_string() == lowercase);
+        let mnemonic = if known && !self.macros.contains(&instruction.mnemonic) {
+            lowercase
+        } else {
+            instruction.mnemonic.clone()
+        };
+        columns(&mnemonic, &self.operands(&instruction.operands, 0))
+    }
+
+    /// Comma-separated operands; registers in the first `keep` keep their names
+    fn operands(&self, operands: &[Operand], keep: usize) -> String {
+        let operands: Vec<String> =
+            operands.iter().enumerate().map(|(i, operand)| self.operand(operand, i >= keep)).collect();
+        operands.join(", ")
+    }
+
+    /// Operand with canonical spacing, literals and (if `rename`) registers
+    ///
+    /// Operands that do not tokenize, such as macro parameter defaults,
+    /// are kept as written.
+    fn operand(&self, operand: &Operand, rename: bool) -> String {
+        let text = &self.source[operand.span.clone()];
+        let Ok(tokens) = tokenize_spanned(text) else {
+            return text.to_string();
+        };
+
+        #[derive(PartialEq)]
+        enum Previous {
+            /// Start of the operand, or after `(`
+            Start,
+            /// Number, symbol, register, string or `)`
+            Value,
+            /// Binary operator, followed by a space
+            Binary,
+            /// Unary operator or the `%` of `%hi`, directly followed by its operand
+            Prefix,
+        }
+
+        let mut out = String::new();
+        let mut previous = Previous::Start;
+        for (token, span) in tokens {
+            let slice = &text[span];
+            let (piece, next) = match token {
+                Token::Register(name) if rename => (self.register(&name), Previous::Value),
+                Token::Hex(_) => (format!("0x{}", slice[2..].to_uppercase()), Previous::Value),
+                // `a-1` lexes as `a` and `-1`
+                Token::Number(_) if previous == Previous::Value && slice.starts_with('-') => {
+                    (format!(" - {}", &slice[1..]), Previous::Value)
+                }
+                Token::LParen => ("(".to_string(), Previous::Start),
+                Token::RParen => (")".to_string(), Previous::Value),
+                Token::Plus | Token::Minus | Token::Percent if previous != Previous::Value => {
+                    (slice.to_string(), Previous::Prefix)
+                }
+                Token::Tilde | Token::Bang => (slice.to_string(), Previous::Prefix),
+                Token::Plus
+                | Token::Minus
+                | Token::Percent
+                | Token::Star
+                | Token::Slash
+                | Token::Shl
+                | Token::Shr
+                | Token::Amp
+                | Token::Pipe
+                | Token::Caret
+                | Token::AndAnd
+                | Token::OrOr
+                | Token::EqEq
+                | Token::NotEq
+                | Token::Lt
+                | Token::Le
+                | Token::Gt
+                | Token::Ge => (format!(" {} ", slice), Previous::Binary),
+                _ => (slice.to_string(), Previous::Value),
+            };
+            // Adjacent values, such as `limb_bits 20`, keep one space apart
+            let starts_value = !matches!(next, Previous::Binary) && !piece.starts_with([')', '(', ' ']);
+            if previous == Previous::Value && starts_value {
+                out.push(' ');
+            }
+            out.push_str(&piece);
+            previous = next;
+        }
+        out
+    }
+
+    /// Register `name` under the current ABI, renamed as configured
+    fn register(&self, name: &str) -> String {
+        match (self.abi.register(name), self.options.registers) {
+            (Some(register), RegisterNames::Abi) => self.abi.names()[register.index() as usize].to_string(),
+            (Some(register), RegisterNames::Numeric) => register.numeric_name(),
+            (None, _) => name.to_string(),
+        }
+    }
+
+    /// Align trailing comments per paragraph and join the lines
+    fn finish(self) -> String {
+        let mut out = String::new();
+        for paragraph in self.lines.split(Line::is_blank).filter(|paragraph| !paragraph.is_empty()) {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            let column = paragraph
+                .iter()
+                .filter(|line| line.comment.is_some())
+                .map(|line| line.code.chars().count() + 2)
+                .fold(COMMENT_COLUMN, usize::max);
+            for line in paragraph {
+                match &line.comment {
+                    Some(comment) => out.push_str(&format!("{:<column$}{}\n", line.code, comment)),
+                    None => out.push_str(&format!("{}\n", line.code)),
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Mnemonic (or data directive) and operands, indented
+fn columns(mnemonic: &str, operands: &str) -> String {
+    if operands.is_empty() {
+        format!("{}{}", INDENT, mnemonic)
+    } else {
+        format!("{}{:<width$} {}", INDENT, mnemonic, operands, width = MNEMONIC_WIDTH - 1)
+    }
+}
+
+/// Parameterized macro body line: kept as written, in the column of the
+/// statement it starts with
+fn raw(text: &str) -> String {
+    let first = text.split_whitespace().next().unwrap_or_default();
+    let data = first.strip_prefix('.').is_some_and(|name| DATA_DIRECTIVES.contains(&name));
+    if first.ends_with(':') || (first.starts_with('.') && !data) {
+        text.to_string()
+    } else {
+        format!("{}{}", INDENT, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(source: &str) -> String {
+        format_source(source, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_layout() {
+        let source = "\n\n.EQU N,0x1f\n  .section .data\ntable:.word 1,2 ,N\n\n\n\n  .text\nmain: LI a0,N # count\n1: addi a0,a0,-1\n   bnez a0,1b\n";
+        assert_eq!(
+            format(source),
+            "\
+.equ N, 0x1F
+.section .data
+table:
+    .word   1, 2, N
+
+.text
+main:
+    li      a0, N           # count
+1:
+    addi    a0, a0, -1
+    bnez    a0, 1b
+"
+        );
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(format("li a0,(N+1)*4-x-1\n"), "    li      a0, (N + 1) * 4 - x - 1\n");
+        assert_eq!(format("la a0, %hi( x )<<16|%lo(x)\n"), "    la      a0, %hi(x) << 16 | %lo(x)\n");
+        assert_eq!(format(".word -N, ~0xab, x%4, -(1)\n"), "    .word   -N, ~0xAB, x % 4, -(1)\n");
+        assert_eq!(format("lw a0,8 ( sp )\nsw a0,(sp)\n"), "    lw      a0, 8(sp)\n    sw      a0, (sp)\n");
+        assert_eq!(format(".config limb_bits   20\n"), ".config limb_bits 20\n");
+        assert_eq!(format(".if DATA_BITS>=40&&!X\n.endif\n"), ".if DATA_BITS >= 40 && !X\n.endif\n");
+    }
+
+    #[test]
+    fn test_directive_names() {
+        assert_eq!(format(".Data\n.WORD 1\n.Abi 1\nmv r11, s0\n"), ".data\n    .word   1\n.abi 1\n    mv      a0, s0\n");
+        // Section names and other arguments keep their spelling
+        assert_eq!(format(".SECTION .Data\n"), ".section .Data\n");
+    }
+
+    #[test]
+    fn test_register_names() {
+        let source = "mv r10, s0\n.abi 1\nmv r11, s0\n";
+        assert_eq!(format(source), "    mv      a0, fp\n.abi 1\n    mv      a0, s0\n");
+
+        let numeric = FormatOptions { registers: RegisterNames::Numeric };
+        assert_eq!(
+            format_source(source, &numeric).unwrap(),
+            "    mv      r10, r8\n.abi 1\n    mv      r11, r6\n"
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "# header\n  # body\nmain: # entry\n    addi a0, a0, 1 # a very long comment on a line\n    li a0, SOME_LONG_CONSTANT_NAME + 1 # wide\n\n    nop #x\n";
+        assert_eq!(
+            format(source),
+            "\
+# header
+    # body
+main:                                        # entry
+    addi    a0, a0, 1                        # a very long comment on a line
+    li      a0, SOME_LONG_CONSTANT_NAME + 1  # wide
+
+    nop                     #x
+"
+        );
+    }
+
+    #[test]
+    fn test_macros_keep_parameters() {
+        let source = ".macro SAVE a0, by=1\n  addi \\a0, \\a0, \\by\nloop\\@: j loop\\@\n.endm\nSAVE t0, 2\n.irp t1, 1, 2\n.word \\t1\n.endr\n";
+        assert_eq!(
+            format(source),
+            "\
+.macro SAVE a0, by=1
+    addi \\a0, \\a0, \\by
+loop\\@: j loop\\@
+.endm
+    SAVE    t0, 2
+.irp t1, 1, 2
+    .word \\t1
+.endr
+"
+        );
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "start: li a0 , 0xff\n\n\n# x\n.data\nmsg: .asciz \"a, b # c\"  # text\n";
+        let once = format(source);
+        assert_eq!(format(&once), once);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(format_source("add r1, @, r2", &FormatOptions::default()).is_err());
+    }
+}
@@ -11,7 +11,10 @@ pub enum Token {
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string())]
     Identifier(String),
 
-    /// Register (r0-r15, or ABI names)
+    /// Register (r0-r15, or an ABI name of any [`zkir_spec::AbiVersion`])
+    ///
+    /// The ABI name pattern must list exactly the names in the spec's
+    /// register tables; `test_lexer_register_names_match_abi` checks this.
     #[regex(r"r([0-9]|1[0-5])", |lex| lex.slice().to_string())]
     #[regex(r"(zero|ra|sp|gp|tp|fp|s[01]|t[0-2]|a[0-5])", |lex| lex.slice().to_string())]
     Register(String),
@@ -20,6 +23,11 @@ pub enum Token {
     #[regex(r"-?[0-9]+", |lex| lex.slice().parse().ok())]
     Number(i64),
 
+    /// Reference to a numeric local label: `1b` (nearest backward) or `1f`
+    /// (nearest forward)
+    #[regex(r"[0-9]+[bf]", |lex| lex.slice().to_string())]
+    LocalLabel(String),
+
     /// Hexadecimal number
     #[regex(r"0x[0-9a-fA-F]+", |lex| u64::from_str_radix(&lex.slice()[2..], 16).ok())]
     Hex(u64),
@@ -32,6 +40,10 @@ pub enum Token {
     #[regex(r"\.[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice()[1..].to_string())]
     Directive(String),
 
+    /// String literal (raw bytes, escapes already processed)
+    #[regex(r#""([^"\\\n]|\\.)*""#, |lex| unescape(&lex.slice()[1..lex.slice().len() - 1]))]
+    Str(Vec<u8>),
+
     /// Comma
     #[token(",")]
     Comma,
@@ -48,11 +60,123 @@ pub enum Token {
     #[token(")")]
     RParen,
 
+    // ========== Expression Operators ==========
+
+    /// Plus
+    #[token("+")]
+    Plus,
+
+    /// Minus (a `-` directly followed by digits lexes as a negative number)
+    #[token("-")]
+    Minus,
+
+    /// Multiply
+    #[token("*")]
+    Star,
+
+    /// Divide
+    #[token("/")]
+    Slash,
+
+    /// Modulo, or the prefix of `%hi`/`%lo`
+    #[token("%")]
+    Percent,
+
+    /// Shift left
+    #[token("<<")]
+    Shl,
+
+    /// Arithmetic shift right
+    #[token(">>")]
+    Shr,
+
+    /// Bitwise and
+    #[token("&")]
+    Amp,
+
+    /// Bitwise or
+    #[token("|")]
+    Pipe,
+
+    /// Bitwise xor
+    #[token("^")]
+    Caret,
+
+    /// Bitwise not
+    #[token("~")]
+    Tilde,
+
+    /// Logical not
+    #[token("!")]
+    Bang,
+
+    /// Logical and
+    #[token("&&")]
+    AndAnd,
+
+    /// Logical or
+    #[token("||")]
+    OrOr,
+
+    /// Equal
+    #[token("==")]
+    EqEq,
+
+    /// Not equal
+    #[token("!=")]
+    NotEq,
+
+    /// Less than
+    #[token("<")]
+    Lt,
+
+    /// Less than or equal
+    #[token("<=")]
+    Le,
+
+    /// Greater than
+    #[token(">")]
+    Gt,
+
+    /// Greater than or equal
+    #[token(">=")]
+    Ge,
+
     /// Newline
     #[regex(r"\n")]
     Newline,
 }
 
+/// Process backslash escapes in a string literal body
+///
+/// Supports `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\'` and `\xHH`.
+fn unescape(body: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(body.len());
+    let mut bytes = body.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        let escaped = match bytes.next()? {
+            b'n' => b'\n',
+            b't' => b'\t',
+            b'r' => b'\r',
+            b'0' => 0,
+            b'\\' => b'\\',
+            b'"' => b'"',
+            b'\'' => b'\'',
+            b'x' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?
+            }
+            _ => return None,
+        };
+        out.push(escaped);
+    }
+    Some(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +191,28 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::Register("sp".to_string()))));
     }
 
+    #[test]
+    fn test_lexer_register_names_match_abi() {
+        use zkir_spec::{AbiVersion, Register};
+
+        let is_register = |name: &str| matches!(Token::lexer(name).next(), Some(Ok(Token::Register(_))));
+        for abi in [AbiVersion::V1, AbiVersion::V2] {
+            for name in abi.names() {
+                assert!(is_register(name), "{} is not lexed as a register", name);
+            }
+        }
+        // Every other short name is an identifier
+        for prefix in ["a", "s", "t", "r", "x"] {
+            for n in 0..20 {
+                let name = format!("{}{}", prefix, n);
+                let known = [AbiVersion::V1, AbiVersion::V2].iter().any(|abi| abi.register(&name).is_some());
+                assert_eq!(is_register(&name), known, "{}", name);
+            }
+        }
+        assert_eq!(Register::from_name("s0"), Some(Register::FP));
+        assert!(is_register("s0"));
+    }
+
     #[test]
     fn test_lexer_numbers() {
         let mut lex = Token::lexer("42 -10 0x1A 0b1010");
@@ -76,6 +222,18 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::Binary(0b1010))));
     }
 
+    #[test]
+    fn test_lexer_local_labels() {
+        let mut lex = Token::lexer("1: beq r1, r2, 1b 12f 0b1");
+        assert_eq!(lex.next(), Some(Ok(Token::Number(1))));
+        assert_eq!(lex.next(), Some(Ok(Token::Colon)));
+        lex.nth(4);
+        assert_eq!(lex.next(), Some(Ok(Token::LocalLabel("1b".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::LocalLabel("12f".to_string()))));
+        // Binary numbers take precedence
+        assert_eq!(lex.next(), Some(Ok(Token::Binary(1))));
+    }
+
     #[test]
     fn test_lexer_directive() {
         let mut lex = Token::lexer(".config .text .data");
@@ -84,6 +242,47 @@ mod tests {
         assert_eq!(lex.next(), Some(Ok(Token::Directive("data".to_string()))));
     }
 
+    #[test]
+    fn test_lexer_string() {
+        let mut lex = Token::lexer(r#".asciz "hi\n\x41 # not a comment""#);
+        assert_eq!(lex.next(), Some(Ok(Token::Directive("asciz".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::Str(b"hi\nA # not a comment".to_vec()))));
+        assert_eq!(lex.next(), None);
+
+        let mut lex = Token::lexer(r#""bad \q escape""#);
+        assert!(matches!(lex.next(), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_lexer_operators() {
+        let mut lex = Token::lexer("%hi(a) + b*4 - 1 << ~c");
+        assert_eq!(lex.next(), Some(Ok(Token::Percent)));
+        assert_eq!(lex.next(), Some(Ok(Token::Identifier("hi".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::LParen)));
+        assert_eq!(lex.next(), Some(Ok(Token::Identifier("a".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::RParen)));
+        assert_eq!(lex.next(), Some(Ok(Token::Plus)));
+        assert_eq!(lex.next(), Some(Ok(Token::Identifier("b".to_string()))));
+        assert_eq!(lex.next(), Some(Ok(Token::Star)));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(4))));
+        assert_eq!(lex.next(), Some(Ok(Token::Minus)));
+        assert_eq!(lex.next(), Some(Ok(Token::Number(1))));
+        assert_eq!(lex.next(), Some(Ok(Token::Shl)));
+        assert_eq!(lex.next(), Some(Ok(Token::Tilde)));
+    }
+
+    #[test]
+    fn test_lexer_comparisons() {
+        let tokens: Vec<_> = Token::lexer("< <= << > >= >> == != ! && & || |").map(Result::unwrap).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Lt, Token::Le, Token::Shl, Token::Gt, Token::Ge, Token::Shr, Token::EqEq, Token::NotEq,
+                Token::Bang, Token::AndAnd, Token::Amp, Token::OrOr, Token::Pipe,
+            ]
+        );
+    }
+
     #[test]
     fn test_lexer_instruction() {
         let mut lex = Token::lexer("add r1, r2, r3");
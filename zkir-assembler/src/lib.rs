@@ -20,15 +20,30 @@
 //! ```
 
 pub mod error;
+pub mod ast;
+pub mod diagnostic;
+pub mod listing;
 pub mod lexer;
 pub mod parser;
 pub mod encoder;
+pub mod expr;
+pub mod macros;
+pub mod source;
 pub mod assembler;
+pub mod pseudo;
+pub mod formatter;
+mod lint;
+mod object;
+mod relax;
 
 pub use error::{AssemblerError, Result};
-pub use assembler::assemble;
-pub use parser::parse_register;
-pub use encoder::encode;
+pub use diagnostic::{Diagnostic, Severity, Span};
+pub use listing::{Listing, ListingLine, ListingSection, ListingSymbol};
+pub use assembler::{assemble, assemble_file, assemble_object, Assembler, AssemblerOptions};
+pub use ast::Module;
+pub use parser::{parse, parse_register, parse_register_with_abi};
+pub use encoder::{encode, try_encode, EncodeError};
+pub use formatter::{format_source, FormatOptions, RegisterNames};
 
 #[cfg(test)]
 mod tests {
@@ -57,6 +72,7 @@ mod tests {
         };
         let encoded = encode(&instr);
         assert!(encoded > 0);
+        assert_eq!(try_encode(&instr, &zkir_spec::Config::DEFAULT).unwrap(), encoded);
     }
 
     #[test]
@@ -131,8 +147,8 @@ mod tests {
             ("zero", Register::R0),
             ("ra", Register::R1),
             ("sp", Register::R2),
-            ("a0", Register::R11),  // a0-a4 map to R11-R15 in ZKIR
-            ("a1", Register::R12),
+            ("a0", Register::R10),  // a0-a5 map to R10-R15 in ZKIR
+            ("a1", Register::R11),
         ];
 
         for (name, expected) in abi_names {
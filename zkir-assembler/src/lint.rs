@@ -0,0 +1,181 @@
+//! Static checks over assembled instructions
+//!
+//! Once every instruction is resolved, [`zkir_spec::validation::validate_program`]
+//! runs over the code and each finding is reported against the source line
+//! it came from: validation errors as errors, validation warnings
+//! (`WriteToR0`, `NoOp`, `UnconditionalBranch`) as warnings. Config-aware
+//! lints are added on top:
+//!
+//! | Lint        | Warns about                                                  |
+//! |-------------|--------------------------------------------------------------|
+//! | wide shift  | `slli`/`srli`/`srai` by `config.data_bits()` or more         |
+//! | wide load   | `lw` under a config whose data width is below 32 bits        |
+//!
+//! Instructions the author did not write (pseudo-instruction expansions,
+//! `.align` padding and relaxed branches) are exempt from warnings, as is
+//! `jal`/`jalr` with `zero` as link register, the usual way to jump without
+//! linking. [`crate::AssemblerOptions::deny_warnings`] turns warnings into
+//! errors.
+
+use crate::assembler::Item;
+use crate::error::AssemblerError;
+use crate::source::Location;
+use zkir_spec::validation::{validate_program, ValidationWarning};
+use zkir_spec::{Config, Instruction};
+
+/// Errors and warnings found in the resolved `instructions` of `items`
+///
+/// `instructions` holds one entry per [`Item::Instruction`], in order.
+pub(crate) fn check(
+    items: &[Item],
+    instructions: &[Instruction],
+    config: &Config,
+) -> (Vec<AssemblerError>, Vec<AssemblerError>) {
+    let sources: Vec<(usize, &Location, bool)> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Instruction { line, location, relaxed, pseudo, .. } => Some((*line, location, *relaxed || *pseudo)),
+            _ => None,
+        })
+        .collect();
+    let finding = |index: usize, message: String| {
+        let (line, location, _) = sources[index];
+        location.wrap(AssemblerError::StaticCheck { line, message })
+    };
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, result) in validate_program(instructions) {
+        let instr = &instructions[index];
+        errors.extend(result.errors.iter().map(|err| finding(index, err.to_string())));
+        if sources[index].2 {
+            continue;
+        }
+        for warning in &result.warnings {
+            if matches!(warning, ValidationWarning::WriteToR0 { .. })
+                && matches!(instr, Instruction::Jal { .. } | Instruction::Jalr { .. })
+            {
+                continue;
+            }
+            warnings.push(finding(index, describe(warning, instr)));
+        }
+    }
+
+    for (index, instr) in instructions.iter().enumerate() {
+        if sources[index].2 {
+            continue;
+        }
+        if let Some(message) = config_lint(instr, config) {
+            warnings.push(finding(index, message));
+        }
+    }
+
+    (errors, warnings)
+}
+
+/// Message for a validation warning on `instr`
+fn describe(warning: &ValidationWarning, instr: &Instruction) -> String {
+    match warning {
+        ValidationWarning::WriteToR0 { instruction } => {
+            format!("`{}` writes to `zero`, which is hardwired to 0; the result is discarded", instruction)
+        }
+        ValidationWarning::UnconditionalBranch { instruction } => {
+            format!("`{}` compares a register with itself and is always taken; use `j`", instruction)
+        }
+        ValidationWarning::NoOp { instruction } if instr.is_branch() => {
+            format!("`{}` compares a register with itself and is never taken", instruction)
+        }
+        ValidationWarning::NoOp { instruction } => format!("`{}` has no effect", instruction),
+    }
+}
+
+/// Lint that depends on the active config
+fn config_lint(instr: &Instruction, config: &Config) -> Option<String> {
+    let data_bits = config.data_bits();
+    match instr {
+        Instruction::Slli { shamt, .. } | Instruction::Srli { shamt, .. } | Instruction::Srai { shamt, .. }
+            if *shamt as u32 >= data_bits =>
+        {
+            Some(format!(
+                "`{}` shifts by {} but registers hold {} bits under the active config; every bit is shifted out",
+                instr.mnemonic(),
+                shamt,
+                data_bits
+            ))
+        }
+        Instruction::Lw { .. } if data_bits < 32 => Some(format!(
+            "`lw` loads 32 bits but registers hold {} bits under the active config; the upper bits are not representable",
+            data_bits
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Assembler, AssemblerOptions, Severity};
+
+    fn warnings(source: &str) -> Vec<String> {
+        let (program, diagnostics) = Assembler::new().assemble_with_diagnostics(source);
+        assert!(program.is_some(), "{:?}", diagnostics);
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+            .map(|diagnostic| format!("{}: {}", diagnostic.span.unwrap().line, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_validation_warnings_map_to_lines() {
+        let source = "add zero, r1, r2\nbeq r1, r1, 8\nbne r3, r3, 4\necall";
+        assert_eq!(
+            warnings(source),
+            vec![
+                "1: Static check: `add` writes to `zero`, which is hardwired to 0; the result is discarded",
+                "2: Static check: `beq` compares a register with itself and is always taken; use `j`",
+                "3: Static check: `bne` compares a register with itself and is never taken",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generated_instructions_are_exempt() {
+        // `nop`, `j`, `ret` and `.align` padding all write to `zero`
+        let source = "start:\n    nop\n    j start\n    ret\n    .align 4\n    jal zero, start\n    ecall";
+        assert!(warnings(source).is_empty());
+    }
+
+    #[test]
+    fn test_wide_load_lint() {
+        let source = ".config limb_bits 16\n.config data_limbs 1\n.config addr_limbs 1\nlw r1, 0(r2)\nlh r1, 0(r2)";
+        let found = warnings(source);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("4: Static check: `lw` loads 32 bits but registers hold 16 bits"));
+        assert!(warnings("lw r1, 0(r2)").is_empty());
+    }
+
+    #[test]
+    fn test_wide_shift_lint() {
+        let source = "slli r1, r2, 39\nsrli r1, r2, 40\n\nsrai r1, r2, 45";
+        assert_eq!(
+            warnings(source),
+            vec![
+                "2: Static check: `srli` shifts by 40 but registers hold 40 bits under the active config; every bit is shifted out",
+                "4: Static check: `srai` shifts by 45 but registers hold 40 bits under the active config; every bit is shifted out",
+            ]
+        );
+        assert!(warnings(".config limb_bits 30\nslli r1, r2, 45").is_empty());
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let options = AssemblerOptions { deny_warnings: true, ..AssemblerOptions::default() };
+        let assembler = Assembler::with_options(options);
+        let err = assembler.assemble("add zero, r1, r2\necall").unwrap_err();
+        assert_eq!(err.line(), Some(1));
+        assert!(err.to_string().contains("writes to `zero`"));
+
+        assert!(assembler.assemble("add r1, r2, r3\necall").is_ok());
+        assert!(Assembler::new().assemble("add zero, r1, r2\necall").is_ok());
+    }
+}
@@ -0,0 +1,119 @@
+//! Assembly listings
+//!
+//! A [`Listing`] ties every code word back to the source line that produced
+//! it, after pseudo-instruction and macro expansion, and records where each
+//! label and section ended up. Branches rewritten to reach a distant label
+//! are marked `[relaxed]`. Its `Display` renders the text form:
+//!
+//! ```text
+//! 00001000  00050588  addi a1, zero, 10         2 | li a1, 10
+//! 00001004  00000188  addi gp, zero, 0          3 | la r3, table
+//! 00001008  0008199b  slli gp, gp, 16             |
+//! 0000100c  08081994  ori gp, gp, 4112            |
+//!
+//! Sections:
+//!   .text    00001000  16 bytes
+//!   .data    00001010  4 bytes
+//!   .rodata  00001014  0 bytes
+//!   .bss     00001014  0 bytes
+//!
+//! Symbols:
+//!   00001000  .text    main
+//!   00001010  .data    table
+//! ```
+
+use std::fmt;
+use zkir_spec::{Instruction, SectionKind};
+
+/// Listing of an assembled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    /// One entry per code word, in address order
+    pub lines: Vec<ListingLine>,
+    /// Every section, in layout order (empty sections included)
+    pub sections: Vec<ListingSection>,
+    /// Labels, sorted by address
+    pub symbols: Vec<ListingSymbol>,
+}
+
+/// Code word and the source it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLine {
+    /// Runtime address (from `CODE_BASE`)
+    pub address: u64,
+    /// Encoded instruction
+    pub word: u32,
+    /// Instruction after expansion, with resolved operands
+    pub instruction: Instruction,
+    /// File of the source line (`None` for in-memory source)
+    pub file: Option<String>,
+    /// 1-based source line number
+    pub line: usize,
+    /// Text of the source line, as written
+    pub source: String,
+    /// Part of a branch or jump rewritten because its target was out of range
+    pub relaxed: bool,
+}
+
+/// Section placement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingSection {
+    pub section: SectionKind,
+    pub address: u64,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Label with its resolved address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingSymbol {
+    pub name: String,
+    pub section: SectionKind,
+    pub address: u64,
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = |line: &ListingLine| match &line.file {
+            Some(file) => format!("{}:{}", file, line.line),
+            None => line.line.to_string(),
+        };
+        let width = self.lines.iter().map(|line| location(line).len()).max().unwrap_or(0);
+
+        // Each source line is shown next to the first word it produced
+        let mut previous = None;
+        for line in &self.lines {
+            let instruction = line.instruction.to_string();
+            let key = (&line.file, line.line);
+            if previous == Some(key) {
+                writeln!(f, "{:08x}  {:08x}  {:<24}  {:>width$} |", line.address, line.word, instruction, "")?;
+            } else {
+                let relaxed = if line.relaxed { "  [relaxed]" } else { "" };
+                writeln!(
+                    f,
+                    "{:08x}  {:08x}  {:<24}  {:>width$} | {}{}",
+                    line.address,
+                    line.word,
+                    instruction,
+                    location(line),
+                    line.source.trim(),
+                    relaxed
+                )?;
+            }
+            previous = Some(key);
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Sections:")?;
+        for section in &self.sections {
+            writeln!(f, "  {:<8} {:08x}  {} bytes", section.section.name(), section.address, section.size)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Symbols:")?;
+        for symbol in &self.symbols {
+            writeln!(f, "  {:08x}  {:<8} {}", symbol.address, symbol.section.name(), symbol.name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+//! Macro expansion for ZKIR v3.4 assembly
+//!
+//! Supports:
+//! - `.macro name arg1, arg2=default` ... `.endm`, invoked as `name a, b`.
+//!   `\arg` in the body is replaced by the argument text; `\()` separates a
+//!   parameter from following text (`\reg\()_save`).
+//! - `\@` in a macro body: a number unique to each expansion, for local
+//!   labels (`loop\@:`)
+//! - `.rept count` ... `.endr`: repeat the body `count` times
+//! - `.irp sym, a, b, ...` ... `.endr`: repeat the body with `\sym` bound to
+//!   each value in turn
+//!
+//! Expansion happens line by line in the first pass, before layout, so
+//! `.rept` counts may use constants defined earlier in the file. Lines
+//! produced by a macro keep the line number of the macro body they came
+//! from, and errors on them are wrapped with the invocation site (see
+//! [`crate::source::Location`]).
+
+use crate::error::{AssemblerError, Result};
+use crate::source::{FileRef, Location, SourceLine};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Maximum nesting of macro invocations (guards against recursive macros)
+const MAX_EXPANSION_DEPTH: usize = 64;
+
+/// Macro invocation that produced a line
+#[derive(Debug)]
+pub(crate) struct Expansion {
+    pub(crate) name: String,
+    /// Line of the invocation (a macro-body line itself for nested calls)
+    pub(crate) line: usize,
+    /// File of the invocation
+    pub(crate) file: FileRef,
+    depth: usize,
+    pub(crate) parent: Origin,
+}
+
+/// Chain of macro invocations a line came from (`None` for file lines)
+pub(crate) type Origin = Option<Rc<Expansion>>;
+
+/// Macro parameter with optional default value
+#[derive(Debug, Clone)]
+struct Param {
+    name: String,
+    default: Option<String>,
+}
+
+/// Macro definition
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Vec<Param>,
+    body: Vec<SourceLine>,
+}
+
+/// Defined macros and the expansion counter behind `\@`
+#[derive(Debug, Default)]
+pub(crate) struct Macros {
+    defs: HashMap<String, Macro>,
+    expansions: u64,
+}
+
+impl Macros {
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.defs.contains_key(name)
+    }
+
+    /// Define a macro from its `.macro` header arguments and body
+    pub(crate) fn define(&mut self, header: &str, body: Vec<SourceLine>, line: usize) -> Result<()> {
+        let syntax_error = |message: String| AssemblerError::SyntaxError { line, message };
+
+        let header = header.trim();
+        let name_end = header.find(|c: char| c.is_whitespace() || c == ',').unwrap_or(header.len());
+        let (name, params) = header.split_at(name_end);
+        if !is_identifier(name) {
+            return Err(syntax_error(format!("Invalid macro name: {:?}", name)));
+        }
+        if self.defs.contains_key(name) {
+            return Err(syntax_error(format!("Duplicate macro: {}", name)));
+        }
+
+        let mut parsed: Vec<Param> = Vec::new();
+        for param in params.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+            let (param_name, default) = match param.split_once('=') {
+                Some((n, d)) => (n, Some(d.to_string())),
+                None => (param, None),
+            };
+            if !is_identifier(param_name) || parsed.iter().any(|p| p.name == param_name) {
+                return Err(syntax_error(format!("Invalid macro parameter: {:?}", param)));
+            }
+            parsed.push(Param { name: param_name.to_string(), default });
+        }
+
+        self.defs.insert(name.to_string(), Macro { params: parsed, body });
+        Ok(())
+    }
+
+    /// Expand an invocation of macro `name` with argument text `args`
+    pub(crate) fn invoke(&mut self, name: &str, args: &str, call: &SourceLine) -> Result<Vec<SourceLine>> {
+        let syntax_error = |message: String| AssemblerError::SyntaxError { line: call.line, message };
+        let def = &self.defs[name];
+
+        let depth = call.location.origin.as_ref().map_or(0, |e| e.depth) + 1;
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err(syntax_error(format!("Macro expansion nested too deeply (recursive macro {}?)", name)));
+        }
+
+        let values = split_arguments(args);
+        if values.len() > def.params.len() {
+            return Err(syntax_error(format!(
+                "Macro {} takes {} arguments, got {}",
+                name,
+                def.params.len(),
+                values.len()
+            )));
+        }
+        let mut bindings = Vec::with_capacity(def.params.len());
+        for (i, param) in def.params.iter().enumerate() {
+            let value = match values.get(i).filter(|v| !v.is_empty()) {
+                Some(value) => value.clone(),
+                None => param
+                    .default
+                    .clone()
+                    .ok_or_else(|| syntax_error(format!("Missing argument {} for macro {}", param.name, name)))?,
+            };
+            bindings.push((param.name.as_str(), value));
+        }
+
+        let unique = self.expansions;
+        self.expansions += 1;
+        let origin = Some(Rc::new(Expansion {
+            name: name.to_string(),
+            line: call.line,
+            file: call.location.file.clone(),
+            depth,
+            parent: call.location.origin.clone(),
+        }));
+
+        Ok(def
+            .body
+            .iter()
+            .map(|body_line| SourceLine {
+                text: substitute(&body_line.text, &bindings, Some(unique)),
+                line: body_line.line,
+                location: Location {
+                    file: body_line.location.file.clone(),
+                    origin: origin.clone(),
+                },
+            })
+            .collect())
+    }
+}
+
+/// Expand an `.irp sym, values...` block
+pub(crate) fn expand_irp(args: &str, body: &[SourceLine], line: usize) -> Result<Vec<SourceLine>> {
+    let mut values = split_arguments(args);
+    if values.is_empty() || !is_identifier(&values[0]) {
+        return Err(AssemblerError::SyntaxError {
+            line,
+            message: ".irp requires a symbol name followed by values".to_string(),
+        });
+    }
+    let symbol = values.remove(0);
+
+    let mut lines = Vec::with_capacity(body.len() * values.len());
+    for value in &values {
+        let bindings = [(symbol.as_str(), value.clone())];
+        lines.extend(body.iter().map(|body_line| SourceLine {
+            text: substitute(&body_line.text, &bindings, None),
+            ..body_line.clone()
+        }));
+    }
+    Ok(lines)
+}
+
+/// Replace `\param`, `\@` and `\()` in a body line
+///
+/// Backslash sequences that name no parameter (such as `\n` in a string
+/// literal) are left alone.
+fn substitute(text: &str, bindings: &[(&str, String)], unique: Option<u64>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('\\') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        if let (Some('@'), Some(n)) = (after.chars().next(), unique) {
+            out.push_str(&n.to_string());
+            rest = &after[1..];
+            continue;
+        }
+        if let Some(stripped) = after.strip_prefix("()") {
+            rest = stripped;
+            continue;
+        }
+
+        let ident_len = after.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(after.len());
+        match bindings.iter().find(|(name, _)| *name == &after[..ident_len]) {
+            Some((_, value)) if ident_len > 0 => {
+                out.push_str(value);
+                rest = &after[ident_len..];
+            }
+            _ => {
+                out.push('\\');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split invocation arguments on top-level commas
+///
+/// Commas inside string literals or parentheses do not split.
+fn split_arguments(args: &str) -> Vec<String> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Vec::new();
+    }
+
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let (mut depth, mut in_string, mut escaped) = (0i32, false, false);
+    for c in args.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                values.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    values.push(current.trim().to_string());
+    values
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        let bindings = [("reg", "r5".to_string()), ("n", "4".to_string())];
+        assert_eq!(substitute("addi \\reg, \\reg, \\n", &bindings, None), "addi r5, r5, 4");
+        assert_eq!(substitute("loop\\@:", &bindings, Some(7)), "loop7:");
+        assert_eq!(substitute("\\reg\\()_save:", &bindings, None), "r5_save:");
+        // Unknown escapes are left for the lexer (string escapes)
+        assert_eq!(substitute(".ascii \"\\t\"", &bindings, None), ".ascii \"\\t\"");
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        assert_eq!(split_arguments("r1, 4(r2), \"a,b\""), vec!["r1", "4(r2)", "\"a,b\""]);
+        assert_eq!(split_arguments(" , 2"), vec!["", "2"]);
+        assert!(split_arguments("  ").is_empty());
+    }
+}
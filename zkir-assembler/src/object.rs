@@ -0,0 +1,261 @@
+//! Relocatable object output
+//!
+//! Builds a [`zkir_spec::ObjectFile`] from the first pass instead of laying
+//! the unit out at `CODE_BASE`. Every section starts at offset 0; operands
+//! that depend on where the linker places a section or on an imported
+//! symbol are encoded as zero and described by a relocation record.
+//!
+//! Expressions are evaluated with [`Expr::eval_relocatable`]: a label is
+//! its section's start plus its offset, an imported symbol is its own
+//! base. Branches to labels in the same `.text` need no relocation, and
+//! differences of labels in one section are plain numbers.
+
+use crate::assembler::{patch_imm, patch_offset, written_label, Fixup, Item, Unit, MAX_CONSTANT_DEPTH};
+use crate::encoder::try_encode;
+use crate::error::{AssemblerError, Result};
+use crate::expr::{Expr, Relocatable};
+use crate::relax;
+use zkir_spec::{
+    Binding, Instruction, ObjectFile, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, SectionKind,
+};
+use std::collections::HashMap;
+
+/// Link-time address an expression may depend on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Base<'a> {
+    Section(SectionKind),
+    Import(&'a str),
+}
+
+/// Relocation of an instruction operand: kind, target and addend
+type Pending<'a> = (RelocationKind, Base<'a>, i64);
+
+/// Build an object file, collecting every error
+pub(crate) fn build(mut unit: Unit) -> std::result::Result<ObjectFile, Vec<AssemblerError>> {
+    // Only branches within `.text` can be relaxed; the linker range-checks the rest
+    relax::relax(&mut unit, &|unit, target, pc| {
+        let scope = ObjectScope { unit };
+        match scope.eval(target, 0).ok()?.single() {
+            Some((Some(Base::Section(SectionKind::Text)), target)) => Some(target - pc as i64),
+            _ => None,
+        }
+    });
+
+    let mut errors = std::mem::take(&mut unit.errors);
+    let mut object = ObjectFile::new(unit.config);
+    object.symbols = symbol_table(&unit);
+    let index: HashMap<&str, usize> = object
+        .symbols
+        .iter()
+        .enumerate()
+        .map(|(i, symbol)| (symbol.name.as_str(), i))
+        .collect();
+    let scope = ObjectScope { unit: &unit };
+    let target = |base: &Base| match base {
+        Base::Section(section) => RelocationTarget::Section(*section),
+        Base::Import(name) => RelocationTarget::Symbol(index[name]),
+    };
+
+    // Instructions
+    let mut offset = 0;
+    for item in &unit.items {
+        if let Item::Instruction { line, instr, fixup, location, .. } = item {
+            let relocated = match fixup {
+                Some(fixup) => resolve_instruction(&scope, instr, fixup, offset, *line),
+                None => Ok((*instr, None)),
+            };
+            let encoded = relocated.and_then(|(instr, relocation)| {
+                try_encode(&instr, &unit.config)
+                    .map(|word| (word, relocation))
+                    .map_err(|source| AssemblerError::Encode { line: *line, source })
+            });
+            match encoded {
+                Ok((word, relocation)) => {
+                    object.text.push(word);
+                    if let Some((kind, base, addend)) = relocation {
+                        object.relocations.push(Relocation {
+                            section: SectionKind::Text,
+                            offset,
+                            kind,
+                            target: target(&base),
+                            addend,
+                        });
+                    }
+                }
+                Err(err) => {
+                    errors.push(location.wrap(err));
+                    object.text.push(0);
+                }
+            }
+            offset += 4;
+        }
+    }
+
+    // Data
+    object.data = unit.data.clone();
+    object.rodata = unit.rodata.clone();
+    for fixup in &unit.fixups {
+        let value = scope.eval(&fixup.expr, fixup.line).and_then(|value| match value.single() {
+            Some((None, value)) => fixup.check(value).map(|_| (None, value)),
+            Some((Some(base), addend)) => Ok((Some(*base), addend)),
+            None => Err(fixup.expr.not_relocatable(fixup.line)),
+        });
+        let (base, value) = match value {
+            Ok(resolved) => resolved,
+            Err(err) => {
+                errors.push(fixup.location.wrap(err));
+                continue;
+            }
+        };
+        let section = fixup.section.kind();
+        match base {
+            Some(base) => object.relocations.push(Relocation {
+                section,
+                offset: fixup.offset as u64,
+                kind: RelocationKind::Data(fixup.width as u8),
+                target: target(&base),
+                addend: value,
+            }),
+            None => {
+                let bytes = match section {
+                    SectionKind::Data => &mut object.data,
+                    SectionKind::Rodata => &mut object.rodata,
+                    SectionKind::Text | SectionKind::Bss => unreachable!("data fixups only live in initialized sections"),
+                };
+                bytes[fixup.offset..fixup.offset + fixup.width].copy_from_slice(&value.to_le_bytes()[..fixup.width]);
+            }
+        }
+    }
+
+    // `.entry` may name a label or an imported symbol
+    if let Some((name, line, location)) = &unit.entry {
+        match (unit.entry_label(), index.get(name.as_str())) {
+            (Err(err), _) => errors.push(err),
+            (Ok(_), Some(&symbol)) => object.entry = Some(symbol),
+            (Ok(_), None) => errors.push(location.wrap(AssemblerError::UndefinedLabel {
+                line: *line,
+                label: name.clone(),
+            })),
+        }
+    }
+
+    object.bss_size = unit.bss_size;
+    object.data_align = unit.data_align;
+    object.rodata_align = unit.rodata_align;
+    object.bss_align = unit.bss_align;
+
+    if errors.is_empty() {
+        Ok(object)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Labels in definition order, then imported symbols in declaration order
+fn symbol_table(unit: &Unit) -> Vec<ObjectSymbol> {
+    let labels = unit.items.iter().filter_map(|item| match item {
+        Item::Label(name) => {
+            let symbol = &unit.labels[name];
+            Some(ObjectSymbol {
+                name: name.clone(),
+                binding: unit.bindings.get(name).copied().unwrap_or(Binding::Local),
+                definition: Some((symbol.section.kind(), symbol.offset)),
+            })
+        }
+        _ => None,
+    });
+    let imports = unit
+        .imports
+        .iter()
+        .filter(|name| !unit.labels.contains_key(*name))
+        .map(|name| ObjectSymbol {
+            name: name.clone(),
+            binding: Binding::Global,
+            definition: None,
+        });
+    labels.chain(imports).collect()
+}
+
+/// Patch or relocate an instruction operand at `offset` in `.text`
+fn resolve_instruction<'a>(
+    scope: &ObjectScope<'a>,
+    instr: &Instruction,
+    fixup: &Fixup,
+    offset: u64,
+    line: usize,
+) -> Result<(Instruction, Option<Pending<'a>>)> {
+    match fixup {
+        Fixup::PcRel(expr) => {
+            let kind = if matches!(instr, Instruction::Jal { .. }) { RelocationKind::Jump } else { RelocationKind::Branch };
+            match scope.eval(expr, line)?.single() {
+                // Same section as the instruction: the offset is known now
+                Some((Some(Base::Section(SectionKind::Text)), target)) => {
+                    Ok((patch_offset(instr, target - offset as i64, expr, line)?, None))
+                }
+                Some((Some(base), addend)) => Ok((*instr, Some((kind, *base, addend)))),
+                _ => Err(AssemblerError::InvalidExpression {
+                    line,
+                    expr: expr.to_string(),
+                    message: "branch target in a relocatable object must be `symbol + constant`".to_string(),
+                }),
+            }
+        }
+        Fixup::Imm(expr) => {
+            let (kind, value_expr) = match expr {
+                Expr::Hi(inner) => (RelocationKind::Hi, inner.as_ref()),
+                Expr::Lo(inner) => (RelocationKind::Lo, inner.as_ref()),
+                _ => (RelocationKind::Imm, expr),
+            };
+            let value = scope.eval(value_expr, line)?;
+            if value.absolute_value().is_some() {
+                let value = scope.eval(expr, line)?.absolute_value().expect("absolute operand");
+                return Ok((patch_imm(instr, value, expr, line)?, None));
+            }
+
+            let is_shift = matches!(instr, Instruction::Slli { .. } | Instruction::Srli { .. } | Instruction::Srai { .. });
+            match value.single() {
+                Some((Some(base), addend)) if !is_shift => Ok((*instr, Some((kind, *base, addend)))),
+                _ => Err(expr.not_relocatable(line)),
+            }
+        }
+    }
+}
+
+/// Symbol values of an object: constants, section-relative labels and imports
+struct ObjectScope<'a> {
+    unit: &'a Unit,
+}
+
+impl<'a> ObjectScope<'a> {
+    fn eval(&self, expr: &Expr, line: usize) -> Result<Relocatable<Base<'a>>> {
+        self.eval_nested(expr, line, 0)
+    }
+
+    fn eval_nested(&self, expr: &Expr, line: usize, depth: usize) -> Result<Relocatable<Base<'a>>> {
+        expr.eval_relocatable(line, &|name| self.lookup(name, line, depth))
+    }
+
+    fn lookup(&self, name: &str, line: usize, depth: usize) -> Result<Relocatable<Base<'a>>> {
+        let unit = self.unit;
+        if let Some(constant) = unit.constants.get(name) {
+            if depth >= MAX_CONSTANT_DEPTH {
+                return Err(AssemblerError::InvalidExpression {
+                    line,
+                    expr: name.to_string(),
+                    message: "recursive constant definition".to_string(),
+                });
+            }
+            return self.eval_nested(&constant.expr, line, depth + 1);
+        }
+        if let Some(symbol) = unit.labels.get(name) {
+            return Ok(Relocatable::relative(Base::Section(symbol.section.kind()), symbol.offset as i64));
+        }
+        match unit.imports.iter().find(|import| *import == name) {
+            Some(import) => Ok(Relocatable::relative(Base::Import(import), 0)),
+            None => Err(AssemblerError::UndefinedLabel {
+                line,
+                label: written_label(name),
+            }),
+        }
+    }
+}
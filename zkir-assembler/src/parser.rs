@@ -1,56 +1,29 @@
 //! Assembly parser for ZKIR v3.4
 //!
-//! Parses assembly lines with config directive support.
+//! Parses assembly lines with config directive support, and whole sources
+//! into the [`crate::ast`] without assembling them.
+//!
+//! The line-level functions (register names, numbers, tokenizing) report
+//! errors at line 0; the assembler fills in the line being parsed.
 
-use zkir_spec::Register;
+use zkir_spec::{AbiVersion, Register};
+use crate::ast::{self, Directive, Module, Operand, OperandKind, Statement, StatementKind};
 use crate::error::{AssemblerError, Result};
+use crate::expr::Expr;
 use crate::lexer::Token;
+use crate::source;
 use logos::Logos;
+use std::ops::Range;
 
-/// Parse register name
+/// Parse register name under the current ABI ([`AbiVersion::CURRENT`])
 pub fn parse_register(name: &str) -> Result<Register> {
-    let name = name.trim().to_lowercase();
-
-    match name.as_str() {
-        // R0 - hardwired zero
-        "zero" | "r0" => Ok(Register::R0),
-
-        // R1 - return address
-        "ra" | "r1" => Ok(Register::R1),
-
-        // R2 - stack pointer
-        "sp" | "r2" => Ok(Register::R2),
-
-        // R3 - global pointer
-        "gp" | "r3" => Ok(Register::R3),
-
-        // R4 - thread pointer
-        "tp" | "r4" => Ok(Register::R4),
-
-        // R5 - frame pointer
-        "fp" | "r5" => Ok(Register::R5),
-
-        // R6-R7 - saved registers s0-s1
-        "s0" | "r6" => Ok(Register::R6),
-        "s1" | "r7" => Ok(Register::R7),
-
-        // R8-R10 - temporaries t0-t2
-        "t0" | "r8" => Ok(Register::R8),
-        "t1" | "r9" => Ok(Register::R9),
-        "t2" | "r10" => Ok(Register::R10),
-
-        // R11-R15 - arguments a0-a5
-        "a0" | "r11" => Ok(Register::R11),
-        "a1" | "r12" => Ok(Register::R12),
-        "a2" | "r13" => Ok(Register::R13),
-        "a3" | "r14" => Ok(Register::R14),
-        "a4" | "r15" => Ok(Register::R15),
+    parse_register_with_abi(name, AbiVersion::CURRENT)
+}
 
-        _ => Err(AssemblerError::InvalidRegister {
-            line: 0,
-            register: name.to_string(),
-        }),
-    }
+/// Parse register name (numeric `r0`-`r15` or an ABI name of `abi`)
+pub fn parse_register_with_abi(name: &str, abi: AbiVersion) -> Result<Register> {
+    let name = name.trim().to_lowercase();
+    abi.register(&name).ok_or(AssemblerError::InvalidRegister { line: 0, register: name })
 }
 
 /// Extract numeric value from token
@@ -66,18 +39,39 @@ pub fn extract_number(token: &Token) -> Result<i64> {
     }
 }
 
+/// Remove a trailing `#` comment, ignoring `#` inside string literals
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
 /// Tokenize a line of assembly
 pub fn tokenize(line: &str) -> Result<Vec<Token>> {
+    Ok(tokenize_spanned(line)?.into_iter().map(|(token, _)| token).collect())
+}
+
+/// Tokenize a line of assembly, with the byte range of each token in `line`
+pub fn tokenize_spanned(line: &str) -> Result<Vec<(Token, Range<usize>)>> {
     let mut tokens = Vec::new();
     let mut lexer = Token::lexer(line);
 
     while let Some(result) = lexer.next() {
         match result {
-            Ok(token) => tokens.push(token),
+            Ok(token) => tokens.push((token, lexer.span())),
             Err(_) => {
                 return Err(AssemblerError::SyntaxError {
                     line: 0,
-                    message: format!("Invalid token at position {}", lexer.span().start),
+                    message: format!("Invalid token `{}`", lexer.slice()),
                 });
             }
         }
@@ -86,6 +80,243 @@ pub fn tokenize(line: &str) -> Result<Vec<Token>> {
     Ok(tokens)
 }
 
+/// Parse source into a [`Module`] of statements, without assembling it
+///
+/// Only lines that cannot be tokenized, or that do not start with a label,
+/// directive or mnemonic, are errors; operands the assembler would reject
+/// are still returned. If several lines are bad, the error is
+/// [`AssemblerError::Multiple`].
+///
+/// # Example
+/// ```
+/// use zkir_assembler::ast::{OperandKind, StatementKind};
+///
+/// let source = "loop: lw a0, 8(sp) # load";
+/// let module = zkir_assembler::parse(source).unwrap();
+/// assert_eq!(module.statements.len(), 3);
+/// assert_eq!(module.statements[0].kind, StatementKind::Label("loop".to_string()));
+/// let StatementKind::Instruction(lw) = &module.statements[1].kind else { panic!() };
+/// assert!(matches!(&lw.operands[1].kind, OperandKind::Memory { base, .. } if base == "sp"));
+/// assert_eq!(&source[lw.operands[1].span.clone()], "8(sp)");
+/// ```
+pub fn parse(source: &str) -> Result<Module> {
+    let (module, errors) = parse_with_errors(source);
+    if errors.is_empty() {
+        Ok(module)
+    } else {
+        Err(AssemblerError::from_errors(errors))
+    }
+}
+
+/// Parse source like [`parse`], keeping the statements of every good line
+///
+/// Bad lines contribute only their comments; their errors are returned
+/// alongside. Editors use this to keep working on source being typed.
+pub fn parse_with_errors(source: &str) -> (Module, Vec<AssemblerError>) {
+    let mut module = Module::default();
+    let mut errors = Vec::new();
+    // Open `.macro`/`.rept`/`.irp` blocks, to recognize parameterized body lines
+    let mut blocks: Vec<String> = Vec::new();
+    let mut start = 0;
+
+    for (i, raw) in source.split_inclusive('\n').enumerate() {
+        let line = i + 1;
+        let text = raw.trim_end_matches(['\n', '\r']);
+        let offset = start;
+        start += raw.len();
+
+        let code = strip_comment(text);
+        let in_parameterized = blocks.iter().any(|block| block == "macro" || block == "irp");
+        if in_parameterized && code.contains('\\') {
+            let trimmed = code.trim();
+            let begin = offset + code.find(trimmed).unwrap_or(0);
+            module.statements.push(Statement {
+                kind: StatementKind::Raw(trimmed.to_string()),
+                line,
+                span: begin..begin + trimmed.len(),
+            });
+        } else if let Err(err) = parse_statements(code, line, offset, &mut module.statements) {
+            match macro_header(code, line, offset) {
+                Some(statement) => module.statements.push(statement),
+                None => errors.push(err.at_line(line)),
+            }
+        }
+
+        match source::directive_name(code.trim()) {
+            Some(block @ ("macro" | "rept" | "irp")) => blocks.push(block.to_string()),
+            Some("endm" | "endr") => {
+                blocks.pop();
+            }
+            _ => {}
+        }
+
+        if code.len() < text.len() {
+            let comment = text[code.len() + 1..].trim_end();
+            let begin = offset + code.len();
+            module.statements.push(Statement {
+                kind: StatementKind::Comment(comment.to_string()),
+                line,
+                span: begin..begin + comment.len() + 1,
+            });
+        }
+    }
+
+    (module, errors)
+}
+
+/// Parse the label and directive or instruction of one line (comment removed)
+/// Token with its byte range in the line
+pub(crate) type Spanned = (Token, Range<usize>);
+
+/// One line of code split into its parts, the same way for [`parse`] and
+/// the assembler
+pub(crate) struct Split<'t> {
+    /// `label:` at the start of the line (identifier or local label number, then the colon)
+    pub(crate) label: Option<&'t [Spanned]>,
+    /// Tokens after the label
+    pub(crate) rest: &'t [Spanned],
+    /// What the tokens after the label are
+    pub(crate) body: Body<'t>,
+}
+
+/// Statement following an optional label
+pub(crate) enum Body<'t> {
+    Empty,
+    /// Directive name (without the dot) and argument tokens
+    Directive(&'t str, &'t [Spanned]),
+    /// Mnemonic or macro name and operand tokens
+    Instruction(&'t str, &'t [Spanned]),
+    /// Token that cannot start a statement
+    Invalid(&'t Token),
+}
+
+impl Body<'_> {
+    /// Error for a line whose statement does not start with a directive or mnemonic
+    pub(crate) fn invalid(token: &Token, line: usize) -> AssemblerError {
+        AssemblerError::SyntaxError {
+            line,
+            message: format!("Expected label, directive or instruction, got {:?}", token),
+        }
+    }
+}
+
+/// Split the tokens of a line into its label and statement
+pub(crate) fn split(tokens: &[Spanned]) -> Split<'_> {
+    let (label, rest) = match tokens {
+        [(Token::Identifier(_) | Token::Number(_), _), (Token::Colon, _), ..] => (Some(&tokens[..2]), &tokens[2..]),
+        _ => (None, tokens),
+    };
+    let body = match rest {
+        [] => Body::Empty,
+        [(Token::Directive(name), _), args @ ..] => Body::Directive(name, args),
+        [(Token::Identifier(mnemonic), _), args @ ..] => Body::Instruction(mnemonic, args),
+        [(token, _), ..] => Body::Invalid(token),
+    };
+    Split { label, rest, body }
+}
+
+fn parse_statements(code: &str, line: usize, offset: usize, out: &mut Vec<Statement>) -> Result<()> {
+    let tokens = tokenize_spanned(code)?;
+    let span = |tokens: &[Spanned]| match tokens {
+        [first, .., last] => offset + first.1.start..offset + last.1.end,
+        [only] => offset + only.1.start..offset + only.1.end,
+        [] => offset..offset,
+    };
+
+    let split = split(&tokens);
+    if let Some(label) = split.label {
+        let name = match &label[0].0 {
+            Token::Identifier(name) => name.clone(),
+            _ => code[label[0].1.clone()].to_string(),
+        };
+        out.push(Statement { kind: StatementKind::Label(name), line, span: span(label) });
+    }
+
+    let kind = match split.body {
+        Body::Empty => return Ok(()),
+        Body::Directive(name, args) => StatementKind::Directive(Directive {
+            name: name.to_string(),
+            args: operands(code, offset, args),
+        }),
+        Body::Instruction(mnemonic, args) => StatementKind::Instruction(ast::Instruction {
+            mnemonic: mnemonic.to_string(),
+            operands: operands(code, offset, args),
+        }),
+        Body::Invalid(token) => return Err(Body::invalid(token, line)),
+    };
+    out.push(Statement { kind, line, span: span(split.rest) });
+    Ok(())
+}
+
+/// `.macro` line whose parameters do not tokenize (defaults such as
+/// `arg=value`), with each comma-separated argument kept as raw text
+fn macro_header(code: &str, line: usize, offset: usize) -> Option<Statement> {
+    let trimmed = code.trim();
+    let header = trimmed.strip_prefix(".macro")?;
+    if !header.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let begin = offset + (code.len() - code.trim_start().len());
+    let mut start = begin + trimmed.len() - header.len();
+    let mut args = Vec::new();
+    for arg in header.split(',') {
+        let lead = arg.len() - arg.trim_start().len();
+        let text = arg.trim();
+        args.push(Operand {
+            kind: OperandKind::Raw(text.to_string()),
+            span: start + lead..start + lead + text.len(),
+        });
+        start += arg.len() + 1;
+    }
+    Some(Statement {
+        kind: StatementKind::Directive(Directive { name: "macro".to_string(), args }),
+        line,
+        span: begin..begin + trimmed.len(),
+    })
+}
+
+/// Split comma-separated operand tokens and classify each group
+fn operands(code: &str, offset: usize, tokens: &[Spanned]) -> Vec<Operand> {
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+    tokens
+        .split(|(token, _)| *token == Token::Comma)
+        .map(|group| {
+            let span = match (group.first(), group.last()) {
+                (Some(first), Some(last)) => first.1.start..last.1.end,
+                _ => 0..0,
+            };
+            let text = &code[span.clone()];
+            let group: Vec<Token> = group
+                .iter()
+                .map(|(token, _)| match token {
+                    // Local label references stay as written
+                    Token::LocalLabel(name) => Token::Identifier(name.clone()),
+                    token => token.clone(),
+                })
+                .collect();
+            Operand {
+                kind: operand_kind(&group).unwrap_or_else(|| OperandKind::Raw(text.to_string())),
+                span: offset + span.start..offset + span.end,
+            }
+        })
+        .collect()
+}
+
+/// Register, memory operand, string or expression (`None` for anything else)
+fn operand_kind(group: &[Token]) -> Option<OperandKind> {
+    match group {
+        [Token::Register(name)] => Some(OperandKind::Register(name.clone())),
+        [Token::Str(bytes)] => Some(OperandKind::Str(bytes.clone())),
+        [offset @ .., Token::LParen, Token::Register(base), Token::RParen] => {
+            let offset = if offset.is_empty() { None } else { Some(Expr::parse(offset, 0).ok()?) };
+            Some(OperandKind::Memory { offset, base: base.clone() })
+        }
+        _ => Expr::parse(group, 0).ok().map(OperandKind::Expr),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -105,16 +336,31 @@ mod tests {
         assert_eq!(parse_register("r2").unwrap(), Register::R2);
 
         // Arguments
-        assert_eq!(parse_register("a0").unwrap(), Register::R11);
-        assert_eq!(parse_register("a4").unwrap(), Register::R15);
+        assert_eq!(parse_register("a0").unwrap(), Register::R10);
+        assert_eq!(parse_register("a5").unwrap(), Register::R15);
 
-        // Saved registers
-        assert_eq!(parse_register("s0").unwrap(), Register::R6);
-        assert_eq!(parse_register("s1").unwrap(), Register::R7);
+        // Frame pointer and saved registers
+        assert_eq!(parse_register("fp").unwrap(), Register::R8);
+        assert_eq!(parse_register("s0").unwrap(), Register::R8);
+        assert_eq!(parse_register("s1").unwrap(), Register::R9);
 
         // Temporaries
-        assert_eq!(parse_register("t0").unwrap(), Register::R8);
-        assert_eq!(parse_register("t2").unwrap(), Register::R10);
+        assert_eq!(parse_register("t0").unwrap(), Register::R5);
+        assert_eq!(parse_register("t2").unwrap(), Register::R7);
+
+        assert!(parse_register("s2").is_err());
+        assert!(parse_register("r16").is_err());
+    }
+
+    #[test]
+    fn test_parse_register_abi_v1() {
+        let v1 = AbiVersion::V1;
+        assert_eq!(parse_register_with_abi("A0", v1).unwrap(), Register::R11);
+        assert_eq!(parse_register_with_abi("a4", v1).unwrap(), Register::R15);
+        assert_eq!(parse_register_with_abi("s0", v1).unwrap(), Register::R6);
+        assert_eq!(parse_register_with_abi("t2", v1).unwrap(), Register::R10);
+        assert_eq!(parse_register_with_abi("r3", v1).unwrap(), Register::R3);
+        assert!(parse_register_with_abi("a5", v1).is_err());
     }
 
     #[test]
@@ -133,6 +379,86 @@ mod tests {
         assert!(matches!(tokens[0], Token::Directive(_)));
     }
 
+    #[test]
+    fn test_strip_comment() {
+        assert_eq!(strip_comment("add r1, r2, r3 # sum"), "add r1, r2, r3 ");
+        assert_eq!(strip_comment("# whole line"), "");
+        assert_eq!(strip_comment(r#".ascii "a#b" # c"#), r#".ascii "a#b" "#);
+        assert_eq!(strip_comment(r##".ascii "q\"#" # c"##), r##".ascii "q\"#" "##);
+    }
+
+    #[test]
+    fn test_tokenize_spanned() {
+        let tokens = tokenize_spanned("  lw a0, 8(sp)").unwrap();
+        assert_eq!(tokens[0], (Token::Identifier("lw".to_string()), 2..4));
+        assert_eq!(tokens.last().unwrap().1, 13..14);
+    }
+
+    #[test]
+    fn test_parse_statements_and_spans() {
+        let source = "main:\n  li a0, BUF*4   # size\n\n1: .word 0x10, 1f\n  sw a0, (sp)\n";
+        let module = parse(source).unwrap();
+        let text = |span: &Range<usize>| &source[span.clone()];
+
+        let kinds: Vec<_> = module.statements.iter().map(|s| (s.line, text(&s.span))).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, "main:"),
+                (2, "li a0, BUF*4"),
+                (2, "# size"),
+                (4, "1:"),
+                (4, ".word 0x10, 1f"),
+                (5, "sw a0, (sp)"),
+            ]
+        );
+
+        let StatementKind::Instruction(li) = &module.statements[1].kind else { panic!() };
+        assert_eq!(li.mnemonic, "li");
+        assert_eq!(li.operands[0].kind, OperandKind::Register("a0".to_string()));
+        assert!(matches!(&li.operands[1].kind, OperandKind::Expr(expr) if expr.to_string() == "BUF * 4"));
+        assert_eq!(module.statements[2].kind, StatementKind::Comment(" size".to_string()));
+        assert_eq!(module.statements[3].kind, StatementKind::Label("1".to_string()));
+
+        let StatementKind::Directive(word) = &module.statements[4].kind else { panic!() };
+        assert_eq!(word.args[1].kind, OperandKind::Expr(Expr::Symbol("1f".to_string())));
+        assert_eq!(text(&word.args[1].span), "1f");
+
+        let StatementKind::Instruction(sw) = &module.statements[5].kind else { panic!() };
+        assert_eq!(sw.operands[1].kind, OperandKind::Memory { offset: None, base: "sp".to_string() });
+    }
+
+    #[test]
+    fn test_parse_keeps_unparsed_operands() {
+        let module = parse(".config limb_bits 20\n.macro push reg\n  addi \\reg, \\reg, 1\n.endm").unwrap();
+        let StatementKind::Directive(config) = &module.statements[0].kind else { panic!() };
+        assert_eq!(config.args[0].kind, OperandKind::Raw("limb_bits 20".to_string()));
+        // Parameterized body lines are only parsed once the macro is expanded
+        assert_eq!(module.statements[2].kind, StatementKind::Raw("addi \\reg, \\reg, 1".to_string()));
+        assert_eq!(module.line(4).count(), 1);
+
+        // Parameter defaults do not tokenize; the header keeps them as text
+        let source = "  .macro inc reg, by=1 # bump";
+        let module = parse(source).unwrap();
+        let StatementKind::Directive(header) = &module.statements[0].kind else { panic!() };
+        assert_eq!(header.name, "macro");
+        let args: Vec<_> = header.args.iter().map(|arg| &source[arg.span.clone()]).collect();
+        assert_eq!(args, vec!["inc reg", "by=1"]);
+        assert_eq!(header.args[1].kind, OperandKind::Raw("by=1".to_string()));
+        assert_eq!(&source[module.statements[0].span.clone()], ".macro inc reg, by=1");
+    }
+
+    #[test]
+    fn test_parse_errors_carry_line_numbers() {
+        let errors = parse("nop\nadd r1, @, r2\n, nop\n").unwrap_err().into_errors();
+        assert_eq!(errors.iter().map(|e| e.line()).collect::<Vec<_>>(), vec![Some(2), Some(3)]);
+
+        let (module, errors) = parse_with_errors("main:\n  add r1, @ # typing\n  j main");
+        assert_eq!(errors.len(), 1);
+        let lines: Vec<_> = module.statements.iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+    }
+
     #[test]
     fn test_extract_number() {
         let token = Token::Number(42);
@@ -0,0 +1,269 @@
+//! Pseudo-instruction expansion for ZKIR v3.4
+//!
+//! Pseudo-instructions are assembler conveniences that expand into one or
+//! more real instructions. Expansion happens in the first pass, so the label
+//! pass sees the expanded size.
+//!
+//! | Pseudo              | Expansion                                   |
+//! |---------------------|---------------------------------------------|
+//! | `nop`               | `addi zero, zero, 0`                        |
+//! | `li rd, imm`        | `addi` (+ `slli`/`ori` pairs for wide values; `la` form if `imm` uses labels) |
+//! | `la rd, label`      | `addi rd, zero, %hi(label)` / `slli rd, rd, 16` / `ori rd, rd, %lo(label)` |
+//! | `mv rd, rs`         | `addi rd, rs, 0`                            |
+//! | `not rd, rs`        | `xori rd, rs, -1`                           |
+//! | `neg rd, rs`        | `sub rd, zero, rs`                          |
+//! | `seqz rd, rs`       | `seq rd, rs, zero`                          |
+//! | `snez rd, rs`       | `sne rd, rs, zero`                          |
+//! | `j label`           | `jal zero, label`                           |
+//! | `jr rs`             | `jalr zero, rs, 0`                          |
+//! | `call label`        | `jal ra, label`                             |
+//! | `ret`               | `jalr zero, ra, 0`                          |
+//! | `beqz rs, label`    | `beq rs, zero, label`                       |
+//! | `bnez rs, label`    | `bne rs, zero, label`                       |
+//! | `bgt rs, rt, label` | `blt rt, rs, label` (also `ble`, `bgtu`, `bleu`) |
+
+use zkir_spec::{Config, Instruction, Register};
+use crate::assembler::{Context, Parsed, fits_imm, expect_no_operands};
+use crate::error::{AssemblerError, Result};
+use crate::expr::Expr;
+use crate::lexer::Token;
+
+/// Mnemonics recognized as pseudo-instructions
+pub const MNEMONICS: &[&str] = &[
+    "nop", "li", "la", "mv", "not", "neg", "seqz", "snez", "j", "jr", "call", "ret",
+    "beqz", "bnez", "bgt", "ble", "bgtu", "bleu",
+];
+
+/// Check whether a (lowercase) mnemonic names a pseudo-instruction
+pub fn is_pseudo(mnemonic: &str) -> bool {
+    MNEMONICS.contains(&mnemonic)
+}
+
+/// Expand a pseudo-instruction into real instructions
+///
+/// Returns `Ok(None)` if `mnemonic` is not a pseudo-instruction.
+pub(crate) fn expand(ctx: &Context, mnemonic: &str, operands: &[Token]) -> Result<Option<Vec<Parsed>>> {
+    let expansion = match mnemonic {
+        "nop" => {
+            expect_no_operands(operands, ctx.line_num)?;
+            vec![nop().into()]
+        }
+        "li" => {
+            let ops = ctx.operands(operands, 2, "li requires 2 operands: rd, imm")?;
+            let rd = ctx.register(ops[0])?;
+            let value = ctx.expression(ops[1])?;
+            match ctx.constant_value(&value)? {
+                Some(v) => load_immediate(rd, normalize(v, ctx.config, ctx.line_num)?)
+                    .into_iter()
+                    .map(Parsed::from)
+                    .collect(),
+                // Depends on labels: size must not depend on the value
+                None => load_address(ctx, rd, value)?,
+            }
+        }
+        "la" => {
+            let ops = ctx.operands(operands, 2, "la requires 2 operands: rd, label")?;
+            let rd = ctx.register(ops[0])?;
+            load_address(ctx, rd, ctx.expression(ops[1])?)?
+        }
+        "mv" => {
+            let (rd, rs) = parse_reg_reg(ctx, operands, "mv")?;
+            vec![Instruction::Addi { rd, rs1: rs, imm: 0 }.into()]
+        }
+        "not" => {
+            let (rd, rs) = parse_reg_reg(ctx, operands, "not")?;
+            vec![Instruction::Xori { rd, rs1: rs, imm: -1 }.into()]
+        }
+        "neg" => {
+            let (rd, rs) = parse_reg_reg(ctx, operands, "neg")?;
+            vec![Instruction::Sub { rd, rs1: Register::R0, rs2: rs }.into()]
+        }
+        "seqz" => {
+            let (rd, rs) = parse_reg_reg(ctx, operands, "seqz")?;
+            vec![Instruction::Seq { rd, rs1: rs, rs2: Register::R0 }.into()]
+        }
+        "snez" => {
+            let (rd, rs) = parse_reg_reg(ctx, operands, "snez")?;
+            vec![Instruction::Sne { rd, rs1: rs, rs2: Register::R0 }.into()]
+        }
+        "j" => {
+            let ops = ctx.operands(operands, 1, "j requires 1 operand: offset")?;
+            vec![ctx.with_target(Instruction::Jal { rd: Register::R0, offset: 0 }, ops[0])?]
+        }
+        "call" => {
+            let ops = ctx.operands(operands, 1, "call requires 1 operand: offset")?;
+            vec![ctx.with_target(Instruction::Jal { rd: Register::R1, offset: 0 }, ops[0])?]
+        }
+        "jr" => {
+            let ops = ctx.operands(operands, 1, "jr requires 1 operand: rs")?;
+            let rs = ctx.register(ops[0])?;
+            vec![Instruction::Jalr { rd: Register::R0, rs1: rs, imm: 0 }.into()]
+        }
+        "ret" => {
+            expect_no_operands(operands, ctx.line_num)?;
+            vec![Instruction::Jalr { rd: Register::R0, rs1: Register::R1, imm: 0 }.into()]
+        }
+        "beqz" | "bnez" => {
+            let usage = format!("{} requires 2 operands: rs, offset", mnemonic);
+            let ops = ctx.operands(operands, 2, &usage)?;
+            let rs = ctx.register(ops[0])?;
+            let instr = if mnemonic == "beqz" {
+                Instruction::Beq { rs1: rs, rs2: Register::R0, offset: 0 }
+            } else {
+                Instruction::Bne { rs1: rs, rs2: Register::R0, offset: 0 }
+            };
+            vec![ctx.with_target(instr, ops[1])?]
+        }
+        "bgt" | "ble" | "bgtu" | "bleu" => {
+            let usage = format!("{} requires 3 operands: rs1, rs2, offset", mnemonic);
+            let ops = ctx.operands(operands, 3, &usage)?;
+            let rs = ctx.register(ops[0])?;
+            let rt = ctx.register(ops[1])?;
+            // Swap the operands: a > b is b < a, a <= b is b >= a
+            let instr = match mnemonic {
+                "bgt" => Instruction::Blt { rs1: rt, rs2: rs, offset: 0 },
+                "ble" => Instruction::Bge { rs1: rt, rs2: rs, offset: 0 },
+                "bgtu" => Instruction::Bltu { rs1: rt, rs2: rs, offset: 0 },
+                _ => Instruction::Bgeu { rs1: rt, rs2: rs, offset: 0 },
+            };
+            vec![ctx.with_target(instr, ops[2])?]
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(expansion))
+}
+
+/// Canonical no-op
+fn nop() -> Instruction {
+    Instruction::Addi { rd: Register::R0, rs1: Register::R0, imm: 0 }
+}
+
+/// Build the shortest `addi`/`slli`/`ori` sequence that loads `value` into `rd`
+///
+/// Values that fit the 17-bit signed immediate take a single `addi`. Wider
+/// values load their upper bits recursively, then shift in 16 bits at a time
+/// and `ori` the low chunk (skipped when zero, merging adjacent shifts).
+pub fn load_immediate(rd: Register, value: i64) -> Vec<Instruction> {
+    if fits_imm(value) {
+        return vec![Instruction::Addi { rd, rs1: Register::R0, imm: value as i32 }];
+    }
+
+    let low = (value & 0xFFFF) as i32;
+    let mut seq = load_immediate(rd, value >> 16);
+    match seq.last_mut() {
+        Some(Instruction::Slli { shamt, .. }) => *shamt += 16,
+        _ => seq.push(Instruction::Slli { rd, rs1: rd, shamt: 16 }),
+    }
+    if low != 0 {
+        seq.push(Instruction::Ori { rd, rs1: rd, imm: low });
+    }
+    seq
+}
+
+/// Fixed three-instruction load of `%hi(value)` and `%lo(value)`
+///
+/// Used for addresses, whose value is only known after layout, so the
+/// sequence length cannot depend on it.
+fn load_address(ctx: &Context, rd: Register, value: Expr) -> Result<Vec<Parsed>> {
+    Ok(vec![
+        ctx.with_imm(
+            Instruction::Addi { rd, rs1: Register::R0, imm: 0 },
+            Expr::Hi(Box::new(value.clone())),
+        )?,
+        Instruction::Slli { rd, rs1: rd, shamt: 16 }.into(),
+        ctx.with_imm(Instruction::Ori { rd, rs1: rd, imm: 0 }, Expr::Lo(Box::new(value)))?,
+    ])
+}
+
+/// Check an `li` constant against the data width and map it to its signed form
+///
+/// Values in the upper half of the unsigned range (e.g. `0xFFFFFFFFFF` for
+/// 40-bit data) are congruent to a small negative number, which loads in
+/// fewer instructions.
+fn normalize(value: i64, config: &Config, line_num: usize) -> Result<i64> {
+    let bits = config.data_bits();
+    if bits >= 64 {
+        return Ok(value);
+    }
+
+    let modulus = 1i64 << bits;
+    if value < -(modulus >> 1) || value >= modulus {
+        return Err(AssemblerError::InvalidImmediate {
+            line: line_num + 1,
+            value: format!("{} does not fit in {} data bits", value, bits),
+        });
+    }
+    Ok(if value >= modulus >> 1 { value - modulus } else { value })
+}
+
+/// Parse: rd, rs
+fn parse_reg_reg(ctx: &Context, operands: &[Token], mnemonic: &str) -> Result<(Register, Register)> {
+    let ops = ctx.operands(operands, 2, &format!("{} requires 2 operands: rd, rs", mnemonic))?;
+    Ok((ctx.register(ops[0])?, ctx.register(ops[1])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate an `li` sequence the way the VM does (wrapping at 40 bits)
+    fn run(seq: &[Instruction]) -> u64 {
+        let mask = (1u64 << 40) - 1;
+        let mut reg = 0u64;
+        for instr in seq {
+            reg = match *instr {
+                Instruction::Addi { imm, .. } => (imm as i64 as u64) & mask,
+                Instruction::Slli { shamt, .. } => (reg << shamt) & mask,
+                Instruction::Ori { imm, .. } => reg | (imm as u64 & mask),
+                ref other => panic!("unexpected instruction {:?}", other),
+            };
+        }
+        reg
+    }
+
+    #[test]
+    fn test_li_small_is_single_addi() {
+        assert_eq!(load_immediate(Register::R1, 42).len(), 1);
+        assert_eq!(load_immediate(Register::R1, -65536).len(), 1);
+        assert_eq!(load_immediate(Register::R1, 65535).len(), 1);
+    }
+
+    #[test]
+    fn test_li_wide_values() {
+        let config = Config::DEFAULT;
+        let mask = (1u64 << 40) - 1;
+        for value in [65536i64, -65537, 0x12345678, 0x7F_FFFF_FFFF, 0xFF_FFFF_FFFF, 1 << 39, 1 << 32, -0x80_0000_0000] {
+            let seq = load_immediate(Register::R1, normalize(value, &config, 0).unwrap());
+            assert_eq!(run(&seq), value as u64 & mask, "li {:#x}", value);
+        }
+    }
+
+    #[test]
+    fn test_li_merges_zero_chunks() {
+        // 1 << 32 = addi 1; slli 32
+        let seq = load_immediate(Register::R1, 1 << 32);
+        assert_eq!(seq.len(), 2);
+        assert!(matches!(seq[1], Instruction::Slli { shamt: 32, .. }));
+    }
+
+    #[test]
+    fn test_li_all_ones_is_minus_one() {
+        let value = normalize(0xFF_FFFF_FFFF, &Config::DEFAULT, 0).unwrap();
+        assert_eq!(value, -1);
+        assert_eq!(load_immediate(Register::R1, value).len(), 1);
+    }
+
+    #[test]
+    fn test_li_rejects_values_wider_than_data() {
+        assert!(normalize(1 << 40, &Config::DEFAULT, 0).is_err());
+        assert!(normalize(-(1 << 39) - 1, &Config::DEFAULT, 0).is_err());
+    }
+
+    #[test]
+    fn test_is_pseudo() {
+        assert!(is_pseudo("li"));
+        assert!(is_pseudo("ret"));
+        assert!(!is_pseudo("addi"));
+    }
+}
@@ -0,0 +1,286 @@
+//! Branch relaxation
+//!
+//! Conditional branches reach ±64 KiB (17-bit offset) and `jal` ±1 MiB
+//! (21-bit offset). A branch or jump whose label is further away is
+//! rewritten, and the text laid out again, until no target is out of reach:
+//!
+//! | Form     | Branch `beq rs1, rs2, label`                     | Jump `jal rd, label`         |
+//! |----------|--------------------------------------------------|------------------------------|
+//! | inverted | `bne rs1, rs2, 8` / `jal zero, label`            | -                            |
+//! | far      | `bne rs1, rs2, 20` / far jump to `label`         | far jump to `label`          |
+//!
+//! A far jump loads the absolute address like `la` and jumps through it:
+//! `addi s, zero, %hi(label)` / `slli s, s, 16` / `ori s, s, %lo(label)` /
+//! `jalr rd, s, 0`. The scratch register `s` is `rd` itself for calls;
+//! jumps that do not link (and far branches) clobber [`SCRATCH`], with a
+//! warning.
+//!
+//! Forms only ever grow, so the layout converges. Explicit numeric offsets
+//! (`beq r1, r2, -8`) are never relaxed or adjusted: one that spans a
+//! branch or jump whose form grew no longer reaches the instruction it did,
+//! and is an error.
+
+use crate::assembler::{align_up, Fixup, Item, Section, Unit};
+use crate::error::AssemblerError;
+use crate::expr::Expr;
+use std::collections::HashMap;
+use zkir_spec::encoding::{IMM_BITS, OFFSET_BITS};
+use zkir_spec::{Instruction, Register};
+
+/// Register clobbered by far jumps and branches that do not link (`t1`)
+pub(crate) const SCRATCH: Register = Register::T1;
+
+/// How far a branch or jump has been rewritten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Form {
+    Inverted,
+    Far,
+}
+
+/// Relax out-of-range branches and jumps, then lay out `.text`
+///
+/// `offset(unit, target, pc)` is the distance from the instruction at
+/// `.text` offset `pc` to `target` under the current layout, or `None` if
+/// it is not known while assembling (for example an imported symbol).
+/// Text labels, `.align` padding and `unit.pc` are updated for the final
+/// forms, and what relaxation changed is reported (see [`check`]).
+pub(crate) fn relax(unit: &mut Unit, offset: &dyn Fn(&Unit, &Expr, u64) -> Option<i64>) {
+    let items = std::mem::take(&mut unit.items);
+    let mut forms: HashMap<usize, Form> = HashMap::new();
+    let mut unrelaxed = None;
+
+    loop {
+        let offsets = lay_out(unit, &items, &forms);
+        let unrelaxed = unrelaxed.get_or_insert_with(|| offsets.clone());
+        let mut changed = false;
+
+        for (index, item) in items.iter().enumerate() {
+            let Item::Instruction { instr, fixup: Some(Fixup::PcRel(target)), .. } = item else {
+                continue;
+            };
+            let is_jump = matches!(instr, Instruction::Jal { .. });
+            // Only the near branch and the `jal` of an inverted branch are PC-relative
+            let (pc, bits) = match (forms.get(&index), is_jump) {
+                (None, false) => (offsets[index], IMM_BITS),
+                (None, true) => (offsets[index], OFFSET_BITS),
+                (Some(Form::Inverted), _) => (offsets[index] + 4, OFFSET_BITS),
+                (Some(Form::Far), _) => continue,
+            };
+            let Some(distance) = offset(unit, target, pc) else {
+                continue;
+            };
+            if fits(distance, bits) {
+                continue;
+            }
+
+            let next = match forms.get(&index) {
+                None if !is_jump => Form::Inverted,
+                _ => Form::Far,
+            };
+            forms.insert(index, next);
+            changed = true;
+        }
+
+        if !changed {
+            check(unit, &items, &forms, unrelaxed);
+            return;
+        }
+    }
+}
+
+/// Warn about far forms that clobber [`SCRATCH`], and reject explicit
+/// offsets that span a branch or jump whose form grew
+///
+/// `unrelaxed` holds the `.text` offset of every item before relaxation.
+fn check(unit: &mut Unit, items: &[Item], forms: &HashMap<usize, Form>, unrelaxed: &[u64]) {
+    for (index, item) in items.iter().enumerate() {
+        let Item::Instruction { line, instr, fixup, location, .. } = item else {
+            continue;
+        };
+        let line = *line;
+        match fixup {
+            Some(Fixup::PcRel(target))
+                if forms.get(&index) == Some(&Form::Far) && instr.rd().is_none_or(Register::is_zero) =>
+            {
+                let message = format!(
+                    "`{}` to `{}` is rewritten into a far jump through {} ({}), which it overwrites",
+                    instr.mnemonic(),
+                    target,
+                    SCRATCH.name(),
+                    SCRATCH.numeric_name()
+                );
+                unit.warnings.push(location.wrap(AssemblerError::Relaxation { line, message }));
+            }
+            None => {
+                let Some(offset) = instr.pc_offset() else {
+                    continue;
+                };
+                let pc = unrelaxed[index] as i64;
+                let target = pc + offset as i64;
+                let span = if offset > 0 { pc + 4..target } else { target..pc };
+                let grown = forms.keys().copied().filter(|&k| span.contains(&(unrelaxed[k] as i64))).min();
+                if let Some(Item::Instruction { line: grown, .. }) = grown.map(|k| &items[k]) {
+                    let message = format!(
+                        "offset {} spans the branch or jump at line {}, which is rewritten into a longer sequence; \
+                         use a label",
+                        offset, grown
+                    );
+                    unit.errors.push(location.wrap(AssemblerError::Relaxation { line, message }));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Expand `items` with the chosen forms into `unit.items`
+///
+/// Returns the `.text` offset of every item in `items`.
+fn lay_out(unit: &mut Unit, items: &[Item], forms: &HashMap<usize, Form>) -> Vec<u64> {
+    let mut expanded = Vec::with_capacity(items.len());
+    let mut offsets = Vec::with_capacity(items.len());
+    let mut pc = 0;
+
+    for (index, item) in items.iter().enumerate() {
+        offsets.push(pc);
+        match item {
+            Item::Label(name) => {
+                if let Some(symbol) = unit.labels.get_mut(name).filter(|symbol| symbol.section == Section::Text) {
+                    symbol.offset = pc;
+                }
+                expanded.push(item.clone());
+            }
+            Item::Align { align, line, location } => {
+                for _ in 0..(align_up(pc, *align) - pc).div_ceil(4) {
+                    expanded.push(Item::Instruction {
+                        line: *line,
+                        instr: Instruction::Addi { rd: Register::R0, rs1: Register::R0, imm: 0 },
+                        fixup: None,
+                        location: location.clone(),
+                        relaxed: false,
+                        pseudo: true,
+                    });
+                    pc += 4;
+                }
+            }
+            Item::Instruction { line, instr, fixup: Some(Fixup::PcRel(target)), location, pseudo, .. }
+                if forms.contains_key(&index) =>
+            {
+                for (instr, fixup) in sequence(instr, target, forms[&index]) {
+                    expanded.push(Item::Instruction {
+                        line: *line,
+                        instr,
+                        fixup,
+                        location: location.clone(),
+                        relaxed: true,
+                        pseudo: *pseudo,
+                    });
+                    pc += 4;
+                }
+            }
+            Item::Instruction { .. } => {
+                expanded.push(item.clone());
+                pc += 4;
+            }
+            _ => expanded.push(item.clone()),
+        }
+    }
+
+    unit.items = expanded;
+    unit.pc = pc;
+    offsets
+}
+
+/// Instructions replacing a branch or jump to `target`
+fn sequence(instr: &Instruction, target: &Expr, form: Form) -> Vec<(Instruction, Option<Fixup>)> {
+    let mut seq = Vec::new();
+    let rd = match *instr {
+        Instruction::Jal { rd, .. } => rd,
+        ref branch => {
+            // Skip the jump that follows when the condition does not hold
+            let skip = if form == Form::Inverted { 8 } else { 20 };
+            seq.push((invert(branch, skip), None));
+            if form == Form::Inverted {
+                seq.push((Instruction::Jal { rd: Register::R0, offset: 0 }, Some(Fixup::PcRel(target.clone()))));
+                return seq;
+            }
+            Register::R0
+        }
+    };
+
+    let scratch = if rd.is_zero() { SCRATCH } else { rd };
+    seq.extend([
+        (
+            Instruction::Addi { rd: scratch, rs1: Register::R0, imm: 0 },
+            Some(Fixup::Imm(Expr::Hi(Box::new(target.clone())))),
+        ),
+        (Instruction::Slli { rd: scratch, rs1: scratch, shamt: 16 }, None),
+        (
+            Instruction::Ori { rd: scratch, rs1: scratch, imm: 0 },
+            Some(Fixup::Imm(Expr::Lo(Box::new(target.clone())))),
+        ),
+        (Instruction::Jalr { rd, rs1: scratch, imm: 0 }, None),
+    ]);
+    seq
+}
+
+/// Branch on the opposite condition to `offset`
+fn invert(branch: &Instruction, offset: i32) -> Instruction {
+    match *branch {
+        Instruction::Beq { rs1, rs2, .. } => Instruction::Bne { rs1, rs2, offset },
+        Instruction::Bne { rs1, rs2, .. } => Instruction::Beq { rs1, rs2, offset },
+        Instruction::Blt { rs1, rs2, .. } => Instruction::Bge { rs1, rs2, offset },
+        Instruction::Bge { rs1, rs2, .. } => Instruction::Blt { rs1, rs2, offset },
+        Instruction::Bltu { rs1, rs2, .. } => Instruction::Bgeu { rs1, rs2, offset },
+        Instruction::Bgeu { rs1, rs2, .. } => Instruction::Bltu { rs1, rs2, offset },
+        _ => unreachable!("target operands are only parsed for branches and jumps"),
+    }
+}
+
+/// Check whether a byte offset fits a signed field of `bits`
+fn fits(offset: i64, bits: u32) -> bool {
+    let limit = 1i64 << (bits - 1);
+    (-limit..limit).contains(&offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invert_round_trips() {
+        let branches = [
+            Instruction::Beq { rs1: Register::R1, rs2: Register::R2, offset: 0 },
+            Instruction::Blt { rs1: Register::R1, rs2: Register::R2, offset: 0 },
+            Instruction::Bgeu { rs1: Register::R1, rs2: Register::R2, offset: 0 },
+        ];
+        for branch in branches {
+            let inverted = invert(&branch, 8);
+            assert_ne!(std::mem::discriminant(&inverted), std::mem::discriminant(&branch));
+            assert_eq!(invert(&inverted, 0), branch);
+        }
+    }
+
+    #[test]
+    fn test_far_sequences() {
+        let target = Expr::Symbol("far".to_string());
+
+        // A call computes the address in the link register itself
+        let call = sequence(&Instruction::Jal { rd: Register::RA, offset: 0 }, &target, Form::Far);
+        assert_eq!(call.len(), 4);
+        assert_eq!(call[3].0, Instruction::Jalr { rd: Register::RA, rs1: Register::RA, imm: 0 });
+
+        let branch = sequence(&Instruction::Beq { rs1: Register::R1, rs2: Register::R2, offset: 0 }, &target, Form::Far);
+        assert_eq!(branch.len(), 5);
+        assert_eq!(branch[0].0, Instruction::Bne { rs1: Register::R1, rs2: Register::R2, offset: 20 });
+        assert_eq!(branch[4].0, Instruction::Jalr { rd: Register::R0, rs1: SCRATCH, imm: 0 });
+    }
+
+    #[test]
+    fn test_fits() {
+        assert!(fits(65532, IMM_BITS));
+        assert!(!fits(65536, IMM_BITS));
+        assert!(fits(-65536, IMM_BITS));
+        assert!(fits(65536, OFFSET_BITS));
+    }
+}
@@ -0,0 +1,259 @@
+//! Source files and the line stream read by the first pass
+//!
+//! Lines come from the top-level source, from files pulled in with
+//! `.include`, and from macro expansions. Each line remembers the file and
+//! line it was written on and the macro invocations that produced it, so
+//! errors can point at all of them.
+//!
+//! `.include` and `.incbin` paths are resolved relative to the directory of
+//! the including file (the working directory for in-memory sources), then
+//! against the configured include search paths in order.
+
+use crate::error::{AssemblerError, Result};
+use crate::macros::Origin;
+use crate::parser::strip_comment;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// File that lines were read from
+#[derive(Debug)]
+pub(crate) struct SourceFile {
+    /// Path as resolved, shown in diagnostics
+    path: PathBuf,
+    /// Canonical path, used to detect include cycles
+    canonical: PathBuf,
+    /// File that included this one
+    parent: FileRef,
+}
+
+/// File a line came from (`None` for in-memory sources)
+pub(crate) type FileRef = Option<Rc<SourceFile>>;
+
+impl SourceFile {
+    /// Read a top-level source file
+    pub(crate) fn read(path: &Path) -> Result<(Rc<Self>, String)> {
+        let in_file = |err: std::io::Error| AssemblerError::InFile {
+            file: path.display().to_string(),
+            source: Box::new(err.into()),
+        };
+        let text = fs::read_to_string(path).map_err(in_file)?;
+        let canonical = path.canonicalize().map_err(in_file)?;
+        let file = Rc::new(SourceFile {
+            path: path.to_path_buf(),
+            canonical,
+            parent: None,
+        });
+        Ok((file, text))
+    }
+}
+
+/// Where a line came from: its file and the macro invocations that produced it
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Location {
+    pub(crate) file: FileRef,
+    pub(crate) origin: Origin,
+}
+
+impl Location {
+    /// Display name of the file (`None` for in-memory source)
+    pub(crate) fn file_name(&self) -> Option<String> {
+        self.file.as_ref().map(|file| file.path.display().to_string())
+    }
+
+    /// Attach the file and macro invocations to an error raised on this line
+    pub(crate) fn wrap(&self, err: AssemblerError) -> AssemblerError {
+        let mut err = in_file(err, &self.file);
+        let mut next = self.origin.as_deref();
+        while let Some(expansion) = next {
+            err = AssemblerError::MacroExpansion {
+                line: expansion.line,
+                name: expansion.name.clone(),
+                source: Box::new(err),
+            };
+            err = in_file(err, &expansion.file);
+            next = expansion.parent.as_deref();
+        }
+        err
+    }
+}
+
+fn in_file(err: AssemblerError, file: &FileRef) -> AssemblerError {
+    match file {
+        Some(file) => AssemblerError::InFile {
+            file: file.path.display().to_string(),
+            source: Box::new(err),
+        },
+        None => err,
+    }
+}
+
+/// Line of source, possibly produced by an expansion
+#[derive(Debug, Clone)]
+pub(crate) struct SourceLine {
+    /// Text with comments removed
+    pub(crate) text: String,
+    /// 1-based line number in its file
+    pub(crate) line: usize,
+    pub(crate) location: Location,
+}
+
+/// Stream of lines for the first pass; includes and expansions are pushed to the front
+pub(crate) struct LineSource {
+    /// Pending lines in reverse order (next line last)
+    pending: Vec<SourceLine>,
+    /// Raw text of every file read, keyed by display name, for diagnostics
+    texts: HashMap<Option<String>, String>,
+}
+
+impl LineSource {
+    pub(crate) fn new(source: &str, file: FileRef) -> Self {
+        let mut lines = Self {
+            pending: Vec::new(),
+            texts: HashMap::new(),
+        };
+        lines.add_file(source.to_string(), file);
+        lines
+    }
+
+    fn add_file(&mut self, text: String, file: FileRef) {
+        self.push_front(split_lines(&text, file.clone()));
+        self.texts.insert(file.map(|f| f.path.display().to_string()), text);
+    }
+
+    /// Raw text of `line` (1-based) in `file` (`None` for the in-memory source)
+    pub(crate) fn source_line(&self, file: Option<&str>, line: usize) -> Option<&str> {
+        let text = self.texts.get(&file.map(str::to_string))?;
+        text.lines().nth(line.checked_sub(1)?)
+    }
+
+    pub(crate) fn next_line(&mut self) -> Option<SourceLine> {
+        self.pending.pop()
+    }
+
+    /// Insert lines to be read next
+    pub(crate) fn push_front(&mut self, lines: Vec<SourceLine>) {
+        self.pending.extend(lines.into_iter().rev());
+    }
+
+    /// Take the body of a block opened by `start`, up to its matching `close`
+    ///
+    /// Blocks opened by any of `open` nest.
+    pub(crate) fn collect_block(&mut self, start: &SourceLine, open: &[&str], close: &str) -> Result<Vec<SourceLine>> {
+        let mut body = Vec::new();
+        let mut depth = 0;
+        while let Some(line) = self.next_line() {
+            match directive_name(&line.text) {
+                Some(name) if name == close && depth == 0 => return Ok(body),
+                Some(name) if name == close => depth -= 1,
+                Some(name) if open.contains(&name) => depth += 1,
+                _ => {}
+            }
+            body.push(line);
+        }
+        Err(AssemblerError::SyntaxError {
+            line: start.line,
+            message: format!("Unterminated .{} (missing .{})", directive_name(&start.text).unwrap_or("?"), close),
+        })
+    }
+
+    /// Read the file named by `.include` on line `at` and queue its lines
+    pub(crate) fn include(&mut self, name: &str, at: &SourceLine, search: &[PathBuf]) -> Result<()> {
+        let path = locate(name, at, search)?;
+        let error = |message: String| AssemblerError::Include {
+            line: at.line,
+            path: name.to_string(),
+            message,
+        };
+        let canonical = path.canonicalize().map_err(|e| error(e.to_string()))?;
+
+        // Including any file on the current include chain would never end
+        let mut chain = Vec::new();
+        let mut next = at.location.file.as_deref();
+        while let Some(file) = next {
+            chain.push(file.path.display().to_string());
+            if file.canonical == canonical {
+                chain.reverse();
+                chain.push(path.display().to_string());
+                return Err(error(format!("include cycle: {}", chain.join(" -> "))));
+            }
+            next = file.parent.as_deref();
+        }
+
+        let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+        let file = Rc::new(SourceFile {
+            path,
+            canonical,
+            parent: at.location.file.clone(),
+        });
+        self.add_file(text, Some(file));
+        Ok(())
+    }
+}
+
+/// Read the file named by `.incbin` on line `at`
+pub(crate) fn read_binary(name: &str, at: &SourceLine, search: &[PathBuf]) -> Result<Vec<u8>> {
+    let path = locate(name, at, search)?;
+    fs::read(&path).map_err(|e| AssemblerError::Include {
+        line: at.line,
+        path: name.to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Find an included file relative to the including file, then in `search`
+fn locate(name: &str, at: &SourceLine, search: &[PathBuf]) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    let local = match &at.location.file {
+        Some(file) => file.path.parent().unwrap_or(Path::new("")).join(relative),
+        None => relative.to_path_buf(),
+    };
+    std::iter::once(local)
+        .chain(search.iter().map(|dir| dir.join(relative)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| AssemblerError::Include {
+            line: at.line,
+            path: name.to_string(),
+            message: "file not found".to_string(),
+        })
+}
+
+fn split_lines(text: &str, file: FileRef) -> Vec<SourceLine> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| SourceLine {
+            text: strip_comment(line).trim().to_string(),
+            line: i + 1,
+            location: Location {
+                file: file.clone(),
+                origin: None,
+            },
+        })
+        .collect()
+}
+
+/// Directive name (without the dot) if the line starts with one
+pub(crate) fn directive_name(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('.')?;
+    let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directive_name() {
+        assert_eq!(directive_name(".macro push reg"), Some("macro"));
+        assert_eq!(directive_name(".endm"), Some("endm"));
+        assert_eq!(directive_name("addi r1, r1, 1"), None);
+    }
+
+    #[test]
+    fn test_wrap_without_file_is_unchanged() {
+        let err = Location::default().wrap(AssemblerError::Other("x".to_string()));
+        assert!(matches!(err, AssemblerError::Other(_)));
+    }
+}
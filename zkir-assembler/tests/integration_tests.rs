@@ -4,10 +4,15 @@
 //! - Instruction parsing and encoding
 //! - Label resolution
 //! - Configuration directives
-//! - Error handling for malformed input
+//! - File includes
+//! - Error handling and diagnostics for malformed input
 
-use zkir_assembler::{assemble, encode, parse_register, AssemblerError};
+use std::fs;
+use std::path::PathBuf;
+use zkir_assembler::{assemble, assemble_file, assemble_object, encode, parse_register, Assembler, AssemblerError, AssemblerOptions, Severity};
+use zkir_spec::{Binding, RelocationKind, RelocationTarget, SectionKind};
 use zkir_spec::{Instruction, Register, Opcode};
+use zkir_spec::encoding::{extract_imm_signed, extract_offset_signed};
 
 // ============================================================================
 // Basic Assembly Tests
@@ -159,6 +164,19 @@ fn test_assemble_shift_immediate() {
     assert_eq!(program.code.len(), 4);
 }
 
+#[test]
+fn test_assemble_checks_encoded_fields() {
+    // Shift amounts past the data width encode; the parser rejects amounts
+    // above 63 before encoding
+    assert!(assemble("slli r1, r2, 45").is_ok());
+    assert!(matches!(assemble("nop\nslli r1, r2, 64"), Err(AssemblerError::ValueOutOfRange { line: 2, .. })));
+
+    // Explicit branch offsets must be whole instructions
+    let err = assemble("beq r1, r2, 6").unwrap_err();
+    assert!(err.to_string().ends_with("beq offset 6 is not aligned to 4 bytes"), "{}", err);
+    assert!(matches!(assemble_object("jal ra, 2"), Err(AssemblerError::Encode { line: 1, .. })));
+}
+
 // ============================================================================
 // Load/Store Instruction Tests
 // ============================================================================
@@ -314,6 +332,1167 @@ fn test_assemble_underscore_label() {
     assert_eq!(program.code.len(), 2);
 }
 
+#[test]
+fn test_assemble_branch_to_label() {
+    let source = r#"
+    loop:
+        sub r1, r1, r4
+        bne r1, zero, loop
+        beq r1, r2, done
+        add r1, r2, r3
+    done:
+        ecall
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 5);
+
+    // Backward branch: loop is one instruction before the bne
+    assert_eq!(extract_imm_signed(program.code[1]), -4);
+    // Forward branch: done is three instructions after the beq
+    assert_eq!(extract_imm_signed(program.code[2]), 8);
+}
+
+#[test]
+fn test_assemble_jal_to_label() {
+    let source = r#"
+    _start:
+        jal ra, fib
+        ebreak
+    fib:
+        add r1, r2, r3
+        jal zero, _start
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(extract_offset_signed(program.code[0]), 8);
+    assert_eq!(extract_offset_signed(program.code[3]), -12);
+}
+
+#[test]
+fn test_assemble_undefined_label() {
+    let source = r#"
+        beq r1, r2, nowhere
+        ecall
+    "#;
+    match assemble(source) {
+        Err(AssemblerError::UndefinedLabel { line, label }) => {
+            assert_eq!(line, 2);
+            assert_eq!(label, "nowhere");
+        }
+        other => panic!("Expected UndefinedLabel error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_assemble_branch_relaxation() {
+    // 16384 instructions put the label exactly 2^16 bytes past the branch
+    let mut source = String::from("beq r1, r2, far\n");
+    for _ in 0..16383 {
+        source.push_str("add r1, r2, r3\n");
+    }
+    source.push_str("far:\necall\n");
+
+    // Out of B-type range: inverted to skip a `jal` to the label
+    let program = assemble(&source).unwrap();
+    assert_eq!(program.code.len(), 16386);
+    assert_eq!(program.code[0], encode(&Instruction::Bne { rs1: Register::R1, rs2: Register::R2, offset: 8 }));
+    assert_eq!(program.code[1] & 0x7F, Opcode::Jal.to_u8() as u32);
+    assert_eq!(extract_offset_signed(program.code[1]), 65536);
+
+    // The same distance is well within the 21-bit JAL range
+    let source = source.replacen("beq r1, r2, far", "jal zero, far", 1);
+    let program = assemble(&source).unwrap();
+    assert_eq!(program.code.len(), 16385);
+    assert_eq!(extract_offset_signed(program.code[0]), 65536);
+
+    // Explicit offsets are never relaxed
+    match assemble("beq r1, r2, 65536") {
+        Err(AssemblerError::OffsetOutOfRange { line, offset, bits, .. }) => {
+            assert_eq!((line, offset, bits), (1, 65536, 17));
+        }
+        other => panic!("Expected OffsetOutOfRange error, got {:?}", other.map(|p| p.code.len())),
+    }
+
+    // ... and one that spans a branch that grew would land elsewhere
+    let spanning = format!("beq r1, r2, 8\n{}", source.replacen("jal zero, far", "beq r1, r2, far", 1));
+    match assemble(&spanning) {
+        Err(err @ AssemblerError::Relaxation { line: 1, .. }) => assert_eq!(
+            err.to_string(),
+            "Branch relaxation at line 1: offset 8 spans the branch or jump at line 2, \
+             which is rewritten into a longer sequence; use a label"
+        ),
+        other => panic!("Expected Relaxation error, got {:?}", other.map(|p| p.code.len())),
+    }
+    let before = format!("beq r1, r2, 4\n{}", source.replacen("jal zero, far", "beq r1, r2, far", 1));
+    assert!(assemble(&before).is_ok());
+}
+
+#[test]
+fn test_assemble_far_jump_relaxation() {
+    // Each repetition adds 4 KiB, putting `far` past the 1 MiB JAL range
+    let source = r#"
+        j far
+        call far
+        blt r1, r2, far
+        .align 4
+    after:
+        .rept 257
+        nop
+        .align 12
+        .endr
+    far:
+        ret
+    "#;
+    let (program, listing) = Assembler::new().assemble_with_listing(source).unwrap();
+    let instructions: Vec<_> = listing.lines.iter().take(13).map(|line| line.instruction).collect();
+    let far = listing.symbols.iter().find(|s| s.name == "far").unwrap().address as i32;
+    let (hi, lo) = (far >> 16, far & 0xFFFF);
+    let t1 = Register::T1;
+    let ra = Register::RA;
+    assert_eq!(
+        instructions,
+        vec![
+            Instruction::Addi { rd: t1, rs1: Register::R0, imm: hi },
+            Instruction::Slli { rd: t1, rs1: t1, shamt: 16 },
+            Instruction::Ori { rd: t1, rs1: t1, imm: lo },
+            Instruction::Jalr { rd: Register::R0, rs1: t1, imm: 0 },
+            Instruction::Addi { rd: ra, rs1: Register::R0, imm: hi },
+            Instruction::Slli { rd: ra, rs1: ra, shamt: 16 },
+            Instruction::Ori { rd: ra, rs1: ra, imm: lo },
+            Instruction::Jalr { rd: ra, rs1: ra, imm: 0 },
+            Instruction::Bge { rs1: Register::R1, rs2: Register::R2, offset: 20 },
+            Instruction::Addi { rd: t1, rs1: Register::R0, imm: hi },
+            Instruction::Slli { rd: t1, rs1: t1, shamt: 16 },
+            Instruction::Ori { rd: t1, rs1: t1, imm: lo },
+            Instruction::Jalr { rd: Register::R0, rs1: t1, imm: 0 },
+        ]
+    );
+    assert!(listing.lines[..13].iter().all(|line| line.relaxed));
+
+    // `.align` padding follows the grown code
+    let after = listing.symbols.iter().find(|s| s.name == "after").unwrap().address;
+    assert_eq!(after, 0x1040);
+    assert_eq!(program.code.len() as u64 * 4, far as u64 + 4 - 0x1000);
+
+    let text = listing.to_string();
+    assert!(text.contains(" 2 | j far  [relaxed]\n"), "{}", &text[..400]);
+    assert!(!text.contains("ret  [relaxed]"));
+
+    // The jump and the branch overwrite `t1`; the call only its link register
+    let (_, diagnostics) = Assembler::new().assemble_with_diagnostics(source);
+    let warnings: Vec<_> = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.message.starts_with("Branch relaxation"))
+        .map(|diagnostic| (diagnostic.span.as_ref().unwrap().line, diagnostic.message.as_str()))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (2, "Branch relaxation: `jal` to `far` is rewritten into a far jump through t1 (r6), which it overwrites"),
+            (4, "Branch relaxation: `blt` to `far` is rewritten into a far jump through t1 (r6), which it overwrites"),
+        ]
+    );
+    assert!(diagnostics.iter().all(|diagnostic| diagnostic.severity == Severity::Warning));
+}
+
+// ============================================================================
+// Data Section Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_data_directives() {
+    let source = r#"
+        ecall
+        .data
+    bytes:
+        .byte 1, 2, 0xFF
+        .align 1
+    halves:
+        .half -1
+    words:
+        .word 0x12345678
+        .align 3
+    dwords:
+        .dword 0x0102030405060708
+    text:
+        .ascii "ab"
+        .asciz "c"
+    "#;
+    let program = assemble(source).unwrap();
+
+    // One instruction of code ends at 0x1004; the .dword makes .data 8-byte
+    // aligned, so the image starts with 4 bytes of padding.
+    let expected: Vec<u8> = vec![
+        0, 0, 0, 0,                         // section alignment padding
+        1, 2, 0xFF, 0,                      // .byte + .align 1 padding
+        0xFF, 0xFF,                         // .half -1
+        0x78, 0x56, 0x34, 0x12,             // .word
+        0, 0, 0, 0, 0, 0,                   // .align 3 padding
+        8, 7, 6, 5, 4, 3, 2, 1,             // .dword
+        b'a', b'b', b'c', 0,                // .ascii + .asciz
+    ];
+    assert_eq!(program.data, expected);
+    assert_eq!(program.header.data_size, expected.len() as u32);
+    assert!(program.validate().is_ok());
+}
+
+#[test]
+fn test_assemble_data_labels_resolve_to_runtime_addresses() {
+    // One instruction of code: .data starts at CODE_BASE + 4, 8-byte aligned
+    // because of the .dword, so 4 bytes of padding precede it.
+    let source = r#"
+        ecall
+        .section .data
+    counter:
+        .dword 7
+    table:
+        .word counter, table, buffer
+        .rodata
+    message:
+        .asciz "hi"
+        .bss
+        .align 2
+    buffer:
+        .space 64
+    "#;
+    let program = assemble(source).unwrap();
+
+    let counter = 0x1008u32;
+    let table = counter + 8;
+    let message = table + 12;
+    let buffer = (message + 3 + 3) & !3; // .align 2 in .bss
+
+    assert_eq!(&program.data[..4], &[0, 0, 0, 0]);
+    let word = |i: usize| {
+        let at = 12 + i * 4;
+        u32::from_le_bytes(program.data[at..at + 4].try_into().unwrap())
+    };
+    assert_eq!(word(0), counter);
+    assert_eq!(word(1), table);
+    assert_eq!(word(2), buffer);
+
+    // .bss is not in the image but is reserved after it
+    assert_eq!(program.data.len() as u32, message + 3 - 0x1004);
+    assert_eq!(program.header.bss_size, buffer + 64 - (message + 3));
+}
+
+#[test]
+fn test_assemble_data_directive_errors() {
+    // Data in .text, initialized data in .bss, instructions in .data
+    assert!(assemble(".word 1").is_err());
+    assert!(assemble(".bss\n.byte 1").is_err());
+    assert!(assemble(".data\nadd r1, r2, r3").is_err());
+    // Value too wide for its directive
+    assert!(assemble(".data\n.byte 256").is_err());
+    assert!(assemble(".data\n.half 0x10000").is_err());
+    // Unknown section and undefined data label
+    assert!(assemble(".section .foo").is_err());
+    assert!(matches!(
+        assemble(".data\n.word missing"),
+        Err(AssemblerError::UndefinedLabel { line: 2, .. })
+    ));
+}
+
+// ============================================================================
+// Pseudo-Instruction Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_simple_pseudo_instructions() {
+    let source = r#"
+        nop
+        mv a0, a1
+        not r1, r2
+        neg r1, r2
+        seqz r1, r2
+        snez r1, r2
+        jr r5
+        ret
+    "#;
+    let program = assemble(source).unwrap();
+    let expected = [
+        Instruction::Addi { rd: Register::R0, rs1: Register::R0, imm: 0 },
+        Instruction::Addi { rd: Register::R10, rs1: Register::R11, imm: 0 },
+        Instruction::Xori { rd: Register::R1, rs1: Register::R2, imm: -1 },
+        Instruction::Sub { rd: Register::R1, rs1: Register::R0, rs2: Register::R2 },
+        Instruction::Seq { rd: Register::R1, rs1: Register::R2, rs2: Register::R0 },
+        Instruction::Sne { rd: Register::R1, rs1: Register::R2, rs2: Register::R0 },
+        Instruction::Jalr { rd: Register::R0, rs1: Register::R5, imm: 0 },
+        Instruction::Jalr { rd: Register::R0, rs1: Register::R1, imm: 0 },
+    ];
+    let expected: Vec<u32> = expected.iter().map(encode).collect();
+    assert_eq!(program.code, expected);
+}
+
+#[test]
+fn test_assemble_pseudo_branches() {
+    let source = r#"
+    top:
+        beqz r1, top
+        bnez r1, top
+        bgt r1, r2, top
+        ble r1, r2, top
+        bgtu r1, r2, top
+        bleu r1, r2, top
+        j top
+        call top
+    "#;
+    let program = assemble(source).unwrap();
+    let expected = [
+        Instruction::Beq { rs1: Register::R1, rs2: Register::R0, offset: 0 },
+        Instruction::Bne { rs1: Register::R1, rs2: Register::R0, offset: -4 },
+        Instruction::Blt { rs1: Register::R2, rs2: Register::R1, offset: -8 },
+        Instruction::Bge { rs1: Register::R2, rs2: Register::R1, offset: -12 },
+        Instruction::Bltu { rs1: Register::R2, rs2: Register::R1, offset: -16 },
+        Instruction::Bgeu { rs1: Register::R2, rs2: Register::R1, offset: -20 },
+        Instruction::Jal { rd: Register::R0, offset: -24 },
+        Instruction::Jal { rd: Register::R1, offset: -28 },
+    ];
+    let expected: Vec<u32> = expected.iter().map(encode).collect();
+    assert_eq!(program.code, expected);
+}
+
+#[test]
+fn test_assemble_li_expansion_size() {
+    // Small constants take one instruction, 40-bit constants several
+    let program = assemble("li r1, -5").unwrap();
+    assert_eq!(program.code.len(), 1);
+
+    let program = assemble("li r1, 0x12345678").unwrap();
+    assert_eq!(program.code.len(), 3);
+
+    // Value does not fit the 40-bit data width
+    assert!(matches!(
+        assemble("li r1, 0x10000000000"),
+        Err(AssemblerError::InvalidImmediate { line: 1, .. })
+    ));
+}
+
+#[test]
+fn test_assemble_labels_account_for_expansion() {
+    let source = r#"
+        li r1, 0x123456789
+        la r2, target
+        j target
+    target:
+        ecall
+    "#;
+    let program = assemble(source).unwrap();
+    // li: 5 instructions, la: 3, j: 1
+    assert_eq!(program.code.len(), 10);
+    assert_eq!(extract_offset_signed(program.code[8]), 4);
+
+    // la loads the absolute address of target: CODE_BASE + 9 * 4
+    let addr = 0x1000 + 9 * 4;
+    assert_eq!(extract_imm_signed(program.code[5]), addr >> 16);
+    assert_eq!(extract_imm_signed(program.code[7]), addr & 0xFFFF);
+}
+
+#[test]
+fn test_assemble_pseudo_operand_errors() {
+    assert!(assemble("mv r1").is_err());
+    assert!(assemble("ret r1").is_err());
+    assert!(assemble("la r1, r2").is_err());
+    assert!(assemble("li r1, label").is_err());
+    assert!(matches!(
+        assemble("call nowhere"),
+        Err(AssemblerError::UndefinedLabel { line: 1, .. })
+    ));
+}
+
+// ============================================================================
+// Constant and Expression Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_equ_constants() {
+    let source = r#"
+        .equ SYSCALL_SHA256, 3
+        .equ BUF_SIZE, 16
+        addi r10, zero, SYSCALL_SHA256
+        addi r1, zero, BUF_SIZE*4 + 8
+        slli r2, r2, BUF_SIZE >> 2
+        lw r3, BUF_SIZE-4(r1)
+        sw r3, (BUF_SIZE | 1) & ~1(r1)
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(extract_imm_signed(program.code[0]), 3);
+    assert_eq!(extract_imm_signed(program.code[1]), 72);
+    assert_eq!(extract_imm_signed(program.code[2]), 4);
+    assert_eq!(extract_imm_signed(program.code[3]), 12);
+    assert_eq!(program.code[4], encode(&Instruction::Sw { rs1: Register::R1, rs2: Register::R3, imm: 16 }));
+}
+
+#[test]
+fn test_assemble_set_is_redefinable() {
+    let source = r#"
+        .set N, 1
+        addi r1, zero, N
+        .set N, N + 1
+        addi r1, zero, N
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(extract_imm_signed(program.code[0]), 1);
+    assert_eq!(extract_imm_signed(program.code[1]), 2);
+
+    // .equ cannot be redefined, and neither can labels
+    assert!(assemble(".equ N, 1\n.equ N, 2").is_err());
+    assert!(assemble(".equ N, 1\n.set N, 2").is_err());
+    assert!(assemble("here: ecall\n.equ here, 2").is_err());
+}
+
+#[test]
+fn test_assemble_label_differences() {
+    let source = r#"
+        addi r1, zero, (table_end - table) / 4
+        addi r2, zero, COUNT
+        ecall
+
+        .data
+    table:
+        .word 1, 2, 3
+    table_end:
+        .word table_end - table
+        .equ COUNT, (table_end - table) / 4
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(extract_imm_signed(program.code[0]), 3);
+    assert_eq!(extract_imm_signed(program.code[1]), 3);
+    assert_eq!(&program.data[12..16], &12u32.to_le_bytes());
+}
+
+#[test]
+fn test_assemble_hi_lo() {
+    let source = r#"
+        addi r1, zero, %hi(value)
+        slli r1, r1, 16
+        lw r2, %lo(value)(r1)
+        ecall
+
+        .data
+    value:
+        .word 42
+    "#;
+    let program = assemble(source).unwrap();
+    // value lives right after the four instructions
+    let addr = 0x1000 + 16;
+    assert_eq!(extract_imm_signed(program.code[0]), addr >> 16);
+    assert_eq!(extract_imm_signed(program.code[2]), addr & 0xFFFF);
+}
+
+#[test]
+fn test_assemble_expression_out_of_range_names_sub_expression() {
+    let source = ".equ BUF_SIZE, 0x8000\naddi r1, zero, BUF_SIZE * 4";
+    match assemble(source) {
+        Err(err @ AssemblerError::ValueOutOfRange { line: 2, .. }) => {
+            assert!(err.to_string().contains("`BUF_SIZE * 4` = 131072"), "{}", err);
+        }
+        other => panic!("expected ValueOutOfRange, got {:?}", other),
+    }
+
+    // Shift amounts and data widths are checked too
+    assert!(matches!(assemble("slli r1, r1, 32 + 32"), Err(AssemblerError::ValueOutOfRange { .. })));
+    assert!(matches!(assemble(".data\n.byte 200 + 100"), Err(AssemblerError::ValueOutOfRange { .. })));
+
+    // Arithmetic overflow names the overflowing part
+    let err = assemble("addi r1, zero, 1 + (0x4000000000000000 * 4)").unwrap_err();
+    assert!(err.to_string().contains("`4611686018427387904 * 4`: arithmetic overflow"), "{}", err);
+}
+
+#[test]
+fn test_assemble_expression_errors() {
+    assert!(assemble("addi r1, zero, 1 +").is_err());
+    assert!(assemble("addi r1, zero, (1").is_err());
+    assert!(assemble("addi r1, zero, 4 / 0").is_err());
+    assert!(assemble(".equ A, B\n.equ B, A\naddi r1, zero, A").is_err());
+    // .space needs a value known at that point
+    assert!(assemble(".bss\nbuf: .space later\n.equ later, 4").is_err());
+}
+
+// ============================================================================
+// Macro Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_macro_with_arguments_and_defaults() {
+    let source = r#"
+        .macro inc reg, amount=1
+            addi \reg, \reg, \amount
+        .endm
+
+        inc r1
+        inc r2, 5
+        inc r3, # empty argument takes the default
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 3);
+    assert_eq!(program.code[0], encode(&Instruction::Addi { rd: Register::R1, rs1: Register::R1, imm: 1 }));
+    assert_eq!(program.code[1], encode(&Instruction::Addi { rd: Register::R2, rs1: Register::R2, imm: 5 }));
+    assert_eq!(program.code[2], encode(&Instruction::Addi { rd: Register::R3, rs1: Register::R3, imm: 1 }));
+}
+
+#[test]
+fn test_assemble_macro_unique_labels() {
+    let source = r#"
+        .macro countdown reg
+        loop\@:
+            addi \reg, \reg, -1
+            bnez \reg, loop\@
+        .endm
+
+        countdown r1
+        countdown r2
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 4);
+    // Each expansion branches back to its own loop label
+    assert_eq!(extract_imm_signed(program.code[1]), -4);
+    assert_eq!(extract_imm_signed(program.code[3]), -4);
+}
+
+#[test]
+fn test_assemble_nested_macros() {
+    let source = r#"
+        .macro push reg
+            addi sp, sp, -8
+            sd \reg, 0(sp)
+        .endm
+        .macro push2 a, b
+            push \a
+            push \b
+        .endm
+
+        push2 r5, r6
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 4);
+    assert_eq!(program.code[3], encode(&Instruction::Sd { rs1: Register::R2, rs2: Register::R6, imm: 0 }));
+}
+
+#[test]
+fn test_assemble_rept_and_irp() {
+    let source = r#"
+        .equ UNROLL, 3
+        .rept UNROLL
+            addi r1, r1, 1
+        .endr
+        .irp reg, r5, r6, r7
+            addi \reg, zero, 0
+        .endr
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 6);
+    for word in &program.code[..3] {
+        assert_eq!(*word, encode(&Instruction::Addi { rd: Register::R1, rs1: Register::R1, imm: 1 }));
+    }
+    assert_eq!(program.code[5], encode(&Instruction::Addi { rd: Register::R7, rs1: Register::R0, imm: 0 }));
+}
+
+#[test]
+fn test_assemble_macro_expansion_is_laid_out_before_labels() {
+    let source = r#"
+        .macro pad
+            nop
+            nop
+        .endm
+
+        j end
+        pad
+    end:
+        ecall
+    "#;
+    let program = assemble(source).unwrap();
+    assert_eq!(extract_offset_signed(program.code[0]), 12);
+}
+
+#[test]
+fn test_assemble_macro_error_reports_call_site_and_body_line() {
+    let source = r#"
+        .macro load_big reg
+            addi \reg, zero, 100000
+        .endm
+
+        load_big r1
+    "#;
+    match assemble(source) {
+        Err(AssemblerError::MacroExpansion { line: 6, name, source }) => {
+            assert_eq!(name, "load_big");
+            assert!(matches!(*source, AssemblerError::ValueOutOfRange { line: 3, .. }), "{:?}", source);
+        }
+        other => panic!("expected MacroExpansion, got {:?}", other),
+    }
+
+    // Second-pass errors are attributed the same way
+    let source = ".macro go\nbeq r1, r2, missing\n.endm\ngo";
+    assert!(matches!(
+        assemble(source),
+        Err(AssemblerError::MacroExpansion { line: 4, source, .. }) if matches!(*source, AssemblerError::UndefinedLabel { line: 2, .. })
+    ));
+}
+
+#[test]
+fn test_assemble_macro_errors() {
+    assert!(assemble(".macro m\nnop").is_err());
+    assert!(assemble(".endm").is_err());
+    assert!(assemble(".rept 2\nnop").is_err());
+    assert!(assemble(".macro m a\n.endm\nm").is_err());
+    assert!(assemble(".macro m a\n.endm\nm 1, 2").is_err());
+    // Recursion is cut off instead of looping forever
+    assert!(assemble(".macro m\nm\n.endm\nm").is_err());
+}
+
+// ============================================================================
+// Include Tests
+// ============================================================================
+
+/// Write `files` into a fresh temporary directory and return its path
+fn temp_project(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zkir-asm-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn test_assemble_file_with_includes() {
+    let dir = temp_project(
+        "include",
+        &[
+            ("main.zkasm", b".include \"defs/consts.zkasm\"\nli r10, SYS_EXIT\nsave r5\necall\n"),
+            ("defs/consts.zkasm", b".equ SYS_EXIT, 0\n.include \"macros.zkasm\"\n"),
+            // Resolved relative to defs/, not to main.zkasm
+            ("defs/macros.zkasm", b".macro save reg\naddi sp, sp, -8\nsd \\reg, 0(sp)\n.endm\n"),
+        ],
+    );
+    let program = assemble_file(dir.join("main.zkasm")).unwrap();
+    assert_eq!(program.code.len(), 4);
+    assert_eq!(program.code[2], encode(&Instruction::Sd { rs1: Register::R2, rs2: Register::R5, imm: 0 }));
+}
+
+#[test]
+fn test_assemble_include_search_paths() {
+    let dir = temp_project("search", &[("lib/util.zkasm", b"util: ret\n")]);
+    let source = ".include \"util.zkasm\"\ncall util";
+
+    let errors = assemble(source).unwrap_err().into_errors();
+    assert!(matches!(errors[0], AssemblerError::Include { line: 1, .. }), "{:?}", errors);
+    let program = Assembler::with_include_paths([dir.join("lib")]).assemble(source).unwrap();
+    assert_eq!(program.code.len(), 2);
+}
+
+#[test]
+fn test_assemble_incbin() {
+    let dir = temp_project(
+        "incbin",
+        &[
+            ("main.zkasm", b"ecall\n.rodata\nblob: .incbin \"blob.bin\"\ntail: .incbin \"blob.bin\", 1, 2\n"),
+            ("blob.bin", &[0xDE, 0xAD, 0xBE, 0xEF]),
+        ],
+    );
+    let program = assemble_file(dir.join("main.zkasm")).unwrap();
+    assert_eq!(program.data, vec![0xDE, 0xAD, 0xBE, 0xEF, 0xAD, 0xBE]);
+
+    let dir = temp_project(
+        "incbin-range",
+        &[("main.zkasm", b".data\n.incbin \"blob.bin\", 2, 8\n"), ("blob.bin", &[1, 2, 3])],
+    );
+    assert!(assemble_file(dir.join("main.zkasm")).is_err());
+}
+
+#[test]
+fn test_assemble_include_cycle() {
+    let dir = temp_project(
+        "cycle",
+        &[
+            ("a.zkasm", b"nop\n.include \"b.zkasm\"\n"),
+            ("b.zkasm", b"\n.include \"a.zkasm\"\n"),
+        ],
+    );
+    let err = assemble_file(dir.join("a.zkasm")).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("include cycle"), "{}", message);
+    assert!(message.contains("a.zkasm -> ") && message.contains("b.zkasm -> "), "{}", message);
+}
+
+#[test]
+fn test_assemble_file_errors_name_the_file() {
+    let dir = temp_project(
+        "errors",
+        &[
+            ("main.zkasm", b"nop\n.include \"bad.zkasm\"\n"),
+            ("bad.zkasm", b"nop\nnop\nfoo r1, r2\n"),
+        ],
+    );
+    match assemble_file(dir.join("main.zkasm")) {
+        Err(AssemblerError::InFile { file, source }) => {
+            assert!(file.ends_with("bad.zkasm"), "{}", file);
+            assert!(matches!(*source, AssemblerError::InvalidInstruction { line: 3, .. }), "{:?}", source);
+        }
+        other => panic!("expected InFile, got {:?}", other),
+    }
+
+    // Missing top-level file
+    let err = assemble_file(dir.join("missing.zkasm")).unwrap_err();
+    assert!(err.to_string().contains("missing.zkasm"), "{}", err);
+}
+
+// ============================================================================
+// Local Label Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_numeric_local_labels() {
+    let source = r#"
+    1:  addi r1, r1, -1
+        bne r1, zero, 1b
+        beq r2, zero, 1f
+        j 2f
+    1:  addi r2, r2, 1
+    2:  bne r2, zero, 1b
+    1:  j 1b
+    "#;
+    let program = assemble(source).unwrap();
+    let offsets: Vec<i32> = [1, 2, 3, 5, 6].iter().map(|&i| decode_offset(program.code[i])).collect();
+    // Each reference picks the nearest definition in its direction
+    assert_eq!(offsets, vec![-4, 8, 8, -4, 0]);
+}
+
+/// Branch or jump offset of an encoded instruction
+fn decode_offset(word: u32) -> i32 {
+    if word & 0x7F == Opcode::Jal.to_u8() as u32 {
+        extract_offset_signed(word)
+    } else {
+        extract_imm_signed(word)
+    }
+}
+
+#[test]
+fn test_assemble_local_labels_in_macros_and_includes() {
+    // Each expansion defines its own `1`, so the macro can be used twice
+    let dir = temp_project(
+        "local-labels",
+        &[
+            ("main.zkasm", b".include \"wait.zkasm\"\nwait r1\nwait r2\n2: ecall\n"),
+            ("wait.zkasm", b".macro wait reg\n1: addi \\reg, \\reg, -1\nbne \\reg, zero, 1b\n.endm\nj 2f\n"),
+        ],
+    );
+    let program = assemble_file(dir.join("main.zkasm")).unwrap();
+    assert_eq!(program.code.len(), 6);
+    let offsets: Vec<i32> = [0, 2, 4].iter().map(|&i| decode_offset(program.code[i])).collect();
+    assert_eq!(offsets, vec![20, -4, -4]);
+
+    // Data can refer to them too
+    let program = assemble("1: nop
+.data
+.word 1b, 1f
+1: .word 0").unwrap();
+    assert_eq!(program.data[..8], [0x00, 0x10, 0, 0, 0x0C, 0x10, 0, 0]);
+}
+
+#[test]
+fn test_assemble_local_label_errors() {
+    match assemble("nop
+j 1b") {
+        Err(AssemblerError::UndefinedLabel { line, label }) => assert_eq!((line, label.as_str()), (2, "1b")),
+        other => panic!("Expected UndefinedLabel, got {:?}", other),
+    }
+    // Forward references are reported as written, not by their internal name
+    match assemble("1: nop
+beq r1, r2, 1f") {
+        Err(AssemblerError::UndefinedLabel { line, label }) => assert_eq!((line, label.as_str()), (2, "1f")),
+        other => panic!("Expected UndefinedLabel, got {:?}", other),
+    }
+    assert!(assemble_object("j 3f").unwrap_err().to_string().contains("3f"));
+}
+
+// ============================================================================
+// Conditional Assembly Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_nested_conditionals() {
+    let source = "
+        .equ MODE, 2
+        .if MODE == 2
+            .ifdef UNDEFINED
+                addi r1, zero, 1
+            .else
+                addi r1, zero, 2
+                .if MODE > 5
+                    addi r1, zero, 3
+                .endif
+            .endif
+        .else
+            .if 1
+                addi r1, zero, 4
+            .else
+                bogus line inside a skipped branch
+            .endif
+        .endif
+        .ifndef MODE
+            addi r1, zero, 5
+        .endif
+        ecall
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(program.code.len(), 2);
+    assert_eq!(program.code[0], encode(&zkir_spec::Instruction::Addi { rd: zkir_spec::Register::R1, rs1: zkir_spec::Register::R0, imm: 2 }));
+}
+
+#[test]
+fn test_assemble_conditionals_on_config() {
+    let source = "
+        .config data_limbs DATA_LIMBS_WANTED
+        .if DATA_BITS > 40
+            addi r1, zero, 1
+        .else
+            addi r1, zero, 2
+            addi r1, r1, 2
+        .endif
+    ";
+    let source = |limbs: u8| source.replace("DATA_LIMBS_WANTED", &limbs.to_string());
+    assert_eq!(assemble(&source(2)).unwrap().code.len(), 2);
+    assert_eq!(assemble(&source(3)).unwrap().code.len(), 1);
+
+    // A constant of the same name takes precedence over the config field
+    let program = assemble(".equ DATA_BITS, 64\n.if DATA_BITS == 64\nnop\n.endif").unwrap();
+    assert_eq!(program.code.len(), 1);
+}
+
+#[test]
+fn test_assemble_conditionals_with_defines() {
+    let source = "
+        .ifdef DEBUG
+            ebreak
+        .endif
+        .if LEVEL >= 2
+            nop
+        .endif
+        addi r1, zero, LEVEL
+        ecall
+    ";
+    let mut options = AssemblerOptions::default();
+    options.defines.insert("LEVEL".to_string(), 1);
+    assert_eq!(Assembler::with_options(options.clone()).assemble(source).unwrap().code.len(), 2);
+
+    options.defines.insert("DEBUG".to_string(), 1);
+    options.defines.insert("LEVEL".to_string(), 3);
+    assert_eq!(Assembler::with_options(options.clone()).assemble(source).unwrap().code.len(), 4);
+
+    // Defines are fixed constants
+    let err = Assembler::with_options(options).assemble(".equ LEVEL, 4").unwrap_err();
+    assert!(err.to_string().contains("Duplicate label: LEVEL"), "{}", err);
+}
+
+#[test]
+fn test_assemble_unbalanced_conditionals() {
+    let message = |source: &str| {
+        let err = assemble(source).unwrap_err();
+        (err.line(), err.to_string())
+    };
+
+    let (line, text) = message("nop\n.if 1\nnop\n.ifdef X\n.endif");
+    assert_eq!(line, Some(2));
+    assert!(text.contains(".if without matching .endif"), "{}", text);
+
+    let (line, text) = message("nop\n.endif");
+    assert_eq!(line, Some(2));
+    assert!(text.contains(".endif without matching .if"), "{}", text);
+
+    let (line, text) = message(".else");
+    assert_eq!(line, Some(1));
+    assert!(text.contains(".else without matching .if"), "{}", text);
+
+    let (line, text) = message(".ifndef X\n.else\nnop\n.else\n.endif");
+    assert_eq!(line, Some(4));
+    assert!(text.contains("Second .else for the .ifndef at line 1"), "{}", text);
+
+    // Conditions must be known on the `.if` line
+    let (line, text) = message(".if later\n.endif\nlater:");
+    assert_eq!(line, Some(1));
+    assert!(text.contains("must be a constant defined before this line"), "{}", text);
+}
+
+// ============================================================================
+// Diagnostic Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_reports_every_bad_line() {
+    let source = "nop\nfoo r1, r2\naddi r1, r1, 100000\nnop\nbeq r1, r2, nowhere\n";
+    let errors = assemble(source).unwrap_err().into_errors();
+    assert_eq!(errors.len(), 3, "{:?}", errors);
+    assert_eq!(errors.iter().map(|e| e.line()).collect::<Vec<_>>(), vec![Some(2), Some(3), Some(5)]);
+}
+
+#[test]
+fn test_assemble_errors_carry_line_numbers() {
+    // Lexer errors used to report line 0
+    let err = assemble("nop\nnop\nadd r1, @, r2").unwrap_err();
+    assert_eq!(err.line(), Some(3));
+    assert!(err.to_string().contains("Invalid token `@`"), "{}", err);
+
+    let err = assemble("nop\n.config limb_bits abc").unwrap_err();
+    assert_eq!(err.line(), Some(2));
+}
+
+#[test]
+fn test_assemble_with_diagnostics() {
+    let source = "main:\n    addi r1, zero, BUF*4 # scaled\n    jal zero, mian\n";
+    let (program, diagnostics) = Assembler::new().assemble_with_diagnostics(&format!(".equ BUF, 0x8000\n{}", source));
+    assert!(program.is_none());
+    assert_eq!(diagnostics.len(), 2);
+
+    let first = &diagnostics[0];
+    assert_eq!(first.severity, Severity::Error);
+    assert_eq!(first.message, "Value out of range: `BUF * 4` = 131072 does not fit in 17-bit signed immediate");
+    let span = first.span.as_ref().unwrap();
+    assert_eq!((span.file.as_deref(), span.line, span.columns.clone()), (None, 3, 19..24));
+    assert_eq!(first.source_line.as_deref(), Some("    addi r1, zero, BUF*4 # scaled"));
+
+    assert_eq!(
+        diagnostics[1].to_string(),
+        "error: Undefined label: mian\n --> <source>:4:15\n  |\n4 |     jal zero, mian\n  |               ^^^^"
+    );
+
+    let (program, diagnostics) = Assembler::new().assemble_with_diagnostics("ecall");
+    assert!(program.is_some() && diagnostics.is_empty());
+}
+
+#[test]
+fn test_assemble_file_with_diagnostics() {
+    let dir = temp_project(
+        "diagnostics",
+        &[
+            ("main.zkasm", b".include \"lib.zkasm\"\n.macro twice op\n\\op\n\\op\n.endm\ntwice bogus\n"),
+            ("lib.zkasm", b"nop\naddi r1, r1\n"),
+        ],
+    );
+    let (program, diagnostics) = Assembler::new().assemble_file_with_diagnostics(dir.join("main.zkasm"));
+    assert!(program.is_none());
+    assert_eq!(diagnostics.len(), 3, "{:#?}", diagnostics);
+
+    let span = diagnostics[0].span.as_ref().unwrap();
+    assert!(span.file.as_deref().unwrap().ends_with("lib.zkasm"));
+    assert_eq!(span.line, 2);
+
+    // Both macro-body lines are reported, with the call site as a note
+    for (diagnostic, line) in diagnostics[1..].iter().zip([3, 4]) {
+        let span = diagnostic.span.as_ref().unwrap();
+        assert!(span.file.as_deref().unwrap().ends_with("main.zkasm"));
+        assert_eq!((span.line, span.columns.clone()), (line, 0..3));
+        assert!(diagnostic.notes[0].starts_with("in expansion of macro `twice` at "), "{:?}", diagnostic.notes);
+        assert!(diagnostic.notes[0].ends_with("main.zkasm:6"), "{:?}", diagnostic.notes);
+    }
+}
+
+// ============================================================================
+// Entry Point Tests
+// ============================================================================
+
+#[test]
+fn test_entry_defaults_to_first_instruction() {
+    let program = assemble("helper:\n    ret\nmain:\n    ecall").unwrap();
+    assert_eq!(program.header.entry_point, 0x1000);
+
+    // `_start` only counts when it is global
+    let program = assemble("helper:\n    ret\n_start:\n    ecall").unwrap();
+    assert_eq!(program.header.entry_point, 0x1000);
+}
+
+#[test]
+fn test_entry_from_global_start() {
+    let program = assemble(".global _start\nhelper:\n    ret\n_start:\n    call helper\n    ecall").unwrap();
+    assert_eq!(program.header.entry_point, 0x1004);
+}
+
+#[test]
+fn test_entry_directive() {
+    let source = ".global _start\n.entry main\n_start:\n    ebreak\nhelper:\n    ret\nmain:\n    ecall";
+    let program = assemble(source).unwrap();
+    assert_eq!(program.header.entry_point, 0x1008);
+}
+
+#[test]
+fn test_entry_errors() {
+    let err = assemble("nop\n.entry main\nstart:\n    ecall").unwrap_err();
+    assert!(matches!(err, AssemblerError::UndefinedLabel { line: 2, ref label } if label == "main"), "{}", err);
+
+    let err = assemble(".entry table\necall\n.data\ntable:\n    .word 0").unwrap_err();
+    assert!(err.to_string().contains("Entry point table is not in .text"), "{}", err);
+
+    let err = assemble(".entry a\n.entry b\na:\nb:\n    ecall").unwrap_err();
+    assert!(err.to_string().contains("Duplicate .entry (already set at line 1)"), "{}", err);
+}
+
+#[test]
+fn test_object_entry() {
+    let object = assemble_object(".entry main\nhelper:\n    ret\nmain:\n    ecall").unwrap();
+    assert_eq!(object.entry.map(|i| object.symbols[i].name.as_str()), Some("main"));
+
+    let object = assemble_object(".extern main\n.entry main\nnop").unwrap();
+    assert!(object.symbols[object.entry.unwrap()].is_undefined());
+
+    assert!(assemble_object(".entry main\nnop").is_err());
+}
+
+// ============================================================================
+// Listing Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_with_listing() {
+    let source = "main:\n    la r3, table\n    ecall\n    .data\ntable:\n    .word 1, 2\n    .bss\nbuf:\n    .space 8\n";
+    let (program, listing) = Assembler::new().assemble_with_listing(source).unwrap();
+
+    assert_eq!(listing.lines.len(), program.code.len());
+    let addresses: Vec<_> = listing.lines.iter().map(|l| (l.address, l.line)).collect();
+    assert_eq!(addresses, vec![(0x1000, 2), (0x1004, 2), (0x1008, 2), (0x100c, 3)]);
+    for (line, &word) in listing.lines.iter().zip(&program.code) {
+        assert_eq!(line.word, word);
+        assert_eq!(encode(&line.instruction), word);
+    }
+    assert_eq!(listing.lines[0].source, "    la r3, table");
+    assert_eq!(listing.lines[2].instruction, Instruction::Ori { rd: Register::R3, rs1: Register::R3, imm: 0x1010 });
+
+    let sections: Vec<_> = listing.sections.iter().map(|s| (s.section, s.address, s.size)).collect();
+    assert_eq!(
+        sections,
+        vec![
+            (SectionKind::Text, 0x1000, 16),
+            (SectionKind::Data, 0x1010, 8),
+            (SectionKind::Rodata, 0x1018, 0),
+            (SectionKind::Bss, 0x1018, 8),
+        ]
+    );
+    let symbols: Vec<_> = listing.symbols.iter().map(|s| (s.name.as_str(), s.address)).collect();
+    assert_eq!(symbols, vec![("main", 0x1000), ("table", 0x1010), ("buf", 0x1018)]);
+
+    let text = listing.to_string();
+    assert!(text.contains("00001000  00000188  addi gp, zero, 0          2 | la r3, table\n"), "{}", text);
+    assert!(text.contains("\n0000100c  00000050  ecall                     3 | ecall\n"), "{}", text);
+    assert!(text.contains("  00001018  .bss     buf\n"), "{}", text);
+}
+
+#[test]
+fn test_listing_names_included_files() {
+    let dir = temp_project(
+        "listing",
+        &[
+            ("main.zkasm", b".include \"lib.zkasm\"\n.macro twice\nnop\nnop\n.endm\ntwice\n"),
+            ("lib.zkasm", b"ecall # from lib\n"),
+        ],
+    );
+    let (_, listing) = Assembler::new().assemble_file_with_listing(dir.join("main.zkasm")).unwrap();
+
+    let first = &listing.lines[0];
+    assert!(first.file.as_deref().unwrap().ends_with("lib.zkasm"));
+    assert_eq!((first.line, first.source.as_str()), (1, "ecall # from lib"));
+    // Macro expansions point at the body lines
+    let lines: Vec<_> = listing.lines[1..].iter().map(|l| l.line).collect();
+    assert_eq!(lines, vec![3, 4]);
+    assert!(listing.lines[1].file.as_deref().unwrap().ends_with("main.zkasm"));
+}
+
+// ============================================================================
+// Object Tests
+// ============================================================================
+
+#[test]
+fn test_assemble_object_symbols() {
+    let source = r#"
+        .global main, shared
+        .extern helper
+    main:
+        call helper
+    local:
+        ecall
+        .data
+    shared:
+        .word 7
+    "#;
+    let object = assemble_object(source).unwrap();
+
+    let names: Vec<_> = object.symbols.iter().map(|s| (s.name.as_str(), s.binding, s.definition)).collect();
+    assert_eq!(
+        names,
+        vec![
+            ("main", Binding::Global, Some((SectionKind::Text, 0))),
+            ("local", Binding::Local, Some((SectionKind::Text, 4))),
+            ("shared", Binding::Global, Some((SectionKind::Data, 0))),
+            ("helper", Binding::Global, None),
+        ]
+    );
+    assert_eq!(object.data, vec![7, 0, 0, 0]);
+}
+
+#[test]
+fn test_assemble_object_relocations() {
+    let source = r#"
+        .extern ext
+    start:
+        beq r1, r2, start
+        jal ra, ext + 8
+        la r3, table
+        beq r1, r2, ext
+        .rodata
+    table:
+        .word ext, end - start, table + 4
+        .text
+    end:
+    "#;
+    let object = assemble_object(source).unwrap();
+    let ext = RelocationTarget::Symbol(object.symbols.iter().position(|s| s.name == "ext").unwrap());
+    let rodata = RelocationTarget::Section(SectionKind::Rodata);
+
+    // Branches within .text are resolved by the assembler
+    assert_eq!(extract_imm_signed(object.text[0]), 0);
+    let relocations: Vec<_> = object.relocations.iter().map(|r| (r.section, r.offset, r.kind, r.target, r.addend)).collect();
+    assert_eq!(
+        relocations,
+        vec![
+            (SectionKind::Text, 4, RelocationKind::Jump, ext, 8),
+            (SectionKind::Text, 8, RelocationKind::Hi, rodata, 0),
+            (SectionKind::Text, 16, RelocationKind::Lo, rodata, 0),
+            (SectionKind::Text, 20, RelocationKind::Branch, ext, 0),
+            (SectionKind::Rodata, 0, RelocationKind::Data(4), ext, 0),
+            (SectionKind::Rodata, 8, RelocationKind::Data(4), rodata, 4),
+        ]
+    );
+    // Label differences are plain numbers
+    assert_eq!(object.rodata[4..8], 24u32.to_le_bytes());
+}
+
+#[test]
+fn test_assemble_object_errors() {
+    // Undeclared symbols are not imported implicitly
+    let err = assemble_object("jal ra, helper").unwrap_err();
+    assert!(matches!(err, AssemblerError::UndefinedLabel { ref label, .. } if label == "helper"), "{}", err);
+
+    let err = assemble_object(".extern ext
+addi r1, r1, ext * 2").unwrap_err();
+    assert!(err.to_string().contains("only `symbol + constant` can be relocated"), "{}", err);
+
+    let err = assemble_object(".local x
+.global x").unwrap_err();
+    assert!(err.to_string().contains("cannot be both .local and .global/.extern"), "{}", err);
+
+    let err = assemble_object(".equ N, 4
+.global N").unwrap_err();
+    assert!(err.to_string().contains("Constant N cannot be declared .global"), "{}", err);
+}
+
+#[test]
+fn test_assemble_ignores_bindings() {
+    let program = assemble(".global main
+main:
+    ecall").unwrap();
+    assert_eq!(program.code.len(), 1);
+}
+
 // ============================================================================
 // Configuration Directive Tests
 // ============================================================================
@@ -383,6 +1562,63 @@ fn test_assemble_abi_register_names() {
     assert_eq!(program.code.len(), 4);
 }
 
+#[test]
+fn test_abi_register_names_follow_spec() {
+    let program = assemble(".abi 2\nadd a0, t0, s1\nmv fp, s0").unwrap();
+    let expected = [
+        Instruction::Add { rd: Register::R10, rs1: Register::R5, rs2: Register::R9 },
+        Instruction::Addi { rd: Register::R8, rs1: Register::R8, imm: 0 },
+    ];
+    assert_eq!(program.code, expected.iter().map(encode).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_abi_v1_register_names() {
+    let v1 = assemble(".abi 1\nadd a0, t0, s1\nmv fp, a4").unwrap();
+    let numeric = assemble("add r11, r8, r7\nmv r5, r15").unwrap();
+    assert_eq!(v1.code, numeric.code);
+
+    let err = assemble(".abi 1\nmv a5, zero").unwrap_err();
+    assert!(matches!(err, AssemblerError::InvalidRegister { line: 2, .. }), "{}", err);
+    let err = assemble(".abi 3").unwrap_err();
+    assert!(err.to_string().contains("Unsupported ABI version 3 (expected 1 or 2)"), "{}", err);
+}
+
+#[test]
+fn test_abi_register_name_warnings() {
+    let assembler = Assembler::new();
+
+    // Without `.abi`, the first name that changed meaning is reported once
+    let (program, diagnostics) = assembler.assemble_with_diagnostics("li sp, 0\nli a0, 1\nli t0, 2\n");
+    assert!(program.is_some());
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "warning: Register name: a0 is r10 under ABI v2 (r11 under ABI v1); add `.abi 2` to confirm or `.abi 1` to keep the old names\n --> <source>:2:4\n  |\n2 | li a0, 1\n  |    ^^"
+    );
+
+    // `.abi 1` reports every use with its new spelling
+    let (_, diagnostics) = assembler.assemble_with_diagnostics(".abi 1\nadd a0, t2, r3\n");
+    let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Register name: a0 is r11 under ABI v1, which is deprecated; write a1 for ABI v2",
+            "Register name: t2 is r10 under ABI v1, which is deprecated; write a0 for ABI v2",
+        ]
+    );
+
+    // Warnings are kept when assembly fails
+    let (program, diagnostics) = assembler.assemble_with_diagnostics("mv a0, a1\nbogus\n");
+    assert!(program.is_none());
+    let severities: Vec<_> = diagnostics.iter().map(|d| d.severity).collect();
+    assert_eq!(severities, vec![Severity::Warning, Severity::Error]);
+
+    let (_, diagnostics) = assembler.assemble_with_diagnostics(".abi 2\nmv a0, a1\n");
+    assert!(diagnostics.is_empty());
+}
+
 // ============================================================================
 // Comment Tests
 // ============================================================================
@@ -550,7 +1786,7 @@ fn test_parse_register_abi_names() {
     assert_eq!(parse_register("zero").unwrap(), Register::R0);
     assert_eq!(parse_register("ra").unwrap(), Register::R1);
     assert_eq!(parse_register("sp").unwrap(), Register::R2);
-    assert_eq!(parse_register("a0").unwrap(), Register::R11);  // a0-a4 map to R11-R15 in ZKIR
+    assert_eq!(parse_register("a0").unwrap(), Register::R10);  // a0-a5 map to R10-R15 in ZKIR
 }
 
 #[test]
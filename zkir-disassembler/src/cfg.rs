@@ -0,0 +1,129 @@
+//! Control-flow graph export for ZKIR v3.4
+//!
+//! [`control_flow_graph`] decodes a program and recovers its
+//! [`ControlFlowGraph`]; [`disassemble_dot`] renders that graph as Graphviz
+//! DOT, one box per basic block holding its formatted instructions:
+//!
+//! ```text
+//! digraph cfg {
+//!     node [shape=box, fontname="monospace"];
+//!
+//!     b_00001000 [label="func_00001000:\l0x00001000  addi a0, a0, -1\l0x00001004  bne a0, zero, -4\l"];
+//!     b_00001000 -> b_00001000 [label="taken"];
+//!     b_00001000 -> b_00001008;
+//! }
+//! ```
+//!
+//! Function entries are labeled `func_XXXXXXXX` as in
+//! [`crate::disassemble_reassemblable`]. Unresolved `jalr` edges lead to a
+//! `?` node, and direct targets outside the code to a node with the address.
+
+use crate::decoder::decode;
+use crate::error::Result;
+use crate::formatter::format;
+use std::fmt::Write;
+use zkir_spec::{ControlFlowGraph, EdgeKind, Instruction, Program};
+
+/// Decode a program and recover its control-flow graph
+///
+/// Fails if any code word does not decode.
+pub fn control_flow_graph(program: &Program) -> Result<ControlFlowGraph> {
+    let instructions = decode_all(program)?;
+    Ok(ControlFlowGraph::new(&instructions, program.header.entry_point as u64))
+}
+
+/// Render a program's control-flow graph as Graphviz DOT
+///
+/// # Example
+/// ```
+/// use zkir_assembler::assemble;
+/// use zkir_disassembler::disassemble_dot;
+///
+/// let program = assemble("loop:\n    addi a0, a0, -1\n    bne a0, zero, loop\n    ebreak").unwrap();
+/// let dot = disassemble_dot(&program).unwrap();
+/// assert!(dot.contains("b_00001000 -> b_00001000 [label=\"taken\"];"));
+/// ```
+pub fn disassemble_dot(program: &Program) -> Result<String> {
+    let instructions = decode_all(program)?;
+    let graph = ControlFlowGraph::new(&instructions, program.header.entry_point as u64);
+
+    let mut output = String::new();
+    output.push_str("digraph cfg {\n");
+    output.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+    for block in &graph.blocks {
+        let mut label = String::new();
+        if graph.functions.iter().any(|function| function.entry == block.start) {
+            let _ = write!(label, "func_{:08X}:\\l", block.start);
+        }
+        for (address, instr) in (block.start..block.end).step_by(4).zip(&instructions[block.instructions()]) {
+            let _ = write!(label, "0x{:08X}  {}\\l", address, escape(&format(instr)));
+        }
+        let _ = writeln!(output, "\n    b_{:08X} [label=\"{}\"];", block.start, label);
+
+        for edge in &block.successors {
+            let to = match edge.target {
+                Some(target) if graph.block(target).is_some() => format!("b_{:08X}", target),
+                Some(target) => {
+                    let _ = writeln!(output, "    ext_{:08X} [shape=plaintext, label=\"0x{:08X}\"];", target, target);
+                    format!("ext_{:08X}", target)
+                }
+                None => {
+                    let _ = writeln!(output, "    unresolved_{:08X} [shape=plaintext, label=\"?\"];", block.start);
+                    format!("unresolved_{:08X}", block.start)
+                }
+            };
+            let attributes = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Branch => " [label=\"taken\"]",
+                EdgeKind::Jump => " [label=\"jump\"]",
+                EdgeKind::Call => " [label=\"call\", style=dashed]",
+                EdgeKind::Indirect => " [label=\"indirect\", style=dotted]",
+            };
+            let _ = writeln!(output, "    b_{:08X} -> {}{};", block.start, to, attributes);
+        }
+    }
+
+    output.push_str("}\n");
+    Ok(output)
+}
+
+fn decode_all(program: &Program) -> Result<Vec<Instruction>> {
+    program.code.iter().map(|&word| decode(word)).collect()
+}
+
+/// Escape text for a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DisassemblerError;
+    use zkir_spec::Opcode;
+
+    #[test]
+    fn test_dot_output() {
+        let source = "main:\n    call f\n    li a0, 0\n    ecall\nf:\n    jr t0";
+        let program = zkir_assembler::assemble(source).unwrap();
+        let dot = disassemble_dot(&program).unwrap();
+
+        assert!(dot.starts_with("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n"), "{}", dot);
+        assert!(dot.contains("    b_00001000 [label=\"func_00001000:\\l0x00001000  jal ra, 12\\l\"];\n"), "{}", dot);
+        assert!(dot.contains("    b_00001000 -> b_0000100C [label=\"call\", style=dashed];\n"), "{}", dot);
+        assert!(dot.contains("    b_00001000 -> b_00001004;\n"), "{}", dot);
+        assert!(dot.contains("    b_0000100C -> unresolved_0000100C [label=\"indirect\", style=dotted];\n"), "{}", dot);
+        // The exit `ecall` has no successors
+        assert!(!dot.contains("b_00001004 ->"), "{}", dot);
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_undecodable_code() {
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32, 0x7F];
+        program.header.code_size = 8;
+        assert!(matches!(control_flow_graph(&program), Err(DisassemblerError::UnknownOpcode(0x7F))));
+    }
+}
@@ -0,0 +1,286 @@
+//! Data section rendering for ZKIR v3.4
+//!
+//! The runtime loads `Program.data` directly after the code, at
+//! `CODE_BASE + code_size`, and the BSS follows the data. Listings show
+//! the data as a hexdump with an ASCII gutter; reassemblable output splits
+//! it into directives by heuristics, tried in order at each offset:
+//!
+//! | Data                                                     | Directive |
+//! |----------------------------------------------------------|-----------|
+//! | 4 or more printable characters and a NUL                 | `.asciz`  |
+//! | 8 or more zero bytes                                     | `.space`  |
+//! | 2 or more 8-aligned values whose upper half is a sign or zero extension | `.dword`  |
+//! | 2 or more 4-aligned words                                | `.word`   |
+//! | anything else                                            | `.byte`   |
+//!
+//! `.dword` is only used when the data starts 8-aligned, because the
+//! assembler aligns the whole section to its widest value.
+
+use std::fmt::Write;
+use zkir_spec::memory::CODE_BASE;
+use zkir_spec::Program;
+
+/// Bytes per hexdump line
+const HEXDUMP_WIDTH: usize = 16;
+
+/// Shortest string recognized, without its NUL
+const MIN_STRING: usize = 4;
+
+/// Shortest zero run rendered as `.space`
+const MIN_ZEROS: usize = 8;
+
+/// Values per directive line
+const BYTES_PER_LINE: usize = 16;
+const WORDS_PER_LINE: usize = 8;
+const DWORDS_PER_LINE: usize = 4;
+
+/// Address the runtime loads `Program.data` at
+pub(crate) fn data_base(program: &Program) -> u64 {
+    CODE_BASE + program.code.len() as u64 * 4
+}
+
+/// Address of the BSS, directly after the data
+pub(crate) fn bss_base(program: &Program) -> u64 {
+    data_base(program) + program.data.len() as u64
+}
+
+/// Hexdump of `data` loaded at `base`, 16 bytes per line with an ASCII gutter
+///
+/// ```text
+/// 0x00001010:  48 69 00 00 2A 00 00 00                           |Hi..*...|
+/// ```
+pub(crate) fn hexdump(output: &mut String, data: &[u8], base: u64) {
+    for (i, chunk) in data.chunks(HEXDUMP_WIDTH).enumerate() {
+        let _ = write!(output, "0x{:08X}: ", base + (i * HEXDUMP_WIDTH) as u64);
+        for column in 0..HEXDUMP_WIDTH {
+            if column == HEXDUMP_WIDTH / 2 {
+                output.push(' ');
+            }
+            match chunk.get(column) {
+                Some(byte) => {
+                    let _ = write!(output, " {:02X}", byte);
+                }
+                None => output.push_str("   "),
+            }
+        }
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        let _ = writeln!(output, "  |{}|", ascii);
+    }
+}
+
+/// Data directive covering part of the data section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Directive {
+    /// String including its NUL terminator
+    Asciz(Vec<u8>),
+    Space(usize),
+    Dword(Vec<u64>),
+    Word(Vec<u32>),
+    Byte(Vec<u8>),
+}
+
+impl std::fmt::Display for Directive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Directive::Asciz(bytes) => {
+                write!(f, ".asciz \"")?;
+                for &byte in &bytes[..bytes.len() - 1] {
+                    match byte {
+                        b'\n' => write!(f, "\\n")?,
+                        b'\t' => write!(f, "\\t")?,
+                        b'\r' => write!(f, "\\r")?,
+                        b'"' => write!(f, "\\\"")?,
+                        b'\\' => write!(f, "\\\\")?,
+                        _ => write!(f, "{}", byte as char)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Directive::Space(size) => write!(f, ".space {}", size),
+            Directive::Dword(values) => write!(f, ".dword {}", join(values.iter().map(|&v| dword_literal(v)))),
+            Directive::Word(values) => write!(f, ".word {}", join(values.iter().map(|v| format!("0x{:08X}", v)))),
+            Directive::Byte(bytes) => write!(f, ".byte {}", join(bytes.iter().map(|b| format!("0x{:02X}", b)))),
+        }
+    }
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+/// `.dword` operand; assembler expressions are signed 64-bit
+fn dword_literal(value: u64) -> String {
+    if value as i64 >= 0 {
+        format!("0x{:X}", value)
+    } else {
+        (value as i64).to_string()
+    }
+}
+
+/// Split `data` loaded at `base` into directives, each with its address
+pub(crate) fn directives(data: &[u8], base: u64) -> Vec<(u64, Directive)> {
+    let allow_dwords = base & 7 == 0;
+    let mut out: Vec<(u64, Directive)> = Vec::new();
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut i = 0;
+
+    let push = |out: &mut Vec<(u64, Directive)>, bytes: &mut Vec<u8>, end: usize, directive: Option<Directive>| {
+        if !bytes.is_empty() {
+            let start = end - bytes.len();
+            for (j, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+                out.push((base + (start + j * BYTES_PER_LINE) as u64, Directive::Byte(chunk.to_vec())));
+            }
+            bytes.clear();
+        }
+        if let Some(directive) = directive {
+            out.push((base + end as u64, directive));
+        }
+    };
+
+    while i < data.len() {
+        let address = base + i as u64;
+        let rest = &data[i..];
+
+        if let Some(len) = string_len(rest) {
+            push(&mut out, &mut bytes, i, Some(Directive::Asciz(rest[..len].to_vec())));
+            i += len;
+            continue;
+        }
+
+        let zeros = zero_len(rest);
+        if zeros >= MIN_ZEROS {
+            push(&mut out, &mut bytes, i, Some(Directive::Space(zeros)));
+            i += zeros;
+            continue;
+        }
+
+        if allow_dwords && address & 7 == 0 {
+            let values = table(rest, 8, DWORDS_PER_LINE, |v| {
+                let (low, high) = (v as u32, (v >> 32) as u32);
+                high == 0 || (high == u32::MAX && (low as i32) < 0)
+            });
+            if values.len() >= 2 {
+                push(&mut out, &mut bytes, i, Some(Directive::Dword(values.clone())));
+                i += values.len() * 8;
+                continue;
+            }
+        }
+
+        if address & 3 == 0 {
+            let values = table(rest, 4, WORDS_PER_LINE, |_| true);
+            if values.len() >= 2 {
+                let words = values.iter().map(|&v| v as u32).collect();
+                push(&mut out, &mut bytes, i, Some(Directive::Word(words)));
+                i += values.len() * 4;
+                continue;
+            }
+        }
+
+        bytes.push(data[i]);
+        i += 1;
+    }
+    push(&mut out, &mut bytes, data.len(), None);
+    out
+}
+
+/// Length of a NUL-terminated printable string at the start of `data`, with the NUL
+fn string_len(data: &[u8]) -> Option<usize> {
+    let printable = data
+        .iter()
+        .take_while(|&&byte| byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r'))
+        .count();
+    (printable >= MIN_STRING && data.get(printable) == Some(&0)).then_some(printable + 1)
+}
+
+/// Number of zero bytes at the start of `data`
+fn zero_len(data: &[u8]) -> usize {
+    data.iter().take_while(|&&byte| byte == 0).count()
+}
+
+/// Little-endian values of `width` bytes at the start of `data` accepted by
+/// `accept`, up to `limit` and stopping where a string or zero run starts
+fn table(data: &[u8], width: usize, limit: usize, accept: impl Fn(u64) -> bool) -> Vec<u64> {
+    let mut values = Vec::new();
+    for (i, chunk) in data.chunks_exact(width).take(limit).enumerate() {
+        let rest = &data[i * width..];
+        if i > 0 && (string_len(rest).is_some() || zero_len(rest) >= MIN_ZEROS) {
+            break;
+        }
+        let mut value = [0u8; 8];
+        value[..width].copy_from_slice(chunk);
+        let value = u64::from_le_bytes(value);
+        if !accept(value) {
+            break;
+        }
+        values.push(value);
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(directive: &Directive) -> usize {
+        match directive {
+            Directive::Asciz(bytes) | Directive::Byte(bytes) => bytes.len(),
+            Directive::Space(size) => *size,
+            Directive::Dword(values) => values.len() * 8,
+            Directive::Word(values) => values.len() * 4,
+        }
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let mut output = String::new();
+        hexdump(&mut output, b"Hi\0\0*\0\0\0", 0x1010);
+        assert_eq!(
+            output,
+            "0x00001010:  48 69 00 00 2A 00 00 00                           |Hi..*...|\n"
+        );
+    }
+
+    #[test]
+    fn test_directives() {
+        let mut data = b"hello, \"world\"\n\0".to_vec();
+        data.extend_from_slice(&[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]);
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&[5, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        data.extend_from_slice(&[7, 8, 9]);
+
+        let rendered: Vec<_> = directives(&data, 0x1004)
+            .into_iter()
+            .map(|(address, directive)| format!("{:X}: {}", address, directive))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "1004: .asciz \"hello, \\\"world\\\"\\n\"",
+                "1014: .word 0x00000001, 0x00000002, 0x00000003",
+                "1020: .space 12",
+                "102C: .word 0x00000005, 0x00000000, 0xFFFFFFFF, 0xFFFFFFFF",
+                "103C: .byte 0x07, 0x08, 0x09",
+            ]
+        );
+
+        // 8-aligned data may use `.dword`
+        let rendered: Vec<_> = directives(&data, 0x1000).into_iter().map(|(_, d)| d.to_string()).collect();
+        assert_eq!(rendered[3], ".dword 0x5, -1");
+    }
+
+    #[test]
+    fn test_directives_cover_data() {
+        let data: Vec<u8> = (0..200u32).map(|i| (i * 37 % 256) as u8).collect();
+        for base in [0x1000, 0x1001, 0x1004] {
+            let mut next = base;
+            for (address, directive) in directives(&data, base) {
+                assert_eq!(address, next);
+                next += size(&directive) as u64;
+            }
+            assert_eq!(next, base + data.len() as u64);
+        }
+    }
+}
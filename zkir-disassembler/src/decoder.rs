@@ -13,6 +13,7 @@
 //! Note: Despite documentation claiming "6-bit opcodes", the actual opcode values
 //! range from 0x00-0x51 which requires 7 bits. We use 7 bits for the opcode field.
 
+use zkir_spec::encoding::is_rtype;
 use zkir_spec::{Instruction, Opcode, Register};
 use crate::error::{DisassemblerError, Result};
 
@@ -104,6 +105,27 @@ pub fn decode(word: u32) -> Result<Instruction> {
     }
 }
 
+/// Decode 32-bit instruction word, rejecting non-canonical encodings
+///
+/// [`decode`] ignores bits that no field uses: the R-type `funct` field,
+/// the shift immediate above the 8-bit shift amount, and everything above
+/// the opcode of `ecall`/`ebreak`. Several words then decode to the same
+/// instruction. This accepts only the word the encoder produces, so that
+/// re-encoding every accepted word gives back the same word.
+pub fn decode_strict(word: u32) -> Result<Instruction> {
+    let instr = decode(word)?;
+    let (field, mask) = match instr {
+        Instruction::Slli { .. } | Instruction::Srli { .. } | Instruction::Srai { .. } => ("shift immediate", !0 << 23),
+        Instruction::Ecall | Instruction::Ebreak => ("operand", !0x7F),
+        _ if is_rtype(word) => ("funct", !0 << 19),
+        _ => return Ok(instr),
+    };
+    match word & mask {
+        0 => Ok(instr),
+        bits => Err(DisassemblerError::NonCanonical { word, field, bits }),
+    }
+}
+
 /// Decode R-type instruction
 /// Format: [opcode:7][rd:4][rs1:4][rs2:4][funct:13]
 fn decode_r_type<F>(word: u32, constructor: F) -> Result<Instruction>
@@ -402,6 +424,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_decode_strict_rejects_unused_bits() {
+        let add = Opcode::Add.to_u8() as u32 | 1 << 7 | 2 << 11 | 3 << 15;
+        assert!(decode_strict(add).is_ok());
+        assert!(matches!(
+            decode_strict(add | 1 << 31),
+            Err(DisassemblerError::NonCanonical { field: "funct", bits: 0x8000_0000, .. })
+        ));
+        // The lenient decoder ignores the funct field
+        assert_eq!(decode(add | 1 << 31).unwrap(), decode(add).unwrap());
+
+        let slli = Opcode::Slli.to_u8() as u32 | 1 << 7 | 63 << 15;
+        assert!(decode_strict(slli).is_ok());
+        assert!(matches!(
+            decode_strict(slli | 1 << 23),
+            Err(DisassemblerError::NonCanonical { field: "shift immediate", .. })
+        ));
+
+        let ecall = Opcode::Ecall.to_u8() as u32;
+        assert!(matches!(
+            decode_strict(ecall | 10 << 7),
+            Err(DisassemblerError::NonCanonical { field: "operand", bits: 0x500, .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_strict_accepts_full_fields() {
+        // Every bit of I-, S-, B- and J-type words belongs to a field
+        for opcode in [Opcode::Addi, Opcode::Sw, Opcode::Beq, Opcode::Jal, Opcode::Jalr] {
+            let word = opcode.to_u8() as u32 | !0x7F;
+            assert_eq!(decode_strict(word).unwrap(), decode(word).unwrap());
+        }
+    }
+
     #[test]
     fn test_opcode_values() {
         // Verify opcodes match ZKIR v3.4 spec
@@ -1,21 +1,73 @@
 //! Main disassembler logic for ZKIR v3.4
 
-use zkir_spec::Program;
-use crate::error::Result;
+use zkir_spec::{DebugFunction, DebugProgram, FormatMode, Program};
+use zkir_spec::memory::CODE_BASE;
+use crate::error::{DisassemblerError, Result};
 use crate::decoder::decode;
 use crate::formatter::format;
+use crate::data::{bss_base, data_base, hexdump};
 
 /// Disassemble a program into assembly text
 pub fn disassemble(program: &Program) -> Result<String> {
     let mut output = String::new();
+    write_header(&mut output, program);
+    output.push('\n');
+    write_code(&mut output, program, &[]);
+    write_data(&mut output, program);
+    Ok(output)
+}
+
+/// Disassemble a debug-format program
+///
+/// Like [`disassemble`], plus a table of the global variables and a label
+/// line at the start of each function:
+///
+/// ```text
+/// ; Globals:
+/// ;   0x0000100C  4 bytes  counter
+///
+/// main:  ; 8 bytes
+/// 0x00001000:  00000048  addi zero, zero, 0
+/// ```
+pub fn disassemble_debug(debug: &DebugProgram) -> Result<String> {
+    let mut output = String::new();
+    write_header(&mut output, &debug.program);
+
+    if !debug.globals.is_empty() {
+        output.push_str(";\n; Globals:\n");
+        for global in &debug.globals {
+            output.push_str(&std::format!(";   0x{:08X}  {} bytes  {}\n", global.address, global.size, global.name));
+        }
+    }
+    output.push_str(&std::format!("; Functions:   {}\n", debug.functions.len()));
+    output.push('\n');
 
-    // Header
+    write_code(&mut output, &debug.program, &debug.functions);
+    write_data(&mut output, &debug.program);
+    Ok(output)
+}
+
+/// Disassemble bytecode in release or debug format
+///
+/// The format is detected with [`FormatMode::detect`].
+pub fn disassemble_bytes(bytes: &[u8]) -> Result<String> {
+    match FormatMode::detect(bytes) {
+        Some(FormatMode::Release) => disassemble(&Program::from_bytes(bytes)?),
+        Some(FormatMode::Debug) => disassemble_debug(&DebugProgram::from_bytes(bytes)?),
+        None => Err(DisassemblerError::InvalidProgram(zkir_spec::ZkIrError::Other(
+            "not a ZKIR bytecode file".to_string(),
+        ))),
+    }
+}
+
+/// Header comments: configuration and section sizes
+fn write_header(output: &mut String, program: &Program) {
     output.push_str("; ZKIR v3.4 Disassembly\n");
     output.push_str(";\n");
 
     // Configuration
     let config = program.config();
-    output.push_str(&std::format!("; Configuration:\n"));
+    output.push_str("; Configuration:\n");
     output.push_str(&std::format!(";   Limb bits:  {}\n", config.limb_bits));
     output.push_str(&std::format!(";   Data limbs: {} ({}-bit values)\n", config.data_limbs, config.data_bits()));
     output.push_str(&std::format!(";   Addr limbs: {} ({}-bit addresses)\n", config.addr_limbs, config.addr_bits()));
@@ -26,11 +78,21 @@ pub fn disassemble(program: &Program) -> Result<String> {
     output.push_str(&std::format!("; Code size:   {} bytes ({} instructions)\n",
         program.header.code_size, program.code.len()));
     output.push_str(&std::format!("; Data size:   {} bytes\n", program.header.data_size));
-    output.push_str("\n");
+}
 
-    let mut addr = program.header.entry_point;
+/// One line per code word, with a label line where each function starts
+fn write_code(output: &mut String, program: &Program, functions: &[DebugFunction]) {
+    // Code is loaded at CODE_BASE; the entry point may be anywhere in it
+    let mut addr = CODE_BASE as u32;
 
     for &word in &program.code {
+        for function in functions.iter().filter(|function| function.address() == addr as u64) {
+            if addr != CODE_BASE as u32 {
+                output.push('\n');
+            }
+            output.push_str(&std::format!("{}:  ; {} bytes\n", function.name, function.size));
+        }
+
         // Address label
         output.push_str(&std::format!("0x{:08X}:  ", addr));
 
@@ -50,8 +112,26 @@ pub fn disassemble(program: &Program) -> Result<String> {
         output.push('\n');
         addr += 4;
     }
+}
 
-    Ok(output)
+/// Hexdump of the data and the BSS reservation, at their runtime addresses
+///
+/// ```text
+/// .data  ; 0x00001008, 3 bytes
+/// 0x00001008:  48 69 00                                          |Hi.|
+///
+/// .bss  ; 0x0000100B
+/// 0x0000100B:  .space 16
+/// ```
+fn write_data(output: &mut String, program: &Program) {
+    if !program.data.is_empty() {
+        output.push_str(&std::format!("\n.data  ; 0x{:08X}, {} bytes\n", data_base(program), program.data.len()));
+        hexdump(output, &program.data, data_base(program));
+    }
+    if program.header.bss_size > 0 {
+        output.push_str(&std::format!("\n.bss  ; 0x{:08X}\n", bss_base(program)));
+        output.push_str(&std::format!("0x{:08X}:  .space {}\n", bss_base(program), program.header.bss_size));
+    }
 }
 
 #[cfg(test)]
@@ -99,4 +179,73 @@ mod tests {
         assert!(asm.contains("Data limbs: 2"));
         assert!(asm.contains("40-bit"));
     }
+
+    #[test]
+    fn test_disassemble_debug() {
+        use zkir_spec::{DebugGlobal, Opcode};
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ebreak.to_u8() as u32, Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 8;
+        let debug = DebugProgram {
+            program,
+            globals: vec![DebugGlobal { name: "counter".to_string(), address: 0x1008, size: 4 }],
+            functions: vec![
+                DebugFunction { name: "main".to_string(), offset: 0, size: 4 },
+                DebugFunction { name: "exit".to_string(), offset: 4, size: 4 },
+            ],
+        };
+
+        let asm = disassemble_bytes(&debug.to_bytes().unwrap()).unwrap();
+
+        assert!(asm.contains("; Globals:\n;   0x00001008  4 bytes  counter\n"), "{}", asm);
+        assert!(asm.contains("; Functions:   2\n"), "{}", asm);
+        assert!(asm.contains("\nmain:  ; 4 bytes\n0x00001000:  00000051  ebreak\n"), "{}", asm);
+        assert!(asm.contains("\n\nexit:  ; 4 bytes\n0x00001004:  00000050  ecall\n"), "{}", asm);
+    }
+
+    #[test]
+    fn test_disassemble_bytes_release() {
+        use zkir_spec::Opcode;
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 4;
+
+        assert_eq!(disassemble_bytes(&program.to_bytes()).unwrap(), disassemble(&program).unwrap());
+        assert!(disassemble_bytes(&[0; 40]).is_err());
+    }
+
+    #[test]
+    fn test_disassemble_addresses_start_at_code_base() {
+        use zkir_spec::Opcode;
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ebreak.to_u8() as u32, Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 8;
+        program.header.entry_point = 0x1004;
+
+        let asm = disassemble(&program).unwrap();
+
+        assert!(asm.contains("; Entry point: 0x00001004"));
+        assert!(asm.contains("0x00001000:  00000051  ebreak"), "{}", asm);
+        assert!(asm.contains("0x00001004:  00000050  ecall"), "{}", asm);
+    }
+
+    #[test]
+    fn test_disassemble_data_and_bss() {
+        use zkir_spec::Opcode;
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 4;
+        program.data = b"Hi\0".to_vec();
+        program.header.data_size = 3;
+        program.header.bss_size = 16;
+
+        let asm = disassemble(&program).unwrap();
+
+        assert!(asm.ends_with(
+            "\n.data  ; 0x00001004, 3 bytes\n\
+             0x00001004:  48 69 00                                          |Hi.|\n\
+             \n.bss  ; 0x00001007\n\
+             0x00001007:  .space 16\n"
+        ), "{}", asm);
+    }
 }
@@ -10,6 +10,22 @@ pub enum DisassemblerError {
     #[error("Unknown opcode: 0x{0:02X}")]
     UnknownOpcode(u8),
 
+    /// Word with bits set that its instruction does not use (strict decoding)
+    #[error("Non-canonical encoding 0x{word:08X}: unused {field} bits 0x{bits:08X} are set")]
+    NonCanonical {
+        word: u32,
+        field: &'static str,
+        bits: u32,
+    },
+
+    /// Bytecode that is not a valid release or debug program
+    #[error("Invalid program: {0}")]
+    InvalidProgram(#[from] zkir_spec::ZkIrError),
+
+    /// Program the assembler cannot reproduce from reassemblable output
+    #[error("Cannot disassemble for reassembly: {0}")]
+    NotReassemblable(String),
+
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -33,6 +49,16 @@ mod tests {
         assert_eq!(err.to_string(), "Invalid instruction encoding: 0x00000000");
     }
 
+    #[test]
+    fn test_non_canonical_display() {
+        let err = DisassemblerError::NonCanonical {
+            word: 0x0008_1080,
+            field: "funct",
+            bits: 0x0008_0000,
+        };
+        assert_eq!(err.to_string(), "Non-canonical encoding 0x00081080: unused funct bits 0x00080000 are set");
+    }
+
     #[test]
     fn test_unknown_opcode_display() {
         let err = DisassemblerError::UnknownOpcode(0xFF);
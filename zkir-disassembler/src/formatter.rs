@@ -48,6 +48,12 @@ pub fn format(instr: &Instruction) -> String {
         Instruction::Sll { rd, rs1, rs2 } => {
             format!("sll {}, {}, {}", format_reg(*rd), format_reg(*rs1), format_reg(*rs2))
         }
+        Instruction::Srl { rd, rs1, rs2 } => {
+            format!("srl {}, {}, {}", format_reg(*rd), format_reg(*rs1), format_reg(*rs2))
+        }
+        Instruction::Sra { rd, rs1, rs2 } => {
+            format!("sra {}, {}, {}", format_reg(*rd), format_reg(*rs1), format_reg(*rs2))
+        }
         Instruction::Slt { rd, rs1, rs2 } => {
             format!("slt {}, {}, {}", format_reg(*rd), format_reg(*rs1), format_reg(*rs2))
         }
@@ -109,9 +115,15 @@ pub fn format(instr: &Instruction) -> String {
         Instruction::Lb { rd, rs1, imm } => {
             format!("lb {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
         }
+        Instruction::Lbu { rd, rs1, imm } => {
+            format!("lbu {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
+        }
         Instruction::Lh { rd, rs1, imm } => {
             format!("lh {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
         }
+        Instruction::Lhu { rd, rs1, imm } => {
+            format!("lhu {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
+        }
         Instruction::Lw { rd, rs1, imm } => {
             format!("lw {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
         }
@@ -158,15 +170,12 @@ pub fn format(instr: &Instruction) -> String {
             format!("jal {}, {}", format_reg(*rd), offset)
         }
         Instruction::Jalr { rd, rs1, imm } => {
-            format!("jalr {}, {}({})", format_reg(*rd), imm, format_reg(*rs1))
+            format!("jalr {}, {}, {}", format_reg(*rd), format_reg(*rs1), imm)
         }
-
-        // Fallback for any instruction not explicitly handled
-        _ => format!("??? (unknown instruction)"),
     }
 }
 
-/// Format register using its name (e.g., "a0" instead of "r10")
+/// Format register using its canonical ABI name (e.g., "a0" instead of "r10")
 fn format_reg(reg: Register) -> String {
     reg.name().to_string()
 }
@@ -188,9 +197,9 @@ mod tests {
     #[test]
     fn test_format_add() {
         let instr = Instruction::Add {
-            rd: Register::R4,   // a0
-            rs1: Register::R5,  // a1
-            rs2: Register::R6,  // a2
+            rd: Register::R10,  // a0
+            rs1: Register::R11, // a1
+            rs2: Register::R12, // a2
         };
         assert_eq!(format(&instr), "add a0, a1, a2");
     }
@@ -198,8 +207,8 @@ mod tests {
     #[test]
     fn test_format_addi() {
         let instr = Instruction::Addi {
-            rd: Register::R4,   // a0
-            rs1: Register::R5,  // a1
+            rd: Register::R10,  // a0
+            rs1: Register::R11, // a1
             imm: 100,
         };
         assert_eq!(format(&instr), "addi a0, a1, 100");
@@ -208,7 +217,7 @@ mod tests {
     #[test]
     fn test_format_lw() {
         let instr = Instruction::Lw {
-            rd: Register::R4,   // a0
+            rd: Register::R10,  // a0
             rs1: Register::R2,  // sp
             imm: 16,
         };
@@ -219,7 +228,7 @@ mod tests {
     fn test_format_sw() {
         let instr = Instruction::Sw {
             rs1: Register::R2,  // sp
-            rs2: Register::R4,  // a0
+            rs2: Register::R10, // a0
             imm: 16,
         };
         assert_eq!(format(&instr), "sw a0, 16(sp)");
@@ -228,8 +237,8 @@ mod tests {
     #[test]
     fn test_format_beq() {
         let instr = Instruction::Beq {
-            rs1: Register::R4,  // a0
-            rs2: Register::R5,  // a1
+            rs1: Register::R10, // a0
+            rs2: Register::R11, // a1
             offset: 8,
         };
         assert_eq!(format(&instr), "beq a0, a1, 8");
@@ -247,9 +256,9 @@ mod tests {
     #[test]
     fn test_format_cmov() {
         let instr = Instruction::Cmov {
-            rd: Register::R4,   // a0
-            rs1: Register::R5,  // a1
-            rs2: Register::R6,  // a2
+            rd: Register::R10,  // a0
+            rs1: Register::R11, // a1
+            rs2: Register::R12, // a2
         };
         assert_eq!(format(&instr), "cmov a0, a1, a2");
     }
@@ -257,8 +266,8 @@ mod tests {
     #[test]
     fn test_format_slli() {
         let instr = Instruction::Slli {
-            rd: Register::R4,   // a0
-            rs1: Register::R5,  // a1
+            rd: Register::R10,  // a0
+            rs1: Register::R11, // a1
             shamt: 5,
         };
         assert_eq!(format(&instr), "slli a0, a1, 5");
@@ -267,8 +276,8 @@ mod tests {
     #[test]
     fn test_format_negative_immediate() {
         let instr = Instruction::Addi {
-            rd: Register::R4,
-            rs1: Register::R5,
+            rd: Register::R10,
+            rs1: Register::R11,
             imm: -1,
         };
         assert_eq!(format(&instr), "addi a0, a1, -1");
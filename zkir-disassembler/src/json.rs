@@ -0,0 +1,310 @@
+//! Structured JSON disassembly for ZKIR v3.4
+//!
+//! [`disassemble_json`] prints a program as a JSON document for tools that
+//! would otherwise parse listing text. The layout is versioned by
+//! `schema_version` ([`JSON_SCHEMA_VERSION`]); fields are only ever added
+//! within a version.
+//!
+//! ```text
+//! {
+//!   "schema_version": 1,
+//!   "header": { "magic", "version", "limb_bits", "data_limbs", "addr_limbs",
+//!               "flags", "entry_point", "code_size", "data_size", "bss_size",
+//!               "stack_size" },
+//!   "config": { "limb_bits", "data_limbs", "addr_limbs", "data_bits", "addr_bits" },
+//!   "instructions": [ record, ... ]
+//! }
+//! ```
+//!
+//! Each record has `address` and `word` (the raw code word). Words that
+//! decode add:
+//! - `opcode` (numeric), `mnemonic` and `family` (an `InstructionFamily`,
+//!   lowercase)
+//! - `operands`: `rd`, `rs1`, `rs2` as register names and `imm`, `shamt`,
+//!   `offset` as numbers, each present only if the instruction has it
+//! - `target`: address a branch or `jal` transfers to
+//! - `text`: the instruction as [`crate::format`] prints it
+//!
+//! Words that do not decode add `error` instead, with a `kind`
+//! (`unknown_opcode` or `invalid_encoding`) and a `message`.
+//!
+//! Numbers are plain JSON numbers; addresses and words fit in 32 bits.
+
+use crate::decoder::decode;
+use crate::error::{DisassemblerError, Result};
+use crate::formatter::format;
+use serde::Serialize;
+use zkir_spec::memory::CODE_BASE;
+use zkir_spec::{Config, Instruction, Opcode, Program, ProgramHeader, Register};
+
+/// Version of the JSON layout written by [`disassemble_json`]
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Document {
+    schema_version: u32,
+    header: Header,
+    config: ConfigRecord,
+    instructions: Vec<Record>,
+}
+
+#[derive(Serialize)]
+struct Header {
+    magic: u32,
+    version: u32,
+    limb_bits: u8,
+    data_limbs: u8,
+    addr_limbs: u8,
+    flags: u8,
+    entry_point: u32,
+    code_size: u32,
+    data_size: u32,
+    bss_size: u32,
+    stack_size: u32,
+}
+
+impl From<&ProgramHeader> for Header {
+    fn from(header: &ProgramHeader) -> Self {
+        Self {
+            magic: header.magic,
+            version: header.version,
+            limb_bits: header.limb_bits,
+            data_limbs: header.data_limbs,
+            addr_limbs: header.addr_limbs,
+            flags: header.flags,
+            entry_point: header.entry_point,
+            code_size: header.code_size,
+            data_size: header.data_size,
+            bss_size: header.bss_size,
+            stack_size: header.stack_size,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ConfigRecord {
+    limb_bits: u8,
+    data_limbs: u8,
+    addr_limbs: u8,
+    data_bits: u32,
+    addr_bits: u32,
+}
+
+impl From<Config> for ConfigRecord {
+    fn from(config: Config) -> Self {
+        Self {
+            limb_bits: config.limb_bits,
+            data_limbs: config.data_limbs,
+            addr_limbs: config.addr_limbs,
+            data_bits: config.data_bits(),
+            addr_bits: config.addr_bits(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record {
+    address: u64,
+    word: u32,
+    #[serde(flatten)]
+    decoded: Decoded,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Decoded {
+    Instruction {
+        opcode: u8,
+        mnemonic: &'static str,
+        family: String,
+        operands: Operands,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target: Option<u64>,
+        text: String,
+    },
+    Error {
+        error: DecodeError,
+    },
+}
+
+#[derive(Serialize, Default)]
+struct Operands {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rd: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rs1: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rs2: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imm: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shamt: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<i32>,
+}
+
+impl From<&Instruction> for Operands {
+    fn from(instr: &Instruction) -> Self {
+        let mut operands = Operands {
+            rd: instr.rd().map(Register::name),
+            rs1: instr.rs1().map(Register::name),
+            rs2: instr.rs2().map(Register::name),
+            offset: instr.pc_offset(),
+            ..Operands::default()
+        };
+        match *instr {
+            Instruction::Addi { imm, .. }
+            | Instruction::Andi { imm, .. }
+            | Instruction::Ori { imm, .. }
+            | Instruction::Xori { imm, .. }
+            | Instruction::Lb { imm, .. }
+            | Instruction::Lbu { imm, .. }
+            | Instruction::Lh { imm, .. }
+            | Instruction::Lhu { imm, .. }
+            | Instruction::Lw { imm, .. }
+            | Instruction::Ld { imm, .. }
+            | Instruction::Sb { imm, .. }
+            | Instruction::Sh { imm, .. }
+            | Instruction::Sw { imm, .. }
+            | Instruction::Sd { imm, .. }
+            | Instruction::Jalr { imm, .. } => operands.imm = Some(imm),
+            Instruction::Slli { shamt, .. } | Instruction::Srli { shamt, .. } | Instruction::Srai { shamt, .. } => {
+                operands.shamt = Some(shamt)
+            }
+            _ => {}
+        }
+        operands
+    }
+}
+
+#[derive(Serialize)]
+struct DecodeError {
+    kind: &'static str,
+    message: String,
+}
+
+impl From<DisassemblerError> for DecodeError {
+    fn from(err: DisassemblerError) -> Self {
+        let kind = match err {
+            DisassemblerError::UnknownOpcode(_) => "unknown_opcode",
+            _ => "invalid_encoding",
+        };
+        Self { kind, message: err.to_string() }
+    }
+}
+
+/// Disassemble a program into a versioned JSON document
+///
+/// Decode errors are reported per word, so this only fails if the document
+/// cannot be serialized.
+///
+/// # Example
+/// ```
+/// use zkir_assembler::assemble;
+/// use zkir_disassembler::disassemble_json;
+///
+/// let program = assemble("addi a0, zero, 42\necall").unwrap();
+/// let json = disassemble_json(&program).unwrap();
+/// assert!(json.contains("\"mnemonic\": \"addi\""));
+/// ```
+pub fn disassemble_json(program: &Program) -> Result<String> {
+    let instructions = program
+        .code
+        .iter()
+        .enumerate()
+        .map(|(i, &word)| {
+            let address = CODE_BASE + i as u64 * 4;
+            let decoded = match decode(word) {
+                Ok(instr) => {
+                    let opcode = (word & 0x7F) as u8;
+                    Decoded::Instruction {
+                        opcode,
+                        mnemonic: instr.mnemonic(),
+                        family: Opcode::from_u8(opcode).map(|op| op.family().to_string()).unwrap_or_default(),
+                        operands: Operands::from(&instr),
+                        target: instr.pc_offset().map(|offset| address.wrapping_add(offset as i64 as u64)),
+                        text: format(&instr),
+                    }
+                }
+                Err(err) => Decoded::Error { error: err.into() },
+            };
+            Record { address, word, decoded }
+        })
+        .collect();
+
+    let document = Document {
+        schema_version: JSON_SCHEMA_VERSION,
+        header: Header::from(&program.header),
+        config: program.config().into(),
+        instructions,
+    };
+    serde_json::to_string_pretty(&document).map_err(|err| DisassemblerError::IoError(err.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn document(program: &Program) -> Value {
+        serde_json::from_str(&disassemble_json(program).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_json_header_and_config() {
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 4;
+        program.header.bss_size = 16;
+
+        let doc = document(&program);
+        assert_eq!(doc["schema_version"], JSON_SCHEMA_VERSION);
+        assert_eq!(doc["header"]["entry_point"], CODE_BASE);
+        assert_eq!(doc["header"]["bss_size"], 16);
+        assert_eq!(
+            doc["config"],
+            json!({"limb_bits": 20, "data_limbs": 2, "addr_limbs": 2, "data_bits": 40, "addr_bits": 40})
+        );
+    }
+
+    #[test]
+    fn test_json_instruction_records() {
+        let program = zkir_assembler::assemble("loop:\n    lw a1, -8(sp)\n    slli a1, a1, 3\n    bne a1, zero, loop").unwrap();
+        let doc = document(&program);
+        let records = doc["instructions"].as_array().unwrap();
+
+        assert_eq!(
+            records[0],
+            json!({
+                "address": 0x1000,
+                "word": program.code[0],
+                "opcode": Opcode::Lw.to_u8(),
+                "mnemonic": "lw",
+                "family": "load",
+                "operands": {"rd": "a1", "rs1": "sp", "imm": -8},
+                "text": "lw a1, -8(sp)",
+            })
+        );
+        assert_eq!(records[1]["operands"], json!({"rd": "a1", "rs1": "a1", "shamt": 3}));
+        assert_eq!(records[2]["operands"], json!({"rs1": "a1", "rs2": "zero", "offset": -8}));
+        assert_eq!(records[2]["target"], 0x1000);
+        assert_eq!(records[2]["family"], "branch");
+    }
+
+    #[test]
+    fn test_json_decode_errors() {
+        let mut program = Program::new();
+        program.code = vec![0x7F];
+        program.header.code_size = 4;
+
+        let doc = document(&program);
+        assert_eq!(
+            doc["instructions"][0],
+            json!({
+                "address": 0x1000,
+                "word": 0x7F,
+                "error": {"kind": "unknown_opcode", "message": "Unknown opcode: 0x7F"},
+            })
+        );
+    }
+}
@@ -7,20 +7,27 @@
 //! ZKIR bytecode files can be in two formats:
 //!
 //! - **Release mode**: `[header][code][data]` - standard format for execution
-//! - **Debug mode**: `[header][globals][functions][code]` - includes symbol tables
+//! - **Debug mode**: `[header][globals][functions][entry][code][data]` - includes symbol tables
 //!
-//! This disassembler works with `Program` objects (release format only).
-//! For debug format files produced by `zkir-llvm --debug`, use the built-in
-//! `zkir-llvm --emit disasm` command which understands the function table format.
+//! [`disassemble`] works with `Program` objects (release format);
+//! [`disassemble_debug`] reads the function table and globals of a
+//! [`zkir_spec::DebugProgram`]. [`disassemble_bytes`] takes a file in either
+//! format, using [`zkir_spec::FormatMode::detect()`] to tell them apart.
 //!
-//! Use [`zkir_spec::FormatMode::detect()`] to check if a bytecode file is
-//! in release or debug format before attempting to load it.
+//! [`disassemble`] produces an annotated listing, with the data section as a
+//! hexdump. [`disassemble_reassemblable`]
+//! produces source for `zkir_assembler` with synthesized labels, which
+//! reassembles to the same program; its data section is split into
+//! `.asciz`, `.word`, `.dword` and `.byte` directives by heuristics.
+//! [`disassemble_dot`] renders the control-flow graph (see
+//! [`zkir_spec::ControlFlowGraph`]) as Graphviz DOT, and [`disassemble_json`]
+//! emits a versioned JSON document with one record per code word.
 //!
 //! ## Example
 //!
 //! ```rust
-//! use zkir_spec::{Program, FormatMode};
-//! use zkir_disassembler::disassemble;
+//! use zkir_spec::{DebugProgram, Program, FormatMode};
+//! use zkir_disassembler::{disassemble, disassemble_debug};
 //!
 //! // Check format before loading
 //! let bytes: &[u8] = &[/* ... bytecode ... */];
@@ -36,7 +43,8 @@
 //!         println!("{}", asm);
 //!     }
 //!     Some(FormatMode::Debug) => {
-//!         eprintln!("Debug format - use 'zkir-llvm --emit disasm' instead");
+//!         let program = DebugProgram::from_bytes(&bytes).unwrap();
+//!         println!("{}", disassemble_debug(&program).unwrap());
 //!     }
 //!     None => {
 //!         eprintln!("Invalid ZKIR file");
@@ -48,10 +56,17 @@ pub mod error;
 pub mod decoder;
 pub mod formatter;
 pub mod disassembler;
+pub mod reassemble;
+pub mod cfg;
+pub mod json;
+mod data;
 
 pub use error::{DisassemblerError, Result};
-pub use disassembler::disassemble;
-pub use decoder::decode;
+pub use disassembler::{disassemble, disassemble_bytes, disassemble_debug};
+pub use reassemble::disassemble_reassemblable;
+pub use cfg::{control_flow_graph, disassemble_dot};
+pub use json::{disassemble_json, JSON_SCHEMA_VERSION};
+pub use decoder::{decode, decode_strict};
 pub use formatter::format;
 
 #[cfg(test)]
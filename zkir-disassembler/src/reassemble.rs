@@ -0,0 +1,259 @@
+//! Reassemblable disassembly for ZKIR v3.4
+//!
+//! [`disassemble_reassemblable`] prints a program as assembly source that
+//! `zkir_assembler::assemble` turns back into the same `Program`: config
+//! directives, entry point, code, data and BSS size are all preserved.
+//!
+//! Branch and jump targets inside the code become synthesized labels named
+//! after their address: `func_XXXXXXXX` for `jal ra` call targets and the
+//! entry point, `L_XXXXXXXX` for everything else. Each label is preceded by
+//! a comment listing the instructions that refer to it:
+//!
+//! ```text
+//! # refs: 0x00001004, 0x00001018
+//! L_00001010:
+//!     addi a0, a0, -1                 # 0x00001010
+//!     bne a0, zero, L_00001010        # 0x00001014
+//! ```
+//!
+//! Offsets that leave the code or are misaligned stay numeric.
+//!
+//! The data section is split into `.asciz` for NUL-terminated strings,
+//! `.space` for zero runs, `.word`/`.dword` for word tables and `.byte` for
+//! the rest, each commented with its runtime address.
+
+use crate::data::{data_base, directives};
+use crate::decoder::decode_strict;
+use crate::error::{DisassemblerError, Result};
+use crate::formatter::format;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use zkir_spec::memory::CODE_BASE;
+use zkir_spec::{Instruction, Program, Register};
+
+/// Column of the address comment after an instruction
+const COMMENT_COLUMN: usize = 36;
+
+/// Kind of a synthesized label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum LabelKind {
+    /// Branch or jump target
+    Local,
+    /// Call target or entry point
+    Function,
+}
+
+/// Synthesized label at a code address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Label {
+    pub(crate) kind: LabelKind,
+    /// Addresses of the branches and jumps that target it
+    pub(crate) refs: Vec<u64>,
+}
+
+impl Label {
+    pub(crate) fn name(&self, address: u64) -> String {
+        match self.kind {
+            LabelKind::Local => format!("L_{:08X}", address),
+            LabelKind::Function => format!("func_{:08X}", address),
+        }
+    }
+}
+
+/// Target address of a branch or `jal` at `address`
+pub(crate) fn target(instr: &Instruction, address: u64) -> Option<u64> {
+    instr.pc_offset().map(|offset| address.wrapping_add(offset as i64 as u64))
+}
+
+/// Labels for every in-code branch and jump target, keyed by address
+///
+/// Targets may be one past the last instruction (the end of the code).
+pub(crate) fn synthesize_labels(instructions: &[Instruction]) -> BTreeMap<u64, Label> {
+    let end = CODE_BASE + instructions.len() as u64 * 4;
+    let mut labels: BTreeMap<u64, Label> = BTreeMap::new();
+    for (i, instr) in instructions.iter().enumerate() {
+        let address = CODE_BASE + i as u64 * 4;
+        let Some(target) = target(instr, address).filter(|&t| in_code(t, end)) else {
+            continue;
+        };
+        let kind = match instr {
+            Instruction::Jal { rd: Register::RA, .. } => LabelKind::Function,
+            _ => LabelKind::Local,
+        };
+        let label = labels.entry(target).or_insert(Label { kind, refs: Vec::new() });
+        label.kind = label.kind.max(kind);
+        label.refs.push(address);
+    }
+    labels
+}
+
+fn in_code(address: u64, end: u64) -> bool {
+    (CODE_BASE..=end).contains(&address) && address & 3 == 0
+}
+
+/// Disassemble a program into source that reassembles to the same program
+///
+/// Every code word must decode strictly (see [`decode_strict`]), since the
+/// assembler only emits canonical encodings, and the entry point must be an
+/// instruction address (or the end of the code). Header fields the
+/// assembler does not set (`flags`, `stack_size`) are not reproduced.
+///
+/// # Example
+/// ```
+/// use zkir_assembler::assemble;
+/// use zkir_disassembler::disassemble_reassemblable;
+///
+/// let program = assemble("    addi a0, zero, 3\nloop:\n    addi a0, a0, -1\n    bne a0, zero, loop\n    ecall").unwrap();
+/// let source = disassemble_reassemblable(&program).unwrap();
+/// assert!(source.contains("L_00001004:"));
+/// assert!(source.contains("bne a0, zero, L_00001004"));
+/// assert_eq!(assemble(&source).unwrap().to_bytes(), program.to_bytes());
+/// ```
+pub fn disassemble_reassemblable(program: &Program) -> Result<String> {
+    let instructions = program
+        .code
+        .iter()
+        .map(|&word| decode_strict(word))
+        .collect::<Result<Vec<_>>>()?;
+    let end = CODE_BASE + instructions.len() as u64 * 4;
+
+    let mut labels = synthesize_labels(&instructions);
+    let entry = program.header.entry_point as u64;
+    if !in_code(entry, end) {
+        return Err(DisassemblerError::NotReassemblable(format!(
+            "entry point 0x{:08X} is not an instruction address",
+            entry
+        )));
+    }
+    labels.entry(entry).or_insert(Label { kind: LabelKind::Function, refs: Vec::new() }).kind = LabelKind::Function;
+
+    let config = program.config();
+    let mut output = String::new();
+    output.push_str("# ZKIR v3.4 Disassembly\n\n");
+    let _ = writeln!(output, ".config limb_bits {}", config.limb_bits);
+    let _ = writeln!(output, ".config data_limbs {}", config.data_limbs);
+    let _ = writeln!(output, ".config addr_limbs {}", config.addr_limbs);
+    output.push_str(".abi 2\n");
+    let _ = writeln!(output, ".entry {}", labels[&entry].name(entry));
+    output.push_str("\n.text\n");
+
+    for (i, instr) in instructions.iter().enumerate() {
+        let address = CODE_BASE + i as u64 * 4;
+        write_label(&mut output, &labels, address);
+        let text = match target(instr, address).and_then(|t| labels.get(&t).map(|label| label.name(t))) {
+            Some(name) => with_target(instr, &name),
+            None => format(instr),
+        };
+        let _ = writeln!(output, "    {:<width$}# 0x{:08X}", text, address, width = COMMENT_COLUMN - 4);
+    }
+    write_label(&mut output, &labels, end);
+
+    if !program.data.is_empty() {
+        output.push_str("\n.data\n");
+        for (address, directive) in directives(&program.data, data_base(program)) {
+            let _ = writeln!(output, "    {:<width$} # 0x{:08X}", directive.to_string(), address, width = COMMENT_COLUMN - 5);
+        }
+    }
+    if program.header.bss_size > 0 {
+        output.push_str("\n.bss\n");
+        let _ = writeln!(output, "    .space {}", program.header.bss_size);
+    }
+
+    Ok(output)
+}
+
+/// Write the label at `address`, if any, with its cross-references
+fn write_label(output: &mut String, labels: &BTreeMap<u64, Label>, address: u64) {
+    let Some(label) = labels.get(&address) else { return };
+    if !label.refs.is_empty() {
+        let refs: Vec<String> = label.refs.iter().map(|r| format!("0x{:08X}", r)).collect();
+        let _ = writeln!(output, "# refs: {}", refs.join(", "));
+    }
+    let _ = writeln!(output, "{}:", label.name(address));
+}
+
+/// Format a branch or `jal` with a label in place of its offset
+fn with_target(instr: &Instruction, label: &str) -> String {
+    match (instr.rd(), instr.rs1(), instr.rs2()) {
+        (Some(rd), _, _) => format!("{} {}, {}", instr.mnemonic(), rd.name(), label),
+        (None, Some(rs1), Some(rs2)) => format!("{} {}, {}, {}", instr.mnemonic(), rs1.name(), rs2.name(), label),
+        _ => format(instr),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkir_spec::Opcode;
+
+    #[test]
+    fn test_synthesize_labels() {
+        let instructions = [
+            Instruction::Jal { rd: Register::RA, offset: 12 },
+            Instruction::Beq { rs1: Register::R10, rs2: Register::R0, offset: 8 },
+            Instruction::Jal { rd: Register::R0, offset: -8 },
+            Instruction::Bne { rs1: Register::R10, rs2: Register::R0, offset: 4 },
+            // Leaves the code: no label
+            Instruction::Jal { rd: Register::R0, offset: 0x100 },
+        ];
+        let labels = synthesize_labels(&instructions);
+
+        let named: Vec<_> = labels.iter().map(|(&address, label)| (label.name(address), label.refs.clone())).collect();
+        assert_eq!(
+            named,
+            vec![
+                ("L_00001000".to_string(), vec![0x1008]),
+                ("func_0000100C".to_string(), vec![0x1000, 0x1004]),
+                ("L_00001010".to_string(), vec![0x100C]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_canonical_words() {
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32 | 0x8000_0000];
+        program.header.code_size = 4;
+        assert!(matches!(disassemble_reassemblable(&program), Err(DisassemblerError::NonCanonical { .. })));
+    }
+
+    #[test]
+    fn test_rejects_entry_outside_code() {
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 4;
+        program.header.entry_point = 0x1002;
+        let err = disassemble_reassemblable(&program).unwrap_err();
+        assert_eq!(err.to_string(), "Cannot disassemble for reassembly: entry point 0x00001002 is not an instruction address");
+    }
+
+    #[test]
+    fn test_data_directives_reassemble() {
+        let source = ".data\nmsg: .asciz \"a \\\"quoted\\\" # string!!!\\n\"\ntable: .dword 1, -2, 3\n.space 9\n.byte 1, 2, 3\n.text\necall\necall";
+        let program = zkir_assembler::assemble(source).unwrap();
+        let output = disassemble_reassemblable(&program).unwrap();
+        assert!(output.contains(".asciz \"a \\\"quoted\\\" # string!!!\\n\""), "{}", output);
+        assert!(output.contains(".dword 0x1, -2, 0x3"), "{}", output);
+        assert_eq!(zkir_assembler::assemble(&output).unwrap().to_bytes(), program.to_bytes());
+    }
+
+    #[test]
+    fn test_output_layout() {
+        let mut program = Program::new();
+        program.code = vec![Opcode::Ecall.to_u8() as u32];
+        program.header.code_size = 4;
+        program.data = b"hi".to_vec();
+        program.header.data_size = 2;
+        program.header.bss_size = 8;
+
+        let source = disassemble_reassemblable(&program).unwrap();
+        assert_eq!(
+            source,
+            "# ZKIR v3.4 Disassembly\n\n\
+             .config limb_bits 20\n.config data_limbs 2\n.config addr_limbs 2\n.abi 2\n.entry func_00001000\n\n\
+             .text\nfunc_00001000:\n    ecall                           # 0x00001000\n\n\
+             .data\n    .byte 0x68, 0x69                # 0x00001004\n\n\
+             .bss\n    .space 8\n"
+        );
+    }
+}
@@ -5,7 +5,8 @@
 //! - Output formatting
 //! - Error handling for invalid encodings
 
-use zkir_disassembler::{disassemble, decode, format, DisassemblerError};
+use proptest::prelude::*;
+use zkir_disassembler::{disassemble, decode, decode_strict, format, DisassemblerError};
 use zkir_spec::{Instruction, Program, Opcode, Register, Config};
 
 // ============================================================================
@@ -411,10 +412,10 @@ fn test_format_r_type() {
     };
     let formatted = format(&instr);
     assert!(formatted.contains("add"));
-    // Formatter uses ABI names: R1=ra, R2=sp, R3=fp
+    // Formatter uses ABI names: R1=ra, R2=sp, R3=gp
     assert!(formatted.contains("ra"));
     assert!(formatted.contains("sp"));
-    assert!(formatted.contains("fp"));
+    assert!(formatted.contains("gp"));
 }
 
 #[test]
@@ -601,7 +602,7 @@ fn test_decode_encode_roundtrip() {
         rs2: Register::R15,
     };
 
-    let encoded = zkir_assembler::encode(&original);
+    let encoded = zkir_assembler::try_encode(&original, &Config::DEFAULT).unwrap();
     let decoded = decode(encoded).unwrap();
 
     assert_eq!(decoded, original);
@@ -651,6 +652,35 @@ fn test_decode_encode_immediate_range() {
     }
 }
 
+/// Words with a known opcode and random (often cleared) upper bits
+fn known_opcode_words() -> impl Strategy<Value = u32> {
+    (0u8..=0x51, any::<u32>(), any::<u32>())
+        .prop_filter("known opcode", |(opcode, _, _)| Opcode::from_u8(*opcode).is_some())
+        .prop_map(|(opcode, bits, keep)| opcode as u32 | (bits & keep & !0x7F))
+}
+
+proptest! {
+    #[test]
+    fn prop_strict_decode_round_trips(word in prop_oneof![any::<u32>(), known_opcode_words()]) {
+        match decode_strict(word) {
+            // Every accepted word is the encoding of what it decodes to
+            Ok(instr) => prop_assert_eq!(zkir_assembler::encode(&instr), word),
+            // Strict decoding only rejects words the lenient decoder maps
+            // to an instruction with a different encoding
+            Err(DisassemblerError::NonCanonical { .. }) => {
+                prop_assert_ne!(zkir_assembler::encode(&decode(word).unwrap()), word)
+            }
+            Err(_) => prop_assert!(decode(word).is_err()),
+        }
+    }
+
+    #[test]
+    fn prop_strict_decode_accepts_encoder_output(word in known_opcode_words()) {
+        let instr = decode(word).unwrap();
+        prop_assert_eq!(decode_strict(zkir_assembler::encode(&instr)).unwrap(), instr);
+    }
+}
+
 // ============================================================================
 // Edge Case Tests
 // ============================================================================
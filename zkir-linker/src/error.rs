@@ -0,0 +1,91 @@
+//! Linker errors
+
+use thiserror::Error;
+use zkir_spec::{Config, ZkIrError};
+
+#[derive(Debug, Error)]
+pub enum LinkError {
+    #[error("Duplicate symbol `{name}`: defined in {first} and {second}")]
+    DuplicateSymbol {
+        name: String,
+        first: String,
+        second: String,
+    },
+
+    #[error("Undefined symbol `{name}` referenced in {object}")]
+    UndefinedSymbol { name: String, object: String },
+
+    #[error("Entry point set by both {first} and {second}")]
+    MultipleEntryPoints { first: String, second: String },
+
+    #[error("Invalid entry point `{name}`: {message}")]
+    InvalidEntry { name: String, message: String },
+
+    #[error("Config mismatch: {object} uses {found}, expected {expected}")]
+    ConfigMismatch {
+        object: String,
+        expected: Config,
+        found: Config,
+    },
+
+    #[error("Relocation out of range in {object}: {section}+{offset:#x} needs {value}, which does not fit in {field}")]
+    RelocationOutOfRange {
+        object: String,
+        section: &'static str,
+        offset: u64,
+        value: i64,
+        field: String,
+    },
+
+    #[error("Invalid relocation in {object} at {section}+{offset:#x}: {message}")]
+    InvalidRelocation {
+        object: String,
+        section: &'static str,
+        offset: u64,
+        message: String,
+    },
+
+    #[error("Spec error: {0}")]
+    SpecError(#[from] ZkIrError),
+}
+
+pub type Result<T> = std::result::Result<T, LinkError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_symbol_display() {
+        let err = LinkError::DuplicateSymbol {
+            name: "main".to_string(),
+            first: "a.zkasm".to_string(),
+            second: "b.zkasm".to_string(),
+        };
+        assert_eq!(err.to_string(), "Duplicate symbol `main`: defined in a.zkasm and b.zkasm");
+    }
+
+    #[test]
+    fn test_undefined_symbol_display() {
+        let err = LinkError::UndefinedSymbol {
+            name: "memcpy".to_string(),
+            object: "app.zkasm".to_string(),
+        };
+        assert_eq!(err.to_string(), "Undefined symbol `memcpy` referenced in app.zkasm");
+    }
+
+    #[test]
+    fn test_relocation_out_of_range_display() {
+        let err = LinkError::RelocationOutOfRange {
+            object: "app.zkasm".to_string(),
+            section: ".text",
+            offset: 0x10,
+            value: 70000,
+            field: "17-bit branch offset".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Relocation out of range in app.zkasm: .text+0x10 needs 70000, which does not fit in 17-bit branch offset"
+        );
+    }
+}
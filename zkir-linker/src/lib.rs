@@ -0,0 +1,41 @@
+//! # ZKIR Linker v3.4
+//!
+//! Combine separately assembled relocatable objects into an executable
+//! [`Program`](zkir_spec::Program).
+//!
+//! Each module is assembled with `zkir_assembler::assemble_object`, which
+//! records its symbols (`.global`, `.local`, `.extern`) and the operands
+//! that depend on addresses only known after layout. The linker places the
+//! sections per [`zkir_spec::memory`], resolves every reference to a global
+//! definition and patches branch, jump, `%hi`/`%lo` and data relocations.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use zkir_assembler::assemble_object;
+//! use zkir_linker::link;
+//!
+//! let app = assemble_object(r#"
+//!     .global _start
+//!     .extern greeting
+//! _start:
+//!     la a0, greeting
+//!     ecall
+//! "#).unwrap();
+//!
+//! let data = assemble_object(r#"
+//!     .global greeting
+//!     .rodata
+//! greeting:
+//!     .asciz "hello"
+//! "#).unwrap();
+//!
+//! let program = link([("app", app), ("data", data)]).unwrap();
+//! assert_eq!(program.data, b"hello\0");
+//! ```
+
+pub mod error;
+pub mod linker;
+
+pub use error::{LinkError, Result};
+pub use linker::{link, Linker};
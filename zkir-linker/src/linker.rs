@@ -0,0 +1,466 @@
+//! Symbol resolution, layout and relocation
+
+use crate::error::{LinkError, Result};
+use std::collections::HashMap;
+use zkir_spec::encoding::{
+    is_btype, is_itype, is_jtype, is_stype, IMM_BITS, IMM_MASK, IMM_SHIFT, OFFSET_BITS, OFFSET_MASK, OFFSET_SHIFT,
+};
+use zkir_spec::memory::CODE_BASE;
+use zkir_spec::{Binding, Config, ObjectFile, ObjectSymbol, Program, Relocation, RelocationKind, RelocationTarget, SectionKind};
+
+/// Links relocatable objects into a [`Program`]
+///
+/// Objects are laid out in the order they were added, as the runtime loads
+/// a program: every `.text` from `CODE_BASE`, then every `.data`, every
+/// `.rodata` and every `.bss`, each padded to its alignment. `.data` and
+/// `.rodata` form `Program.data`, which the runtime loads directly after
+/// the code.
+///
+/// Execution starts at the symbol named by `.entry` (in at most one
+/// object), else at the global `_start`, else at the first instruction.
+///
+/// # Example
+/// ```
+/// use zkir_assembler::assemble_object;
+/// use zkir_linker::Linker;
+///
+/// let main = assemble_object(".extern square\ncall square\necall").unwrap();
+/// let lib = assemble_object(".global square\nsquare:\n    mul a0, a0, a0\n    ret").unwrap();
+///
+/// let program = Linker::new().add("main", main).add("lib", lib).link().unwrap();
+/// assert_eq!(program.code.len(), 4);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Linker {
+    objects: Vec<(String, ObjectFile)>,
+}
+
+/// Global symbol definition
+#[derive(Debug, Clone, Copy)]
+struct Definition<'a> {
+    /// Name of the defining object
+    object: &'a str,
+    section: SectionKind,
+    address: u64,
+}
+
+/// Address assigned to each section of an object
+#[derive(Debug, Clone, Copy, Default)]
+struct Placement {
+    text: u64,
+    data: u64,
+    rodata: u64,
+    bss: u64,
+}
+
+impl Placement {
+    fn base(&self, section: SectionKind) -> u64 {
+        match section {
+            SectionKind::Text => self.text,
+            SectionKind::Data => self.data,
+            SectionKind::Rodata => self.rodata,
+            SectionKind::Bss => self.bss,
+        }
+    }
+}
+
+impl Linker {
+    /// Create a linker without objects
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an object; `name` identifies it in errors
+    pub fn add(&mut self, name: impl Into<String>, object: ObjectFile) -> &mut Self {
+        self.objects.push((name.into(), object));
+        self
+    }
+
+    /// Lay out the objects, resolve symbols and apply relocations
+    pub fn link(&self) -> Result<Program> {
+        let config = self.config()?;
+        let (placements, data_start, data_end, bss_end) = self.layout();
+        let globals = self.globals(&placements)?;
+        let entry_point = self.entry_point(&placements, &globals)?;
+
+        let mut code = Vec::new();
+        let mut data = vec![0u8; (data_end - data_start) as usize];
+        for ((name, object), placement) in self.objects.iter().zip(&placements) {
+            let mut text = object.text.clone();
+            let data_offset = (placement.data - data_start) as usize;
+            let rodata_offset = (placement.rodata - data_start) as usize;
+            data[data_offset..data_offset + object.data.len()].copy_from_slice(&object.data);
+            data[rodata_offset..rodata_offset + object.rodata.len()].copy_from_slice(&object.rodata);
+
+            for relocation in &object.relocations {
+                let value = self.resolve(name, object, placement, relocation, &globals)?;
+                let site = Site { object: name, relocation };
+                match relocation.section {
+                    SectionKind::Text => {
+                        let index = (relocation.offset / 4) as usize;
+                        let word = text
+                            .get_mut(index)
+                            .filter(|_| relocation.offset % 4 == 0)
+                            .ok_or_else(|| site.invalid("offset is not an instruction in .text"))?;
+                        let pc = placement.text + relocation.offset;
+                        *word = site.patch_instruction(*word, value, pc)?;
+                    }
+                    SectionKind::Data | SectionKind::Rodata => {
+                        let RelocationKind::Data(width) = relocation.kind else {
+                            return Err(site.invalid("instruction relocation outside .text"));
+                        };
+                        let start = (placement.base(relocation.section) - data_start + relocation.offset) as usize;
+                        let size = object.section_size(relocation.section);
+                        if relocation.offset + width as u64 > size {
+                            return Err(site.invalid("offset is past the end of the section"));
+                        }
+                        site.check(value, fits_width(value, width as usize), || format!("{}-byte data value", width))?;
+                        data[start..start + width as usize].copy_from_slice(&value.to_le_bytes()[..width as usize]);
+                    }
+                    SectionKind::Bss => return Err(site.invalid("relocation in .bss")),
+                }
+            }
+            code.extend(text);
+        }
+
+        let mut program = Program::with_config(config).map_err(|e| LinkError::SpecError(e.into()))?;
+        program.header.code_size = (code.len() * 4) as u32;
+        program.header.data_size = data.len() as u32;
+        program.header.bss_size = (bss_end - data_end) as u32;
+        program.header.entry_point = entry_point as u32;
+        program.code = code;
+        program.data = data;
+        Ok(program)
+    }
+
+    /// Common configuration of all objects
+    fn config(&self) -> Result<Config> {
+        let Some((_, first)) = self.objects.first() else {
+            return Ok(Config::DEFAULT);
+        };
+        for (name, object) in &self.objects[1..] {
+            if object.config != first.config {
+                return Err(LinkError::ConfigMismatch {
+                    object: name.clone(),
+                    expected: first.config,
+                    found: object.config,
+                });
+            }
+        }
+        Ok(first.config)
+    }
+
+    /// Section addresses of each object, plus the start and end of the
+    /// initialized data and the end of `.bss`
+    fn layout(&self) -> (Vec<Placement>, u64, u64, u64) {
+        let mut placements = vec![Placement::default(); self.objects.len()];
+        let mut addr = CODE_BASE;
+        for (placement, (_, object)) in placements.iter_mut().zip(&self.objects) {
+            placement.text = addr;
+            addr += object.section_size(SectionKind::Text);
+        }
+
+        let data_start = addr;
+        let mut data_end = addr;
+        for section in [SectionKind::Data, SectionKind::Rodata, SectionKind::Bss] {
+            if section == SectionKind::Bss {
+                data_end = addr;
+            }
+            for (placement, (_, object)) in placements.iter_mut().zip(&self.objects) {
+                let size = object.section_size(section);
+                let base = place(addr, object.section_align(section), size);
+                match section {
+                    SectionKind::Data => placement.data = base,
+                    SectionKind::Rodata => placement.rodata = base,
+                    _ => placement.bss = base,
+                }
+                addr = base + size;
+            }
+        }
+        (placements, data_start, data_end, addr)
+    }
+
+    /// Addresses of all global definitions
+    fn globals(&self, placements: &[Placement]) -> Result<HashMap<&str, Definition<'_>>> {
+        let mut globals: HashMap<&str, Definition> = HashMap::new();
+        for ((name, object), placement) in self.objects.iter().zip(placements) {
+            for symbol in &object.symbols {
+                let (Binding::Global, Some((section, offset))) = (symbol.binding, symbol.definition) else {
+                    continue;
+                };
+                if let Some(first) = globals.get(symbol.name.as_str()) {
+                    return Err(LinkError::DuplicateSymbol {
+                        name: symbol.name.clone(),
+                        first: first.object.to_string(),
+                        second: name.clone(),
+                    });
+                }
+                let address = placement.base(section) + offset;
+                globals.insert(&symbol.name, Definition { object: name, section, address });
+            }
+        }
+        Ok(globals)
+    }
+
+    /// Value `S + A` of a relocation
+    fn resolve(
+        &self,
+        name: &str,
+        object: &ObjectFile,
+        placement: &Placement,
+        relocation: &Relocation,
+        globals: &HashMap<&str, Definition>,
+    ) -> Result<i64> {
+        let target = match relocation.target {
+            RelocationTarget::Section(section) => placement.base(section),
+            RelocationTarget::Symbol(index) => {
+                let symbol = object.symbols.get(index).ok_or_else(|| {
+                    Site { object: name, relocation }.invalid(&format!("symbol index {} out of range", index))
+                })?;
+                lookup(name, symbol, placement, globals)?.address
+            }
+        };
+        Ok((target as i64).wrapping_add(relocation.addend))
+    }
+
+    /// Start address: see [`Linker`]
+    fn entry_point(&self, placements: &[Placement], globals: &HashMap<&str, Definition>) -> Result<u64> {
+        let mut objects = self.objects.iter().zip(placements).filter(|((_, object), _)| object.entry.is_some());
+        let entry = match (objects.next(), objects.next()) {
+            (Some(((first, _), _)), Some(((second, _), _))) => {
+                return Err(LinkError::MultipleEntryPoints {
+                    first: first.clone(),
+                    second: second.clone(),
+                });
+            }
+            (Some(((name, object), placement)), None) => {
+                let index = object.entry.expect("filtered on entry");
+                let symbol = object.symbols.get(index).ok_or_else(|| LinkError::InvalidEntry {
+                    name: format!("#{}", index),
+                    message: format!("symbol index out of range in {}", name),
+                })?;
+                Some((symbol.name.as_str(), lookup(name, symbol, placement, globals)?))
+            }
+            _ => None,
+        };
+
+        let (symbol, definition) = match entry {
+            Some(entry) => entry,
+            None => match globals.get("_start") {
+                Some(&definition) => ("_start", definition),
+                None => return Ok(CODE_BASE),
+            },
+        };
+        if definition.section != SectionKind::Text {
+            return Err(LinkError::InvalidEntry {
+                name: symbol.to_string(),
+                message: format!("defined in {}, not .text", definition.section.name()),
+            });
+        }
+        Ok(definition.address)
+    }
+}
+
+/// Definition of a symbol of `object`: its own, or the global it imports
+fn lookup<'a>(
+    object: &'a str,
+    symbol: &ObjectSymbol,
+    placement: &Placement,
+    globals: &HashMap<&str, Definition<'a>>,
+) -> Result<Definition<'a>> {
+    match symbol.definition {
+        Some((section, offset)) => Ok(Definition {
+            object,
+            section,
+            address: placement.base(section) + offset,
+        }),
+        None => globals.get(symbol.name.as_str()).copied().ok_or_else(|| LinkError::UndefinedSymbol {
+            name: symbol.name.clone(),
+            object: object.to_string(),
+        }),
+    }
+}
+
+/// Link objects given as `(name, object)` pairs; see [`Linker`]
+pub fn link<I, S>(objects: I) -> Result<Program>
+where
+    I: IntoIterator<Item = (S, ObjectFile)>,
+    S: Into<String>,
+{
+    let mut linker = Linker::new();
+    for (name, object) in objects {
+        linker.add(name, object);
+    }
+    linker.link()
+}
+
+/// Location of a relocation, for patching and error reporting
+struct Site<'a> {
+    object: &'a str,
+    relocation: &'a Relocation,
+}
+
+impl Site<'_> {
+    /// Patch the relocated value into an encoded instruction at `pc`
+    fn patch_instruction(&self, word: u32, value: i64, pc: u64) -> Result<u32> {
+        let kind = self.relocation.kind;
+        let (valid, field) = match kind {
+            RelocationKind::Branch => (is_btype(word), "B-type"),
+            RelocationKind::Jump => (is_jtype(word), "J-type"),
+            RelocationKind::Hi | RelocationKind::Lo | RelocationKind::Imm => (is_itype(word) || is_stype(word), "I/S-type"),
+            RelocationKind::Data(_) => (false, "data"),
+        };
+        if !valid {
+            return Err(self.invalid(&format!("{:?} relocation on an instruction without a {} field", kind, field)));
+        }
+
+        let (value, bits, description) = match kind {
+            RelocationKind::Branch => (value - pc as i64, IMM_BITS, "branch offset"),
+            RelocationKind::Jump => (value - pc as i64, OFFSET_BITS, "jump offset"),
+            RelocationKind::Hi => (value >> 16, IMM_BITS, "signed immediate"),
+            RelocationKind::Lo => (value & 0xFFFF, IMM_BITS, "signed immediate"),
+            _ => (value, IMM_BITS, "signed immediate"),
+        };
+        let limit = 1i64 << (bits - 1);
+        self.check(value, (-limit..limit).contains(&value), || format!("{}-bit {}", bits, description))?;
+
+        let (mask, shift) = if kind == RelocationKind::Jump { (OFFSET_MASK, OFFSET_SHIFT) } else { (IMM_MASK, IMM_SHIFT) };
+        Ok((word & !(mask << shift)) | ((value as u32 & mask) << shift))
+    }
+
+    fn check(&self, value: i64, fits: bool, field: impl FnOnce() -> String) -> Result<()> {
+        if fits {
+            return Ok(());
+        }
+        Err(LinkError::RelocationOutOfRange {
+            object: self.object.to_string(),
+            section: self.relocation.section.name(),
+            offset: self.relocation.offset,
+            value,
+            field: field(),
+        })
+    }
+
+    fn invalid(&self, message: &str) -> LinkError {
+        LinkError::InvalidRelocation {
+            object: self.object.to_string(),
+            section: self.relocation.section.name(),
+            offset: self.relocation.offset,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Start address of a section placed at `addr`; empty sections take no padding
+fn place(addr: u64, align: u64, size: u64) -> u64 {
+    if size == 0 {
+        addr
+    } else {
+        addr.div_ceil(align) * align
+    }
+}
+
+/// Check that `value` is representable in `width` bytes (signed or unsigned)
+fn fits_width(value: i64, width: usize) -> bool {
+    if width >= 8 {
+        return true;
+    }
+    let bits = width as u32 * 8;
+    value >= -(1i64 << (bits - 1)) && value < (1i64 << bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkir_spec::encoding::{extract_imm_signed, extract_offset_signed};
+    use zkir_spec::ObjectSymbol;
+
+    fn object(text: Vec<u32>) -> ObjectFile {
+        let mut object = ObjectFile::new(Config::DEFAULT);
+        object.text = text;
+        object
+    }
+
+    fn global(name: &str, definition: Option<(SectionKind, u64)>) -> ObjectSymbol {
+        ObjectSymbol {
+            name: name.to_string(),
+            binding: Binding::Global,
+            definition,
+        }
+    }
+
+    #[test]
+    fn test_layout_aligns_sections() {
+        let mut a = object(vec![0; 3]);
+        a.data = vec![1];
+        a.bss_size = 4;
+        let mut b = object(vec![0]);
+        b.data = vec![2, 2];
+        b.data_align = 4;
+        b.bss_size = 8;
+        b.bss_align = 8;
+
+        let mut linker = Linker::new();
+        linker.add("a", a).add("b", b);
+        let (placements, data_start, data_end, bss_end) = linker.layout();
+
+        assert_eq!(placements[1].text, CODE_BASE + 12);
+        assert_eq!(data_start, CODE_BASE + 16);
+        assert_eq!((placements[0].data, placements[1].data), (CODE_BASE + 16, CODE_BASE + 20));
+        assert_eq!(data_end, CODE_BASE + 22);
+        assert_eq!((placements[0].bss, placements[1].bss), (CODE_BASE + 22, CODE_BASE + 32));
+        assert_eq!(bss_end, CODE_BASE + 40);
+    }
+
+    #[test]
+    fn test_patch_branch_and_jump() {
+        // beq r0, r0, 0 at .text+4 and jal r0, 0 at .text+8, both to `target`
+        let beq = zkir_spec::encoding::encode_btype(zkir_spec::Opcode::Beq, 0, 0, 0);
+        let jal = zkir_spec::encoding::encode_jtype(zkir_spec::Opcode::Jal, 0, 0);
+        let mut caller = object(vec![0, beq, jal]);
+        caller.symbols.push(global("target", None));
+        for (offset, kind) in [(4, RelocationKind::Branch), (8, RelocationKind::Jump)] {
+            caller.relocations.push(Relocation {
+                section: SectionKind::Text,
+                offset,
+                kind,
+                target: RelocationTarget::Symbol(0),
+                addend: 0,
+            });
+        }
+        let mut callee = object(vec![0]);
+        callee.symbols.push(global("target", Some((SectionKind::Text, 0))));
+
+        let program = link([("caller", caller), ("callee", callee)]).unwrap();
+        assert_eq!(extract_imm_signed(program.code[1]), 8);
+        assert_eq!(extract_offset_signed(program.code[2]), 4);
+    }
+
+    #[test]
+    fn test_duplicate_and_undefined_symbols() {
+        let mut a = object(vec![0]);
+        a.symbols.push(global("main", Some((SectionKind::Text, 0))));
+        let b = a.clone();
+        let err = link([("a", a.clone()), ("b", b)]).unwrap_err();
+        assert!(matches!(err, LinkError::DuplicateSymbol { ref first, ref second, .. } if first == "a" && second == "b"));
+
+        a.symbols.push(global("missing", None));
+        a.relocations.push(Relocation {
+            section: SectionKind::Text,
+            offset: 0,
+            kind: RelocationKind::Imm,
+            target: RelocationTarget::Symbol(1),
+            addend: 0,
+        });
+        let err = link([("a", a)]).unwrap_err();
+        assert!(matches!(err, LinkError::UndefinedSymbol { ref name, .. } if name == "missing"));
+    }
+
+    #[test]
+    fn test_fits_width() {
+        assert!(fits_width(255, 1));
+        assert!(fits_width(-128, 1));
+        assert!(!fits_width(256, 1));
+        assert!(fits_width(i64::MIN, 8));
+    }
+}
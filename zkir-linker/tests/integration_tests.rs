@@ -0,0 +1,166 @@
+//! Integration tests for the ZKIR linker
+//!
+//! Modules are assembled with `assemble_object` and linked; the results are
+//! compared with assembling the same code as a single source.
+
+use zkir_assembler::{assemble, assemble_object};
+use zkir_linker::{link, LinkError, Linker};
+use zkir_spec::encoding::extract_imm_signed;
+use zkir_spec::{Config, ObjectFile};
+
+fn object(source: &str) -> ObjectFile {
+    assemble_object(source).unwrap()
+}
+
+// ============================================================================
+// Layout Tests
+// ============================================================================
+
+#[test]
+fn test_link_matches_single_source() {
+    let main = r#"
+        .global main
+        .extern helper, message
+    main:
+        la r1, message
+        call helper
+        beq r1, r2, main
+        ecall
+    "#;
+    let lib = r#"
+        .global helper, message
+    helper:
+        addi r11, r11, 1
+        ret
+        .rodata
+    message:
+        .asciz "hi"
+        .data
+    counter:
+        .dword message + 1
+        .bss
+        .space 16
+    "#;
+
+    let linked = link([("main", object(main)), ("lib", object(lib))]).unwrap();
+    let single = assemble(&format!("{}\n.text\n{}", main, lib)).unwrap();
+    assert_eq!(linked.code, single.code);
+    assert_eq!(linked.data, single.data);
+    assert_eq!(linked.to_bytes(), single.to_bytes());
+}
+
+#[test]
+fn test_link_empty() {
+    let program = Linker::new().link().unwrap();
+    assert!(program.code.is_empty() && program.data.is_empty());
+}
+
+#[test]
+fn test_link_local_labels_do_not_clash() {
+    let a = object(".global a\na:\nloop:\n    beq r1, r2, loop\n    ret");
+    let b = object(".global b\nb:\n    nop\nloop:\n    beq r1, r2, loop\n    ret");
+    let program = link([("a", a), ("b", b)]).unwrap();
+    assert_eq!(extract_imm_signed(program.code[0]), 0);
+    assert_eq!(extract_imm_signed(program.code[3]), 0);
+}
+
+#[test]
+fn test_link_entry_point() {
+    let lib = || object(".global helper\nhelper:\n    ret");
+    let app = || object(".global _start\n.extern helper\n_start:\n    call helper\n    ecall");
+
+    // The global `_start`, wherever it is placed
+    let program = link([("lib", lib()), ("app", app())]).unwrap();
+    assert_eq!(program.header.entry_point, 0x1004);
+
+    // `.entry` wins, and may name an imported symbol
+    let program = link([("lib", lib()), ("app", app()), ("boot", object(".extern helper\n.entry helper"))]).unwrap();
+    assert_eq!(program.header.entry_point, 0x1000);
+
+    // Without either, execution starts at the first instruction
+    let program = link([("lib", lib())]).unwrap();
+    assert_eq!(program.header.entry_point, 0x1000);
+}
+
+// ============================================================================
+// Error Tests
+// ============================================================================
+
+#[test]
+fn test_link_duplicate_symbol() {
+    let a = object(".global main\nmain:\n    ecall");
+    let b = object(".global main\nmain:\n    ebreak");
+    let err = link([("a.zkasm", a), ("b.zkasm", b)]).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate symbol `main`: defined in a.zkasm and b.zkasm");
+}
+
+#[test]
+fn test_link_undefined_symbol() {
+    let err = link([("app.zkasm", object(".extern memcpy\ncall memcpy"))]).unwrap_err();
+    assert!(matches!(err, LinkError::UndefinedSymbol { ref name, ref object } if name == "memcpy" && object == "app.zkasm"));
+
+    // Local labels of other objects are not visible
+    let lib = object("memcpy:\n    ret");
+    let err = link([("app", object(".extern memcpy\ncall memcpy")), ("lib", lib)]).unwrap_err();
+    assert!(matches!(err, LinkError::UndefinedSymbol { .. }), "{}", err);
+}
+
+#[test]
+fn test_link_entry_errors() {
+    let a = object(".entry a\na:\n    ecall");
+    let b = object(".entry b\nb:\n    ecall");
+    let err = link([("a", a), ("b", b)]).unwrap_err();
+    assert_eq!(err.to_string(), "Entry point set by both a and b");
+
+    let data = object(".global _start\n.data\n_start:\n    .word 0");
+    let err = link([("data", data)]).unwrap_err();
+    assert!(matches!(err, LinkError::InvalidEntry { ref name, .. } if name == "_start"), "{}", err);
+}
+
+#[test]
+fn test_link_config_mismatch() {
+    let a = object("ecall");
+    let b = object(".config limb_bits 30\necall");
+    let err = link([("a", a), ("b", b)]).unwrap_err();
+    assert!(
+        matches!(err, LinkError::ConfigMismatch { ref object, expected, .. } if object == "b" && expected == Config::DEFAULT),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_link_relocation_out_of_range() {
+    let a = object(".extern far\nbeq r1, r2, far");
+    // 64 KiB of code puts `far` just past the reach of a branch
+    let b = object(".global far\n.rept 16384\nnop\n.endr\nfar:\n    ret");
+    let err = link([("a", a), ("b", b)]).unwrap_err();
+    assert!(err.to_string().contains("17-bit branch offset"), "{}", err);
+}
+
+#[test]
+fn test_link_relaxed_branches() {
+    // Branches within an object are relaxed before linking; the far form
+    // leaves %hi/%lo relocations against the object's own .text
+    let main = r#"
+        .global main
+    main:
+        beq r1, r2, near_end
+        bne r1, r2, far_end
+        .rept 16384
+        nop
+        .endr
+    near_end:
+        .rept 262144
+        nop
+        .endr
+    far_end:
+        ret
+    "#;
+    let lib = ".global helper\nhelper:\n    ret";
+
+    let linked = link([("lib", object(lib)), ("main", object(main))]).unwrap();
+    let single = assemble(&format!("{}\n{}", lib, main)).unwrap();
+    assert_eq!(linked.code.len(), 16384 + 262144 + 1 + 2 + 5 + 1);
+    assert_eq!(linked.code, single.code);
+}
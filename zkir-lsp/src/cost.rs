@@ -0,0 +1,80 @@
+//! Constraint cost of an instruction
+//!
+//! Every instruction takes one trace row. Observation points (see
+//! [`zkir_runtime::observation`]) need their source registers normalized
+//! first, and each normalized value is range checked with one lookup per
+//! chunk ([`Config::chunks_per_value`]). `r0` is always normalized.
+
+use std::fmt;
+use zkir_runtime::get_normalize_sources;
+use zkir_spec::{Config, Instruction, Opcode, Register};
+
+/// Trace rows and range-check lookups an instruction costs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintCost {
+    pub rows: usize,
+    /// Source registers normalized before execution
+    pub normalized: Vec<Register>,
+    /// Range-check lookups for the normalized values
+    pub lookups: usize,
+}
+
+/// Cost of executing `instruction` under `config`
+pub fn constraint_cost(instruction: &Instruction, config: &Config) -> ConstraintCost {
+    let opcode = Opcode::from_instruction(zkir_assembler::encode(instruction))
+        .expect("encoded instructions have a valid opcode");
+    let index = |register: Option<Register>| register.map_or(0, Register::index);
+
+    let mut normalized: Vec<Register> =
+        get_normalize_sources(opcode, index(instruction.rs1()), index(instruction.rs2()))
+            .into_iter()
+            .filter_map(Register::from_index)
+            .filter(|register| !register.is_zero())
+            .collect();
+    normalized.dedup();
+
+    ConstraintCost {
+        rows: 1,
+        lookups: normalized.len() * config.chunks_per_value(),
+        normalized,
+    }
+}
+
+impl fmt::Display for ConstraintCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(f, "{} row{}, {} range-check lookup{}", self.rows, plural(self.rows), self.lookups, plural(self.lookups))?;
+        if !self.normalized.is_empty() {
+            let names: Vec<_> = self.normalized.iter().map(|register| register.name()).collect();
+            write!(f, " (normalizes {})", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deferred_arithmetic_is_free() {
+        let add = Instruction::Add { rd: Register::A0, rs1: Register::A1, rs2: Register::A2 };
+        let cost = constraint_cost(&add, &Config::DEFAULT);
+        assert_eq!(cost, ConstraintCost { rows: 1, normalized: vec![], lookups: 0 });
+        assert_eq!(cost.to_string(), "1 row, 0 range-check lookups");
+    }
+
+    #[test]
+    fn test_observation_points_normalize_sources() {
+        let chunks = Config::DEFAULT.chunks_per_value();
+        let beq = Instruction::Beq { rs1: Register::A0, rs2: Register::A1, offset: 8 };
+        let cost = constraint_cost(&beq, &Config::DEFAULT);
+        assert_eq!(cost.normalized, vec![Register::A0, Register::A1]);
+        assert_eq!(cost.lookups, 2 * chunks);
+        assert!(cost.to_string().ends_with("(normalizes a0, a1)"), "{}", cost);
+
+        // Comparing with zero only normalizes one register
+        let beqz = Instruction::Beq { rs1: Register::A0, rs2: Register::R0, offset: 8 };
+        assert_eq!(constraint_cost(&beqz, &Config::DEFAULT).lookups, chunks);
+    }
+}
@@ -0,0 +1,587 @@
+//! Analysis of one open zkasm document
+//!
+//! A [`Document`] holds the text, its parsed [`Module`] and, once a hover
+//! needs it, the listing of its assembly until the next [`Document::update`].
+//! Positions and ranges are LSP ones; internally everything is a byte
+//! offset into the text.
+
+use crate::cost::constraint_cost;
+use crate::position::{self, line_start};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, DocumentSymbol, Position, Range, SymbolKind,
+};
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::ops::Range as Span;
+use std::path::PathBuf;
+use zkir_assembler::ast::{Operand, OperandKind, StatementKind};
+use zkir_assembler::expr::Expr;
+use zkir_assembler::lexer::Token;
+use zkir_assembler::parser::{parse_with_errors, strip_comment, tokenize_spanned};
+use zkir_assembler::{pseudo, Assembler, Listing, Module, Severity};
+use zkir_spec::{AbiVersion, Opcode, Program, Register, ABI_NAMES};
+
+/// Open document: text, file path (for includes) and parsed statements
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    path: Option<PathBuf>,
+    module: Module,
+    /// Assembly of the current text, `None` inside if it does not assemble
+    assembled: OnceCell<Option<(Program, Listing)>>,
+}
+
+/// Diagnostics from assembling a document, by the file they are in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostics {
+    /// In the document itself, or not tied to a line
+    pub document: Vec<Diagnostic>,
+    /// In included files, keyed by path
+    pub included: BTreeMap<PathBuf, Vec<Diagnostic>>,
+}
+
+/// Symbol definition: label or `.equ`/`.set` constant
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Definition {
+    name: String,
+    /// Bytes of the name
+    span: Span<usize>,
+    /// Bytes of the whole statement
+    statement: Span<usize>,
+    kind: SymbolKind,
+}
+
+/// What the cursor is on
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    /// Label or constant name, at `span`
+    Symbol(String, Span<usize>),
+    /// Numeric local label definition (`1:`) or reference (`1b`, `1f`), at `span`
+    Local(String, Span<usize>),
+    Register(String),
+}
+
+impl Document {
+    /// Parse `text`; `path` is the file it was opened from, if any
+    pub fn new(text: String, path: Option<PathBuf>) -> Self {
+        let (module, _) = parse_with_errors(&text);
+        Self { text, path, module, assembled: OnceCell::new() }
+    }
+
+    /// Replace the text, dropping everything derived from the old one
+    pub fn update(&mut self, text: String) {
+        *self = Self::new(text, self.path.take());
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Assembler searching the document's directory for included files
+    fn assembler(&self) -> Assembler {
+        let dir = self.path.as_ref().and_then(|path| path.parent()).map(PathBuf::from);
+        Assembler::with_include_paths(dir)
+    }
+
+    /// Program and listing of the document, assembled once per text
+    fn assembled(&self) -> Option<&(Program, Listing)> {
+        self.assembled
+            .get_or_init(|| self.assembler().assemble_with_listing(&self.text).ok())
+            .as_ref()
+    }
+
+    /// Errors and warnings from assembling the document
+    ///
+    /// Problems in included files are reported against those files, read
+    /// from disk for their positions.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let (_, diagnostics) = self.assembler().assemble_with_diagnostics(&self.text);
+        let mut included_texts: BTreeMap<PathBuf, Option<String>> = BTreeMap::new();
+        let mut result = Diagnostics::default();
+        for diagnostic in diagnostics {
+            let mut message = diagnostic.message.clone();
+            for note in &diagnostic.notes {
+                message = format!("{}\nnote: {}", message, note);
+            }
+            let severity = match diagnostic.severity {
+                Severity::Error => DiagnosticSeverity::ERROR,
+                Severity::Warning => DiagnosticSeverity::WARNING,
+            };
+            let lsp = |range| Diagnostic {
+                range,
+                severity: Some(severity),
+                source: Some("zkasm".to_string()),
+                message: message.clone(),
+                ..Diagnostic::default()
+            };
+
+            match diagnostic.span {
+                Some(span) => match span.file {
+                    None => result.document.push(lsp(line_range(&self.text, span.line, span.columns))),
+                    Some(file) => {
+                        let path = PathBuf::from(file);
+                        let text = included_texts
+                            .entry(path.clone())
+                            .or_insert_with(|| std::fs::read_to_string(&path).ok());
+                        let range = text
+                            .as_deref()
+                            .map_or_else(Range::default, |text| line_range(text, span.line, span.columns));
+                        result.included.entry(path).or_default().push(lsp(range));
+                    }
+                },
+                None => result.document.push(lsp(Range::default())),
+            }
+        }
+        result
+    }
+
+    /// Definition of the symbol at `position`
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let span = match self.target(position)? {
+            Target::Symbol(name, _) => self.definitions().into_iter().find(|d| d.name == name)?.span,
+            Target::Local(name, span) => self.local_definition(&name, span.start)?,
+            Target::Register(_) => return None,
+        };
+        Some(position::range(&self.text, span))
+    }
+
+    /// Uses of the symbol or local label at `position`, with its definition
+    /// if `include_declaration`
+    pub fn references(&self, position: Position, include_declaration: bool) -> Vec<Range> {
+        let (definitions, mut spans) = match self.target(position) {
+            Some(Target::Symbol(name, _)) => {
+                let definitions: Vec<_> =
+                    self.definitions().into_iter().filter(|d| d.name == name).map(|d| d.span).collect();
+                (definitions, self.occurrences(&name))
+            }
+            Some(Target::Local(name, span)) => {
+                let Some(definition) = self.local_definition(&name, span.start) else {
+                    return Vec::new();
+                };
+                let uses = self
+                    .local_occurrences()
+                    .into_iter()
+                    .filter(|(name, span)| self.local_definition(name, span.start).as_ref() == Some(&definition))
+                    .map(|(_, span)| span)
+                    .collect();
+                (vec![definition], uses)
+            }
+            _ => return Vec::new(),
+        };
+
+        spans.retain(|span| !definitions.contains(span));
+        if include_declaration {
+            spans.extend(definitions);
+        }
+        spans.sort_by_key(|span| span.start);
+        spans.into_iter().map(|span| position::range(&self.text, span)).collect()
+    }
+
+    /// Markdown describing what is at `position`
+    ///
+    /// Registers show their names; symbols their definition (and address,
+    /// for labels); anything else on an instruction line the instructions
+    /// it assembles to, with encoding and constraint cost. Addresses and
+    /// instructions are only known while the document assembles.
+    pub fn hover(&self, position: Position) -> Option<String> {
+        match self.target(position) {
+            Some(Target::Register(name)) => {
+                let abi = self.abi_at(position::offset(&self.text, position)?);
+                let register = abi.register(&name.to_lowercase())?;
+                Some(format!("`{}` — `{}`", abi.names()[register.index() as usize], register.numeric_name()))
+            }
+            Some(Target::Symbol(name, _)) => {
+                let definition = self.definitions().into_iter().find(|d| d.name == name)?;
+                let mut text = format!("```zkasm\n{}\n```", &self.text[definition.statement]);
+                if let Some((_, listing)) = self.assembled() {
+                    if let Some(symbol) = listing.symbols.iter().find(|symbol| symbol.name == name) {
+                        text.push_str(&format!("\n\n`{:#010x}` in `{}`", symbol.address, symbol.section.name()));
+                    }
+                }
+                Some(text)
+            }
+            Some(Target::Local(..)) | None => self.hover_instructions(position.line as usize + 1),
+        }
+    }
+
+    /// Instructions assembled from 1-based `line`
+    fn hover_instructions(&self, line: usize) -> Option<String> {
+        let (program, listing) = self.assembled()?;
+        let config = program.config();
+        let entries: Vec<String> = listing
+            .lines
+            .iter()
+            .filter(|entry| entry.file.is_none() && entry.line == line)
+            .map(|entry| {
+                format!(
+                    "```zkasm\n{}\n```\n`{:#010x}`: `{:#010x}` — {}",
+                    entry.instruction,
+                    entry.address,
+                    entry.word,
+                    constraint_cost(&entry.instruction, &config)
+                )
+            })
+            .collect();
+        (!entries.is_empty()).then(|| entries.join("\n\n"))
+    }
+
+    /// Mnemonics, register names and the document's symbols
+    pub fn completions(&self) -> Vec<CompletionItem> {
+        let item = |label: String, kind, detail: &str| CompletionItem {
+            label,
+            kind: Some(kind),
+            detail: Some(detail.to_string()),
+            ..CompletionItem::default()
+        };
+
+        let mut items: Vec<CompletionItem> = (0..=u8::MAX)
+            .filter_map(Opcode::from_u8)
+            .map(|opcode| item(opcode.to_string(), CompletionItemKind::KEYWORD, "instruction"))
+            .collect();
+        items.extend(pseudo::MNEMONICS.iter().map(|m| item(m.to_string(), CompletionItemKind::KEYWORD, "pseudo-instruction")));
+        for (index, name) in ABI_NAMES.iter().enumerate() {
+            let register = Register::from_index(index as u8).expect("one ABI name per register");
+            items.push(item(name.to_string(), CompletionItemKind::VARIABLE, &format!("register {}", register.numeric_name())));
+            items.push(item(register.numeric_name(), CompletionItemKind::VARIABLE, &format!("register {}", name)));
+        }
+        for definition in self.definitions() {
+            let (kind, detail) = match definition.kind {
+                SymbolKind::CONSTANT => (CompletionItemKind::CONSTANT, "constant"),
+                _ => (CompletionItemKind::REFERENCE, "label"),
+            };
+            items.push(item(definition.name, kind, detail));
+        }
+        items
+    }
+
+    /// Labels and constants, in source order
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.definitions()
+            .into_iter()
+            .map(|definition| {
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: definition.name,
+                    detail: None,
+                    kind: definition.kind,
+                    tags: None,
+                    deprecated: None,
+                    range: position::range(&self.text, definition.statement),
+                    selection_range: position::range(&self.text, definition.span),
+                    children: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Named labels and `.equ`/`.set` constants
+    ///
+    /// Labels in `.text` are functions, other labels variables.
+    fn definitions(&self) -> Vec<Definition> {
+        let mut definitions = Vec::new();
+        let mut in_text = true;
+        for statement in &self.module.statements {
+            match &statement.kind {
+                StatementKind::Label(name) if !name.starts_with(|c: char| c.is_ascii_digit()) => {
+                    definitions.push(Definition {
+                        name: name.clone(),
+                        span: statement.span.start..statement.span.start + name.len(),
+                        statement: statement.span.clone(),
+                        kind: if in_text { SymbolKind::FUNCTION } else { SymbolKind::VARIABLE },
+                    });
+                }
+                StatementKind::Directive(directive) => match directive.name.as_str() {
+                    "text" => in_text = true,
+                    "data" | "rodata" | "bss" => in_text = false,
+                    "section" => {
+                        in_text = matches!(directive.args.first(), Some(arg) if &self.text[arg.span.clone()] == ".text");
+                    }
+                    "equ" | "set" => {
+                        if let Some(arg) = directive.args.first() {
+                            if let OperandKind::Expr(Expr::Symbol(name)) = &arg.kind {
+                                definitions.push(Definition {
+                                    name: name.clone(),
+                                    span: arg.span.clone(),
+                                    statement: statement.span.clone(),
+                                    kind: SymbolKind::CONSTANT,
+                                });
+                            }
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        // `.set` may redefine a constant; the first definition is the one to go to
+        let mut seen = std::collections::HashSet::new();
+        definitions.retain(|definition| seen.insert(definition.name.clone()));
+        definitions
+    }
+
+    /// Definition of numeric local label reference `name` (`1b`, `1f`) at
+    /// byte `at`; a definition (`1`) is its own
+    fn local_definition(&self, name: &str, at: usize) -> Option<Span<usize>> {
+        let labels = self.module.statements.iter().filter_map(|statement| match &statement.kind {
+            StatementKind::Label(label) => Some((label, statement.span.clone())),
+            _ => None,
+        });
+        let label_span = |span: Span<usize>, number: &str| span.start..span.start + number.len();
+
+        if let Some(number) = name.strip_suffix('f') {
+            labels.filter(|(label, span)| *label == number && span.start > at).map(|(_, span)| label_span(span, number)).next()
+        } else if let Some(number) = name.strip_suffix('b') {
+            labels.filter(|(label, span)| *label == number && span.start < at).map(|(_, span)| label_span(span, number)).next_back()
+        } else {
+            labels.filter(|(label, span)| *label == name && span.contains(&at)).map(|(_, span)| label_span(span, name)).next()
+        }
+    }
+
+    /// Spans of `name` in operands and directive arguments
+    fn occurrences(&self, name: &str) -> Vec<Span<usize>> {
+        self.operand_tokens()
+            .into_iter()
+            .filter_map(|(token, span)| match token {
+                Token::Identifier(ident) if ident == name => Some(span),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Numeric local label references (`1b`, `1f`) in operands, with their spans
+    fn local_occurrences(&self) -> Vec<(String, Span<usize>)> {
+        self.operand_tokens()
+            .into_iter()
+            .filter_map(|(token, span)| match token {
+                Token::LocalLabel(name) => Some((name, span)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Tokens of every operand and directive argument, with their spans
+    fn operand_tokens(&self) -> Vec<(Token, Span<usize>)> {
+        let mut tokens = Vec::new();
+        for statement in &self.module.statements {
+            let operands = match &statement.kind {
+                StatementKind::Directive(directive) => &directive.args,
+                StatementKind::Instruction(instruction) => &instruction.operands,
+                _ => continue,
+            };
+            for operand in operands {
+                let Ok(found) = tokenize_spanned(&self.text[operand.span.clone()]) else {
+                    continue;
+                };
+                let start = operand.span.start;
+                tokens.extend(found.into_iter().map(|(token, span)| (token, start + span.start..start + span.end)));
+            }
+        }
+        tokens
+    }
+
+    /// ABI version selected by the last `.abi` directive before byte `at`
+    fn abi_at(&self, at: usize) -> AbiVersion {
+        let mut abi = AbiVersion::CURRENT;
+        for statement in self.module.statements.iter().take_while(|statement| statement.span.start < at) {
+            if let StatementKind::Directive(directive) = &statement.kind {
+                if let ("abi", [Operand { kind: OperandKind::Expr(Expr::Number(number)), .. }]) =
+                    (directive.name.as_str(), directive.args.as_slice())
+                {
+                    abi = u64::try_from(*number).ok().and_then(AbiVersion::from_number).unwrap_or(abi);
+                }
+            }
+        }
+        abi
+    }
+
+    /// Symbol, local label or register at `position`
+    fn target(&self, position: Position) -> Option<Target> {
+        let at = position::offset(&self.text, position)?;
+        let statement = self
+            .module
+            .statements
+            .iter()
+            .find(|statement| statement.span.start <= at && at <= statement.span.end)?;
+
+        match &statement.kind {
+            StatementKind::Label(name) if name.starts_with(|c: char| c.is_ascii_digit()) => {
+                Some(Target::Local(name.clone(), statement.span.clone()))
+            }
+            StatementKind::Label(name) => Some(Target::Symbol(name.clone(), statement.span.clone())),
+            StatementKind::Directive(_) | StatementKind::Instruction(_) => {
+                let start = statement.span.start;
+                let tokens = tokenize_spanned(&self.text[statement.span.clone()]).ok()?;
+                let (token, span) = tokens.into_iter().find(|(_, span)| start + span.start <= at && at <= start + span.end)?;
+                let span = start + span.start..start + span.end;
+                match token {
+                    // The mnemonic is an identifier too, but never a symbol
+                    Token::Identifier(name) if span.start != start => Some(Target::Symbol(name, span)),
+                    Token::LocalLabel(name) => Some(Target::Local(name, span)),
+                    Token::Register(name) => Some(Target::Register(name)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Range of byte `columns` on 1-based `line` of `text`; empty columns
+/// cover the line's code
+fn line_range(text: &str, line: usize, columns: Span<usize>) -> Range {
+    let Some(start) = line_start(text, line - 1) else {
+        return Range::default();
+    };
+    let line_text = text[start..].split('\n').next().unwrap_or("");
+    let columns = if columns.is_empty() {
+        let code = strip_comment(line_text).trim_end();
+        let indent = code.len() - code.trim_start().len();
+        indent..code.len()
+    } else {
+        columns.start.min(line_text.len())..columns.end.min(line_text.len())
+    };
+    position::range(text, start + columns.start..start + columns.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+.equ COUNT, 3
+main:
+    li a0, COUNT
+1:  addi a0, a0, -1
+    bnez a0, 1b
+    j done
+done:
+    ecall
+    .data
+table: .word COUNT, main
+";
+
+    fn document() -> Document {
+        Document::new(SOURCE.to_string(), None)
+    }
+
+    /// Position of the `n`th occurrence of `needle`, `offset` bytes in
+    fn at(needle: &str, n: usize, offset: usize) -> Position {
+        let start = SOURCE.match_indices(needle).nth(n).unwrap().0;
+        position::position(SOURCE, start + offset)
+    }
+
+    fn text(range: Range) -> &'static str {
+        let start = position::offset(SOURCE, range.start).unwrap();
+        let end = position::offset(SOURCE, range.end).unwrap();
+        &SOURCE[start..end]
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostics = document().diagnostics().document;
+        assert!(diagnostics.iter().all(|d| d.severity == Some(DiagnosticSeverity::WARNING)), "{:?}", diagnostics);
+
+        let document = Document::new("nop\n    add r1, x9, r3 # bad\nj nowhere".to_string(), None);
+        let diagnostics = document.diagnostics().document;
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].range.start.line, 1);
+        assert_eq!(diagnostics[0].range.end, Position { line: 1, character: 18 });
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert!(diagnostics[1].message.contains("nowhere"), "{}", diagnostics[1].message);
+    }
+
+    #[test]
+    fn test_definition() {
+        let document = document();
+        let range = document.definition(at("COUNT", 1, 2)).unwrap();
+        assert_eq!(range.start, Position { line: 0, character: 5 });
+        assert_eq!(text(range), "COUNT");
+
+        assert_eq!(document.definition(at("done", 0, 0)).unwrap().start.line, 6);
+        // Local labels resolve to the nearest definition in their direction
+        assert_eq!(document.definition(at("1b", 0, 0)).unwrap().start, Position { line: 3, character: 0 });
+        // Mnemonics and registers are not symbols
+        assert_eq!(document.definition(at("li", 0, 0)), None);
+        assert_eq!(document.definition(at("a0", 0, 0)), None);
+    }
+
+    #[test]
+    fn test_references() {
+        let document = document();
+        let lines = |ranges: Vec<Range>| ranges.iter().map(|range| range.start.line).collect::<Vec<_>>();
+        assert_eq!(lines(document.references(at("COUNT", 0, 0), true)), vec![0, 2, 9]);
+        assert_eq!(lines(document.references(at("COUNT", 0, 0), false)), vec![2, 9]);
+        assert_eq!(lines(document.references(at("main", 0, 1), true)), vec![1, 9]);
+
+        // Local labels, from the definition or a use
+        assert_eq!(lines(document.references(at("1:", 0, 0), true)), vec![3, 4]);
+        assert_eq!(lines(document.references(at("1b", 0, 0), false)), vec![4]);
+    }
+
+    #[test]
+    fn test_included_file_diagnostics() {
+        let dir = std::env::temp_dir().join(format!("zkir-lsp-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("inc.zkasm"), "nop\n    add r1, x9, r3\n").unwrap();
+
+        let document = Document::new(".include \"inc.zkasm\"\necall\n".to_string(), Some(dir.join("main.zkasm")));
+        let diagnostics = document.diagnostics();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(diagnostics.document.is_empty(), "{:?}", diagnostics.document);
+        let (path, included) = diagnostics.included.into_iter().next().unwrap();
+        assert!(path.ends_with("inc.zkasm"), "{}", path.display());
+        assert_eq!(included.len(), 1);
+        assert_eq!(included[0].range.start, Position { line: 1, character: 4 });
+        assert_eq!(included[0].severity, Some(DiagnosticSeverity::ERROR));
+    }
+
+    #[test]
+    fn test_update_drops_cached_listing() {
+        let mut document = Document::new("addi a0, a0, 1".to_string(), None);
+        assert!(document.hover(Position { line: 0, character: 0 }).unwrap().contains("addi a0, a0, 1"));
+        document.update("addi a0, a0, 2".to_string());
+        assert!(document.hover(Position { line: 0, character: 0 }).unwrap().contains("addi a0, a0, 2"));
+    }
+
+    #[test]
+    fn test_hover() {
+        let document = document();
+        let hover = document.hover(at("addi", 0, 0)).unwrap();
+        assert!(hover.contains("addi a0, a0, -1"), "{}", hover);
+        assert!(hover.contains("1 row, 0 range-check lookups"), "{}", hover);
+
+        // Pseudo-instructions show every instruction they expand to
+        let hover = document.hover(at("bnez", 0, 0)).unwrap();
+        assert!(hover.contains("bne a0, zero"), "{}", hover);
+        assert!(hover.contains("(normalizes a0)"), "{}", hover);
+
+        assert_eq!(document.hover(at("a0", 1, 0)).unwrap(), "`a0` — `r10`");
+        // Register names follow the `.abi` directive in effect
+        let abi = Document::new("mv a0, a1\n.abi 1\nmv a0, a1\n".to_string(), None);
+        assert_eq!(abi.hover(Position { line: 0, character: 3 }).unwrap(), "`a0` — `r10`");
+        assert_eq!(abi.hover(Position { line: 2, character: 3 }).unwrap(), "`a0` — `r11`");
+        let hover = document.hover(at("main", 1, 0)).unwrap();
+        assert!(hover.contains("main:") && hover.contains("`0x00001000` in `.text`"), "{}", hover);
+    }
+
+    #[test]
+    fn test_completions_and_symbols() {
+        let document = document();
+        let labels: Vec<_> = document.completions().into_iter().map(|item| item.label).collect();
+        for label in ["addi", "ecall", "li", "bnez", "a0", "r15", "zero", "COUNT", "table"] {
+            assert!(labels.iter().any(|l| l == label), "{}", label);
+        }
+
+        let symbols: Vec<_> = document.symbols().into_iter().map(|symbol| (symbol.name, symbol.kind)).collect();
+        assert_eq!(
+            symbols,
+            vec![
+                ("COUNT".to_string(), SymbolKind::CONSTANT),
+                ("main".to_string(), SymbolKind::FUNCTION),
+                ("done".to_string(), SymbolKind::FUNCTION),
+                ("table".to_string(), SymbolKind::VARIABLE),
+            ]
+        );
+    }
+}
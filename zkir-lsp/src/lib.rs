@@ -0,0 +1,20 @@
+//! # ZKIR Language Server
+//!
+//! Language server for `.zkasm` files, speaking LSP over stdio. Everything
+//! runs locally on the assembler's own lexer, parser and listing:
+//!
+//! - Diagnostics: the assembler's errors and warnings, on every change
+//! - Go-to-definition and find-references for labels (including numeric
+//!   local labels) and `.equ`/`.set` symbols
+//! - Hover: each instruction a line assembles to, with its encoding and
+//!   constraint cost (see [`cost`]); register aliases; symbol definitions
+//! - Completion for mnemonics, register names and symbols
+//! - Document symbols for labels and constants
+
+pub mod cost;
+pub mod document;
+pub mod position;
+pub mod server;
+
+pub use document::Document;
+pub use server::run;
@@ -0,0 +1,12 @@
+//! `zkir-lsp`: language server for zkasm over stdio
+
+use lsp_server::Connection;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (connection, io_threads) = Connection::stdio();
+    zkir_lsp::run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Conversion between byte offsets and LSP positions
+//!
+//! LSP columns count UTF-16 code units; the assembler works in bytes.
+
+use lsp_types::{Position, Range};
+
+/// Position of byte `offset` in `text`
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = floor_char_boundary(text, offset.min(text.len()));
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: text[..line_start].matches('\n').count() as u32,
+        character: text[line_start..offset].encode_utf16().count() as u32,
+    }
+}
+
+/// Range of the bytes `span` in `text`
+pub fn range(text: &str, span: std::ops::Range<usize>) -> Range {
+    Range {
+        start: position(text, span.start),
+        end: position(text, span.end),
+    }
+}
+
+/// Byte offset of `position` in `text`, clamped to the end of its line
+///
+/// Returns `None` if the line does not exist.
+pub fn offset(text: &str, position: Position) -> Option<usize> {
+    let start = line_start(text, position.line as usize)?;
+    let line = text[start..].split('\n').next().unwrap_or("");
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character {
+            return Some(start + i);
+        }
+        units += c.len_utf16() as u32;
+    }
+    Some(start + line.len())
+}
+
+/// Byte offset where 0-based `line` starts
+pub fn line_start(text: &str, line: usize) -> Option<usize> {
+    if line == 0 {
+        return Some(0);
+    }
+    text.match_indices('\n').nth(line - 1).map(|(i, _)| i + 1)
+}
+
+fn floor_char_boundary(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "nop\n  li a0, 1 # é\n\nj main";
+        for offset in [0, 3, 4, 6, 17, 20, 21, text.len()] {
+            assert_eq!(super::offset(text, position(text, offset)), Some(offset), "{}", offset);
+        }
+        assert_eq!(position(text, 6), Position { line: 1, character: 2 });
+        assert_eq!(super::offset(text, Position { line: 9, character: 0 }), None);
+    }
+
+    #[test]
+    fn test_utf16_columns() {
+        // `é` is two bytes but one UTF-16 unit; `𝔸` is four bytes and two units
+        let text = "é𝔸x";
+        assert_eq!(position(text, 6), Position { line: 0, character: 3 });
+        assert_eq!(offset(text, Position { line: 0, character: 3 }), Some(6));
+        // Past the end of the line clamps to its end
+        assert_eq!(offset(text, Position { line: 0, character: 40 }), Some(text.len()));
+    }
+}
@@ -0,0 +1,213 @@
+//! LSP message loop
+//!
+//! Documents are synchronized in full on every change, and diagnostics are
+//! published after each open and change. Diagnostics in `.include`d files
+//! are published against those files, and cleared once no longer reported.
+
+use crate::document::Document;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Request as _};
+use lsp_types::{
+    CompletionOptions, CompletionResponse, DocumentSymbolResponse, GotoDefinitionResponse, Hover, HoverContents,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, PublishDiagnosticsParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+/// Serve requests on `connection` until the client shuts down
+pub fn run(connection: &Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                connection.sender.send(server.request(request).into())?;
+            }
+            Message::Notification(notification) => {
+                for publish in server.notification(notification) {
+                    connection.sender.send(publish.into())?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Err("client disconnected without shutting down".into())
+}
+
+/// Open documents
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+    /// Included files each document last published diagnostics to
+    included: HashMap<Url, BTreeSet<Url>>,
+}
+
+impl Server {
+    fn request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => self.handle::<GotoDefinition>(
+                request,
+                |params| &params.text_document_position_params.text_document.uri,
+                |document, params| {
+                    let uri = params.text_document_position_params.text_document.uri;
+                    document
+                        .definition(params.text_document_position_params.position)
+                        .map(|range| GotoDefinitionResponse::Scalar(Location { uri, range }))
+                },
+            ),
+            References::METHOD => self.handle::<References>(
+                request,
+                |params| &params.text_document_position.text_document.uri,
+                |document, params| {
+                    let uri = params.text_document_position.text_document.uri;
+                    let position = params.text_document_position.position;
+                    let ranges = document.references(position, params.context.include_declaration);
+                    Some(ranges.into_iter().map(|range| Location { uri: uri.clone(), range }).collect())
+                },
+            ),
+            HoverRequest::METHOD => self.handle::<HoverRequest>(
+                request,
+                |params| &params.text_document_position_params.text_document.uri,
+                |document, params| {
+                    document.hover(params.text_document_position_params.position).map(|value| Hover {
+                        contents: HoverContents::Markup(MarkupContent { kind: MarkupKind::Markdown, value }),
+                        range: None,
+                    })
+                },
+            ),
+            Completion::METHOD => self.handle::<Completion>(
+                request,
+                |params| &params.text_document_position.text_document.uri,
+                |document, _| Some(CompletionResponse::Array(document.completions())),
+            ),
+            DocumentSymbolRequest::METHOD => self.handle::<DocumentSymbolRequest>(
+                request,
+                |params| &params.text_document.uri,
+                |document, _| Some(DocumentSymbolResponse::Nested(document.symbols())),
+            ),
+            method => Err((ErrorCode::MethodNotFound, format!("Unsupported request {}", method))),
+        };
+
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err((code, message)) => Response::new_err(id, code as i32, message),
+        }
+    }
+
+    /// Decode the parameters of `request` and answer from the document `uri` names
+    ///
+    /// Requests for documents that are not open get a `null` result.
+    fn handle<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        uri: impl FnOnce(&R::Params) -> &Url,
+        answer: impl FnOnce(&Document, R::Params) -> R::Result,
+    ) -> Result<Value, (ErrorCode, String)> {
+        let params: R::Params = params(request.params)?;
+        match self.documents.get(uri(&params)) {
+            Some(document) => serde_json::to_value(answer(document, params))
+                .map_err(|err| (ErrorCode::InternalError, err.to_string())),
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// Update documents; returns diagnostics to publish
+    fn notification(&mut self, notification: Notification) -> Vec<Notification> {
+        let (uri, version) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Ok(params) = params::<lsp_types::DidOpenTextDocumentParams>(notification.params) else {
+                    return Vec::new();
+                };
+                let item = params.text_document;
+                let path = item.uri.to_file_path().ok();
+                self.documents.insert(item.uri.clone(), Document::new(item.text, path));
+                (item.uri, Some(item.version))
+            }
+            DidChangeTextDocument::METHOD => {
+                let Ok(params) = params::<lsp_types::DidChangeTextDocumentParams>(notification.params) else {
+                    return Vec::new();
+                };
+                // Full sync: the last change holds the whole text
+                let Some(change) = params.content_changes.into_iter().last() else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                match self.documents.get_mut(&uri) {
+                    Some(document) => document.update(change.text),
+                    None => {
+                        let path = uri.to_file_path().ok();
+                        self.documents.insert(uri.clone(), Document::new(change.text, path));
+                    }
+                }
+                (uri, Some(params.text_document.version))
+            }
+            DidCloseTextDocument::METHOD => {
+                let Ok(params) = params::<lsp_types::DidCloseTextDocumentParams>(notification.params) else {
+                    return Vec::new();
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                let stale = self.included.remove(&uri).unwrap_or_default();
+                return std::iter::once(uri)
+                    .chain(stale)
+                    .map(|uri| publish(uri, Vec::new(), None))
+                    .collect();
+            }
+            _ => return Vec::new(),
+        };
+
+        let mut diagnostics = self.documents[&uri].diagnostics();
+        let mut included = HashMap::new();
+        for (path, found) in std::mem::take(&mut diagnostics.included) {
+            match path.canonicalize().ok().and_then(|path| Url::from_file_path(path).ok()) {
+                // An open file publishes its own diagnostics
+                Some(file) if self.documents.contains_key(&file) => {}
+                Some(file) => included.entry(file).or_insert_with(Vec::new).extend(found),
+                // Not a file we can name: report it in the document instead
+                None => diagnostics.document.extend(found.into_iter().map(|mut diagnostic| {
+                    diagnostic.message = format!("{} ({})", diagnostic.message, path.display());
+                    diagnostic.range = lsp_types::Range::default();
+                    diagnostic
+                })),
+            }
+        }
+
+        let previous = self.included.insert(uri.clone(), included.keys().cloned().collect()).unwrap_or_default();
+        let mut notifications = vec![publish(uri, diagnostics.document, version)];
+        let stale = previous.into_iter().filter(|file| !included.contains_key(file));
+        notifications.extend(stale.map(|file| publish(file, Vec::new(), None)));
+        notifications.extend(included.into_iter().map(|(file, found)| publish(file, found, None)));
+        notifications
+    }
+}
+
+fn params<P: DeserializeOwned>(value: Value) -> Result<P, (ErrorCode, String)> {
+    serde_json::from_value(value).map_err(|err| (ErrorCode::InvalidParams, err.to_string()))
+}
+
+fn publish(uri: Url, diagnostics: Vec<lsp_types::Diagnostic>, version: Option<i32>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams { uri, diagnostics, version },
+    )
+}
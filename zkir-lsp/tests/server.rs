@@ -0,0 +1,199 @@
+//! Integration tests for the zkir-lsp message loop
+//!
+//! A client talks to the server over an in-memory connection, as an editor
+//! would over stdio.
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidOpenTextDocument, Exit, Initialized, Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Initialize, References, Request as _, Shutdown,
+};
+use lsp_types::Url;
+use serde_json::{json, Value};
+use std::thread;
+
+struct Client {
+    connection: Connection,
+    next_id: i32,
+}
+
+impl Client {
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        self.connection.sender.send(Request::new(id.clone(), method.to_string(), params).into()).unwrap();
+        match self.connection.receiver.recv().unwrap() {
+            Message::Response(response) if response.id == id => response,
+            other => panic!("Expected response to {}, got {:?}", method, other),
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        self.connection.sender.send(Notification::new(method.to_string(), params).into()).unwrap();
+    }
+
+    fn notification(&self) -> Notification {
+        match self.connection.receiver.recv().unwrap() {
+            Message::Notification(notification) => notification,
+            other => panic!("Expected notification, got {:?}", other),
+        }
+    }
+
+    fn open(&self, uri: &str, text: &str) {
+        self.notify(
+            DidOpenTextDocument::METHOD,
+            json!({ "textDocument": { "uri": uri, "languageId": "zkasm", "version": 1, "text": text } }),
+        );
+    }
+
+    fn shutdown(mut self, handle: thread::JoinHandle<Result<(), String>>) {
+        self.request(Shutdown::METHOD, Value::Null);
+        self.notify(Exit::METHOD, Value::Null);
+        assert_eq!(handle.join().unwrap(), Ok(()));
+    }
+}
+
+/// Start a server and initialize it
+fn start() -> (Client, thread::JoinHandle<Result<(), String>>) {
+    let (server, connection) = Connection::memory();
+    let handle = thread::spawn(move || zkir_lsp::run(&server).map_err(|err| err.to_string()));
+    let mut client = Client { connection, next_id: 0 };
+
+    let response = client.request(Initialize::METHOD, json!({ "capabilities": {} }));
+    let capabilities = &response.result.unwrap()["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], json!(true));
+    assert_eq!(capabilities["definitionProvider"], json!(true));
+    client.notify(Initialized::METHOD, json!({}));
+    (client, handle)
+}
+
+/// Start lines of the locations in a references response
+fn lines(locations: &Value) -> Vec<u64> {
+    locations.as_array().unwrap().iter().map(|location| location["range"]["start"]["line"].as_u64().unwrap()).collect()
+}
+
+#[test]
+fn test_session() {
+    let (mut client, handle) = start();
+
+    let uri = "file:///project/main.zkasm";
+    let text = ".abi 2\nmain:\n    li a0, 10\n    j main\n    j missing\n";
+    client.open(uri, text);
+    let published = client.notification();
+    assert_eq!(published.method, PublishDiagnostics::METHOD);
+    let diagnostics = published.params["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], json!(4));
+
+    let position = json!({ "textDocument": { "uri": uri }, "position": { "line": 3, "character": 7 } });
+    let definition = client.request(GotoDefinition::METHOD, position).result.unwrap();
+    assert_eq!(definition["uri"], json!(uri));
+    assert_eq!(definition["range"]["start"], json!({ "line": 1, "character": 0 }));
+
+    // The document does not assemble, so there is nothing to show for `li`
+    let position = json!({ "textDocument": { "uri": uri }, "position": { "line": 2, "character": 4 } });
+    assert_eq!(client.request(HoverRequest::METHOD, position).result, Some(Value::Null));
+
+    let response = client.request("zkir/unknown", json!({}));
+    assert!(response.error.is_some());
+
+    client.shutdown(handle);
+}
+
+#[test]
+fn test_navigation() {
+    let (mut client, handle) = start();
+
+    let uri = "file:///project/count.zkasm";
+    let text = ".abi 2\n.equ COUNT, 3\nmain:\n    li a0, COUNT\n1:  addi a0, a0, -1\n    bnez a0, 1b\n    ecall\n";
+    client.open(uri, text);
+    assert_eq!(client.notification().params["diagnostics"], json!([]));
+
+    let references = |line, character, include_declaration| {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+            "context": { "includeDeclaration": include_declaration },
+        })
+    };
+    let found = client.request(References::METHOD, references(3, 12, true)).result.unwrap();
+    assert_eq!(lines(&found), vec![1, 3]);
+    assert_eq!(found[0]["uri"], json!(uri));
+    // Numeric local labels resolve `1b` to the `1:` before it
+    let found = client.request(References::METHOD, references(5, 13, true)).result.unwrap();
+    assert_eq!(lines(&found), vec![4, 5]);
+    let found = client.request(References::METHOD, references(4, 0, false)).result.unwrap();
+    assert_eq!(lines(&found), vec![5]);
+
+    let position = json!({ "textDocument": { "uri": uri }, "position": { "line": 4, "character": 4 } });
+    let completions = client.request(Completion::METHOD, position.clone()).result.unwrap();
+    let labels: Vec<&str> =
+        completions.as_array().unwrap().iter().map(|item| item["label"].as_str().unwrap()).collect();
+    for label in ["addi", "a0", "main", "COUNT"] {
+        assert!(labels.contains(&label), "{:?}", labels);
+    }
+
+    let params = json!({ "textDocument": { "uri": uri } });
+    let symbols = client.request(DocumentSymbolRequest::METHOD, params).result.unwrap();
+    let names: Vec<&str> = symbols.as_array().unwrap().iter().map(|symbol| symbol["name"].as_str().unwrap()).collect();
+    assert_eq!(names, vec!["COUNT", "main"]);
+
+    // Hovering an instruction shows its address, encoding and cost
+    let program = zkir_assembler::assemble(text).unwrap();
+    let hover = client.request(HoverRequest::METHOD, position.clone()).result.unwrap();
+    let value = hover["contents"]["value"].as_str().unwrap();
+    assert!(value.contains("addi a0, a0, -1"), "{}", value);
+    assert!(value.contains(&format!("`0x00001004`: `{:#010x}`", program.code[1])), "{}", value);
+    assert!(value.contains("1 row, 0 range-check lookups"), "{}", value);
+
+    // A change invalidates the cached listing
+    client.notify(
+        DidChangeTextDocument::METHOD,
+        json!({
+            "textDocument": { "uri": uri, "version": 2 },
+            "contentChanges": [{ "text": text.replace("-1", "-2") }],
+        }),
+    );
+    assert_eq!(client.notification().params["version"], json!(2));
+    let hover = client.request(HoverRequest::METHOD, position).result.unwrap();
+    assert!(hover["contents"]["value"].as_str().unwrap().contains("addi a0, a0, -2"), "{}", hover);
+
+    client.shutdown(handle);
+}
+
+#[test]
+fn test_included_file_diagnostics() {
+    let dir = std::env::temp_dir().join(format!("zkir-lsp-server-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let dir = dir.canonicalize().unwrap();
+    std::fs::write(dir.join("bad.zkasm"), "nop\n    add r1, x9, r3\n").unwrap();
+    let (client, handle) = start();
+
+    let uri = Url::from_file_path(dir.join("main.zkasm")).unwrap();
+    client.open(uri.as_str(), ".include \"bad.zkasm\"\necall\n");
+    let published = client.notification();
+    assert_eq!(published.params["uri"], json!(uri));
+    assert_eq!(published.params["diagnostics"], json!([]));
+
+    // The error is published against the included file, on its line
+    let published = client.notification();
+    assert_eq!(published.params["uri"], json!(Url::from_file_path(dir.join("bad.zkasm")).unwrap()));
+    let diagnostics = published.params["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 1, "character": 4 }));
+
+    // Dropping the include clears them
+    client.notify(
+        DidChangeTextDocument::METHOD,
+        json!({ "textDocument": { "uri": uri, "version": 2 }, "contentChanges": [{ "text": "ecall\n" }] }),
+    );
+    assert_eq!(client.notification().params["uri"], json!(uri));
+    let published = client.notification();
+    assert_eq!(published.params["uri"], json!(Url::from_file_path(dir.join("bad.zkasm")).unwrap()));
+    assert_eq!(published.params["diagnostics"], json!([]));
+
+    client.shutdown(handle);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
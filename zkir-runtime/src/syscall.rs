@@ -79,7 +79,8 @@ impl IOHandler {
 
 /// Handle a syscall
 ///
-/// Syscall convention:
+/// Syscall convention ([`zkir_spec::abi::SYSCALL_NUMBER`] and
+/// [`zkir_spec::abi::SYSCALL_ARGS`]):
 /// - a0 (R10): syscall number
 /// - a1 (R11): first argument (input_ptr for crypto)
 /// - a2 (R12): second argument (input_len for crypto)
@@ -92,45 +93,47 @@ impl IOHandler {
 /// - Keccak-256: a1=input_ptr, a2=input_len, a3=output_ptr (32 bytes)
 /// - Blake3: a1=input_ptr, a2=input_len, a3=output_ptr (32 bytes)
 pub fn handle_syscall(state: &mut VMState, memory: &mut Memory, io: &mut IOHandler) -> Result<()> {
+    use zkir_spec::abi::{SYSCALL_ARGS, SYSCALL_NUMBER};
     use zkir_spec::Register;
 
-    let syscall_num = state.read_reg(Register::R10); // a0
+    let syscall_num = state.read_reg(SYSCALL_NUMBER);
+    let [arg1, arg2, arg3] = SYSCALL_ARGS;
 
     match syscall_num {
         SYSCALL_EXIT => {
-            // Exit with code from a1 (R11)
-            let exit_code = state.read_reg(Register::R11);
+            // Exit with code from a1
+            let exit_code = state.read_reg(arg1);
             state.halt(HaltReason::Exit(exit_code));
             Ok(())
         }
 
         SYSCALL_READ => {
-            // Read from input tape into a0 (R10)
+            // Read from input tape into a0
             let value = io.read();
-            state.write_reg(Register::R10, value);
+            state.write_reg(SYSCALL_NUMBER, value);
             Ok(())
         }
 
         SYSCALL_WRITE => {
-            // Write from a1 (R11) to output tape
-            let value = state.read_reg(Register::R11);
+            // Write from a1 to output tape
+            let value = state.read_reg(arg1);
             io.write(value);
             Ok(())
         }
 
         SYSCALL_SHA256 => {
             // SHA-256: a1=input_ptr, a2=input_len, a3=output_ptr
-            let input_ptr = state.read_reg(Register::R11);
-            let input_len = state.read_reg(Register::R12);
-            let output_ptr = state.read_reg(Register::R13);
+            let input_ptr = state.read_reg(arg1);
+            let input_len = state.read_reg(arg2);
+            let output_ptr = state.read_reg(arg3);
 
             let bound = crypto::sha256_hash(memory, input_ptr, input_len, output_ptr)?;
 
             // Store bound in state for range check optimization (via special bound tracking)
             // For now, return success in a0
-            state.write_reg(Register::R10, 0);
+            state.write_reg(SYSCALL_NUMBER, 0);
 
-            // Write output bound to a designated register (R14 = t4)
+            // Write output bound to a designated register (R14 = a4)
             // This allows the program to track crypto output bounds
             state.write_bound(Register::R14, bound);
 
@@ -139,34 +142,34 @@ pub fn handle_syscall(state: &mut VMState, memory: &mut Memory, io: &mut IOHandl
 
         SYSCALL_POSEIDON2 => {
             // Poseidon2: a1=input_ptr, a2=input_len, a3=output_ptr
-            let input_ptr = state.read_reg(Register::R11);
-            let input_len = state.read_reg(Register::R12);
-            let output_ptr = state.read_reg(Register::R13);
+            let input_ptr = state.read_reg(arg1);
+            let input_len = state.read_reg(arg2);
+            let output_ptr = state.read_reg(arg3);
 
             let _bound = crypto::poseidon2_hash(memory, input_ptr, input_len, output_ptr)?;
-            state.write_reg(Register::R10, 0);
+            state.write_reg(SYSCALL_NUMBER, 0);
             Ok(())
         }
 
         SYSCALL_KECCAK256 => {
             // Keccak-256: a1=input_ptr, a2=input_len, a3=output_ptr
-            let input_ptr = state.read_reg(Register::R11);
-            let input_len = state.read_reg(Register::R12);
-            let output_ptr = state.read_reg(Register::R13);
+            let input_ptr = state.read_reg(arg1);
+            let input_len = state.read_reg(arg2);
+            let output_ptr = state.read_reg(arg3);
 
             let _bound = crypto::keccak256_hash(memory, input_ptr, input_len, output_ptr)?;
-            state.write_reg(Register::R10, 0);
+            state.write_reg(SYSCALL_NUMBER, 0);
             Ok(())
         }
 
         SYSCALL_BLAKE3 => {
             // Blake3: a1=input_ptr, a2=input_len, a3=output_ptr
-            let input_ptr = state.read_reg(Register::R11);
-            let input_len = state.read_reg(Register::R12);
-            let output_ptr = state.read_reg(Register::R13);
+            let input_ptr = state.read_reg(arg1);
+            let input_len = state.read_reg(arg2);
+            let output_ptr = state.read_reg(arg3);
 
             let _bound = crypto::blake3_hash(memory, input_ptr, input_len, output_ptr)?;
-            state.write_reg(Register::R10, 0);
+            state.write_reg(SYSCALL_NUMBER, 0);
             Ok(())
         }
 
@@ -371,8 +371,9 @@ impl VM {
         // Read instruction word from memory
         let word = self.memory.read_u32(self.state.pc)?;
 
-        // Decode instruction using disassembler
-        let inst = zkir_disassembler::decode(word)
+        // Decode instruction using disassembler; non-canonical words are
+        // rejected so that each committed word has a single meaning
+        let inst = zkir_disassembler::decode_strict(word)
             .map_err(|e| RuntimeError::Other(format!("Decode error: {}", e)))?;
 
         Ok((inst, word))
@@ -422,7 +423,7 @@ mod tests {
         // Encode instructions to u32 using assembler
         let code: Vec<u32> = instructions
             .iter()
-            .map(|inst| zkir_assembler::encode(inst))
+            .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
             .collect();
 
         program.code = code;
@@ -551,6 +552,21 @@ mod tests {
         assert_eq!(result.cycles, 100);
     }
 
+    #[test]
+    fn test_vm_rejects_non_canonical_words() {
+        // ADD r1, r2, r3 with a non-zero funct field
+        let mut program = create_program_from_instructions(vec![Instruction::Add {
+            rd: Register::R1,
+            rs1: Register::R2,
+            rs2: Register::R3,
+        }]);
+        program.code[0] |= 1 << 19;
+
+        let vm = VM::new(program, vec![], VMConfig::default());
+        let err = vm.run().unwrap_err();
+        assert!(err.to_string().contains("Non-canonical encoding"), "{}", err);
+    }
+
     #[test]
     fn test_vm_memory_operations() {
         // Program: store and load from memory
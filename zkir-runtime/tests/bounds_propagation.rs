@@ -9,7 +9,7 @@ fn create_program_from_instructions(instructions: Vec<Instruction>) -> Program {
     let mut program = Program::new();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
@@ -460,7 +460,7 @@ fn test_different_limb_config() {
     let mut program = Program::with_config(config30).unwrap();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
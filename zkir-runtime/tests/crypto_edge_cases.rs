@@ -10,7 +10,7 @@ fn create_program_from_instructions(instructions: Vec<Instruction>) -> Program {
     let mut program = Program::new();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
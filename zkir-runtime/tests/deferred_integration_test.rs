@@ -10,7 +10,7 @@ fn create_test_program(instructions: Vec<Instruction>) -> Program {
     let mut program = Program::new();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
@@ -128,11 +128,22 @@ fn test_deferred_arithmetic_chain() {
             rs1: Register::R1,
             imm: 50,
         },
+        // R2 = 0x10000 (store address)
+        Instruction::Addi {
+            rd: Register::R2,
+            rs1: Register::R0,
+            imm: 1,
+        },
+        Instruction::Slli {
+            rd: Register::R2,
+            rs1: Register::R2,
+            shamt: 16,
+        },
         // Store R1 (observation point - forces normalization)
         Instruction::Sw {
-            rs1: Register::R0, // base register
+            rs1: Register::R2, // base register
             rs2: Register::R1, // value = 150
-            imm: 0x10000,      // offset to data section
+            imm: 0,
         },
         // Exit
         Instruction::Addi {
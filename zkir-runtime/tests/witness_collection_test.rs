@@ -11,7 +11,7 @@ fn create_test_program(instructions: Vec<Instruction>) -> Program {
     let mut program = Program::new();
     let code: Vec<u32> = instructions
         .iter()
-        .map(|inst| zkir_assembler::encode(inst))
+        .map(|inst| zkir_assembler::try_encode(inst, &program.config()).unwrap())
         .collect();
     program.code = code;
     program.header.code_size = (program.code.len() * 4) as u32;
@@ -113,11 +113,21 @@ fn test_witness_collection_with_deferred_model() {
 fn test_witness_collection_with_carry_propagation() {
     // Program that triggers carry extraction
     let instructions = vec![
-        // R1 = 2^20 - 10 (near limb boundary)
+        // R1 = 2^20 - 10 (near limb boundary; wider than an immediate)
         Instruction::Addi {
             rd: Register::R1,
             rs1: Register::R0,
-            imm: (1 << 20) - 10,
+            imm: 16,
+        },
+        Instruction::Slli {
+            rd: Register::R1,
+            rs1: Register::R1,
+            shamt: 16,
+        },
+        Instruction::Addi {
+            rd: Register::R1,
+            rs1: Register::R1,
+            imm: -10,
         },
         // R2 = 100 (will cause carry)
         Instruction::Addi {
@@ -135,7 +145,12 @@ fn test_witness_collection_with_carry_propagation() {
         Instruction::Addi {
             rd: Register::R4,
             rs1: Register::R0,
-            imm: 0x10000,
+            imm: 1,
+        },
+        Instruction::Slli {
+            rd: Register::R4,
+            rs1: Register::R4,
+            shamt: 16,
         },
         Instruction::Sw {
             rs1: Register::R4,
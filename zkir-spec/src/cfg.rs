@@ -0,0 +1,375 @@
+//! Control-flow graph recovery for ZKIR v3.4
+//!
+//! [`ControlFlowGraph::new`] splits decoded code, loaded at [`CODE_BASE`],
+//! into basic blocks. A block starts at the entry point, at every in-code
+//! branch or jump target, and after every terminator:
+//! - a branch (`beq` .. `bgeu`), `jal` or `jalr`
+//! - `ebreak`
+//! - an `ecall` known to be the exit syscall: `a0` was last set to
+//!   the exit syscall number (0) by `addi`/`ori`/`xori a0, zero, imm` in the
+//!   same block
+//!
+//! Functions are the entry point and every `jal ra` target. A function's
+//! blocks are those reachable from its entry without following calls, so a
+//! block shared by two functions belongs to both.
+//!
+//! `jalr` targets are only known at run time: its [`EdgeKind::Indirect`]
+//! edge has no target and is reported as unresolved.
+
+use crate::abi::SYSCALL_NUMBER;
+use crate::memory::CODE_BASE;
+use crate::{Instruction, Register, INSTRUCTION_SIZE};
+use std::collections::{BTreeSet, VecDeque};
+use std::ops::Range;
+
+/// Syscall number of `exit`, as the runtime dispatches it
+const SYSCALL_EXIT: i64 = 0;
+
+/// How control reaches an edge's target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Next instruction: straight-line code, branch not taken, or the return
+    /// point of a call
+    Fallthrough,
+    /// Branch taken
+    Branch,
+    /// `jal` that does not link to `ra`
+    Jump,
+    /// `jal ra`
+    Call,
+    /// `jalr`
+    Indirect,
+}
+
+/// Outgoing edge of a basic block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    /// Target address; `None` when only known at run time
+    ///
+    /// Direct targets outside the code (or at its end) have no block.
+    pub target: Option<u64>,
+}
+
+impl Edge {
+    /// Whether the target is only known at run time
+    pub fn is_unresolved(&self) -> bool {
+        self.target.is_none()
+    }
+}
+
+/// Straight-line run of instructions with a single entry and exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Address of the first instruction
+    pub start: u64,
+    /// Address after the last instruction
+    pub end: u64,
+    pub successors: Vec<Edge>,
+}
+
+impl BasicBlock {
+    /// Whether the block covers a memory address
+    pub fn contains(&self, address: u64) -> bool {
+        (self.start..self.end).contains(&address)
+    }
+
+    /// Indices of the block's instructions in the code
+    pub fn instructions(&self) -> Range<usize> {
+        index(self.start)..index(self.end)
+    }
+}
+
+/// Function recovered from a call target or the entry point
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// Address of the first instruction
+    pub entry: u64,
+    /// Start addresses of the function's blocks, in address order
+    pub blocks: Vec<u64>,
+}
+
+/// Control-flow graph of a program's code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// Blocks in address order, covering the whole code
+    pub blocks: Vec<BasicBlock>,
+    /// Functions in entry address order
+    pub functions: Vec<Function>,
+}
+
+impl ControlFlowGraph {
+    /// Recover the control-flow graph of `instructions`, loaded at
+    /// [`CODE_BASE`], starting execution at `entry`
+    pub fn new(instructions: &[Instruction], entry: u64) -> Self {
+        let end = address(instructions.len());
+        let in_code = |address: u64| (CODE_BASE..end).contains(&address) && address & 3 == 0;
+
+        let mut leaders = BTreeSet::new();
+        let mut entries = BTreeSet::new();
+        if in_code(entry) {
+            leaders.insert(entry);
+            entries.insert(entry);
+        }
+        if !instructions.is_empty() {
+            leaders.insert(CODE_BASE);
+        }
+
+        for (i, instr) in instructions.iter().enumerate() {
+            let address = address(i);
+            if let Some(target) = target(instr, address).filter(|&t| in_code(t)) {
+                leaders.insert(target);
+                if matches!(instr, Instruction::Jal { rd: Register::RA, .. }) {
+                    entries.insert(target);
+                }
+            }
+            if is_terminator(instr) {
+                leaders.insert(address + INSTRUCTION_SIZE as u64);
+            }
+        }
+        let exits = exit_ecalls(instructions, &leaders);
+        leaders.extend(exits.iter().map(|&i| address(i + 1)));
+
+        let starts: Vec<u64> = leaders.into_iter().filter(|&address| address < end).collect();
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(n, &start)| {
+                let block_end = starts.get(n + 1).copied().unwrap_or(end);
+                let last = index(block_end) - 1;
+                let successors = if exits.contains(&last) {
+                    Vec::new()
+                } else {
+                    successors(&instructions[last], address(last))
+                };
+                BasicBlock { start, end: block_end, successors }
+            })
+            .collect();
+
+        let mut graph = Self { blocks, functions: Vec::new() };
+        graph.functions = entries.into_iter().map(|entry| graph.function(entry)).collect();
+        graph
+    }
+
+    /// Block starting at `start`
+    pub fn block(&self, start: u64) -> Option<&BasicBlock> {
+        self.blocks
+            .binary_search_by_key(&start, |block| block.start)
+            .ok()
+            .map(|n| &self.blocks[n])
+    }
+
+    /// Block covering a memory address
+    pub fn block_containing(&self, address: u64) -> Option<&BasicBlock> {
+        let n = self.blocks.partition_point(|block| block.start <= address);
+        self.blocks[..n].last().filter(|block| block.contains(address))
+    }
+
+    /// Function whose entry is `entry`
+    pub fn function(&self, entry: u64) -> Function {
+        let mut blocks = BTreeSet::new();
+        let mut queue = VecDeque::from([entry]);
+        while let Some(start) = queue.pop_front() {
+            let Some(block) = self.block(start) else { continue };
+            if !blocks.insert(start) {
+                continue;
+            }
+            for edge in &block.successors {
+                if edge.kind != EdgeKind::Call {
+                    queue.extend(edge.target);
+                }
+            }
+        }
+        Function { entry, blocks: blocks.into_iter().collect() }
+    }
+
+    /// Blocks ending in an edge whose target is only known at run time
+    pub fn unresolved(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.iter().filter(|block| block.successors.iter().any(Edge::is_unresolved))
+    }
+}
+
+fn address(index: usize) -> u64 {
+    CODE_BASE + (index * INSTRUCTION_SIZE) as u64
+}
+
+fn index(address: u64) -> usize {
+    (address - CODE_BASE) as usize / INSTRUCTION_SIZE
+}
+
+/// Target address of a branch or `jal` at `address`
+fn target(instr: &Instruction, address: u64) -> Option<u64> {
+    instr.pc_offset().map(|offset| address.wrapping_add(offset as i64 as u64))
+}
+
+/// Whether `instr` always ends a block
+fn is_terminator(instr: &Instruction) -> bool {
+    instr.is_branch() || instr.is_jump() || matches!(instr, Instruction::Ebreak)
+}
+
+/// Edges out of a block ending in `instr` at `address`
+fn successors(instr: &Instruction, address: u64) -> Vec<Edge> {
+    let next = Some(address + INSTRUCTION_SIZE as u64);
+    let edge = |kind, target| Edge { kind, target };
+    match instr {
+        _ if instr.is_branch() => vec![edge(EdgeKind::Branch, target(instr, address)), edge(EdgeKind::Fallthrough, next)],
+        Instruction::Jal { rd: Register::RA, .. } => {
+            vec![edge(EdgeKind::Call, target(instr, address)), edge(EdgeKind::Fallthrough, next)]
+        }
+        Instruction::Jal { .. } => vec![edge(EdgeKind::Jump, target(instr, address))],
+        // A linking `jalr` is an indirect call and returns
+        Instruction::Jalr { rd: Register::R0, .. } => vec![edge(EdgeKind::Indirect, None)],
+        Instruction::Jalr { .. } => vec![edge(EdgeKind::Indirect, None), edge(EdgeKind::Fallthrough, next)],
+        Instruction::Ebreak => Vec::new(),
+        _ => vec![edge(EdgeKind::Fallthrough, next)],
+    }
+}
+
+/// Indices of the `ecall`s known to be the exit syscall
+///
+/// The syscall number is tracked through constant loads from `zero` and
+/// forgotten at every leader and after every terminator.
+fn exit_ecalls(instructions: &[Instruction], leaders: &BTreeSet<u64>) -> BTreeSet<usize> {
+    let mut exits = BTreeSet::new();
+    let mut number: Option<i64> = None;
+    for (i, instr) in instructions.iter().enumerate() {
+        if leaders.contains(&address(i)) {
+            number = None;
+        }
+        match instr {
+            Instruction::Addi { rd, rs1: Register::R0, imm }
+            | Instruction::Ori { rd, rs1: Register::R0, imm }
+            | Instruction::Xori { rd, rs1: Register::R0, imm }
+                if *rd == SYSCALL_NUMBER =>
+            {
+                number = Some(*imm as i64);
+            }
+            Instruction::Ecall => {
+                if number == Some(SYSCALL_EXIT) {
+                    exits.insert(i);
+                }
+                // The syscall's return value
+                number = None;
+            }
+            _ if is_terminator(instr) || instr.rd() == Some(SYSCALL_NUMBER) => number = None,
+            _ => {}
+        }
+    }
+    exits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn li(rd: Register, imm: i32) -> Instruction {
+        Instruction::Addi { rd, rs1: Register::R0, imm }
+    }
+
+    /// main: loop calling `f`, then exit; f: return
+    fn sample() -> Vec<Instruction> {
+        vec![
+            li(Register::R11, 3),                                                // 0x1000 main
+            Instruction::Jal { rd: Register::RA, offset: 24 },                   // 0x1004 -> f
+            Instruction::Addi { rd: Register::R11, rs1: Register::R11, imm: -1 }, // 0x1008
+            Instruction::Bne { rs1: Register::R11, rs2: Register::R0, offset: -8 }, // 0x100C -> 0x1004
+            li(Register::A0, 0),                                                 // 0x1010
+            Instruction::Ecall,                                                  // 0x1014 exit
+            Instruction::Ebreak,                                                 // 0x1018
+            Instruction::Jalr { rd: Register::R0, rs1: Register::RA, imm: 0 },   // 0x101C f: ret
+        ]
+    }
+
+    fn edge(kind: EdgeKind, target: u64) -> Edge {
+        Edge { kind, target: Some(target) }
+    }
+
+    #[test]
+    fn test_basic_blocks() {
+        let graph = ControlFlowGraph::new(&sample(), CODE_BASE);
+        let blocks: Vec<_> = graph.blocks.iter().map(|block| (block.start, block.end)).collect();
+        assert_eq!(
+            blocks,
+            vec![(0x1000, 0x1004), (0x1004, 0x1008), (0x1008, 0x1010), (0x1010, 0x1018), (0x1018, 0x101C), (0x101C, 0x1020)]
+        );
+
+        let successors: Vec<_> = graph.blocks.iter().map(|block| block.successors.clone()).collect();
+        assert_eq!(
+            successors,
+            vec![
+                vec![edge(EdgeKind::Fallthrough, 0x1004)],
+                vec![edge(EdgeKind::Call, 0x101C), edge(EdgeKind::Fallthrough, 0x1008)],
+                vec![edge(EdgeKind::Branch, 0x1004), edge(EdgeKind::Fallthrough, 0x1010)],
+                vec![],
+                vec![],
+                vec![Edge { kind: EdgeKind::Indirect, target: None }],
+            ]
+        );
+        assert_eq!(graph.block(0x1008).unwrap().instructions(), 2..4);
+        assert_eq!(graph.block_containing(0x100C).unwrap().start, 0x1008);
+        assert!(graph.block_containing(0x1020).is_none());
+        assert_eq!(graph.unresolved().map(|block| block.start).collect::<Vec<_>>(), vec![0x101C]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let graph = ControlFlowGraph::new(&sample(), CODE_BASE);
+        assert_eq!(
+            graph.functions,
+            vec![
+                Function { entry: 0x1000, blocks: vec![0x1000, 0x1004, 0x1008, 0x1010] },
+                Function { entry: 0x101C, blocks: vec![0x101C] },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exit_ecall_detection() {
+        // Syscall number unknown, or not exit: `ecall` falls through
+        let exit_unknown = vec![Instruction::Ecall, Instruction::Ebreak];
+        let not_exit = vec![li(Register::A0, 2), Instruction::Ecall, Instruction::Ebreak];
+        for code in [exit_unknown, not_exit] {
+            let graph = ControlFlowGraph::new(&code, CODE_BASE);
+            assert_eq!(graph.blocks.len(), 1);
+        }
+
+        // Clobbered after being set
+        let code = [
+            li(Register::A0, 0),
+            Instruction::Add { rd: Register::A0, rs1: Register::A0, rs2: Register::A0 },
+            Instruction::Ecall,
+            Instruction::Ebreak,
+        ];
+        assert_eq!(ControlFlowGraph::new(&code, CODE_BASE).blocks.len(), 1);
+
+        // Set before a branch target: unknown in the target's block
+        let code = [
+            li(Register::A0, 0),
+            Instruction::Beq { rs1: Register::R11, rs2: Register::R0, offset: 4 },
+            Instruction::Ecall,
+            Instruction::Ebreak,
+        ];
+        assert_eq!(ControlFlowGraph::new(&code, CODE_BASE).blocks.len(), 2);
+
+        let code = [li(Register::A0, 0), Instruction::Ecall, Instruction::Ebreak];
+        let graph = ControlFlowGraph::new(&code, CODE_BASE);
+        assert_eq!(graph.blocks.len(), 2);
+        assert!(graph.blocks[0].successors.is_empty());
+    }
+
+    #[test]
+    fn test_targets_outside_code() {
+        let code = [
+            Instruction::Jal { rd: Register::R0, offset: 0x100 },
+            Instruction::Jalr { rd: Register::RA, rs1: Register::R5, imm: 0 },
+        ];
+        let graph = ControlFlowGraph::new(&code, CODE_BASE);
+        assert_eq!(graph.blocks.len(), 2);
+        assert_eq!(graph.blocks[0].successors, vec![edge(EdgeKind::Jump, 0x1100)]);
+        assert_eq!(
+            graph.blocks[1].successors,
+            vec![Edge { kind: EdgeKind::Indirect, target: None }, edge(EdgeKind::Fallthrough, 0x1008)]
+        );
+        assert!(graph.block(0x1100).is_none());
+    }
+}
@@ -0,0 +1,295 @@
+//! # Debug Program Format for ZKIR v3.4
+//!
+//! Debug-mode bytecode (see [`FormatMode::Debug`]) carries symbol tables
+//! ahead of the code, for disassemblers and debuggers.
+//!
+//! ## Binary Layout (little-endian)
+//!
+//! ```text
+//! Header (32 bytes): as in release mode, except
+//!   entry_point = file offset of the code
+//! Globals:   count: u32, then per global:
+//!   name_len: u16, name, address: u32, size: u32
+//! Functions: count: u32, then per function:
+//!   name_len: u16, name, offset: u32, size: u32
+//! Entry:     entry point memory address: u32
+//! Code:      code_size bytes
+//! Data:      data_size bytes
+//! ```
+//!
+//! Global addresses are memory addresses. Function offsets and sizes are in
+//! bytes, relative to the start of the code, which is loaded at
+//! [`CODE_BASE`] as in release mode.
+//!
+//! Since [`FormatMode::detect`] tells the modes apart by `entry_point`, the
+//! code must start before file offset 0x1000; [`DebugProgram::to_bytes`]
+//! rejects tables that do not fit, and names longer than `u16::MAX` bytes.
+//!
+//! [`FormatMode::Debug`]: crate::FormatMode::Debug
+//! [`FormatMode::detect`]: crate::FormatMode::detect
+
+use crate::error::ZkIrError;
+use crate::memory::CODE_BASE;
+use crate::object::Reader;
+use crate::program::{Program, ProgramHeader};
+
+/// Global variable metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugGlobal {
+    pub name: String,
+    /// Memory address of the variable
+    pub address: u32,
+    /// Size in bytes
+    pub size: u32,
+}
+
+/// Function table entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugFunction {
+    pub name: String,
+    /// Byte offset of the first instruction within the code
+    pub offset: u32,
+    /// Size in bytes
+    pub size: u32,
+}
+
+impl DebugFunction {
+    /// Memory address of the first instruction
+    pub fn address(&self) -> u64 {
+        CODE_BASE + self.offset as u64
+    }
+
+    /// Whether the function's code covers a memory address
+    pub fn contains(&self, address: u64) -> bool {
+        (self.address()..self.address() + self.size as u64).contains(&address)
+    }
+}
+
+/// Program with the symbol tables of the debug format
+///
+/// `program.header.entry_point` is a memory address like in release mode.
+/// Writing stores it after the tables and puts the file offset of the code
+/// in the header; reading restores it.
+#[derive(Debug, Clone)]
+pub struct DebugProgram {
+    pub program: Program,
+    pub globals: Vec<DebugGlobal>,
+    pub functions: Vec<DebugFunction>,
+}
+
+impl DebugProgram {
+    /// Wrap a program without any symbols
+    pub fn new(program: Program) -> Self {
+        Self {
+            program,
+            globals: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    /// Function whose code covers a memory address
+    pub fn function_at(&self, address: u64) -> Option<&DebugFunction> {
+        self.functions.iter().find(|function| function.contains(address))
+    }
+
+    /// Serialize to bytes
+    ///
+    /// Fails if a name is longer than `u16::MAX` bytes, or if the tables
+    /// push the code to file offset 0x1000 or beyond, where
+    /// [`FormatMode::detect`](crate::FormatMode::detect) would take the file
+    /// for release mode.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ZkIrError> {
+        let mut tables = Vec::new();
+        tables.extend_from_slice(&(self.globals.len() as u32).to_le_bytes());
+        for global in &self.globals {
+            write_name(&mut tables, &global.name)?;
+            tables.extend_from_slice(&global.address.to_le_bytes());
+            tables.extend_from_slice(&global.size.to_le_bytes());
+        }
+        tables.extend_from_slice(&(self.functions.len() as u32).to_le_bytes());
+        for function in &self.functions {
+            write_name(&mut tables, &function.name)?;
+            tables.extend_from_slice(&function.offset.to_le_bytes());
+            tables.extend_from_slice(&function.size.to_le_bytes());
+        }
+        tables.extend_from_slice(&self.program.header.entry_point.to_le_bytes());
+
+        let code_offset = ProgramHeader::SIZE + tables.len();
+        if code_offset as u64 >= CODE_BASE {
+            return Err(invalid(format!(
+                "symbol tables end at byte {}, but the code must start before byte {}",
+                code_offset, CODE_BASE
+            )));
+        }
+        let mut header = self.program.header.clone();
+        header.entry_point = code_offset as u32;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&header.to_bytes());
+        bytes.extend_from_slice(&tables);
+        for &word in &self.program.code {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.program.data);
+        Ok(bytes)
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkIrError> {
+        let mut header = ProgramHeader::from_bytes(bytes)?;
+        let mut reader = Reader::new(bytes, invalid);
+        reader.take(ProgramHeader::SIZE)?;
+
+        let mut globals = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = read_name(&mut reader)?;
+            globals.push(DebugGlobal { name, address: reader.u32()?, size: reader.u32()? });
+        }
+        let mut functions = Vec::new();
+        for _ in 0..reader.u32()? {
+            let name = read_name(&mut reader)?;
+            let function = DebugFunction { name, offset: reader.u32()?, size: reader.u32()? };
+            if function.offset as u64 + function.size as u64 > header.code_size as u64 {
+                return Err(invalid(format!("function `{}` extends past the code", function.name)));
+            }
+            functions.push(function);
+        }
+        let entry_point = reader.u32()?;
+
+        if header.entry_point as usize != reader.pos {
+            return Err(invalid(format!(
+                "code starts at byte {} but the header says {}",
+                reader.pos, header.entry_point
+            )));
+        }
+        let code = reader
+            .take(header.code_size as usize)?
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        let data = reader.take(header.data_size as usize)?.to_vec();
+        if reader.pos != bytes.len() {
+            return Err(invalid(format!("{} trailing bytes", bytes.len() - reader.pos)));
+        }
+
+        header.entry_point = entry_point;
+        let program = Program { header, code, data };
+        program.validate()?;
+        Ok(Self { program, globals, functions })
+    }
+}
+
+fn write_name(bytes: &mut Vec<u8>, name: &str) -> Result<(), ZkIrError> {
+    let len = u16::try_from(name.len())
+        .map_err(|_| invalid(format!("symbol name of {} bytes is longer than {}", name.len(), u16::MAX)))?;
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(name.as_bytes());
+    Ok(())
+}
+
+fn read_name(reader: &mut Reader) -> Result<String, ZkIrError> {
+    let len = reader.u16()? as usize;
+    String::from_utf8(reader.take(len)?.to_vec()).map_err(|_| invalid("symbol name is not UTF-8".to_string()))
+}
+
+fn invalid(message: String) -> ZkIrError {
+    ZkIrError::InvalidDebugInfo(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FormatMode;
+
+    fn sample() -> DebugProgram {
+        let mut program = Program::new();
+        program.code = vec![0x0000_0048, 0x0000_0050, 0x0000_0050];
+        program.header.code_size = 12;
+        program.data = vec![7, 0, 0, 0];
+        program.header.data_size = 4;
+        DebugProgram {
+            program,
+            globals: vec![DebugGlobal { name: "counter".to_string(), address: 0x100C, size: 4 }],
+            functions: vec![
+                DebugFunction { name: "main".to_string(), offset: 0, size: 8 },
+                DebugFunction { name: "exit".to_string(), offset: 8, size: 4 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_debug_roundtrip() {
+        let debug = sample();
+        let bytes = debug.to_bytes().unwrap();
+        assert_eq!(FormatMode::detect(&bytes), Some(FormatMode::Debug));
+
+        let read = DebugProgram::from_bytes(&bytes).unwrap();
+        assert_eq!(read.globals, debug.globals);
+        assert_eq!(read.functions, debug.functions);
+        assert_eq!(read.program.code, debug.program.code);
+        assert_eq!(read.program.data, debug.program.data);
+        assert_eq!(read.program.header.entry_point, CODE_BASE as u32);
+        assert_eq!(read.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_debug_rejects_bad_input() {
+        let bytes = sample().to_bytes().unwrap();
+        assert!(DebugProgram::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(DebugProgram::from_bytes(&trailing).is_err());
+
+        // Code offset in the header disagrees with the tables
+        let mut entry = bytes.clone();
+        entry[12..16].copy_from_slice(&40u32.to_le_bytes());
+        assert!(matches!(DebugProgram::from_bytes(&entry), Err(ZkIrError::InvalidDebugInfo(_))));
+
+        // Function past the end of the code
+        let mut debug = sample();
+        debug.functions[1].size = 8;
+        assert!(DebugProgram::from_bytes(&debug.to_bytes().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_debug_roundtrip_keeps_entry_point() {
+        let mut debug = sample();
+        debug.program.header.entry_point = 0x1004;
+        let bytes = debug.to_bytes().unwrap();
+        assert_eq!(FormatMode::detect(&bytes), Some(FormatMode::Debug));
+
+        let read = DebugProgram::from_bytes(&bytes).unwrap();
+        assert_eq!(read.program.header.entry_point, 0x1004);
+        assert_eq!(read.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_debug_to_bytes_rejects_unencodable_tables() {
+        // Name length does not fit in a u16
+        let mut debug = sample();
+        debug.functions[0].name = "f".repeat(u16::MAX as usize + 1);
+        assert!(matches!(debug.to_bytes(), Err(ZkIrError::InvalidDebugInfo(_))));
+
+        // Tables reach file offset 0x1000, where the file would detect as release
+        let mut debug = sample();
+        debug.globals = (0..400)
+            .map(|i| DebugGlobal { name: format!("global{}", i), address: 0x100C, size: 4 })
+            .collect();
+        let err = debug.to_bytes().unwrap_err();
+        assert!(err.to_string().contains("must start before byte 4096"), "{}", err);
+
+        let mut debug = sample();
+        debug.globals = vec![DebugGlobal { name: "g".repeat(4000), address: 0x100C, size: 4 }];
+        assert!(debug.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn test_function_at() {
+        let debug = sample();
+        assert_eq!(debug.functions[1].address(), 0x1008);
+        assert_eq!(debug.function_at(0x1004).unwrap().name, "main");
+        assert_eq!(debug.function_at(0x1008).unwrap().name, "exit");
+        assert!(debug.function_at(0x100C).is_none());
+    }
+}
@@ -59,6 +59,16 @@ pub const FUNCT_MASK: u32 = 0x1FFF;
 /// Offset field mask for J-type (21 bits)
 pub const OFFSET_MASK: u32 = 0x1FFFFF;
 
+// ============================================================================
+// Field Widths
+// ============================================================================
+
+/// Width of the I/S/B-type immediate field in bits
+pub const IMM_BITS: u32 = 17;
+
+/// Width of the J-type offset field in bits
+pub const OFFSET_BITS: u32 = 21;
+
 /// Sign bit position in 17-bit immediate
 pub const IMM_SIGN_BIT: u32 = 16;
 
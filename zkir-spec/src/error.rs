@@ -28,6 +28,12 @@ pub enum ZkIrError {
     #[error("Invalid data size: expected {expected} bytes, found {found} bytes")]
     InvalidDataSize { expected: usize, found: usize },
 
+    #[error("Invalid object file: {0}")]
+    InvalidObject(String),
+
+    #[error("Invalid debug program: {0}")]
+    InvalidDebugInfo(String),
+
     // Instruction errors
     #[error("Invalid instruction encoding: {0:#010x}")]
     InvalidEncoding(u32),
@@ -324,6 +324,114 @@ impl Instruction {
             _ => None,
         }
     }
+
+    /// Get first source register if present (the base address for memory access)
+    pub fn rs1(&self) -> Option<Register> {
+        match self {
+            Instruction::Add { rs1, .. }
+            | Instruction::Sub { rs1, .. }
+            | Instruction::Mul { rs1, .. }
+            | Instruction::Mulh { rs1, .. }
+            | Instruction::Divu { rs1, .. }
+            | Instruction::Remu { rs1, .. }
+            | Instruction::Div { rs1, .. }
+            | Instruction::Rem { rs1, .. }
+            | Instruction::Addi { rs1, .. }
+            | Instruction::And { rs1, .. }
+            | Instruction::Or { rs1, .. }
+            | Instruction::Xor { rs1, .. }
+            | Instruction::Andi { rs1, .. }
+            | Instruction::Ori { rs1, .. }
+            | Instruction::Xori { rs1, .. }
+            | Instruction::Sll { rs1, .. }
+            | Instruction::Srl { rs1, .. }
+            | Instruction::Sra { rs1, .. }
+            | Instruction::Slli { rs1, .. }
+            | Instruction::Srli { rs1, .. }
+            | Instruction::Srai { rs1, .. }
+            | Instruction::Sltu { rs1, .. }
+            | Instruction::Sgeu { rs1, .. }
+            | Instruction::Slt { rs1, .. }
+            | Instruction::Sge { rs1, .. }
+            | Instruction::Seq { rs1, .. }
+            | Instruction::Sne { rs1, .. }
+            | Instruction::Cmov { rs1, .. }
+            | Instruction::Cmovz { rs1, .. }
+            | Instruction::Cmovnz { rs1, .. }
+            | Instruction::Lb { rs1, .. }
+            | Instruction::Lbu { rs1, .. }
+            | Instruction::Lh { rs1, .. }
+            | Instruction::Lhu { rs1, .. }
+            | Instruction::Lw { rs1, .. }
+            | Instruction::Ld { rs1, .. }
+            | Instruction::Sb { rs1, .. }
+            | Instruction::Sh { rs1, .. }
+            | Instruction::Sw { rs1, .. }
+            | Instruction::Sd { rs1, .. }
+            | Instruction::Beq { rs1, .. }
+            | Instruction::Bne { rs1, .. }
+            | Instruction::Blt { rs1, .. }
+            | Instruction::Bge { rs1, .. }
+            | Instruction::Bltu { rs1, .. }
+            | Instruction::Bgeu { rs1, .. }
+            | Instruction::Jalr { rs1, .. } => Some(*rs1),
+            _ => None,
+        }
+    }
+
+    /// Get second source register if present (the stored value for stores)
+    pub fn rs2(&self) -> Option<Register> {
+        match self {
+            Instruction::Add { rs2, .. }
+            | Instruction::Sub { rs2, .. }
+            | Instruction::Mul { rs2, .. }
+            | Instruction::Mulh { rs2, .. }
+            | Instruction::Divu { rs2, .. }
+            | Instruction::Remu { rs2, .. }
+            | Instruction::Div { rs2, .. }
+            | Instruction::Rem { rs2, .. }
+            | Instruction::And { rs2, .. }
+            | Instruction::Or { rs2, .. }
+            | Instruction::Xor { rs2, .. }
+            | Instruction::Sll { rs2, .. }
+            | Instruction::Srl { rs2, .. }
+            | Instruction::Sra { rs2, .. }
+            | Instruction::Sltu { rs2, .. }
+            | Instruction::Sgeu { rs2, .. }
+            | Instruction::Slt { rs2, .. }
+            | Instruction::Sge { rs2, .. }
+            | Instruction::Seq { rs2, .. }
+            | Instruction::Sne { rs2, .. }
+            | Instruction::Cmov { rs2, .. }
+            | Instruction::Cmovz { rs2, .. }
+            | Instruction::Cmovnz { rs2, .. }
+            | Instruction::Sb { rs2, .. }
+            | Instruction::Sh { rs2, .. }
+            | Instruction::Sw { rs2, .. }
+            | Instruction::Sd { rs2, .. }
+            | Instruction::Beq { rs2, .. }
+            | Instruction::Bne { rs2, .. }
+            | Instruction::Blt { rs2, .. }
+            | Instruction::Bge { rs2, .. }
+            | Instruction::Bltu { rs2, .. }
+            | Instruction::Bgeu { rs2, .. } => Some(*rs2),
+            _ => None,
+        }
+    }
+
+    /// Get the PC-relative offset of a branch or `jal`
+    pub fn pc_offset(&self) -> Option<i32> {
+        match self {
+            Instruction::Beq { offset, .. }
+            | Instruction::Bne { offset, .. }
+            | Instruction::Blt { offset, .. }
+            | Instruction::Bge { offset, .. }
+            | Instruction::Bltu { offset, .. }
+            | Instruction::Bgeu { offset, .. }
+            | Instruction::Jal { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Instruction {
@@ -459,6 +567,19 @@ mod tests {
         assert_eq!(ecall.rd(), None);
     }
 
+    #[test]
+    fn test_source_registers() {
+        let sw = Instruction::Sw {
+            rs1: Register::SP,
+            rs2: Register::A0,
+            imm: 8,
+        };
+        assert_eq!((sw.rs1(), sw.rs2()), (Some(Register::SP), Some(Register::A0)));
+
+        let jal = Instruction::Jal { rd: Register::RA, offset: 8 };
+        assert_eq!((jal.rs1(), jal.rs2()), (None, None));
+    }
+
     #[test]
     fn test_display() {
         let add = Instruction::Add {
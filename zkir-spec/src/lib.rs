@@ -21,8 +21,11 @@ pub mod opcode;
 pub mod encoding;
 pub mod error;
 pub mod program;
+pub mod debug;
+pub mod object;
 pub mod trace;
 pub mod validation;
+pub mod cfg;
 
 // Re-export commonly used types
 pub use config::{Config, ConfigError};
@@ -31,15 +34,20 @@ pub use bound::{
     BoundSource, BoundedValue, CryptoType, ValueBound,
 };
 pub use field::{Mersenne31, MERSENNE31_PRIME};
-pub use register::{Register, NUM_REGISTERS};
+pub use register::{AbiVersion, Register, ABI_NAMES, NUM_REGISTERS};
 pub use instruction::Instruction;
 pub use opcode::{Opcode, InstructionFamily};
 pub use error::ZkIrError;
 pub use program::{Program, ProgramHeader, FormatMode, MAGIC, VERSION};
+pub use debug::{DebugFunction, DebugGlobal, DebugProgram};
+pub use object::{
+    Binding, ObjectFile, ObjectSymbol, Relocation, RelocationKind, RelocationTarget, SectionKind,
+};
 pub use trace::{
     TraceRow, MemoryOp, MemOpType, CryptoWitness, Sha256Witness,
     Poseidon2Witness, Keccak256Witness, RegisterState,
 };
+pub use cfg::{BasicBlock, ControlFlowGraph, Edge, EdgeKind, Function};
 pub use validation::{
     validate, validate_program, ValidationError, ValidationResult, ValidationWarning,
 };
@@ -74,6 +82,8 @@ pub mod memory {
 /// These constants define the Application Binary Interface (ABI) parameters
 /// for the ZKIR calling convention documented in the Register module.
 pub mod abi {
+    use crate::Register;
+
     /// Register size in bytes (32-bit registers).
     ///
     /// Each ZKIR register is encoded as a 32-bit word in stack frames and memory,
@@ -96,6 +106,14 @@ pub mod abi {
     /// - Compatibility with SIMD operations
     /// - Consistency across different ZKIR implementations
     pub const FRAME_ALIGNMENT: usize = 16;
+
+    /// Register holding the syscall number for `ecall`, and its return value.
+    pub const SYSCALL_NUMBER: Register = Register::A0;
+
+    /// Registers holding the syscall arguments, in order.
+    ///
+    /// Crypto syscalls take `input_ptr`, `input_len` and `output_ptr`.
+    pub const SYSCALL_ARGS: [Register; 3] = [Register::A1, Register::A2, Register::A3];
 }
 
 /// Instruction size in bytes
@@ -155,6 +173,10 @@ mod tests {
         // Verify FRAME_ALIGNMENT is a power of 2
         assert_eq!(abi::FRAME_ALIGNMENT & (abi::FRAME_ALIGNMENT - 1), 0);
 
+        // Syscalls follow the register calling convention
+        assert_eq!(abi::SYSCALL_NUMBER, Register::from_name("a0").unwrap());
+        assert_eq!(abi::SYSCALL_ARGS, [Register::R11, Register::R12, Register::R13]);
+
         // Verify PARAM_ALIGNMENT equals REGISTER_SIZE_BYTES
         assert_eq!(abi::PARAM_ALIGNMENT, abi::REGISTER_SIZE_BYTES);
     }
@@ -0,0 +1,490 @@
+//! # Relocatable Object Format for ZKIR v3.4
+//!
+//! Output of separately assembled modules, combined into a [`Program`] by
+//! the linker. Sections are encoded as if they started at address 0;
+//! operands that depend on addresses known only after linking are left zero
+//! and described by [`Relocation`] records.
+//!
+//! ## Binary Layout (little-endian)
+//!
+//! ```text
+//! Header (52 bytes):
+//!   magic: u32 "ZKOB", version: u32,
+//!   limb_bits, data_limbs, addr_limbs, flags: u8,
+//!   text_words, data_size, rodata_size, bss_size: u32,
+//!   data_align, rodata_align, bss_align: u32,
+//!   symbol_count, relocation_count: u32,
+//!   entry_symbol: u32 (0xFFFFFFFF = none)
+//! Sections: text (u32 words), data, rodata
+//! Symbols:  name_len: u16, name, binding: u8, section: u8 (0xFF = undefined), offset: u64
+//! Relocations: section: u8, kind: u8, offset: u64, target: u8 (0 = section, 1 = symbol),
+//!              target_index: u32, addend: i64
+//! ```
+//!
+//! [`Program`]: crate::Program
+
+use crate::config::Config;
+use crate::error::ZkIrError;
+use crate::program::VERSION;
+
+/// Object file magic number ("ZKOB" in little-endian)
+pub const OBJECT_MAGIC: u32 = 0x424F4B5A;
+
+/// Marker for undefined (external) symbols in the serialized symbol table
+const UNDEFINED_SECTION: u8 = 0xFF;
+
+/// Marker for "no entry symbol" in the serialized header
+const NO_ENTRY: u32 = u32::MAX;
+
+/// Section of an object file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SectionKind {
+    Text,
+    Data,
+    Rodata,
+    Bss,
+}
+
+impl SectionKind {
+    /// All sections, in link order
+    pub const ALL: [SectionKind; 4] = [SectionKind::Text, SectionKind::Data, SectionKind::Rodata, SectionKind::Bss];
+
+    /// Assembly name (`.text`, ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionKind::Text => ".text",
+            SectionKind::Data => ".data",
+            SectionKind::Rodata => ".rodata",
+            SectionKind::Bss => ".bss",
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    fn from_u8(value: u8) -> Result<Self, ZkIrError> {
+        Self::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or_else(|| invalid(format!("invalid section {}", value)))
+    }
+}
+
+/// Symbol visibility
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    /// Visible only inside its object (the default for labels, or `.local`)
+    Local,
+    /// Exported to other objects (`.global`)
+    Global,
+}
+
+/// Symbol table entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    pub binding: Binding,
+    /// Section and offset of the definition; `None` for `.extern` symbols
+    pub definition: Option<(SectionKind, u64)>,
+}
+
+impl ObjectSymbol {
+    /// Whether the symbol is defined by another object
+    pub fn is_undefined(&self) -> bool {
+        self.definition.is_none()
+    }
+}
+
+/// How a relocated value is patched in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelocationKind {
+    /// B-type 17-bit offset, relative to the instruction's address
+    Branch,
+    /// J-type 21-bit offset, relative to the instruction's address
+    Jump,
+    /// I/S-type 17-bit immediate := `%hi(value)` (`value >> 16`)
+    Hi,
+    /// I/S-type 17-bit immediate := `%lo(value)` (`value & 0xFFFF`)
+    Lo,
+    /// I/S-type 17-bit signed immediate := `value`
+    Imm,
+    /// Data value of 1, 2, 4 or 8 bytes (`.byte` ... `.dword`)
+    Data(u8),
+}
+
+impl RelocationKind {
+    fn to_u8(self) -> u8 {
+        match self {
+            RelocationKind::Branch => 0,
+            RelocationKind::Jump => 1,
+            RelocationKind::Hi => 2,
+            RelocationKind::Lo => 3,
+            RelocationKind::Imm => 4,
+            RelocationKind::Data(width) => 0x10 | width,
+        }
+    }
+
+    fn from_u8(value: u8) -> Result<Self, ZkIrError> {
+        match value {
+            0 => Ok(RelocationKind::Branch),
+            1 => Ok(RelocationKind::Jump),
+            2 => Ok(RelocationKind::Hi),
+            3 => Ok(RelocationKind::Lo),
+            4 => Ok(RelocationKind::Imm),
+            0x11 | 0x12 | 0x14 | 0x18 => Ok(RelocationKind::Data(value & 0xF)),
+            _ => Err(invalid(format!("invalid relocation kind {:#04x}", value))),
+        }
+    }
+}
+
+/// Address a relocation refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelocationTarget {
+    /// Start of a section of the same object (local labels)
+    Section(SectionKind),
+    /// Symbol table entry, by index
+    Symbol(usize),
+}
+
+/// Relocation record: patch `kind` at `section`+`offset` with `target + addend`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Section containing the patched instruction or data (never `.bss`)
+    pub section: SectionKind,
+    /// Byte offset of the patched location within `section`
+    pub offset: u64,
+    pub kind: RelocationKind,
+    pub target: RelocationTarget,
+    pub addend: i64,
+}
+
+/// Relocatable object file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectFile {
+    pub config: Config,
+    /// Encoded instructions, as if `.text` started at address 0
+    pub text: Vec<u32>,
+    pub data: Vec<u8>,
+    pub rodata: Vec<u8>,
+    pub bss_size: u64,
+    /// Required alignment of `.data`, `.rodata` and `.bss` (powers of two)
+    pub data_align: u64,
+    pub rodata_align: u64,
+    pub bss_align: u64,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<Relocation>,
+    /// Symbol named by `.entry`, by index
+    pub entry: Option<usize>,
+}
+
+impl ObjectFile {
+    /// Create an empty object file
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            text: Vec::new(),
+            data: Vec::new(),
+            rodata: Vec::new(),
+            bss_size: 0,
+            data_align: 1,
+            rodata_align: 1,
+            bss_align: 1,
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+            entry: None,
+        }
+    }
+
+    /// Size in bytes of a section
+    pub fn section_size(&self, section: SectionKind) -> u64 {
+        match section {
+            SectionKind::Text => self.text.len() as u64 * 4,
+            SectionKind::Data => self.data.len() as u64,
+            SectionKind::Rodata => self.rodata.len() as u64,
+            SectionKind::Bss => self.bss_size,
+        }
+    }
+
+    /// Required alignment of a section
+    pub fn section_align(&self, section: SectionKind) -> u64 {
+        match section {
+            SectionKind::Text => 4,
+            SectionKind::Data => self.data_align,
+            SectionKind::Rodata => self.rodata_align,
+            SectionKind::Bss => self.bss_align,
+        }
+    }
+
+    /// Find a symbol by name
+    pub fn symbol(&self, name: &str) -> Option<&ObjectSymbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Serialize to bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        // Header
+        bytes.extend_from_slice(&OBJECT_MAGIC.to_le_bytes());
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[self.config.limb_bits, self.config.data_limbs, self.config.addr_limbs, 0]);
+        for value in [
+            self.text.len() as u32,
+            self.data.len() as u32,
+            self.rodata.len() as u32,
+            self.bss_size as u32,
+            self.data_align as u32,
+            self.rodata_align as u32,
+            self.bss_align as u32,
+            self.symbols.len() as u32,
+            self.relocations.len() as u32,
+            self.entry.map_or(NO_ENTRY, |index| index as u32),
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Sections
+        for &word in &self.text {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&self.rodata);
+
+        // Symbols
+        for symbol in &self.symbols {
+            bytes.extend_from_slice(&(symbol.name.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(symbol.name.as_bytes());
+            bytes.push(symbol.binding as u8);
+            let (section, offset) = match symbol.definition {
+                Some((section, offset)) => (section.to_u8(), offset),
+                None => (UNDEFINED_SECTION, 0),
+            };
+            bytes.push(section);
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+
+        // Relocations
+        for relocation in &self.relocations {
+            bytes.push(relocation.section.to_u8());
+            bytes.push(relocation.kind.to_u8());
+            bytes.extend_from_slice(&relocation.offset.to_le_bytes());
+            let (target, index) = match relocation.target {
+                RelocationTarget::Section(section) => (0u8, section.to_u8() as u32),
+                RelocationTarget::Symbol(index) => (1u8, index as u32),
+            };
+            bytes.push(target);
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.extend_from_slice(&relocation.addend.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Deserialize from bytes
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkIrError> {
+        let mut reader = Reader::new(bytes, invalid);
+
+        let magic = reader.u32()?;
+        if magic != OBJECT_MAGIC {
+            return Err(invalid(format!("bad magic {:#010x}", magic)));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(ZkIrError::InvalidVersion { expected: VERSION, found: version });
+        }
+        let config = Config {
+            limb_bits: reader.u8()?,
+            data_limbs: reader.u8()?,
+            addr_limbs: reader.u8()?,
+        };
+        config.validate()?;
+        reader.u8()?; // flags
+
+        let text_words = reader.u32()? as usize;
+        let data_size = reader.u32()? as usize;
+        let rodata_size = reader.u32()? as usize;
+        let mut object = ObjectFile::new(config);
+        object.bss_size = reader.u32()? as u64;
+        object.data_align = reader.u32()? as u64;
+        object.rodata_align = reader.u32()? as u64;
+        object.bss_align = reader.u32()? as u64;
+        let symbol_count = reader.u32()? as usize;
+        let relocation_count = reader.u32()? as usize;
+        let entry = reader.u32()?;
+
+        object.text = reader
+            .take(text_words.checked_mul(4).ok_or_else(|| invalid("text size overflow".to_string()))?)?
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        object.data = reader.take(data_size)?.to_vec();
+        object.rodata = reader.take(rodata_size)?.to_vec();
+
+        for _ in 0..symbol_count {
+            let name_len = reader.u16()? as usize;
+            let name = String::from_utf8(reader.take(name_len)?.to_vec())
+                .map_err(|_| invalid("symbol name is not UTF-8".to_string()))?;
+            let binding = match reader.u8()? {
+                0 => Binding::Local,
+                1 => Binding::Global,
+                other => return Err(invalid(format!("invalid binding {}", other))),
+            };
+            let section = reader.u8()?;
+            let offset = reader.u64()?;
+            let definition = match section {
+                UNDEFINED_SECTION => None,
+                section => Some((SectionKind::from_u8(section)?, offset)),
+            };
+            object.symbols.push(ObjectSymbol { name, binding, definition });
+        }
+
+        for _ in 0..relocation_count {
+            let section = SectionKind::from_u8(reader.u8()?)?;
+            let kind = RelocationKind::from_u8(reader.u8()?)?;
+            let offset = reader.u64()?;
+            let target = match (reader.u8()?, reader.u32()?) {
+                (0, index) => RelocationTarget::Section(SectionKind::from_u8(index as u8)?),
+                (1, index) if (index as usize) < object.symbols.len() => RelocationTarget::Symbol(index as usize),
+                (_, index) => return Err(invalid(format!("invalid relocation target {}", index))),
+            };
+            let addend = reader.u64()? as i64;
+            object.relocations.push(Relocation { section, offset, kind, target, addend });
+        }
+
+        if entry != NO_ENTRY {
+            if entry as usize >= object.symbols.len() {
+                return Err(invalid(format!("invalid entry symbol {}", entry)));
+            }
+            object.entry = Some(entry as usize);
+        }
+
+        if reader.pos != bytes.len() {
+            return Err(invalid(format!("{} trailing bytes", bytes.len() - reader.pos)));
+        }
+        Ok(object)
+    }
+}
+
+fn invalid(message: String) -> ZkIrError {
+    ZkIrError::InvalidObject(message)
+}
+
+/// Little-endian cursor over a byte slice
+///
+/// Truncation is reported through `invalid`, the error constructor of the
+/// format being read.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pub(crate) pos: usize,
+    invalid: fn(String) -> ZkIrError,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], invalid: fn(String) -> ZkIrError) -> Self {
+        Self { bytes, pos: 0, invalid }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8], ZkIrError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| (self.invalid)(format!("truncated at byte {}", self.pos)))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, ZkIrError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, ZkIrError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, ZkIrError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, ZkIrError> {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ObjectFile {
+        let mut object = ObjectFile::new(Config::DEFAULT);
+        object.text = vec![0x0000_0048, 0x1234_5678];
+        object.data = vec![1, 2, 3, 4];
+        object.bss_size = 64;
+        object.data_align = 4;
+        object.symbols = vec![
+            ObjectSymbol {
+                name: "main".to_string(),
+                binding: Binding::Global,
+                definition: Some((SectionKind::Text, 0)),
+            },
+            ObjectSymbol {
+                name: "sha256".to_string(),
+                binding: Binding::Global,
+                definition: None,
+            },
+        ];
+        object.relocations = vec![
+            Relocation {
+                section: SectionKind::Text,
+                offset: 0,
+                kind: RelocationKind::Jump,
+                target: RelocationTarget::Symbol(1),
+                addend: 0,
+            },
+            Relocation {
+                section: SectionKind::Data,
+                offset: 0,
+                kind: RelocationKind::Data(4),
+                target: RelocationTarget::Section(SectionKind::Bss),
+                addend: -8,
+            },
+        ];
+        object.entry = Some(0);
+        object
+    }
+
+    #[test]
+    fn test_object_roundtrip() {
+        let object = sample();
+        let bytes = object.to_bytes();
+        assert_eq!(ObjectFile::from_bytes(&bytes).unwrap(), object);
+    }
+
+    #[test]
+    fn test_object_rejects_bad_input() {
+        let bytes = sample().to_bytes();
+        assert!(ObjectFile::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(ObjectFile::from_bytes(&[0; 8]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(ObjectFile::from_bytes(&trailing).is_err());
+
+        // Entry symbol index past the symbol table
+        let mut entry = bytes.clone();
+        entry[48..52].copy_from_slice(&2u32.to_le_bytes());
+        assert!(ObjectFile::from_bytes(&entry).is_err());
+    }
+
+    #[test]
+    fn test_section_sizes() {
+        let object = sample();
+        assert_eq!(object.section_size(SectionKind::Text), 8);
+        assert_eq!(object.section_size(SectionKind::Bss), 64);
+        assert_eq!(object.section_align(SectionKind::Data), 4);
+        assert!(object.symbol("sha256").unwrap().is_undefined());
+    }
+}
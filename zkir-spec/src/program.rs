@@ -20,11 +20,13 @@
 //!
 //! Extended format with symbol tables for debugging/disassembly:
 //! ```text
-//! [32-byte header][globals metadata][function table][code]
+//! [32-byte header][globals metadata][function table][entry point][code][data]
 //! ```
 //! - `entry_point` = file offset where code starts
 //! - Includes function names, sizes, and global variable metadata
-//! - NOT compatible with `Program::from_bytes()` - use disassembler
+//! - NOT compatible with `Program::from_bytes()` - read it with
+//!   [`DebugProgram::from_bytes()`](crate::DebugProgram::from_bytes)
+//!   (layout in [`crate::debug`])
 //!
 //! Use `zkir-llvm --debug` or `-g` to produce debug format.
 
@@ -357,7 +359,7 @@ impl Default for Program {
 pub enum FormatMode {
     /// Release mode: [header][code][data] - compatible with Program::from_bytes()
     Release,
-    /// Debug mode: [header][globals][functions][code] - use disassembler
+    /// Debug mode: [header][globals][functions][entry][code][data] - see [`crate::DebugProgram`]
     Debug,
 }
 
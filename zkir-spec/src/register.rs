@@ -15,30 +15,107 @@ pub const NUM_REGISTERS: usize = 16;
 /// - r0 (zero): Hardwired to zero
 /// - r1 (ra): Return address
 /// - r2 (sp): Stack pointer
-/// - r3 (fp): Frame pointer
-/// - r4-r5 (a0-a1): Arguments/return values
-/// - r6-r9 (a2-a5): Arguments
-/// - r10-r13 (s0-s3): Saved registers (callee-saved)
-/// - r14-r15 (t0-t1): Temporaries (caller-saved)
+/// - r3 (gp): Global pointer
+/// - r4 (tp): Thread pointer
+/// - r5-r7 (t0-t2): Temporaries (caller-saved)
+/// - r8 (fp/s0): Frame pointer
+/// - r9 (s1): Saved register (callee-saved)
+/// - r10-r11 (a0-a1): Arguments/return values
+/// - r12-r15 (a2-a5): Arguments
+///
+/// These are the [`ABI_NAMES`] of [`AbiVersion::V2`].
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Register {
     R0 = 0,   // zero - hardwired to 0
     R1 = 1,   // ra   - return address
     R2 = 2,   // sp   - stack pointer
-    R3 = 3,   // fp   - frame pointer
-    R4 = 4,   // a0   - argument 0 / return value
-    R5 = 5,   // a1   - argument 1 / return value
-    R6 = 6,   // a2   - argument 2
-    R7 = 7,   // a3   - argument 3
-    R8 = 8,   // a4   - argument 4
-    R9 = 9,   // a5   - argument 5
-    R10 = 10, // s0   - saved register
-    R11 = 11, // s1   - saved register
-    R12 = 12, // s2   - saved register
-    R13 = 13, // s3   - saved register
-    R14 = 14, // t0   - temporary
-    R15 = 15, // t1   - temporary
+    R3 = 3,   // gp   - global pointer
+    R4 = 4,   // tp   - thread pointer
+    R5 = 5,   // t0   - temporary
+    R6 = 6,   // t1   - temporary
+    R7 = 7,   // t2   - temporary
+    R8 = 8,   // fp   - frame pointer (s0)
+    R9 = 9,   // s1   - saved register
+    R10 = 10, // a0   - argument 0 / return value
+    R11 = 11, // a1   - argument 1 / return value
+    R12 = 12, // a2   - argument 2
+    R13 = 13, // a3   - argument 3
+    R14 = 14, // a4   - argument 4
+    R15 = 15, // a5   - argument 5
+}
+
+/// Canonical ABI register names, indexed by register number
+pub const ABI_NAMES: [&str; NUM_REGISTERS] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "fp", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+];
+
+/// Register names accepted by the assembler before ABI v2
+///
+/// Kept so that old sources can be assembled with `.abi 1` and migrated.
+const V1_NAMES: [&str; NUM_REGISTERS] = [
+    "zero", "ra", "sp", "gp", "tp", "fp", "s0", "s1", "t0", "t1", "t2", "a0", "a1", "a2", "a3", "a4",
+];
+
+/// Register naming convention
+///
+/// Both versions share the numeric names (r0-r15) and the encoding; they
+/// only differ in which register an ABI name such as `a0` refers to.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AbiVersion {
+    /// Legacy assembler names (`a0` = r11, `t0` = r8), deprecated
+    V1,
+    /// Canonical names from the specification (`a0` = r10, `t0` = r5)
+    #[default]
+    V2,
+}
+
+impl AbiVersion {
+    /// Version used when a source does not select one
+    pub const CURRENT: Self = Self::V2;
+
+    /// Look up a version by number (1 or 2)
+    pub fn from_number(number: u64) -> Option<Self> {
+        match number {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+
+    /// Version number
+    pub const fn number(self) -> u32 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    /// ABI name of every register, indexed by register number
+    pub const fn names(self) -> &'static [&'static str; NUM_REGISTERS] {
+        match self {
+            Self::V1 => &V1_NAMES,
+            Self::V2 => &ABI_NAMES,
+        }
+    }
+
+    /// Resolve an ABI name or numeric name (r0-r15) under this version
+    pub fn register(self, name: &str) -> Option<Register> {
+        if let Some(index) = self.names().iter().position(|&abi_name| abi_name == name) {
+            return Register::from_index(index as u8);
+        }
+        match (self, name) {
+            // The frame pointer doubles as the first saved register
+            (Self::V2, "s0") => Some(Register::FP),
+            _ => Register::from_numeric_name(name),
+        }
+    }
+}
+
+impl fmt::Display for AbiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ABI v{}", self.number())
+    }
 }
 
 impl Register {
@@ -46,19 +123,20 @@ impl Register {
     pub const ZERO: Self = Self::R0;
     pub const RA: Self = Self::R1;
     pub const SP: Self = Self::R2;
-    pub const FP: Self = Self::R3;
-    pub const A0: Self = Self::R4;
-    pub const A1: Self = Self::R5;
-    pub const A2: Self = Self::R6;
-    pub const A3: Self = Self::R7;
-    pub const A4: Self = Self::R8;
-    pub const A5: Self = Self::R9;
-    pub const S0: Self = Self::R10;
-    pub const S1: Self = Self::R11;
-    pub const S2: Self = Self::R12;
-    pub const S3: Self = Self::R13;
-    pub const T0: Self = Self::R14;
-    pub const T1: Self = Self::R15;
+    pub const GP: Self = Self::R3;
+    pub const TP: Self = Self::R4;
+    pub const T0: Self = Self::R5;
+    pub const T1: Self = Self::R6;
+    pub const T2: Self = Self::R7;
+    pub const FP: Self = Self::R8;
+    pub const S0: Self = Self::R8;
+    pub const S1: Self = Self::R9;
+    pub const A0: Self = Self::R10;
+    pub const A1: Self = Self::R11;
+    pub const A2: Self = Self::R12;
+    pub const A3: Self = Self::R13;
+    pub const A4: Self = Self::R14;
+    pub const A5: Self = Self::R15;
 
     /// Create register from 4-bit index (0-15)
     #[inline]
@@ -82,26 +160,9 @@ impl Register {
         matches!(self, Self::R0)
     }
 
-    /// Get the register name
+    /// Get the canonical ABI name (see [`ABI_NAMES`])
     pub const fn name(self) -> &'static str {
-        match self {
-            Self::R0 => "zero",
-            Self::R1 => "ra",
-            Self::R2 => "sp",
-            Self::R3 => "fp",
-            Self::R4 => "a0",
-            Self::R5 => "a1",
-            Self::R6 => "a2",
-            Self::R7 => "a3",
-            Self::R8 => "a4",
-            Self::R9 => "a5",
-            Self::R10 => "s0",
-            Self::R11 => "s1",
-            Self::R12 => "s2",
-            Self::R13 => "s3",
-            Self::R14 => "t0",
-            Self::R15 => "t1",
-        }
+        ABI_NAMES[self as usize]
     }
 
     /// Get the numeric name (r0-r15)
@@ -109,27 +170,19 @@ impl Register {
         format!("r{}", self.index())
     }
 
-    /// Parse register from name (supports both aliases and numeric form)
+    /// Parse register from name (canonical ABI names and numeric form)
     pub fn from_name(name: &str) -> Option<Self> {
-        match name {
-            "zero" | "r0" => Some(Self::R0),
-            "ra" | "r1" => Some(Self::R1),
-            "sp" | "r2" => Some(Self::R2),
-            "fp" | "r3" => Some(Self::R3),
-            "a0" | "r4" => Some(Self::R4),
-            "a1" | "r5" => Some(Self::R5),
-            "a2" | "r6" => Some(Self::R6),
-            "a3" | "r7" => Some(Self::R7),
-            "a4" | "r8" => Some(Self::R8),
-            "a5" | "r9" => Some(Self::R9),
-            "s0" | "r10" => Some(Self::R10),
-            "s1" | "r11" => Some(Self::R11),
-            "s2" | "r12" => Some(Self::R12),
-            "s3" | "r13" => Some(Self::R13),
-            "t0" | "r14" => Some(Self::R14),
-            "t1" | "r15" => Some(Self::R15),
-            _ => None,
+        AbiVersion::CURRENT.register(name)
+    }
+
+    /// Parse the numeric form (r0-r15)
+    fn from_numeric_name(name: &str) -> Option<Self> {
+        let digits = name.strip_prefix('r')?;
+        let canonical = digits == "0" || !digits.starts_with('0');
+        if !canonical || digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
+        digits.parse().ok().and_then(Self::from_index)
     }
 }
 
@@ -178,10 +231,11 @@ mod tests {
         assert_eq!(Register::R0.name(), "zero");
         assert_eq!(Register::R1.name(), "ra");
         assert_eq!(Register::R2.name(), "sp");
-        assert_eq!(Register::R3.name(), "fp");
-        assert_eq!(Register::R4.name(), "a0");
-        assert_eq!(Register::R10.name(), "s0");
-        assert_eq!(Register::R14.name(), "t0");
+        assert_eq!(Register::R3.name(), "gp");
+        assert_eq!(Register::R5.name(), "t0");
+        assert_eq!(Register::R8.name(), "fp");
+        assert_eq!(Register::R10.name(), "a0");
+        assert_eq!(Register::R15.name(), "a5");
     }
 
     #[test]
@@ -191,17 +245,50 @@ mod tests {
         assert_eq!(Register::from_name("ra"), Some(Register::R1));
         assert_eq!(Register::from_name("r1"), Some(Register::R1));
         assert_eq!(Register::from_name("sp"), Some(Register::R2));
-        assert_eq!(Register::from_name("a0"), Some(Register::R4));
-        assert_eq!(Register::from_name("s0"), Some(Register::R10));
-        assert_eq!(Register::from_name("t0"), Some(Register::R14));
+        assert_eq!(Register::from_name("a0"), Some(Register::R10));
+        assert_eq!(Register::from_name("s0"), Some(Register::R8));
+        assert_eq!(Register::from_name("t0"), Some(Register::R5));
+        assert_eq!(Register::from_name("r15"), Some(Register::R15));
         assert_eq!(Register::from_name("invalid"), None);
+        assert_eq!(Register::from_name("r16"), None);
+        assert_eq!(Register::from_name("r01"), None);
+        assert_eq!(Register::from_name("r+1"), None);
+        assert_eq!(Register::from_name("s2"), None);
     }
 
     #[test]
     fn test_display() {
         assert_eq!(format!("{}", Register::R0), "zero");
         assert_eq!(format!("{}", Register::R1), "ra");
-        assert_eq!(format!("{}", Register::R15), "t1");
+        assert_eq!(format!("{}", Register::R15), "a5");
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for abi in [AbiVersion::V1, AbiVersion::V2] {
+            for index in 0..NUM_REGISTERS as u8 {
+                let reg = Register::from_index(index).unwrap();
+                assert_eq!(abi.register(abi.names()[index as usize]), Some(reg));
+                assert_eq!(abi.register(&reg.numeric_name()), Some(reg));
+            }
+        }
+        for reg in (0..16).filter_map(Register::from_index) {
+            assert_eq!(Register::from_name(reg.name()), Some(reg));
+        }
+    }
+
+    #[test]
+    fn test_abi_versions() {
+        assert_eq!(AbiVersion::default(), AbiVersion::CURRENT);
+        assert_eq!(AbiVersion::from_number(1), Some(AbiVersion::V1));
+        assert_eq!(AbiVersion::from_number(3), None);
+        assert_eq!(AbiVersion::V1.to_string(), "ABI v1");
+
+        assert_eq!(AbiVersion::V1.register("a0"), Some(Register::R11));
+        assert_eq!(AbiVersion::V1.register("t0"), Some(Register::R8));
+        assert_eq!(AbiVersion::V1.register("fp"), Some(Register::R5));
+        assert_eq!(AbiVersion::V1.register("a5"), None);
+        assert_eq!(AbiVersion::V2.register("a5"), Some(Register::R15));
     }
 
     #[test]
@@ -90,19 +90,22 @@ impl Default for ValidationResult {
 }
 
 /// Maximum immediate value for I-type instructions (17-bit signed)
-const I_TYPE_IMM_MAX: i32 = (1 << 16) - 1;
-const I_TYPE_IMM_MIN: i32 = -(1 << 16);
+pub const I_TYPE_IMM_MAX: i32 = (1 << 16) - 1;
+pub const I_TYPE_IMM_MIN: i32 = -(1 << 16);
 
 /// Maximum offset for B-type instructions (17-bit signed)
-const B_TYPE_OFFSET_MAX: i32 = (1 << 16) - 1;
-const B_TYPE_OFFSET_MIN: i32 = -(1 << 16);
+pub const B_TYPE_OFFSET_MAX: i32 = (1 << 16) - 1;
+pub const B_TYPE_OFFSET_MIN: i32 = -(1 << 16);
 
 /// Maximum offset for J-type instructions (21-bit signed)
-const J_TYPE_OFFSET_MAX: i32 = (1 << 20) - 1;
-const J_TYPE_OFFSET_MIN: i32 = -(1 << 20);
+pub const J_TYPE_OFFSET_MAX: i32 = (1 << 20) - 1;
+pub const J_TYPE_OFFSET_MIN: i32 = -(1 << 20);
 
 /// Maximum shift amount for 40-bit values
-const MAX_SHIFT_AMOUNT: u8 = 63; // Allow up to 63 for flexibility
+pub const MAX_SHIFT_AMOUNT: u8 = 63; // Allow up to 63 for flexibility
+
+/// Required alignment of branch and jump offsets (instruction size)
+pub const OFFSET_ALIGNMENT: u32 = 4;
 
 /// Validate a single instruction
 pub fn validate(inst: &Instruction) -> ValidationResult {
@@ -301,10 +304,10 @@ fn check_branch_offset_range(result: &mut ValidationResult, offset: i32, min: i3
         });
     }
     // Branch offsets should be aligned to 4 bytes (instruction size)
-    if offset % 4 != 0 {
+    if offset % OFFSET_ALIGNMENT as i32 != 0 {
         result.add_error(ValidationError::MisalignedBranchOffset {
             offset,
-            alignment: 4,
+            alignment: OFFSET_ALIGNMENT,
         });
     }
 }
@@ -319,10 +322,10 @@ fn check_jump_offset_range(result: &mut ValidationResult, offset: i32, min: i32,
         });
     }
     // Jump offsets should be aligned to 4 bytes (instruction size)
-    if offset % 4 != 0 {
+    if offset % OFFSET_ALIGNMENT as i32 != 0 {
         result.add_error(ValidationError::MisalignedJumpOffset {
             offset,
-            alignment: 4,
+            alignment: OFFSET_ALIGNMENT,
         });
     }
 }